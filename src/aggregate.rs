@@ -0,0 +1,634 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+};
+
+use anyhow::bail;
+use sqlparser::ast::{Expr, Function, FunctionArg, FunctionArgExpr, Query, Visit, Visitor};
+
+use crate::{
+    collation::Collation,
+    column::{SerialValue, ValueKey},
+    expr::{collation, eval, ColumnName, Context, Scope},
+    json,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunc {
+    Count,
+    Sum,
+    Total,
+    Avg,
+    Min,
+    Max,
+    GroupConcat,
+    JsonGroupArray,
+    JsonGroupObject,
+}
+
+impl AggregateFunc {
+    /// Looks up an aggregate by name. `min()` and `max()` are only aggregates with a single
+    /// argument; with more they are scalar functions.
+    pub fn lookup(name: &str, nargs: usize) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(Self::Count),
+            "sum" => Some(Self::Sum),
+            "total" => Some(Self::Total),
+            "avg" => Some(Self::Avg),
+            "min" if nargs == 1 => Some(Self::Min),
+            "max" if nargs == 1 => Some(Self::Max),
+            "group_concat" | "string_agg" => Some(Self::GroupConcat),
+            "json_group_array" => Some(Self::JsonGroupArray),
+            "json_group_object" => Some(Self::JsonGroupObject),
+            _ => None,
+        }
+    }
+}
+
+/// An aggregate function call in the projection or HAVING clause of a query.
+#[derive(Debug, Clone)]
+pub struct AggregateCall {
+    /// The text of the call, which the evaluator uses to find its result.
+    pub key: String,
+    pub func: AggregateFunc,
+    pub args: Vec<Expr>,
+    pub distinct: bool,
+    pub filter: Option<Expr>,
+}
+
+impl AggregateCall {
+    pub fn from_function(function: &Function) -> anyhow::Result<Option<Self>> {
+        if function.over.is_some() {
+            return Ok(None);
+        }
+
+        let name = function.name.to_string();
+        let Some(func) = AggregateFunc::lookup(&name, function.args.len()) else {
+            return Ok(None);
+        };
+
+        let mut args = Vec::new();
+        for arg in &function.args {
+            match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => args.push(expr.clone()),
+                // count(*) counts rows, like count() without arguments.
+                FunctionArg::Unnamed(FunctionArgExpr::Wildcard) if func == AggregateFunc::Count => {
+                    continue
+                }
+                _ => bail!("wrong arguments to function {name}()"),
+            }
+        }
+
+        let nargs = match func {
+            AggregateFunc::Count => 0..=1,
+            AggregateFunc::GroupConcat => 1..=2,
+            AggregateFunc::JsonGroupObject => 2..=2,
+            _ => 1..=1,
+        };
+        if !nargs.contains(&args.len()) {
+            bail!("wrong number of arguments to function {name}()");
+        }
+        if function.distinct && args.len() != 1 {
+            bail!("DISTINCT aggregates must have exactly one argument");
+        }
+
+        Ok(Some(Self {
+            key: function.to_string(),
+            func,
+            args,
+            distinct: function.distinct,
+            filter: function.filter.as_deref().cloned(),
+        }))
+    }
+
+    /// Evaluates the arguments for a row. The values the JSON aggregates collect are converted
+    /// to JSON text.
+    /// The collating sequence of the first argument, which `min()`, `max()` and DISTINCT compare
+    /// values with.
+    pub fn collation(&self, scope: &Scope) -> anyhow::Result<Collation> {
+        match self.args.first() {
+            Some(arg) => Ok(collation(arg, scope)?.unwrap_or_default()),
+            None => Ok(Collation::Binary),
+        }
+    }
+
+    pub fn eval_args(&self, scope: &Scope) -> anyhow::Result<Vec<SerialValue>> {
+        let mut args = self
+            .args
+            .iter()
+            .map(|arg| eval(arg, scope))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if matches!(
+            self.func,
+            AggregateFunc::JsonGroupArray | AggregateFunc::JsonGroupObject
+        ) {
+            if let (Some(expr), Some(value)) = (self.args.last(), args.last_mut()) {
+                *value = SerialValue::String(json::eval_arg(expr, scope)?.to_json_text()?);
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// Collects the aggregate calls in `exprs`, skipping those that belong to subqueries.
+pub fn collect_aggregates<'a>(
+    exprs: impl IntoIterator<Item = &'a Expr>,
+) -> anyhow::Result<Vec<AggregateCall>> {
+    struct AggregateVisitor {
+        calls: Vec<AggregateCall>,
+        query_depth: usize,
+        aggregate_depth: usize,
+    }
+
+    impl Visitor for AggregateVisitor {
+        type Break = anyhow::Error;
+
+        fn pre_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+            self.query_depth += 1;
+            ControlFlow::Continue(())
+        }
+
+        fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+            self.query_depth -= 1;
+            ControlFlow::Continue(())
+        }
+
+        fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+            if let (Expr::Function(function), 0) = (expr, self.query_depth) {
+                match AggregateCall::from_function(function) {
+                    Ok(Some(call)) => {
+                        if self.aggregate_depth > 0 {
+                            return ControlFlow::Break(anyhow::anyhow!(
+                                "misuse of aggregate function {}()",
+                                function.name
+                            ));
+                        }
+                        self.aggregate_depth += 1;
+                        if !self.calls.iter().any(|c| c.key == call.key) {
+                            self.calls.push(call);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => return ControlFlow::Break(err),
+                }
+            }
+            ControlFlow::Continue(())
+        }
+
+        fn post_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+            if let (Expr::Function(function), 0) = (expr, self.query_depth) {
+                if matches!(AggregateCall::from_function(function), Ok(Some(_))) {
+                    self.aggregate_depth -= 1;
+                }
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut visitor = AggregateVisitor {
+        calls: Vec::new(),
+        query_depth: 0,
+        aggregate_depth: 0,
+    };
+    for expr in exprs {
+        if let ControlFlow::Break(err) = expr.visit(&mut visitor) {
+            return Err(err);
+        }
+    }
+
+    Ok(visitor.calls)
+}
+
+/// Running state of `sum()`, `total()` and `avg()`. Integer inputs are summed exactly until the
+/// first non-integer or an overflow, after which the sum continues as a double using
+/// Kahan-Babuska-Neumaier compensated summation, the same as SQLite.
+#[derive(Debug, Clone, Default)]
+pub struct SumState {
+    rsum: f64,
+    rerr: f64,
+    isum: i64,
+    cnt: i64,
+    approx: bool,
+    overflow: bool,
+}
+
+impl SumState {
+    fn step_real(&mut self, r: f64) {
+        let s = self.rsum;
+        let t = s + r;
+        if s.abs() > r.abs() {
+            self.rerr += (s - t) + r;
+        } else {
+            self.rerr += (r - t) + s;
+        }
+        self.rsum = t;
+    }
+
+    fn step_integer_approx(&mut self, num: i64) {
+        if !(-4503599627370495..=4503599627370495).contains(&num) {
+            let small = num % 16384;
+            self.step_real((num - small) as f64);
+            self.step_real(small as f64);
+        } else {
+            self.step_real(num as f64);
+        }
+    }
+
+    fn start_approx(&mut self) {
+        let num = self.isum;
+        if !(-4503599627370495..=4503599627370495).contains(&num) {
+            let small = num % 16384;
+            self.rsum = (num - small) as f64;
+            self.rerr = small as f64;
+        } else {
+            self.rsum = num as f64;
+            self.rerr = 0.0;
+        }
+        self.approx = true;
+    }
+
+    pub fn step(&mut self, value: &SerialValue) {
+        let value = value.numeric_affinity();
+        if value.is_null() {
+            return;
+        }
+
+        self.cnt += 1;
+        match (self.approx, value.as_integer()) {
+            (false, Some(num)) => match self.isum.checked_add(num) {
+                Some(sum) => self.isum = sum,
+                None => {
+                    self.overflow = true;
+                    self.start_approx();
+                    self.step_integer_approx(num);
+                }
+            },
+            (false, None) => {
+                self.start_approx();
+                self.step_real(value.to_real());
+            }
+            (true, Some(num)) => self.step_integer_approx(num),
+            (true, None) => {
+                self.overflow = false;
+                self.step_real(value.to_real());
+            }
+        }
+    }
+
+    fn real(&self) -> f64 {
+        if !self.approx {
+            self.isum as f64
+        } else if self.rerr.is_finite() {
+            self.rsum + self.rerr
+        } else {
+            self.rsum
+        }
+    }
+
+    pub fn sum(&self) -> anyhow::Result<SerialValue> {
+        if self.cnt == 0 {
+            Ok(SerialValue::Null)
+        } else if !self.approx {
+            Ok(SerialValue::I64(self.isum))
+        } else if self.overflow {
+            bail!("integer overflow")
+        } else {
+            Ok(SerialValue::Float64(self.real()))
+        }
+    }
+
+    pub fn total(&self) -> SerialValue {
+        SerialValue::Float64(self.real())
+    }
+
+    pub fn avg(&self) -> SerialValue {
+        if self.cnt == 0 {
+            SerialValue::Null
+        } else {
+            SerialValue::Float64(self.real() / self.cnt as f64)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum State {
+    Count(i64),
+    Sum(SumState),
+    Best(SerialValue),
+    Concat(Option<String>),
+    /// The JSON texts of the elements, or of the labels and values, collected so far.
+    Json(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    func: AggregateFunc,
+    collation: Collation,
+    seen: Option<HashSet<ValueKey>>,
+    state: State,
+}
+
+impl Accumulator {
+    pub fn new(call: &AggregateCall, collation: Collation) -> Self {
+        let state = match call.func {
+            AggregateFunc::Count => State::Count(0),
+            AggregateFunc::Sum | AggregateFunc::Total | AggregateFunc::Avg => {
+                State::Sum(SumState::default())
+            }
+            AggregateFunc::Min | AggregateFunc::Max => State::Best(SerialValue::Null),
+            AggregateFunc::GroupConcat => State::Concat(None),
+            AggregateFunc::JsonGroupArray | AggregateFunc::JsonGroupObject => {
+                State::Json(Vec::new())
+            }
+        };
+
+        Self {
+            func: call.func,
+            collation,
+            seen: call.distinct.then(HashSet::new),
+            state,
+        }
+    }
+
+    /// Adds a row's arguments. Returns true when `min()` or `max()` took a new value, so that
+    /// bare columns can be read from that row.
+    pub fn step(&mut self, args: &[SerialValue]) -> anyhow::Result<bool> {
+        if let (Some(seen), Some(value)) = (&mut self.seen, args.first()) {
+            if value.is_null() || !seen.insert(self.collation.key(value)) {
+                return Ok(false);
+            }
+        }
+
+        match &mut self.state {
+            State::Count(n) => {
+                if args.first().is_none_or(|value| !value.is_null()) {
+                    *n += 1;
+                }
+            }
+            State::Sum(sum) => sum.step(&args[0]),
+            State::Best(best) => {
+                let value = &args[0];
+                if value.is_null() {
+                    return Ok(false);
+                }
+
+                let ordering = self.collation.compare(value, best);
+                let better = best.is_null()
+                    || (self.func == AggregateFunc::Max && ordering.is_gt())
+                    || (self.func == AggregateFunc::Min && ordering.is_lt());
+                if better {
+                    *best = value.clone();
+                    return Ok(true);
+                }
+            }
+            State::Concat(acc) => {
+                let value = &args[0];
+                if value.is_null() {
+                    return Ok(false);
+                }
+
+                match acc {
+                    Some(acc) => {
+                        match args.get(1) {
+                            Some(separator) => acc.push_str(&separator.display()),
+                            None => acc.push(','),
+                        }
+                        acc.push_str(&value.display());
+                    }
+                    None => *acc = Some(value.display()),
+                }
+            }
+            State::Json(elements) => match args {
+                [value] => elements.push(value.display()),
+                // Members without a label are left out.
+                [SerialValue::Null, _] => {}
+                [label, value] => {
+                    let label = SerialValue::String(label.display());
+                    let label = json::Subtyped {
+                        value: label,
+                        json: false,
+                    };
+                    elements.push(format!("{}:{}", label.to_json_text()?, value.display()));
+                }
+                _ => {}
+            },
+        }
+
+        Ok(false)
+    }
+
+    pub fn finish(&self) -> anyhow::Result<SerialValue> {
+        match &self.state {
+            State::Count(n) => Ok(SerialValue::I64(*n)),
+            State::Sum(sum) => match self.func {
+                AggregateFunc::Total => Ok(sum.total()),
+                AggregateFunc::Avg => Ok(sum.avg()),
+                _ => sum.sum(),
+            },
+            State::Best(best) => Ok(best.clone()),
+            State::Concat(acc) => Ok(match acc {
+                Some(acc) => SerialValue::String(acc.clone()),
+                None => SerialValue::Null,
+            }),
+            State::Json(elements) => Ok(SerialValue::String(match self.func {
+                AggregateFunc::JsonGroupObject => format!("{{{}}}", elements.join(",")),
+                _ => format!("[{}]", elements.join(",")),
+            })),
+        }
+    }
+}
+
+/// The rows of one group, reduced to the accumulated aggregates and the row bare columns are
+/// read from.
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub key: Vec<SerialValue>,
+    pub accumulators: Vec<Accumulator>,
+    pub row: Vec<SerialValue>,
+}
+
+impl Group {
+    fn new(key: Vec<SerialValue>, collations: &Collations, row: Vec<SerialValue>) -> Self {
+        Self {
+            key,
+            accumulators: collations
+                .calls
+                .iter()
+                .map(|(call, collation)| Accumulator::new(call, *collation))
+                .collect(),
+            row,
+        }
+    }
+
+    fn step(
+        &mut self,
+        calls: &[AggregateCall],
+        scope: &Scope,
+        bare_from_best: bool,
+    ) -> anyhow::Result<()> {
+        let mut took_best = false;
+        for (call, accumulator) in calls.iter().zip(self.accumulators.iter_mut()) {
+            if let Some(filter) = &call.filter {
+                if eval(filter, scope)?.as_bool() != Some(true) {
+                    continue;
+                }
+            }
+
+            let args = call.eval_args(scope)?;
+            took_best |= accumulator.step(&args)?;
+        }
+
+        if !bare_from_best || took_best {
+            self.row = scope.row.to_vec();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the results of the aggregates keyed the way the evaluator looks them up.
+    pub fn results(&self, calls: &[AggregateCall]) -> anyhow::Result<HashMap<String, SerialValue>> {
+        calls
+            .iter()
+            .zip(self.accumulators.iter())
+            .map(|(call, accumulator)| Ok((call.key.clone(), accumulator.finish()?)))
+            .collect()
+    }
+}
+
+/// With a single `min()` or `max()`, bare columns take their values from the row holding the
+/// minimum or maximum. Otherwise they come from the last row of the group.
+fn bare_from_best(calls: &[AggregateCall]) -> bool {
+    calls.len() == 1 && matches!(calls[0].func, AggregateFunc::Min | AggregateFunc::Max)
+}
+
+/// The collating sequences rows are grouped by and the aggregates compare their arguments with.
+struct Collations<'c> {
+    group_by: Vec<Collation>,
+    calls: Vec<(&'c AggregateCall, Collation)>,
+}
+
+impl<'c> Collations<'c> {
+    fn new(
+        columns: &[ColumnName],
+        group_by: &[Expr],
+        calls: &'c [AggregateCall],
+        context: Context,
+    ) -> anyhow::Result<Self> {
+        let scope = Scope::within(context, columns, &[]);
+        Ok(Self {
+            group_by: group_by
+                .iter()
+                .map(|expr| Ok(collation(expr, &scope)?.unwrap_or_default()))
+                .collect::<anyhow::Result<_>>()?,
+            calls: calls
+                .iter()
+                .map(|call| Ok((call, call.collation(&scope)?)))
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    /// Values that are equal under the collation of their GROUP BY term share a key.
+    fn key(&self, key: &[SerialValue]) -> Vec<ValueKey> {
+        key.iter()
+            .zip(&self.group_by)
+            .map(|(value, collation)| collation.key(value))
+            .collect()
+    }
+}
+
+fn group_key(group_by: &[Expr], scope: &Scope) -> anyhow::Result<Vec<SerialValue>> {
+    group_by.iter().map(|expr| eval(expr, scope)).collect()
+}
+
+/// Without GROUP BY an aggregate query returns one row, even for empty input.
+fn single_group(groups: &mut Vec<Group>, group_by: &[Expr], collations: &Collations, width: usize) {
+    if group_by.is_empty() && groups.is_empty() {
+        groups.push(Group::new(
+            Vec::new(),
+            collations,
+            vec![SerialValue::Null; width],
+        ));
+    }
+}
+
+/// Groups rows in a hash table. The groups are returned sorted by their key, which is the order
+/// SQLite returns them in.
+pub fn hash_aggregate(
+    columns: &[ColumnName],
+    rows: impl IntoIterator<Item = Vec<SerialValue>>,
+    group_by: &[Expr],
+    calls: &[AggregateCall],
+    context: Context,
+) -> anyhow::Result<Vec<Group>> {
+    let bare_from_best = bare_from_best(calls);
+    let collations = Collations::new(columns, group_by, calls, context)?;
+    let mut groups: Vec<Group> = Vec::new();
+    let mut positions: HashMap<Vec<ValueKey>, usize> = HashMap::new();
+
+    for row in rows {
+        let scope = Scope::within(context, columns, &row);
+        let key = group_key(group_by, &scope)?;
+        let hash_key = collations.key(&key);
+
+        let position = *positions.entry(hash_key).or_insert_with(|| {
+            groups.push(Group::new(key, &collations, row.clone()));
+            groups.len() - 1
+        });
+        groups[position].step(calls, &scope, bare_from_best)?;
+    }
+
+    groups.sort_by(|a, b| {
+        a.key
+            .iter()
+            .zip(b.key.iter())
+            .zip(&collations.group_by)
+            .map(|((a, b), collation)| collation.compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    single_group(&mut groups, group_by, &collations, columns.len());
+
+    Ok(groups)
+}
+
+/// Groups rows that arrive ordered by the group key. A group is finished as soon as the key
+/// changes, so no hash table is needed.
+pub fn stream_aggregate(
+    columns: &[ColumnName],
+    rows: impl IntoIterator<Item = Vec<SerialValue>>,
+    group_by: &[Expr],
+    calls: &[AggregateCall],
+    context: Context,
+) -> anyhow::Result<Vec<Group>> {
+    let bare_from_best = bare_from_best(calls);
+    let collations = Collations::new(columns, group_by, calls, context)?;
+    let mut groups: Vec<Group> = Vec::new();
+    let mut current: Option<(Vec<ValueKey>, Group)> = None;
+
+    for row in rows {
+        let scope = Scope::within(context, columns, &row);
+        let key = group_key(group_by, &scope)?;
+        let hash_key = collations.key(&key);
+
+        match &mut current {
+            Some((current_key, group)) if *current_key == hash_key => {
+                group.step(calls, &scope, bare_from_best)?;
+            }
+            _ => {
+                let mut group = Group::new(key, &collations, row.clone());
+                group.step(calls, &scope, bare_from_best)?;
+                if let Some((_, finished)) = current.replace((hash_key, group)) {
+                    groups.push(finished);
+                }
+            }
+        }
+    }
+
+    if let Some((_, group)) = current {
+        groups.push(group);
+    }
+    single_group(&mut groups, group_by, &collations, columns.len());
+
+    Ok(groups)
+}
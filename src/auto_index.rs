@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    collation::Collation,
+    column::{SerialValue, ValueKey},
+    hash_join::{hash_key, values_size},
+};
+
+/// A transient index that a join builds on the columns of a table that no index of the
+/// database leads. It maps the values of the key columns to the rows holding them, so it
+/// covers every column of the table, and lives until the statement finishes.
+#[derive(Debug, Default)]
+pub struct AutoIndex {
+    entries: BTreeMap<Vec<ValueKey>, Vec<Vec<SerialValue>>>,
+    /// The collating sequence each key column is compared with.
+    collations: Vec<Collation>,
+}
+
+impl AutoIndex {
+    /// Indexes `rows` on the values at the positions `columns`, each compared with its
+    /// collating sequence. Rows with a NULL key are left out since they never match. Returns
+    /// None when the rows take more than `memory_budget` bytes.
+    pub fn build(
+        rows: Vec<Vec<SerialValue>>,
+        columns: &[(usize, Collation)],
+        memory_budget: usize,
+    ) -> Option<Self> {
+        let mut index = Self {
+            entries: BTreeMap::new(),
+            collations: columns.iter().map(|(_, collation)| *collation).collect(),
+        };
+        let mut size = 0;
+
+        for row in rows {
+            let key: Vec<SerialValue> = columns.iter().map(|(i, _)| row[*i].clone()).collect();
+            let Some(key) = hash_key(&key, &index.collations) else {
+                continue;
+            };
+
+            size += values_size(&row);
+            if size > memory_budget {
+                return None;
+            }
+            index.entries.entry(key).or_default().push(row);
+        }
+
+        Some(index)
+    }
+
+    /// The rows whose key columns equal `key`.
+    pub fn search(&self, key: &[SerialValue]) -> &[Vec<SerialValue>] {
+        hash_key(key, &self.collations)
+            .and_then(|key| self.entries.get(&key))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{auto_index::AutoIndex, collation::Collation, column::SerialValue};
+
+    fn rows(keys: &[Option<i64>]) -> Vec<Vec<SerialValue>> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                vec![
+                    SerialValue::I64(i as i64),
+                    key.map_or(SerialValue::Null, SerialValue::I64),
+                ]
+            })
+            .collect()
+    }
+
+    fn ids(found: &[Vec<SerialValue>]) -> Vec<i64> {
+        found
+            .iter()
+            .map(|row| row[0].as_integer().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn test_search() {
+        let index = AutoIndex::build(
+            rows(&[Some(2), Some(1), None, Some(2)]),
+            &[(1, Collation::Binary)],
+            usize::MAX,
+        )
+        .expect("fits in memory");
+
+        assert_eq!(ids(index.search(&[SerialValue::I64(2)])), vec![0, 3]);
+        assert_eq!(ids(index.search(&[SerialValue::Float64(1.0)])), vec![1]);
+        assert!(index.search(&[SerialValue::I64(3)]).is_empty());
+        assert!(index.search(&[SerialValue::Null]).is_empty());
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let key = [(1, Collation::Binary)];
+        assert!(AutoIndex::build(rows(&[Some(1), Some(2)]), &key, 1).is_none());
+        assert!(AutoIndex::build(rows(&[None, None]), &key, 1).is_some());
+    }
+
+    #[test]
+    fn test_search_with_collation() {
+        let text = |txt: &str| SerialValue::String(txt.to_string());
+        let rows = vec![
+            vec![SerialValue::I64(0), text("abc")],
+            vec![SerialValue::I64(1), text("ABC")],
+            vec![SerialValue::I64(2), text("abd")],
+        ];
+        let index =
+            AutoIndex::build(rows, &[(1, Collation::NoCase)], usize::MAX).expect("fits in memory");
+
+        assert_eq!(ids(index.search(&[text("Abc")])), vec![0, 1]);
+        assert_eq!(ids(index.search(&[text("ABD")])), vec![2]);
+    }
+}
@@ -17,12 +17,12 @@ impl Cell {
 
         match page_type {
             PageType::LeafTable => {
-                let (npayload, bytes_read) = decode_varint(&bytes[idx..idx + 9])
-                    .context("decode varint for payload size")?;
+                let (npayload, bytes_read) =
+                    decode_varint(&bytes[idx..]).context("decode varint for payload size")?;
                 idx += bytes_read;
 
-                let (rowid, bytes_read) = decode_varint(&bytes[idx..idx + 9])
-                    .context("decode varint for payload size")?;
+                let (rowid, bytes_read) =
+                    decode_varint(&bytes[idx..]).context("decode varint for payload size")?;
                 idx += bytes_read;
                 //let end = if npayload as usize > bytes.len() {
                 //    bytes.len()
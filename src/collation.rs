@@ -0,0 +1,53 @@
+use std::{borrow::Cow, cmp::Ordering};
+
+use anyhow::bail;
+
+use crate::column::{SerialValue, ValueKey};
+
+/// The collating sequences built into SQLite. They only change how TEXT values compare.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Collation {
+    #[default]
+    Binary,
+    /// Folds the 26 ASCII upper case letters to lower case.
+    NoCase,
+    /// Ignores trailing spaces.
+    RTrim,
+}
+
+impl Collation {
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name.to_uppercase().as_str() {
+            "BINARY" => Ok(Collation::Binary),
+            "NOCASE" => Ok(Collation::NoCase),
+            "RTRIM" => Ok(Collation::RTrim),
+            _ => bail!("no such collation sequence: {name}"),
+        }
+    }
+
+    pub fn compare(&self, a: &SerialValue, b: &SerialValue) -> Ordering {
+        match (a, b) {
+            (SerialValue::String(a), SerialValue::String(b)) => {
+                self.fold(a).as_bytes().cmp(self.fold(b).as_bytes())
+            }
+            (a, b) => a.compare(b),
+        }
+    }
+
+    /// Like [`SerialValue::key`], but TEXT values that are equal under this collation share a
+    /// key.
+    pub fn key(&self, value: &SerialValue) -> ValueKey {
+        match value {
+            SerialValue::String(txt) => ValueKey::Text(self.fold(txt).into_owned()),
+            value => value.key(),
+        }
+    }
+
+    fn fold<'t>(&self, txt: &'t str) -> Cow<'t, str> {
+        match self {
+            Collation::Binary => Cow::Borrowed(txt),
+            Collation::NoCase => Cow::Owned(txt.to_ascii_lowercase()),
+            Collation::RTrim => Cow::Borrowed(txt.trim_end_matches(' ')),
+        }
+    }
+}
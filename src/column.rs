@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+
 use anyhow::anyhow;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -102,8 +104,454 @@ impl SerialValue {
         match self {
             SerialValue::Null => "".to_string(),
             SerialValue::String(txt) => txt.to_string(),
-            _ => todo!(),
+            SerialValue::Float64(num) => format_real(*num),
+            SerialValue::Blob(bytes) => String::from_utf8_lossy(bytes).to_string(),
+            value => value.as_integer().unwrap_or_default().to_string(),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, SerialValue::Null)
+    }
+
+    /// Returns the value of any of the integer serial types.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            SerialValue::I8(num) => Some(*num as i64),
+            SerialValue::I16(num) => Some(*num as i64),
+            SerialValue::I24(num) => Some(*num as i64),
+            SerialValue::I32(num) => Some(*num as i64),
+            SerialValue::I48(num) => Some(*num),
+            SerialValue::I64(num) => Some(*num),
+            SerialValue::Zero => Some(0),
+            SerialValue::One => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an integer or floating point serial type as a double.
+    pub fn as_real(&self) -> Option<f64> {
+        match self {
+            SerialValue::Float64(num) => Some(*num),
+            value => value.as_integer().map(|num| num as f64),
+        }
+    }
+
+    /// Converts a TEXT value that looks exactly like a number into an INTEGER or REAL, the way
+    /// NUMERIC affinity does. Every other value is returned unchanged.
+    pub fn numeric_affinity(&self) -> SerialValue {
+        if let SerialValue::String(txt) = self {
+            if let Some(value) = parse_numeric(txt) {
+                return value;
+            }
+        }
+
+        self.clone()
+    }
+
+    /// Converts the value the way a column of the given affinity stores it: TEXT turns numbers
+    /// into text, the numeric affinities turn numeric-looking text into numbers and REAL also
+    /// turns integers into doubles. BLOB and NULL values are never converted.
+    pub fn apply_affinity(&self, affinity: Affinity) -> SerialValue {
+        match (affinity, self) {
+            (_, SerialValue::Null | SerialValue::Blob(_)) | (Affinity::Blob, _) => self.clone(),
+            (Affinity::Text, SerialValue::String(_)) => self.clone(),
+            (Affinity::Text, value) => SerialValue::String(value.display()),
+            (Affinity::Real, value) => match value.numeric_affinity() {
+                SerialValue::String(txt) => SerialValue::String(txt),
+                value => SerialValue::Float64(value.as_real().unwrap_or_default()),
+            },
+            (Affinity::Integer | Affinity::Numeric, value) => value.numeric_affinity(),
+        }
+    }
+
+    /// Converts the value to a number for arithmetic. TEXT and BLOB values use their longest
+    /// numeric prefix, which is an INTEGER when it is written as one and fits. NULL stays NULL.
+    pub fn to_numeric(&self) -> SerialValue {
+        let bytes = match self {
+            SerialValue::String(txt) => txt.as_bytes(),
+            SerialValue::Blob(bytes) => bytes,
+            value => return value.clone(),
+        };
+
+        let (len, real) = numeric_prefix(bytes);
+        let prefix = String::from_utf8_lossy(&bytes[..len]);
+        let digits = prefix.trim_start().trim_start_matches(['-', '+']);
+        if len == 0 {
+            SerialValue::I64(0)
+        } else if digits.bytes().all(|b| b.is_ascii_digit()) {
+            match prefix.trim_start().parse::<i64>() {
+                Ok(num) => SerialValue::I64(num),
+                Err(_) => SerialValue::Float64(real),
+            }
+        } else {
+            SerialValue::Float64(real)
+        }
+    }
+
+    /// Converts the value the way `CAST(value AS type)` does for a type of the given affinity.
+    pub fn cast(&self, affinity: Affinity) -> SerialValue {
+        match (affinity, self) {
+            (_, SerialValue::Null) => SerialValue::Null,
+            (Affinity::Blob, SerialValue::Blob(_)) => self.clone(),
+            (Affinity::Blob, value) => SerialValue::Blob(value.display().into_bytes()),
+            (Affinity::Text, value) => SerialValue::String(value.display()),
+            (Affinity::Integer, value) => SerialValue::I64(value.to_integer()),
+            (Affinity::Real, value) => SerialValue::Float64(value.to_real()),
+            (Affinity::Numeric, SerialValue::String(_) | SerialValue::Blob(_)) => {
+                match self.to_numeric() {
+                    SerialValue::Float64(num)
+                        if num.fract() == 0.0
+                            && (-9.223372036854776e18..9.223372036854776e18).contains(&num) =>
+                    {
+                        SerialValue::I64(num as i64)
+                    }
+                    value => value,
+                }
+            }
+            (Affinity::Numeric, value) => value.clone(),
+        }
+    }
+
+    /// Converts the value to a double the way `sqlite3_value_double()` does: TEXT and BLOB
+    /// values use their longest numeric prefix and NULL becomes 0.0.
+    pub fn to_real(&self) -> f64 {
+        match self {
+            SerialValue::Null => 0.0,
+            SerialValue::String(txt) => numeric_prefix(txt.as_bytes()).1,
+            SerialValue::Blob(bytes) => numeric_prefix(bytes).1,
+            value => value.as_real().unwrap_or_default(),
+        }
+    }
+
+    /// Converts the value to an integer the way `sqlite3_value_int64()` does.
+    pub fn to_integer(&self) -> i64 {
+        match self {
+            SerialValue::Null => 0,
+            SerialValue::Float64(num) => real_to_integer(*num),
+            SerialValue::String(txt) => text_to_integer(txt.as_bytes()),
+            SerialValue::Blob(bytes) => text_to_integer(bytes),
+            value => value.as_integer().unwrap_or_default(),
+        }
+    }
+
+    /// Interprets the value as a boolean. NULL is neither true nor false.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            SerialValue::Null => None,
+            value => Some(value.to_real() != 0.0),
+        }
+    }
+
+    /// The storage classes sort NULL first, then INTEGER and REAL, then TEXT and BLOB last.
+    fn class_rank(&self) -> u8 {
+        match self {
+            SerialValue::Null => 0,
+            SerialValue::String(_) => 2,
+            SerialValue::Blob(_) => 3,
+            _ => 1,
+        }
+    }
+
+    /// Compares two values the way SQLite orders them under the BINARY collation.
+    pub fn compare(&self, other: &SerialValue) -> Ordering {
+        match (self, other) {
+            (SerialValue::String(a), SerialValue::String(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (SerialValue::Blob(a), SerialValue::Blob(b)) => a.cmp(b),
+            (SerialValue::Float64(a), SerialValue::Float64(b)) => {
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            }
+            (SerialValue::Float64(a), b) if b.class_rank() == 1 => {
+                compare_integer_real(b.as_integer().unwrap_or_default(), *a).reverse()
+            }
+            (a, SerialValue::Float64(b)) if a.class_rank() == 1 => {
+                compare_integer_real(a.as_integer().unwrap_or_default(), *b)
+            }
+            (a, b) if a.class_rank() == 1 && b.class_rank() == 1 => {
+                a.as_integer().cmp(&b.as_integer())
+            }
+            (a, b) => a.class_rank().cmp(&b.class_rank()),
+        }
+    }
+
+    /// Returns a hashable key under which values that SQLite considers equal collide, e.g.
+    /// `2` and `2.0`. NULLs share a single key.
+    pub fn key(&self) -> ValueKey {
+        match self {
+            SerialValue::Null => ValueKey::Null,
+            SerialValue::Float64(num) => {
+                if num.fract() == 0.0
+                    && *num >= -9.223372036854776e18
+                    && *num < 9.223372036854776e18
+                {
+                    ValueKey::Integer(*num as i64)
+                } else {
+                    ValueKey::Real(num.to_bits())
+                }
+            }
+            SerialValue::String(txt) => ValueKey::Text(txt.clone()),
+            SerialValue::Blob(bytes) => ValueKey::Blob(bytes.clone()),
+            value => ValueKey::Integer(value.as_integer().unwrap_or_default()),
+        }
+    }
+}
+
+impl From<bool> for SerialValue {
+    fn from(value: bool) -> Self {
+        if value {
+            SerialValue::One
+        } else {
+            SerialValue::Zero
+        }
+    }
+}
+
+impl From<i32> for SerialValue {
+    fn from(value: i32) -> Self {
+        SerialValue::I64(value.into())
+    }
+}
+
+impl From<i64> for SerialValue {
+    fn from(value: i64) -> Self {
+        SerialValue::I64(value)
+    }
+}
+
+impl From<f64> for SerialValue {
+    fn from(value: f64) -> Self {
+        SerialValue::Float64(value)
+    }
+}
+
+impl From<&str> for SerialValue {
+    fn from(value: &str) -> Self {
+        SerialValue::String(value.to_string())
+    }
+}
+
+impl From<String> for SerialValue {
+    fn from(value: String) -> Self {
+        SerialValue::String(value)
+    }
+}
+
+impl From<&[u8]> for SerialValue {
+    fn from(value: &[u8]) -> Self {
+        SerialValue::Blob(value.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for SerialValue {
+    fn from(value: Vec<u8>) -> Self {
+        SerialValue::Blob(value)
+    }
+}
+
+impl<T: Into<SerialValue>> From<Option<T>> for SerialValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(SerialValue::Null, Into::into)
+    }
+}
+
+/// The type affinity of a declared type, which decides how values are converted to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Blob,
+    Text,
+    Numeric,
+    Integer,
+    Real,
+}
+
+impl Affinity {
+    /// Determines the affinity of a type name by the substrings it contains, the way SQLite
+    /// does. A name matching none of them, such as `DECIMAL` or `DATE`, is NUMERIC.
+    pub fn from_type_name(name: &str) -> Self {
+        let name = name.to_uppercase();
+        if name.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"].iter().any(|s| name.contains(s)) {
+            Affinity::Text
+        } else if name.is_empty() || name.contains("BLOB") {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"].iter().any(|s| name.contains(s)) {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+}
+
+/// A hashable form of [`SerialValue`] used for grouping and deduplication.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValueKey {
+    Null,
+    Integer(i64),
+    Real(u64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Renders a double with 15 significant digits, like SQLite's `%!.15g`.
+pub fn format_real(num: f64) -> String {
+    if num.is_nan() {
+        return String::new();
+    }
+    if num.is_infinite() {
+        return if num > 0.0 { "Inf" } else { "-Inf" }.to_string();
+    }
+    if num == 0.0 {
+        return "0.0".to_string();
+    }
+
+    let scientific = format!("{:.14e}", num);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+
+    if !(-4..15).contains(&exponent) {
+        let mantissa = trim_fraction(mantissa);
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{mantissa}e{sign}{:02}", exponent.abs())
+    } else {
+        trim_fraction(&format!("{:.*}", (14 - exponent) as usize, num))
+    }
+}
+
+/// Drops trailing zeros after the decimal point but always keeps at least one digit there.
+fn trim_fraction(num: &str) -> String {
+    if !num.contains('.') {
+        return format!("{num}.0");
+    }
+
+    let trimmed = num.trim_end_matches('0');
+    if trimmed.ends_with('.') {
+        format!("{trimmed}0")
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn compare_integer_real(int: i64, real: f64) -> Ordering {
+    if real.is_nan() {
+        return Ordering::Greater;
+    }
+    if real < -9.223372036854776e18 {
+        return Ordering::Greater;
+    }
+    if real >= 9.223372036854776e18 {
+        return Ordering::Less;
+    }
+
+    let truncated = real as i64;
+    match int.cmp(&truncated) {
+        Ordering::Equal => (int as f64).partial_cmp(&real).unwrap_or(Ordering::Equal),
+        ordering => ordering,
+    }
+}
+
+/// Converts a double to an integer, saturating at the bounds of `i64`.
+pub fn real_to_integer(num: f64) -> i64 {
+    if num.is_nan() {
+        0
+    } else {
+        num as i64
+    }
+}
+
+/// Parses TEXT that is entirely a number, allowing surrounding whitespace.
+pub fn parse_numeric(txt: &str) -> Option<SerialValue> {
+    let trimmed = txt.trim_matches(|c: char| c.is_ascii_whitespace());
+    let (len, real) = numeric_prefix(trimmed.as_bytes());
+    if trimmed.is_empty() || len != trimmed.len() {
+        return None;
+    }
+
+    let is_integer = trimmed
+        .bytes()
+        .enumerate()
+        .all(|(i, b)| b.is_ascii_digit() || (i == 0 && (b == b'-' || b == b'+')));
+    if is_integer {
+        if let Ok(num) = trimmed.parse::<i64>() {
+            return Some(SerialValue::I64(num));
+        }
+    }
+
+    Some(SerialValue::Float64(real))
+}
+
+/// Returns the length in bytes and the value of the longest prefix of `bytes` that is a
+/// number, after skipping leading whitespace.
+pub fn numeric_prefix(bytes: &[u8]) -> (usize, f64) {
+    let mut idx = 0;
+    while idx < bytes.len() && bytes[idx].is_ascii_whitespace() {
+        idx += 1;
+    }
+    let start = idx;
+
+    if idx < bytes.len() && (bytes[idx] == b'-' || bytes[idx] == b'+') {
+        idx += 1;
+    }
+
+    let digits_start = idx;
+    while idx < bytes.len() && bytes[idx].is_ascii_digit() {
+        idx += 1;
+    }
+    let mut ndigits = idx - digits_start;
+
+    if idx < bytes.len() && bytes[idx] == b'.' {
+        let fraction_start = idx + 1;
+        let mut end = fraction_start;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        if ndigits > 0 || end > fraction_start {
+            ndigits += end - fraction_start;
+            idx = end;
+        }
+    }
+
+    if ndigits == 0 {
+        return (0, 0.0);
+    }
+
+    if idx < bytes.len() && (bytes[idx] == b'e' || bytes[idx] == b'E') {
+        let mut end = idx + 1;
+        if end < bytes.len() && (bytes[end] == b'-' || bytes[end] == b'+') {
+            end += 1;
+        }
+        let exponent_start = end;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        if end > exponent_start {
+            idx = end;
+        }
+    }
+
+    let txt = String::from_utf8_lossy(&bytes[start..idx]);
+    let txt = txt.strip_suffix('.').unwrap_or(&txt);
+    (idx, txt.parse().unwrap_or_default())
+}
+
+/// Converts the integer prefix of TEXT to an integer, saturating on overflow.
+fn text_to_integer(bytes: &[u8]) -> i64 {
+    let (len, real) = numeric_prefix(bytes);
+    let prefix = String::from_utf8_lossy(&bytes[..len]);
+    let prefix = prefix.trim_start();
+
+    let end = prefix
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && (*c == '-' || *c == '+'))))
+        .map(|(i, _)| i)
+        .unwrap_or(prefix.len());
+
+    match prefix[..end].parse::<i64>() {
+        Ok(num) => num,
+        Err(_) if end > 1 || (end == 1 && prefix.as_bytes()[0].is_ascii_digit()) => {
+            real_to_integer(real)
         }
+        Err(_) => 0,
     }
 }
 
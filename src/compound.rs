@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, bail};
+use sqlparser::ast::{Expr, OrderByExpr, SetExpr, SetOperator, SetQuantifier};
+
+use crate::{
+    collation::Collation,
+    column::{SerialValue, ValueKey},
+    executor::{Executor, ResultSet},
+    expr::Context,
+    order::{nth, ordinal, sort, strip_collate, SortTerm},
+};
+
+impl Executor<'_> {
+    /// Runs a chain of UNION, UNION ALL, INTERSECT and EXCEPT operators. SQLite gives them all
+    /// the same precedence and applies them from left to right.
+    pub fn compound(&self, body: &SetExpr, context: Context) -> anyhow::Result<ResultSet> {
+        let mut operands = Vec::new();
+        let first = flatten(body, &mut operands);
+
+        let mut result = self.set_expr(first, context)?;
+        for (op, set_quantifier, right) in operands {
+            let right = self.set_expr(right, context)?;
+            result = combine(op, set_quantifier, result, right)?;
+        }
+
+        Ok(result)
+    }
+}
+
+/// Lists the operands of a chain of compound operators in the order they appear, whichever way
+/// the parser grouped them. Returns the first operand.
+pub fn flatten<'q>(
+    body: &'q SetExpr,
+    operands: &mut Vec<(&'q SetOperator, &'q SetQuantifier, &'q SetExpr)>,
+) -> &'q SetExpr {
+    let SetExpr::SetOperation {
+        op,
+        set_quantifier,
+        left,
+        right,
+    } = body
+    else {
+        return body;
+    };
+
+    let first = flatten(left, operands);
+    let mut rest = Vec::new();
+    operands.push((op, set_quantifier, flatten(right, &mut rest)));
+    operands.extend(rest);
+
+    first
+}
+
+/// Applies one compound operator. Except for UNION ALL, the result has no duplicates and is
+/// ordered by all of its columns, like SQLite's is.
+fn combine(
+    op: &SetOperator,
+    set_quantifier: &SetQuantifier,
+    left: ResultSet,
+    right: ResultSet,
+) -> anyhow::Result<ResultSet> {
+    let all = match set_quantifier {
+        SetQuantifier::All => true,
+        SetQuantifier::Distinct | SetQuantifier::None => false,
+        set_quantifier => bail!("unsupported compound operator: {op} {set_quantifier}"),
+    };
+    let name = if all {
+        format!("{op} ALL")
+    } else {
+        op.to_string()
+    };
+    if all && *op != SetOperator::Union {
+        bail!("unsupported compound operator: {name}");
+    }
+
+    if left.columns.len() != right.columns.len() {
+        bail!(
+            "SELECTs to the left and right of {name} do not have the same number of result columns"
+        );
+    }
+
+    // A column compares with the collation of the left select, if it has one.
+    let collations: Vec<Option<Collation>> = left
+        .collations
+        .iter()
+        .zip(&right.collations)
+        .map(|(left, right)| left.or(*right))
+        .collect();
+    let key = |row: &[SerialValue]| -> Vec<ValueKey> {
+        row.iter()
+            .zip(&collations)
+            .map(|(value, collation)| collation.unwrap_or_default().key(value))
+            .collect()
+    };
+
+    let mut rows = match op {
+        SetOperator::Union if all => {
+            let mut rows = left.rows;
+            rows.extend(right.rows);
+            rows
+        }
+        SetOperator::Union => {
+            // Of two duplicate rows, the one that comes later is kept.
+            let mut positions = HashMap::new();
+            let mut rows = Vec::new();
+            for row in left.rows.into_iter().chain(right.rows) {
+                match positions.get(&key(&row)) {
+                    Some(&i) => rows[i] = row,
+                    None => {
+                        positions.insert(key(&row), rows.len());
+                        rows.push(row);
+                    }
+                }
+            }
+            rows
+        }
+        SetOperator::Intersect | SetOperator::Except => {
+            let keep = *op == SetOperator::Intersect;
+            let right: HashSet<Vec<ValueKey>> = right.rows.iter().map(|row| key(row)).collect();
+            let mut seen = HashSet::new();
+            left.rows
+                .into_iter()
+                .filter(|row| {
+                    let key = key(row);
+                    right.contains(&key) == keep && seen.insert(key)
+                })
+                .collect()
+        }
+    };
+
+    if !all {
+        let terms: Vec<SortTerm> = collations
+            .iter()
+            .enumerate()
+            .map(|(i, collation)| SortTerm::ascending(i, collation.unwrap_or_default()))
+            .collect();
+        sort(&mut rows, &terms);
+    }
+
+    Ok(ResultSet {
+        columns: left.columns,
+        rows,
+        collations,
+        decl_types: left.decl_types,
+    })
+}
+
+/// Resolves the ORDER BY terms of a compound select. Each term has to be the number or the name
+/// of a result column.
+pub fn compound_order(
+    result: &ResultSet,
+    order_by: &[OrderByExpr],
+) -> anyhow::Result<Vec<SortTerm>> {
+    order_by
+        .iter()
+        .enumerate()
+        .map(|(i, order)| {
+            let (expr, collation) = strip_collate(&order.expr)?;
+            let column = match ordinal(expr, i, result.columns.len())? {
+                Some(column) => column,
+                None => {
+                    let name = match expr {
+                        Expr::Identifier(ident) => ident.value.clone(),
+                        expr => expr.to_string(),
+                    };
+                    result
+                        .columns
+                        .iter()
+                        .position(|column| column.eq_ignore_ascii_case(&name))
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "{} ORDER BY term does not match any column in the result set",
+                                nth(i + 1)
+                            )
+                        })?
+                }
+            };
+
+            let collation = collation.or(result.collations[column]).unwrap_or_default();
+            Ok(SortTerm::new(column, order, collation))
+        })
+        .collect()
+}
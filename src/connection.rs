@@ -0,0 +1,60 @@
+use std::{path::Path, rc::Rc};
+
+use crate::{
+    database::Database,
+    executor::Executor,
+    row::Row,
+    schema::Schema,
+    statement::{Params, Statement},
+};
+
+/// An open database file, read into memory with its schema.
+pub struct Connection {
+    db: Database,
+    schema: Rc<Schema>,
+}
+
+impl Connection {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let db = Database::read_file(path)?;
+        let schema = Rc::new(Schema::read(&db)?);
+
+        Ok(Self { db, schema })
+    }
+
+    /// Parses a statement so that it can be run many times with different parameters.
+    pub fn prepare(&self, sql: &str) -> anyhow::Result<Statement<'_>> {
+        Executor::with_schema(&self.db, Rc::clone(&self.schema)).prepare(sql)
+    }
+
+    /// Runs a statement with `params` and converts its first row with `f`.
+    pub fn query_row<T, F>(&self, sql: &str, params: impl Params, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&Row) -> anyhow::Result<T>,
+    {
+        self.prepare(sql)?.query_row(params, f)
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.db.page_size()
+    }
+
+    /// The number of cells on the first page, the root of `sqlite_schema`, which is what the
+    /// sqlite3 shell's `.dbinfo` reports as the number of tables.
+    pub fn schema_cell_count(&self) -> Option<u16> {
+        self.db.pages.first().map(|page| page.btree_header.ncells())
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// The names of the tables, without the internal `sqlite_` ones.
+    pub fn table_names(&self) -> impl Iterator<Item = &str> {
+        self.schema
+            .tables
+            .iter()
+            .map(|table| table.name.as_str())
+            .filter(|name| !name.starts_with("sqlite_"))
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+    ops::ControlFlow,
+    rc::Rc,
+};
+
+use anyhow::bail;
+use sqlparser::ast::{visit_relations, Cte, SetExpr, SetOperator, SetQuantifier};
+
+use crate::{
+    column::SerialValue,
+    compound::compound_order,
+    executor::{Executor, ResultSet},
+    expr::Context,
+    order::{limit_offset, SortTerm},
+};
+
+/// The common table expressions of a WITH clause that are visible to a query.
+pub struct CteScope<'a> {
+    pub ctes: &'a [Cte],
+    /// How many of `ctes` can be referenced. A CTE only sees the ones defined before it, and
+    /// itself when it is recursive.
+    pub visible: usize,
+    /// The row of a recursive CTE that its recursive part is currently run for.
+    pub working: Option<(&'a str, &'a ResultSet)>,
+    /// The WITH clauses of the enclosing queries.
+    pub parent: Option<&'a CteScope<'a>>,
+}
+
+/// What a table name refers to when a WITH clause in scope defines it.
+pub enum CteRef<'a> {
+    /// The row a recursive CTE is currently run for.
+    Working(&'a ResultSet),
+    /// The CTE at this position of a WITH clause.
+    Defined(&'a CteScope<'a>, usize),
+}
+
+/// Finds the CTE called `name` in the innermost WITH clause that defines it.
+pub fn find_cte<'a>(name: &str, context: Context<'a>) -> Option<CteRef<'a>> {
+    let mut scope = context.ctes;
+    while let Some(ctes) = scope {
+        if let Some((cte_name, working)) = ctes.working {
+            if cte_name.eq_ignore_ascii_case(name) {
+                return Some(CteRef::Working(working));
+            }
+        }
+
+        let position = ctes.ctes[..ctes.visible]
+            .iter()
+            .position(|cte| cte.alias.name.value.eq_ignore_ascii_case(name));
+        if let Some(i) = position {
+            return Some(CteRef::Defined(ctes, i));
+        }
+
+        scope = ctes.parent;
+    }
+
+    None
+}
+
+/// A row waiting in the queue of a recursive CTE. The queue hands out the row that sorts
+/// first by the ORDER BY of the CTE, and among equal rows the one queued first.
+struct Queued<'t> {
+    row: Vec<SerialValue>,
+    seq: usize,
+    terms: &'t [SortTerm],
+}
+
+impl Queued<'_> {
+    fn priority(&self, other: &Self) -> Ordering {
+        self.terms
+            .iter()
+            .map(|term| term.compare(&other.row, &self.row))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialEq for Queued<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority(other).is_eq()
+    }
+}
+
+impl Eq for Queued<'_> {}
+
+impl PartialOrd for Queued<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority(other)
+    }
+}
+
+impl<'a> Executor<'a> {
+    /// Computes the rows of the CTE called `name`, if a WITH clause in scope defines one.
+    pub fn cte(&self, name: &str, context: Context) -> anyhow::Result<Option<Rc<ResultSet>>> {
+        match find_cte(name, context) {
+            Some(CteRef::Working(working)) => Ok(Some(Rc::new(working.clone()))),
+            Some(CteRef::Defined(ctes, i)) => self.materialize(ctes, i, context).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Computes the rows of a CTE once per statement. A CTE that refers to the row of an
+    /// enclosing query, or to the working row of a recursive CTE, runs again every time.
+    fn materialize(
+        &self,
+        ctes: &CteScope,
+        i: usize,
+        context: Context,
+    ) -> anyhow::Result<Rc<ResultSet>> {
+        let cte = &ctes.ctes[i];
+        let key = cte as *const Cte;
+        if let Some(result) = self.cte_cache.borrow().get(&key) {
+            return Ok(result.clone());
+        }
+
+        // Only the CTEs before this one are visible to its body.
+        let before = CteScope {
+            ctes: ctes.ctes,
+            visible: i,
+            working: None,
+            parent: ctes.parent,
+        };
+        let correlated = Cell::new(false);
+        let before_context = Context {
+            ctes: Some(&before),
+            correlated: Some(&correlated),
+            ..context
+        };
+
+        let result = Rc::new(match recursive_parts(cte) {
+            Some((initial, recursive, distinct)) => {
+                let initial = self.set_expr(initial, before_context)?;
+                self.recurse(ctes, i, initial, recursive, distinct, before_context)?
+            }
+            None => rename(cte, self.query(&cte.query, before_context)?)?,
+        });
+
+        if correlated.get() {
+            if let Some(outer) = context.correlated {
+                outer.set(true);
+            }
+        } else if !sees_working_row(ctes.parent) {
+            self.cte_cache.borrow_mut().insert(key, result.clone());
+        }
+
+        Ok(result)
+    }
+
+    /// Runs a recursive CTE the way SQLite does: the rows of the initial select go into a
+    /// queue, and each row taken from the queue is output and then used as the only row of the
+    /// CTE while the recursive select runs, whose rows are added to the queue. With UNION, rows
+    /// that were already queued once are dropped. The ORDER BY of the CTE decides which row
+    /// leaves the queue next, and the queue stops once LIMIT rows have been output after the
+    /// OFFSET rows that are skipped.
+    fn recurse(
+        &self,
+        ctes: &CteScope,
+        i: usize,
+        initial: ResultSet,
+        recursive: &SetExpr,
+        distinct: bool,
+        context: Context,
+    ) -> anyhow::Result<ResultSet> {
+        let cte = &ctes.ctes[i];
+        let name = cte.alias.name.value.as_str();
+        // ORDER BY names the columns of the initial select, not those of the CTE.
+        let terms = compound_order(&initial, &cte.query.order_by)?;
+        let (limit, mut offset) = limit_offset(&cte.query, context)?;
+        let ResultSet {
+            columns,
+            collations,
+            decl_types,
+            rows: initial,
+        } = rename(cte, initial)?;
+
+        let mut rows = Vec::new();
+        if limit == Some(0) {
+            return Ok(ResultSet {
+                columns,
+                rows,
+                collations,
+                decl_types,
+            });
+        }
+
+        let mut seen = HashSet::new();
+        let mut queue = BinaryHeap::new();
+        let mut seq = 0;
+        let mut enqueue = |row: Vec<SerialValue>, queue: &mut BinaryHeap<_>| {
+            if !distinct || seen.insert(row.iter().map(|value| value.key()).collect::<Vec<_>>()) {
+                seq += 1;
+                queue.push(Queued {
+                    row,
+                    seq,
+                    terms: &terms,
+                });
+            }
+        };
+        for row in initial {
+            enqueue(row, &mut queue);
+        }
+
+        while let Some(Queued { row, .. }) = queue.pop() {
+            if offset > 0 {
+                offset -= 1;
+            } else {
+                rows.push(row.clone());
+                if Some(rows.len()) == limit {
+                    break;
+                }
+            }
+
+            let working = ResultSet {
+                columns: columns.clone(),
+                rows: vec![row],
+                collations: collations.clone(),
+                decl_types: decl_types.clone(),
+            };
+            let scope = CteScope {
+                ctes: ctes.ctes,
+                visible: i + 1,
+                working: Some((name, &working)),
+                parent: ctes.parent,
+            };
+            let result = self.set_expr(
+                recursive,
+                Context {
+                    ctes: Some(&scope),
+                    ..context
+                },
+            )?;
+
+            if result.columns.len() != columns.len() {
+                bail!("SELECTs to the left and right of UNION do not have the same number of result columns");
+            }
+            for row in result.rows {
+                enqueue(row, &mut queue);
+            }
+        }
+
+        Ok(ResultSet {
+            columns,
+            rows,
+            collations,
+            decl_types,
+        })
+    }
+}
+
+/// Checks whether a WITH clause in scope is running the recursive select of a CTE, whose
+/// working row can change what the CTEs inside it return.
+fn sees_working_row(mut scope: Option<&CteScope>) -> bool {
+    while let Some(ctes) = scope {
+        if ctes.working.is_some() {
+            return true;
+        }
+        scope = ctes.parent;
+    }
+
+    false
+}
+
+/// Splits a recursive CTE into its initial select and its recursive select, and tells whether
+/// rows that were already produced are dropped, as with UNION. Returns None when the CTE does
+/// not refer to itself.
+pub fn recursive_parts(cte: &Cte) -> Option<(&SetExpr, &SetExpr, bool)> {
+    match cte.query.body.as_ref() {
+        SetExpr::SetOperation {
+            op: SetOperator::Union,
+            set_quantifier,
+            left,
+            right,
+        } if cte.query.with.is_none() && refers_to(right, &cte.alias.name.value) => {
+            Some((left, right, !matches!(set_quantifier, SetQuantifier::All)))
+        }
+        _ => None,
+    }
+}
+
+/// Names the columns of a CTE after the column list of its definition, if it has one.
+pub fn rename(cte: &Cte, mut result: ResultSet) -> anyhow::Result<ResultSet> {
+    if cte.alias.columns.is_empty() {
+        return Ok(result);
+    }
+
+    if cte.alias.columns.len() != result.columns.len() {
+        bail!(
+            "table {} has {} values for {} columns",
+            cte.alias.name.value,
+            result.columns.len(),
+            cte.alias.columns.len()
+        );
+    }
+    result.columns = cte
+        .alias
+        .columns
+        .iter()
+        .map(|column| column.value.clone())
+        .collect();
+
+    Ok(result)
+}
+
+/// Checks whether a table called `name` appears anywhere in `body`.
+fn refers_to(body: &SetExpr, name: &str) -> bool {
+    let flow = visit_relations(body, |relation| match relation.0.last() {
+        Some(ident) if ident.value.eq_ignore_ascii_case(name) => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    });
+
+    flow.is_break()
+}
@@ -1,15 +1,19 @@
 use std::{
-    collections::HashSet,
+    cmp::Ordering,
+    collections::HashMap,
     fs::{self},
+    ops::RangeInclusive,
     path::Path,
 };
 
+use anyhow::{bail, Context};
+
 use crate::{
     cell::Cell,
+    collation::Collation,
     column::SerialValue,
     page::{Page, PageType},
     record::Record,
-    sql::Sql,
 };
 
 #[derive(Debug, Clone)]
@@ -45,230 +49,462 @@ impl Database {
         self.header.page_size
     }
 
-    pub fn read_index(
+    /// Reads every row of the table b-tree rooted at page `num` in rowid order.
+    pub fn read_table(&self, num: usize) -> anyhow::Result<Vec<(i64, Record)>> {
+        self.table_cursor(num).collect()
+    }
+
+    /// Walks the table b-tree rooted at page `num` in rowid order, reading a leaf page only when
+    /// the rows before it have been taken.
+    pub fn table_cursor(&self, num: usize) -> TableCursor<'_> {
+        TableCursor {
+            db: self,
+            page_idxes: vec![num - 1],
+            cells: Vec::new().into_iter(),
+        }
+    }
+
+    /// Reads the rows of the table b-tree rooted at page `num` whose rowids are in `ids`, which
+    /// must be sorted.
+    pub fn read_ids_from_table(
         &self,
         num: usize,
-        _index_statement: &Sql,
-        select_statement: &Sql,
-        rowids: &mut HashSet<i64>,
-    ) {
-        let mut page_idxes: Vec<usize> = vec![num - 1];
-        let select_query: Vec<&str> = select_statement
-            .selection
-            .values()
-            .map(|val| val.as_str())
-            .collect();
+        ids: &[i64],
+    ) -> anyhow::Result<Vec<(i64, Record)>> {
+        let mut rows = Vec::new();
+        let mut page_idxes: Vec<(usize, &[i64])> = vec![(num - 1, ids)];
+
+        while let Some((page_idx, ids)) = page_idxes.pop() {
+            if ids.is_empty() {
+                continue;
+            }
 
-        while let Some(page_idx) = page_idxes.pop() {
             if let Some(page) = self.pages.get(page_idx) {
-                let cells: Vec<Cell> = page
-                    .cell_offsets
-                    .iter()
-                    .map(|offset| {
-                        Cell::from_bytes(page.page_type(), *offset as usize, &page.buffer)
-                            .expect("construct cell")
-                    })
-                    .collect();
+                let cells = page.cells()?;
+
                 match page.page_type() {
-                    PageType::InteriorIndex => {
+                    PageType::InteriorTable => {
+                        let mut children = Vec::new();
+                        let mut ids = ids;
                         for cell in cells.iter() {
-                            let page_num_left_child = cell.page_number_left_child.unwrap();
-                            let record = cell.record.clone().unwrap();
-
-                            if let SerialValue::String(country) = record.columns[0].data() {
-                                match country.as_str().cmp(select_query[0]) {
-                                    std::cmp::Ordering::Less => {
-                                        page_idxes.push(page_num_left_child as usize - 1);
-                                    }
-                                    std::cmp::Ordering::Greater => {
-                                        if let Some(num) = page.btree_header.right_most_pointer {
-                                            page_idxes.push(num as usize - 1);
-                                        }
-                                    }
-                                    std::cmp::Ordering::Equal => {
-                                        let num = match record.columns[1].data() {
-                                            SerialValue::I8(num) => *num as i64,
-                                            SerialValue::I16(num) => *num as i64,
-                                            SerialValue::I24(num) => *num as i64,
-                                            SerialValue::I32(num) => *num as i64,
-                                            _ => todo!(),
-                                        };
-
-                                        rowids.insert(num);
-                                    }
-                                }
-                            };
+                            let page_num_left_child = cell
+                                .page_number_left_child
+                                .context("interior cell without left child/rowid")?;
+                            let key = cell
+                                .rowid
+                                .context("interior cell without left child/rowid")?;
+
+                            let split_at = ids.split_at(ids.partition_point(|id| *id <= key));
+                            let left_ids = split_at.0; // Ids to the left
+                            ids = split_at.1; // Ids to the right
+
+                            if !left_ids.is_empty() {
+                                children.push((page_num_left_child as usize - 1, left_ids));
+                            }
                         }
-                    }
 
-                    PageType::LeafIndex => {
-                        for cell in cells.iter() {
-                            let record = cell.record.clone().unwrap();
-
-                            if let SerialValue::String(country) = record.columns[0].data() {
-                                if select_query[0] == country {
-                                    let num = match record.columns[1].data() {
-                                        SerialValue::I8(num) => *num as i64,
-                                        SerialValue::I16(num) => *num as i64,
-                                        SerialValue::I24(num) => *num as i64,
-                                        SerialValue::I32(num) => *num as i64,
-                                        _ => todo!(),
-                                    };
-
-                                    rowids.insert(num);
+                        if let Some(num) = page.btree_header.right_most_pointer {
+                            children.push((num as usize - 1, ids));
+                        }
+
+                        page_idxes.extend(children.into_iter().rev());
+                    }
+                    PageType::LeafTable => {
+                        for cell in cells {
+                            if let (Some(rowid), Some(record)) = (cell.rowid, cell.record) {
+                                if ids.binary_search(&rowid).is_ok() {
+                                    rows.push((rowid, record));
                                 }
-                            };
+                            }
                         }
                     }
-                    _ => {}
+                    _ => bail!("page {} is not a table b-tree page", page_idx + 1),
                 }
             }
         }
+
+        Ok(rows)
     }
 
-    pub fn read_table(
+    /// Reads the rows of the table b-tree rooted at page `num` whose rowids lie in `rowids`, in
+    /// rowid order. Only the children whose key range overlaps `rowids` are visited.
+    pub fn read_table_range(
         &self,
         num: usize,
-        select_statement: &Sql,
-        fields: Vec<(usize, String)>,
-        row_set: &mut HashSet<String>,
-        rowid_set: &mut HashSet<i64>,
-    ) {
+        rowids: RangeInclusive<i64>,
+    ) -> anyhow::Result<Vec<(i64, Record)>> {
+        let mut rows = Vec::new();
+        if rowids.is_empty() {
+            return Ok(rows);
+        }
         let mut page_idxes: Vec<usize> = vec![num - 1];
+
         while let Some(page_idx) = page_idxes.pop() {
             if let Some(page) = self.pages.get(page_idx) {
-                let cells: Vec<Cell> = page
-                    .cell_offsets
-                    .iter()
-                    .map(|offset| {
-                        Cell::from_bytes(page.page_type(), *offset as usize, &page.buffer)
-                            .expect("construct cell")
-                    })
-                    .collect();
-                let cell_len = page.cell_offsets.len();
-
-                if !select_statement.selection.is_empty() {
-                    for cell in cells.iter().take(cell_len) {
-                        if let Some(page_num_left_child) = cell.page_number_left_child {
-                            page_idxes.push(page_num_left_child as usize - 1);
+                let cells = page.cells()?;
+
+                match page.page_type() {
+                    PageType::InteriorTable => {
+                        // A left child holds the rowids above the previous key up to its own key.
+                        let mut children = Vec::new();
+                        let mut previous_key = None;
+                        for cell in cells.iter() {
+                            let page_num_left_child = cell
+                                .page_number_left_child
+                                .context("interior cell without left child/rowid")?;
+                            let key = cell
+                                .rowid
+                                .context("interior cell without left child/rowid")?;
+
+                            if key >= *rowids.start()
+                                && previous_key.is_none_or(|previous| previous < *rowids.end())
+                            {
+                                children.push(page_num_left_child as usize - 1);
+                            }
+                            previous_key = Some(key);
                         }
 
-                        if let Some(record) = &cell.record {
-                            select_statement.print_rows(
-                                record,
-                                &cell.rowid,
-                                &fields,
-                                row_set,
-                                rowid_set,
-                            );
+                        if let Some(num) = page.btree_header.right_most_pointer {
+                            if previous_key.is_none_or(|previous| previous < *rowids.end()) {
+                                children.push(num as usize - 1);
+                            }
                         }
-                    }
 
-                    if let Some(num) = page.btree_header.right_most_pointer {
-                        page_idxes.push(num as usize - 1);
+                        page_idxes.extend(children.into_iter().rev());
                     }
-                } else {
-                    for i in 0..cell_len {
-                        if let Ok((_, Some(record))) = page.read_cell(i as u16) {
-                            let mut values = Vec::new();
-
-                            for (field_idx, _field_name) in &fields {
-                                values.push(record.columns[*field_idx].data().display());
+                    PageType::LeafTable => {
+                        for cell in cells {
+                            if let (Some(rowid), Some(record)) = (cell.rowid, cell.record) {
+                                if rowids.contains(&rowid) {
+                                    rows.push((rowid, record));
+                                }
                             }
-                            println!("{}", values.join("|"));
                         }
                     }
+                    _ => bail!("page {} is not a table b-tree page", page_idx + 1),
                 }
             }
         }
+
+        Ok(rows)
     }
 
-    pub fn read_ids_from_table(
+    /// Reads the rows of the table b-tree rooted at page `num` whose rowids are in `ids`, in the
+    /// order of `ids`.
+    pub fn read_ids_in_order(&self, num: usize, ids: &[i64]) -> anyhow::Result<Vec<(i64, Record)>> {
+        let mut sorted = ids.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let mut rows: HashMap<i64, Record> = self
+            .read_ids_from_table(num, &sorted)?
+            .into_iter()
+            .collect();
+        Ok(ids
+            .iter()
+            .filter_map(|id| Some((*id, rows.remove(id)?)))
+            .collect())
+    }
+
+    /// Finds the entries of the index b-tree rooted at page `num` whose leading columns equal
+    /// `key`, descending only into the pages that can hold them. `desc` gives the sort order of
+    /// each key column and `collations` the collating sequence it is ordered by.
+    pub fn search_index(
         &self,
         num: usize,
-        select_statement: &Sql,
-        fields: Vec<(usize, String)>,
-        row_set: &mut HashSet<String>,
-        rowid_set: &mut HashSet<i64>,
-        ids: &[i64],
-    ) {
-        let mut page_idxes: Vec<usize> = vec![num - 1];
-        while let Some(page_idx) = page_idxes.pop() {
-            if let Some(page) = self.pages.get(page_idx) {
-                let cells: Vec<Cell> = page
-                    .cell_offsets
-                    .iter()
-                    .map(|offset| {
-                        Cell::from_bytes(page.page_type(), *offset as usize, &page.buffer)
-                            .expect("construct cell")
-                    })
-                    .collect();
-                let cell_len = page.cell_offsets.len();
-
-                if !select_statement.selection.is_empty() {
-                    match page.page_type() {
-                        PageType::InteriorTable => {
-                            let mut ids = ids;
-                            for cell in cells.iter().take(cell_len) {
-                                let page_num_left_child = cell.page_number_left_child.unwrap();
-                                let key = cell.rowid.unwrap();
-
-                                let split_at = ids.split_at(ids.partition_point(|id| *id < key));
-                                let left_ids = split_at.0; // Ids to the left
-                                ids = split_at.1; // Ids to the right
-
-                                if !left_ids.is_empty() {
-                                    page_idxes.push(page_num_left_child as usize - 1);
-                                }
-                            }
+        key: &[SerialValue],
+        desc: &[bool],
+        collations: &[Collation],
+    ) -> anyhow::Result<Vec<Record>> {
+        self.search_index_range(num, &|entry| {
+            compare_index_key(entry, key, desc, collations)
+        })
+    }
 
-                            if ids.is_empty() {
-                                continue;
-                            }
+    /// Finds the entries of the index b-tree rooted at page `num` that lie in a range, in index
+    /// order. `position` tells whether an entry sorts before the range, in it or after it. The
+    /// search descends to the first entry of the range and reads forward until an entry sorts
+    /// after it, so only the pages that can hold the range are visited.
+    pub fn search_index_range(
+        &self,
+        num: usize,
+        position: &dyn Fn(&Record) -> Ordering,
+    ) -> anyhow::Result<Vec<Record>> {
+        let mut records = Vec::new();
+        self.search_index_page(num - 1, position, usize::MAX, &mut records)?;
 
-                            if let Some(num) = page.btree_header.right_most_pointer {
-                                page_idxes.push(num as usize - 1);
-                            }
+        Ok(records)
+    }
+
+    /// Like [`Database::search_index_range`], but stops at the first entry of the range.
+    pub fn first_index_entry(
+        &self,
+        num: usize,
+        position: &dyn Fn(&Record) -> Ordering,
+    ) -> anyhow::Result<Option<Record>> {
+        let mut records = Vec::new();
+        self.search_index_page(num - 1, position, 1, &mut records)?;
+
+        Ok(records.pop())
+    }
+
+    /// Returns false once an entry after the range has been seen, or `limit` entries have been
+    /// found.
+    fn search_index_page(
+        &self,
+        page_idx: usize,
+        position: &dyn Fn(&Record) -> Ordering,
+        limit: usize,
+        records: &mut Vec<Record>,
+    ) -> anyhow::Result<bool> {
+        let Some(page) = self.pages.get(page_idx) else {
+            bail!("page {} is out of range", page_idx + 1);
+        };
+
+        match page.page_type() {
+            PageType::InteriorIndex => {
+                for cell in page.cells()? {
+                    let (Some(page_num_left_child), Some(record)) =
+                        (cell.page_number_left_child, cell.record)
+                    else {
+                        continue;
+                    };
+
+                    // Everything left of an entry sorts at or before it.
+                    let ordering = position(&record);
+                    if ordering.is_lt() {
+                        continue;
+                    }
+
+                    if !self.search_index_page(
+                        page_num_left_child as usize - 1,
+                        position,
+                        limit,
+                        records,
+                    )? || ordering.is_gt()
+                    {
+                        return Ok(false);
+                    }
+                    records.push(record);
+                    if records.len() >= limit {
+                        return Ok(false);
+                    }
+                }
+                if let Some(num) = page.btree_header.right_most_pointer {
+                    return self.search_index_page(num as usize - 1, position, limit, records);
+                }
+            }
+            PageType::LeafIndex => {
+                for cell in page.cells()? {
+                    if let Some(record) = cell.record {
+                        match position(&record) {
+                            Ordering::Less => continue,
+                            Ordering::Equal => records.push(record),
+                            Ordering::Greater => return Ok(false),
                         }
-                        PageType::LeafTable => {
-                            let records: Vec<(i64, Record)> = cells
-                                .iter()
-                                .filter(|cell| {
-                                    let rowid = cell.rowid.unwrap();
-                                    ids.binary_search(&rowid).is_ok()
-                                })
-                                .map(|cell| (cell.rowid.unwrap(), cell.record.clone().unwrap()))
-                                .collect();
-
-                            for (rowid, record) in records {
-                                select_statement.print_rows_by_rowid(
-                                    &record,
-                                    &Some(rowid),
-                                    &fields,
-                                    row_set,
-                                    rowid_set,
-                                );
-                            }
+                        if records.len() >= limit {
+                            return Ok(false);
                         }
-                        _ => {}
                     }
-                } else {
-                    for i in 0..cell_len {
-                        if let Ok((_, Some(record))) = page.read_cell(i as u16) {
-                            let mut values = Vec::new();
+                }
+            }
+            _ => bail!("page {} is not an index b-tree page", page_idx + 1),
+        }
 
-                            for (field_idx, _field_name) in &fields {
-                                values.push(record.columns[*field_idx].data().display());
-                            }
-                            println!("{}", values.join("|"));
+        Ok(true)
+    }
+
+    /// Reads the last entry of the index b-tree rooted at page `num`, in index order, by
+    /// following the right-most pointers down to a leaf.
+    pub fn last_index_entry(&self, num: usize) -> anyhow::Result<Option<Record>> {
+        let mut page_idx = num - 1;
+        let mut last = None;
+
+        loop {
+            let Some(page) = self.pages.get(page_idx) else {
+                bail!("page {} is out of range", page_idx + 1);
+            };
+            let cells = page.cells()?;
+
+            match page.page_type() {
+                PageType::InteriorIndex => {
+                    if let Some(record) = cells.into_iter().last().and_then(|cell| cell.record) {
+                        last = Some(record);
+                    }
+                    match page.btree_header.right_most_pointer {
+                        Some(num) => page_idx = num as usize - 1,
+                        None => return Ok(last),
+                    }
+                }
+                PageType::LeafIndex => {
+                    return Ok(cells
+                        .into_iter()
+                        .last()
+                        .and_then(|cell| cell.record)
+                        .or(last));
+                }
+                _ => bail!("page {} is not an index b-tree page", page_idx + 1),
+            }
+        }
+    }
+
+    /// Reads the first or the last row of the table b-tree rooted at page `num`, by following
+    /// the left-most or the right-most pointers down to a leaf.
+    pub fn table_edge(&self, num: usize, last: bool) -> anyhow::Result<Option<(i64, Record)>> {
+        let mut page_idx = num - 1;
+
+        loop {
+            let Some(page) = self.pages.get(page_idx) else {
+                bail!("page {} is out of range", page_idx + 1);
+            };
+            let mut cells = page.cells()?;
+
+            match page.page_type() {
+                PageType::InteriorTable => {
+                    let child = if last {
+                        page.btree_header.right_most_pointer
+                    } else {
+                        cells.first().and_then(|cell| cell.page_number_left_child)
+                    };
+                    match child {
+                        Some(num) => page_idx = num as usize - 1,
+                        None => return Ok(None),
+                    }
+                }
+                PageType::LeafTable => {
+                    let cell = if last {
+                        cells.pop()
+                    } else {
+                        cells.into_iter().next()
+                    };
+                    return Ok(cell.and_then(|cell| Some((cell.rowid?, cell.record?))));
+                }
+                _ => bail!("page {} is not a table b-tree page", page_idx + 1),
+            }
+        }
+    }
+
+    /// Reads every entry of the index b-tree rooted at page `num` in index order.
+    pub fn scan_index(&self, num: usize) -> anyhow::Result<Vec<Record>> {
+        let mut records = Vec::new();
+        self.scan_index_page(num - 1, &mut records)?;
+
+        Ok(records)
+    }
+
+    fn scan_index_page(&self, page_idx: usize, records: &mut Vec<Record>) -> anyhow::Result<()> {
+        let Some(page) = self.pages.get(page_idx) else {
+            bail!("page {} is out of range", page_idx + 1);
+        };
+
+        match page.page_type() {
+            PageType::InteriorIndex => {
+                for cell in page.cells()? {
+                    if let Some(page_num_left_child) = cell.page_number_left_child {
+                        self.scan_index_page(page_num_left_child as usize - 1, records)?;
+                    }
+                    if let Some(record) = cell.record {
+                        records.push(record);
+                    }
+                }
+                if let Some(num) = page.btree_header.right_most_pointer {
+                    self.scan_index_page(num as usize - 1, records)?;
+                }
+            }
+            PageType::LeafIndex => {
+                records.extend(page.cells()?.into_iter().filter_map(|cell| cell.record));
+            }
+            _ => bail!("page {} is not an index b-tree page", page_idx + 1),
+        }
+
+        Ok(())
+    }
+}
+
+/// The rows of a table b-tree in rowid order, as [`Database::table_cursor`] finds them.
+pub struct TableCursor<'a> {
+    db: &'a Database,
+    /// The pages still to visit, the next one last.
+    page_idxes: Vec<usize>,
+    /// The cells of the leaf page being read.
+    cells: std::vec::IntoIter<Cell>,
+}
+
+impl Iterator for TableCursor<'_> {
+    type Item = anyhow::Result<(i64, Record)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for cell in self.cells.by_ref() {
+                if let (Some(rowid), Some(record)) = (cell.rowid, cell.record) {
+                    return Some(Ok((rowid, record)));
+                }
+            }
+
+            let page_idx = self.page_idxes.pop()?;
+            let Some(page) = self.db.pages.get(page_idx) else {
+                continue;
+            };
+            let cells = match page.cells() {
+                Ok(cells) => cells,
+                Err(err) => {
+                    self.page_idxes.clear();
+                    return Some(Err(err));
+                }
+            };
+
+            match page.page_type() {
+                PageType::InteriorTable => {
+                    if let Some(num) = page.btree_header.right_most_pointer {
+                        self.page_idxes.push(num as usize - 1);
+                    }
+                    for cell in cells.iter().rev() {
+                        if let Some(page_num_left_child) = cell.page_number_left_child {
+                            self.page_idxes.push(page_num_left_child as usize - 1);
                         }
                     }
                 }
+                PageType::LeafTable => self.cells = cells.into_iter(),
+                _ => {
+                    self.page_idxes.clear();
+                    return Some(Err(anyhow::anyhow!(
+                        "page {} is not a table b-tree page",
+                        page_idx + 1
+                    )));
+                }
             }
         }
     }
 }
 
+/// Compares the leading columns of an index entry with `key` in index order. Columns past the
+/// end of `collations` are ordered by BINARY.
+pub fn compare_index_key(
+    entry: &Record,
+    key: &[SerialValue],
+    desc: &[bool],
+    collations: &[Collation],
+) -> Ordering {
+    for (i, value) in key.iter().enumerate() {
+        let collation = collations.get(i).copied().unwrap_or_default();
+        let ordering = match entry.columns.get(i) {
+            Some(column) => collation.compare(column.data(), value),
+            None => Ordering::Less,
+        };
+        let ordering = if desc.get(i).copied().unwrap_or_default() {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
 #[derive(Debug, Clone)]
 pub struct DbHeader {
     header_string: String,
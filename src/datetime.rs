@@ -0,0 +1,1029 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::bail;
+
+use crate::{
+    column::{parse_numeric, SerialValue},
+    printf::printf,
+};
+
+/// Milliseconds from the start of the Julian calendar to 1970-01-01.
+const UNIX_EPOCH_JD: i64 = 210_866_760_000_000;
+/// The last millisecond of 9999-12-31, the largest time the functions accept.
+const MAX_JD: i64 = 464_269_060_799_999;
+const DAY_MS: i64 = 86_400_000;
+
+/// A point in time, kept as a Julian day number in milliseconds, as broken-down fields, or
+/// both. The fields mirror SQLite's own `DateTime`, since the result of a chain of modifiers
+/// depends on which representation each one works from.
+#[derive(Debug, Clone, Copy, Default)]
+struct DateTime {
+    jd: i64,
+    year: i32,
+    month: i32,
+    day: i32,
+    hour: i32,
+    minute: i32,
+    /// The timezone offset in minutes.
+    tz: i32,
+    second: f64,
+    valid_jd: bool,
+    valid_ymd: bool,
+    valid_hms: bool,
+    /// Days past the end of the month, which the "floor" modifier takes back.
+    floor: i32,
+    /// `second` holds a number that is not yet known to be a Julian day or a unix time.
+    raw: bool,
+    error: bool,
+    subsec: bool,
+    utc: bool,
+    local: bool,
+}
+
+impl DateTime {
+    fn from_jd(jd: i64) -> Self {
+        Self {
+            jd,
+            valid_jd: true,
+            ..Self::default()
+        }
+    }
+
+    fn now(now: Option<i64>) -> Self {
+        Self {
+            utc: true,
+            ..Self::from_jd(now.unwrap_or_else(current_time))
+        }
+    }
+
+    fn raw_number(&mut self, num: f64) {
+        self.second = num;
+        self.raw = true;
+        if (0.0..5_373_484.5).contains(&num) {
+            self.jd = (num * DAY_MS as f64 + 0.5) as i64;
+            self.valid_jd = true;
+        }
+    }
+
+    fn set_error(&mut self) {
+        *self = Self {
+            error: true,
+            ..Self::default()
+        };
+    }
+
+    fn clear_fields(&mut self) {
+        self.valid_ymd = false;
+        self.valid_hms = false;
+        self.tz = 0;
+    }
+
+    fn compute_jd(&mut self) {
+        if self.valid_jd {
+            return;
+        }
+
+        let (mut y, mut m, d) = if self.valid_ymd {
+            (self.year, self.month, self.day)
+        } else {
+            (2000, 1, 1)
+        };
+        if !(-4713..=14712).contains(&y) || self.raw {
+            self.set_error();
+            return;
+        }
+        if m <= 2 {
+            y -= 1;
+            m += 12;
+        }
+        let a = (y + 4800) / 100;
+        let b = 38 - a + a / 4;
+        let x1 = 36525 * (y + 4716) / 100;
+        let x2 = 306001 * (m + 1) / 10000;
+        self.jd = ((f64::from(x1 + x2 + d + b) - 1524.5) * DAY_MS as f64) as i64;
+        self.valid_jd = true;
+
+        if self.valid_hms {
+            self.jd += i64::from(self.hour) * 3_600_000
+                + i64::from(self.minute) * 60_000
+                + (self.second * 1000.0 + 0.5) as i64;
+            if self.tz != 0 {
+                self.jd -= i64::from(self.tz) * 60_000;
+                self.valid_ymd = false;
+                self.valid_hms = false;
+                self.tz = 0;
+                self.utc = true;
+                self.local = false;
+            }
+        }
+    }
+
+    fn compute_ymd(&mut self) {
+        if self.valid_ymd {
+            return;
+        }
+
+        if !self.valid_jd {
+            self.year = 2000;
+            self.month = 1;
+            self.day = 1;
+        } else if !valid_jd(self.jd) {
+            self.set_error();
+            return;
+        } else {
+            let z = ((self.jd + 43_200_000) / DAY_MS) as i32;
+            let alpha = ((f64::from(z) + 32044.75) / 36524.25) as i32 - 52;
+            let a = z + 1 + alpha - (alpha + 100) / 4 + 25;
+            let b = a + 1524;
+            let c = ((f64::from(b) - 122.1) / 365.25) as i32;
+            let d = (36525 * (c & 32767)) / 100;
+            let e = (f64::from(b - d) / 30.6001) as i32;
+            let x1 = (30.6001 * f64::from(e)) as i32;
+            self.day = b - d - x1;
+            self.month = if e < 14 { e - 1 } else { e - 13 };
+            self.year = if self.month > 2 { c - 4716 } else { c - 4715 };
+        }
+        self.valid_ymd = true;
+    }
+
+    fn compute_hms(&mut self) {
+        if self.valid_hms {
+            return;
+        }
+
+        self.compute_jd();
+        let day_ms = ((self.jd + 43_200_000) % DAY_MS) as i32;
+        self.second = f64::from(day_ms % 60_000) / 1000.0;
+        let day_min = day_ms / 60_000;
+        self.minute = day_min % 60;
+        self.hour = day_min / 60;
+        self.raw = false;
+        self.valid_hms = true;
+    }
+
+    fn compute_ymd_hms(&mut self) {
+        self.compute_ymd();
+        self.compute_hms();
+    }
+
+    /// Works out how many days past the end of its month the date is.
+    fn compute_floor(&mut self) {
+        self.floor = if self.day <= 28 || (1 << self.month) & 0x15aa != 0 {
+            0
+        } else if self.month != 2 {
+            i32::from(self.day == 31)
+        } else if self.year % 4 != 0 || (self.year % 100 == 0 && self.year % 400 != 0) {
+            self.day - 28
+        } else {
+            self.day - 29
+        };
+    }
+
+    /// Converts the time, taken as UTC, to local time. Years the C library may not handle are
+    /// moved to an equivalent year between 2000 and 2003 first.
+    fn convert_to_local(&mut self) -> anyhow::Result<()> {
+        self.compute_jd();
+        let (t, year_diff) = if (210_866_760_000_000..=213_014_145_600_000).contains(&self.jd) {
+            (self.jd / 1000 - UNIX_EPOCH_JD / 1000, 0)
+        } else {
+            let mut x = *self;
+            x.compute_ymd_hms();
+            let year_diff = (2000 + x.year % 4) - x.year;
+            x.year += year_diff;
+            x.valid_jd = false;
+            x.compute_jd();
+            (x.jd / 1000 - UNIX_EPOCH_JD / 1000, year_diff)
+        };
+
+        let Some(local) = local_time(t) else {
+            bail!("local time unavailable");
+        };
+        self.year = local.tm_year + 1900 - year_diff;
+        self.month = local.tm_mon + 1;
+        self.day = local.tm_mday;
+        self.hour = local.tm_hour;
+        self.minute = local.tm_min;
+        self.second = f64::from(local.tm_sec) + (self.jd % 1000) as f64 * 0.001;
+        self.valid_ymd = true;
+        self.valid_hms = true;
+        self.valid_jd = false;
+        self.raw = false;
+        self.tz = 0;
+        self.error = false;
+        Ok(())
+    }
+
+    /// Converts the time, taken as local time, to UTC by guessing and correcting with
+    /// `convert_to_local`.
+    fn convert_to_utc(&mut self) -> anyhow::Result<()> {
+        self.compute_jd();
+        let original = self.jd;
+        let mut guess = original;
+        let mut err = 0;
+        for _ in 0..4 {
+            guess -= err;
+            let mut new = Self::from_jd(guess);
+            new.convert_to_local()?;
+            new.compute_jd();
+            err = new.jd - original;
+            if err == 0 {
+                break;
+            }
+        }
+
+        *self = Self {
+            utc: true,
+            ..Self::from_jd(guess)
+        };
+        Ok(())
+    }
+
+    fn days_after_jan01(&self) -> i64 {
+        let mut jan01 = *self;
+        jan01.valid_jd = false;
+        jan01.month = 1;
+        jan01.day = 1;
+        jan01.compute_jd();
+        (self.jd - jan01.jd + 43_200_000) / DAY_MS
+    }
+
+    fn days_after_monday(&self) -> i64 {
+        ((self.jd + 43_200_000) / DAY_MS) % 7
+    }
+
+    fn days_after_sunday(&self) -> i64 {
+        ((self.jd + 129_600_000) / DAY_MS) % 7
+    }
+
+    /// The Thursday of the same ISO week, which decides the ISO year and week number.
+    fn iso_thursday(&self) -> Self {
+        let mut thursday = *self;
+        thursday.jd += (3 - self.days_after_monday()) * DAY_MS;
+        thursday.valid_ymd = false;
+        thursday.compute_ymd();
+        thursday
+    }
+
+    fn unix_seconds(&self) -> i64 {
+        self.jd / 1000 - UNIX_EPOCH_JD / 1000
+    }
+
+    fn unix_subsec(&self) -> f64 {
+        (self.jd - UNIX_EPOCH_JD) as f64 / 1000.0
+    }
+
+    fn date(&self) -> String {
+        let sign = if self.year < 0 { "-" } else { "" };
+        format!(
+            "{sign}{:04}-{:02}-{:02}",
+            self.year.abs() % 10000,
+            self.month,
+            self.day
+        )
+    }
+
+    fn time(&self) -> String {
+        if self.subsec {
+            let ms = (1000.0 * self.second + 0.5) as i32;
+            format!(
+                "{:02}:{:02}:{:02}.{:03}",
+                self.hour,
+                self.minute,
+                ms / 1000 % 100,
+                ms % 1000
+            )
+        } else {
+            format!(
+                "{:02}:{:02}:{:02}",
+                self.hour,
+                self.minute,
+                self.second as i32 % 100
+            )
+        }
+    }
+}
+
+fn valid_jd(jd: i64) -> bool {
+    (0..=MAX_JD).contains(&jd)
+}
+
+/// The current time as a Julian day number in milliseconds.
+pub fn current_time() -> i64 {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    UNIX_EPOCH_JD + elapsed.as_millis() as i64
+}
+
+fn local_time(t: i64) -> Option<libc::tm> {
+    let t = t as libc::time_t;
+    // SAFETY: `tm` is plain old data, and `localtime_r` only writes to it and reads `t`.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        (!libc::localtime_r(&t, &mut tm).is_null()).then_some(tm)
+    }
+}
+
+/// The byte at `i`, or NUL past the end, so that the parsers can look ahead the way C code
+/// walking a NUL-terminated string does.
+fn at(z: &[u8], i: usize) -> u8 {
+    z.get(i).copied().unwrap_or(0)
+}
+
+/// Reads fixed-width numbers. Each field is its width, its smallest and largest values and
+/// the byte that must follow it, with 0 meaning anything may follow.
+fn get_digits(z: &[u8], fields: &[(usize, i32, i32, u8)]) -> Option<Vec<i32>> {
+    let mut values = Vec::new();
+    let mut i = 0;
+    for &(width, min, max, next) in fields {
+        let mut value = 0;
+        for _ in 0..width {
+            let c = at(z, i);
+            if !c.is_ascii_digit() {
+                return None;
+            }
+            value = value * 10 + i32::from(c - b'0');
+            i += 1;
+        }
+        if value < min || value > max || (next != 0 && next != at(z, i)) {
+            return None;
+        }
+        values.push(value);
+        i += 1;
+    }
+
+    Some(values)
+}
+
+fn skip_spaces(z: &[u8], mut i: usize) -> usize {
+    while at(z, i).is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Parses a `[+-]HH:MM` or `Z` suffix. Returns false if there is anything else.
+fn parse_timezone(z: &[u8], p: &mut DateTime) -> bool {
+    let mut i = skip_spaces(z, 0);
+    p.tz = 0;
+    let sign = match at(z, i) {
+        b'-' => -1,
+        b'+' => 1,
+        b'Z' | b'z' => {
+            p.local = false;
+            p.utc = true;
+            return at(z, skip_spaces(z, i + 1)) == 0;
+        }
+        c => return c == 0,
+    };
+    i += 1;
+    let Some(hm) = get_digits(&z[i.min(z.len())..], &[(2, 0, 14, b':'), (2, 0, 59, 0)]) else {
+        return false;
+    };
+    i += 5;
+    p.tz = sign * (hm[1] + hm[0] * 60);
+    at(z, skip_spaces(z, i)) == 0
+}
+
+/// Parses `HH:MM`, `HH:MM:SS` or `HH:MM:SS.SSS`, optionally followed by a timezone.
+fn parse_hms(z: &[u8], p: &mut DateTime) -> bool {
+    let Some(hm) = get_digits(z, &[(2, 0, 24, b':'), (2, 0, 59, 0)]) else {
+        return false;
+    };
+    let mut i = 5;
+    let mut second = 0.0;
+    if at(z, i) == b':' {
+        i += 1;
+        let Some(s) = get_digits(&z[i.min(z.len())..], &[(2, 0, 59, 0)]) else {
+            return false;
+        };
+        i += 2;
+        second = f64::from(s[0]);
+        if at(z, i) == b'.' && at(z, i + 1).is_ascii_digit() {
+            let mut fraction = 0.0;
+            let mut scale = 1.0;
+            i += 1;
+            while at(z, i).is_ascii_digit() {
+                fraction = fraction * 10.0 + f64::from(at(z, i) - b'0');
+                scale *= 10.0;
+                i += 1;
+            }
+            // Truncated so that rounding to milliseconds never carries into the next second.
+            second += (fraction / scale).min(0.999);
+        }
+    }
+
+    p.valid_jd = false;
+    p.raw = false;
+    p.valid_hms = true;
+    p.hour = hm[0];
+    p.minute = hm[1];
+    p.second = second;
+    parse_timezone(&z[i.min(z.len())..], p)
+}
+
+/// Parses `YYYY-MM-DD`, optionally followed by a time.
+fn parse_ymd(z: &[u8], p: &mut DateTime) -> bool {
+    let (negative, z) = match z.first() {
+        Some(b'-') => (true, &z[1..]),
+        _ => (false, z),
+    };
+    let Some(ymd) = get_digits(z, &[(4, 0, 9999, b'-'), (2, 1, 12, b'-'), (2, 1, 31, 0)]) else {
+        return false;
+    };
+    let mut i = 10;
+    while at(z, i).is_ascii_whitespace() || at(z, i) == b'T' {
+        i += 1;
+    }
+    let rest = &z[i.min(z.len())..];
+    if !parse_hms(rest, p) {
+        if !rest.is_empty() {
+            return false;
+        }
+        p.valid_hms = false;
+    }
+
+    p.valid_jd = false;
+    p.valid_ymd = true;
+    p.year = if negative { -ymd[0] } else { ymd[0] };
+    p.month = ymd[1];
+    p.day = ymd[2];
+    p.compute_floor();
+    if p.tz != 0 {
+        p.compute_jd();
+    }
+    true
+}
+
+/// Parses a time value given as TEXT.
+fn parse_date_or_time(txt: &str, p: &mut DateTime, now: Option<i64>) -> bool {
+    let z = txt.as_bytes();
+    if parse_ymd(z, p) || parse_hms(z, p) {
+        return true;
+    }
+    if txt.eq_ignore_ascii_case("now") {
+        *p = DateTime::now(now);
+        return true;
+    }
+    if let Some(num) = parse_numeric(txt) {
+        p.raw_number(num.to_real());
+        return true;
+    }
+    if txt.eq_ignore_ascii_case("subsec") || txt.eq_ignore_ascii_case("subsecond") {
+        *p = DateTime {
+            subsec: true,
+            ..DateTime::now(now)
+        };
+        return true;
+    }
+
+    false
+}
+
+/// The units of "+NNN units" modifiers, with the largest amount each accepts and its length
+/// in seconds.
+const UNITS: [(&str, f32, f32); 6] = [
+    ("second", 4.6427e14, 1.0),
+    ("minute", 7.7379e12, 60.0),
+    ("hour", 1.2897e11, 3600.0),
+    ("day", 5373485.0, 86400.0),
+    ("month", 176546.0, 2592000.0),
+    ("year", 14713.0, 31536000.0),
+];
+
+/// Normalizes a month that arithmetic took outside 1 to 12 by carrying into the year.
+fn carry_months(p: &mut DateTime) {
+    let x = if p.month > 0 {
+        (p.month - 1) / 12
+    } else {
+        (p.month - 12) / 12
+    };
+    p.year += x;
+    p.month -= x * 12;
+}
+
+fn starts_with_ignore_case(txt: &str, prefix: &str) -> bool {
+    txt.len() >= prefix.len()
+        && txt.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+/// Applies one modifier. `index` is its position among the arguments, since a few modifiers
+/// are only allowed right after the time value. Returns false if the modifier is not
+/// recognized, which makes the result NULL.
+fn apply_modifier(modifier: &str, p: &mut DateTime, index: usize) -> anyhow::Result<bool> {
+    let z = modifier.as_bytes();
+    let lower = modifier.to_ascii_lowercase();
+
+    Ok(match at(z, 0).to_ascii_lowercase() {
+        b'a' if lower == "auto" => {
+            if index > 1 {
+                return Ok(false);
+            }
+            if !p.raw || p.valid_jd {
+                p.raw = false;
+            } else if (-210_866_760_000.0..=253_402_300_799.0).contains(&p.second) {
+                let r = p.second * 1000.0 + UNIX_EPOCH_JD as f64;
+                p.clear_fields();
+                p.jd = (r + 0.5) as i64;
+                p.valid_jd = true;
+                p.raw = false;
+            }
+            true
+        }
+        b'c' if lower == "ceiling" => {
+            p.compute_jd();
+            p.clear_fields();
+            p.floor = 0;
+            true
+        }
+        b'f' if lower == "floor" => {
+            p.compute_jd();
+            p.jd -= i64::from(p.floor) * DAY_MS;
+            p.clear_fields();
+            true
+        }
+        b'j' if lower == "julianday" => {
+            if index > 1 {
+                return Ok(false);
+            }
+            let ok = p.valid_jd && p.raw;
+            if ok {
+                p.raw = false;
+            }
+            ok
+        }
+        b'l' if lower == "localtime" => {
+            if !p.local {
+                p.convert_to_local()?;
+            }
+            p.utc = false;
+            p.local = true;
+            true
+        }
+        b'u' if lower == "unixepoch" && p.raw => {
+            if index > 1 {
+                return Ok(false);
+            }
+            let r = p.second * 1000.0 + UNIX_EPOCH_JD as f64;
+            let ok = (0.0..(MAX_JD + 1) as f64).contains(&r);
+            if ok {
+                p.clear_fields();
+                p.jd = (r + 0.5) as i64;
+                p.valid_jd = true;
+                p.raw = false;
+            }
+            ok
+        }
+        b'u' if lower == "utc" => {
+            if !p.utc {
+                p.convert_to_utc()?;
+            }
+            true
+        }
+        b'w' if starts_with_ignore_case(modifier, "weekday ") => {
+            let Some(n) = parse_numeric(&modifier[8..]).map(|n| n.to_real()) else {
+                return Ok(false);
+            };
+            if !(0.0..7.0).contains(&n) || n.fract() != 0.0 {
+                return Ok(false);
+            }
+            p.compute_ymd_hms();
+            p.tz = 0;
+            p.valid_jd = false;
+            p.compute_jd();
+            let mut z = ((p.jd + 129_600_000) / DAY_MS) % 7;
+            if z > n as i64 {
+                z -= 7;
+            }
+            p.jd += (n as i64 - z) * DAY_MS;
+            p.clear_fields();
+            true
+        }
+        b's' => {
+            if !starts_with_ignore_case(modifier, "start of ") {
+                let ok = lower == "subsec" || lower == "subsecond";
+                if ok {
+                    p.subsec = true;
+                }
+                return Ok(ok);
+            }
+            if !p.valid_jd && !p.valid_ymd && !p.valid_hms {
+                return Ok(false);
+            }
+            p.compute_ymd();
+            p.valid_hms = true;
+            p.hour = 0;
+            p.minute = 0;
+            p.second = 0.0;
+            p.raw = false;
+            p.tz = 0;
+            p.valid_jd = false;
+            match &lower[9..] {
+                "month" => {
+                    p.day = 1;
+                    true
+                }
+                "year" => {
+                    p.month = 1;
+                    p.day = 1;
+                    true
+                }
+                "day" => true,
+                _ => false,
+            }
+        }
+        b'+' | b'-' | b'0'..=b'9' => shift(modifier, p)?,
+        _ => false,
+    })
+}
+
+/// Applies a modifier that starts with a number: `±NNN units`, `±HH:MM[:SS[.SSS]]` or
+/// `±YYYY-MM-DD[ HH:MM[:SS[.SSS]]]`.
+fn shift(modifier: &str, p: &mut DateTime) -> anyhow::Result<bool> {
+    let z = modifier.as_bytes();
+    let sign = z[0];
+    let mut n = 1;
+    while n < z.len() {
+        let c = z[n];
+        if c == b':' || c.is_ascii_whitespace() {
+            break;
+        }
+        if c == b'-'
+            && ((n == 5 && get_digits(&z[1..], &[(4, 0, 9999, 0)]).is_some())
+                || (n == 6 && get_digits(&z[1..], &[(5, 0, 14712, 0)]).is_some()))
+        {
+            break;
+        }
+        n += 1;
+    }
+    let Some(r) = parse_numeric(&modifier[..n]).map(|num| num.to_real()) else {
+        return Ok(false);
+    };
+
+    // Where an `HH:MM` part starts, and how long the number in front of its colon is.
+    let mut time = (0, n);
+    if at(z, n) == b'-' {
+        if sign != b'+' && sign != b'-' {
+            return Ok(false);
+        }
+        let mut z = z;
+        let width = n - 1;
+        let Some(ymd) = get_digits(
+            &z[1..],
+            &[(width, 0, 14712, b'-'), (2, 0, 11, b'-'), (2, 0, 30, 0)],
+        ) else {
+            return Ok(false);
+        };
+        if width == 5 {
+            z = &z[1..];
+        }
+        let (years, months, mut days) = (ymd[0], ymd[1], ymd[2]);
+        p.compute_ymd_hms();
+        p.valid_jd = false;
+        if sign == b'-' {
+            p.year -= years;
+            p.month -= months;
+            days = -days;
+        } else {
+            p.year += years;
+            p.month += months;
+        }
+        carry_months(p);
+        p.compute_floor();
+        p.compute_jd();
+        p.valid_hms = false;
+        p.valid_ymd = false;
+        p.jd += i64::from(days) * DAY_MS;
+
+        let offset = modifier.len() - z.len();
+        if at(z, 11) == 0 {
+            return Ok(true);
+        }
+        if at(z, 11).is_ascii_whitespace()
+            && get_digits(&z[12.min(z.len())..], &[(2, 0, 24, b':'), (2, 0, 59, 0)]).is_some()
+        {
+            time = (offset + 12, 2);
+        } else {
+            return Ok(false);
+        }
+    }
+
+    let (start, n) = time;
+    let z2 = &z[start..];
+    if at(z2, n) == b':' {
+        let z2 = if z2[0].is_ascii_digit() { z2 } else { &z2[1..] };
+        let mut tx = DateTime::default();
+        if !parse_hms(z2, &mut tx) {
+            return Ok(false);
+        }
+        tx.compute_jd();
+        tx.jd -= 43_200_000;
+        let day = tx.jd / DAY_MS;
+        tx.jd -= day * DAY_MS;
+        if sign == b'-' {
+            tx.jd = -tx.jd;
+        }
+        p.compute_jd();
+        p.clear_fields();
+        p.jd += tx.jd;
+        return Ok(true);
+    }
+    if start != 0 {
+        return Ok(false);
+    }
+
+    let unit = modifier[n..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+    if !(3..=10).contains(&unit.len()) {
+        return Ok(false);
+    }
+    let unit = match unit.strip_suffix(['s', 'S']) {
+        Some(unit) => unit,
+        None => unit,
+    };
+    p.compute_jd();
+    let rounder = if r < 0.0 { -0.5 } else { 0.5 };
+    p.floor = 0;
+
+    let mut ok = false;
+    for (i, &(name, limit, seconds)) in UNITS.iter().enumerate() {
+        let limit = f64::from(limit);
+        if !unit.eq_ignore_ascii_case(name) || r <= -limit || r >= limit {
+            continue;
+        }
+        let mut r = r;
+        match i {
+            4 => {
+                p.compute_ymd_hms();
+                p.month += r as i32;
+                carry_months(p);
+                p.compute_floor();
+                p.valid_jd = false;
+                r -= f64::from(r as i32);
+            }
+            5 => {
+                p.compute_ymd_hms();
+                p.year += r as i32;
+                p.compute_floor();
+                p.valid_jd = false;
+                r -= f64::from(r as i32);
+            }
+            _ => {}
+        }
+        p.compute_jd();
+        p.jd += (r * 1000.0 * f64::from(seconds) + rounder) as i64;
+        ok = true;
+        break;
+    }
+    p.clear_fields();
+
+    Ok(ok)
+}
+
+fn text(value: &SerialValue) -> Option<String> {
+    match value {
+        SerialValue::Null => None,
+        value => Some(value.display()),
+    }
+}
+
+/// Reads a time value and applies the modifiers that follow it. With no arguments the time
+/// is the current one. Returns `None` if the result is NULL.
+fn date_time(values: &[SerialValue], now: Option<i64>) -> anyhow::Result<Option<DateTime>> {
+    let mut p = DateTime::default();
+    let Some(first) = values.first() else {
+        return Ok(Some(DateTime::now(now)));
+    };
+
+    match first {
+        SerialValue::Null => return Ok(None),
+        SerialValue::Blob(_) | SerialValue::String(_) => {
+            let Some(txt) = text(first) else {
+                return Ok(None);
+            };
+            if !parse_date_or_time(&txt, &mut p, now) {
+                return Ok(None);
+            }
+        }
+        num => p.raw_number(num.to_real()),
+    }
+
+    for (i, value) in values.iter().enumerate().skip(1) {
+        let Some(modifier) = text(value) else {
+            return Ok(None);
+        };
+        if !apply_modifier(&modifier, &mut p, i)? {
+            return Ok(None);
+        }
+    }
+
+    p.compute_jd();
+    if p.error || !valid_jd(p.jd) {
+        return Ok(None);
+    }
+    // A lone date such as 2023-02-31 is shown normalized, as 2023-03-03.
+    if values.len() == 1 && p.valid_ymd && p.day > 28 {
+        p.valid_ymd = false;
+    }
+
+    Ok(Some(p))
+}
+
+/// Calls one of the date and time functions. `now` is the time the statement started, so
+/// that every call in it sees the same current time.
+pub fn call(name: &str, values: &[SerialValue], now: Option<i64>) -> anyhow::Result<SerialValue> {
+    let result = match name {
+        "strftime" => strftime(values, now)?.map(SerialValue::String),
+        "timediff" => timediff(values, now)?.map(SerialValue::String),
+        _ => date_time(values, now)?.map(|mut p| match name {
+            "date" | "current_date" => {
+                p.compute_ymd();
+                SerialValue::String(p.date())
+            }
+            "time" | "current_time" => {
+                p.compute_hms();
+                SerialValue::String(p.time())
+            }
+            "julianday" => SerialValue::Float64(p.jd as f64 / DAY_MS as f64),
+            "unixepoch" if p.subsec => SerialValue::Float64(p.unix_subsec()),
+            "unixepoch" => SerialValue::I64(p.unix_seconds()),
+            _ => {
+                p.compute_ymd_hms();
+                SerialValue::String(format!("{} {}", p.date(), p.time()))
+            }
+        }),
+    };
+
+    Ok(result.unwrap_or(SerialValue::Null))
+}
+
+fn strftime(values: &[SerialValue], now: Option<i64>) -> anyhow::Result<Option<String>> {
+    let Some(format) = values.first().and_then(text) else {
+        return Ok(None);
+    };
+    let Some(mut x) = date_time(&values[1..], now)? else {
+        return Ok(None);
+    };
+    x.compute_jd();
+    x.compute_ymd_hms();
+
+    let real = |format: &str, num: f64| printf(format, &[SerialValue::Float64(num)]);
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('d') => out.push_str(&format!("{:02}", x.day)),
+            Some('e') => out.push_str(&format!("{:2}", x.day)),
+            Some('f') => out.push_str(&real("%06.3f", x.second.min(59.999))?),
+            Some('F') => out.push_str(&format!("{:04}-{:02}-{:02}", x.year, x.month, x.day)),
+            Some('G') => out.push_str(&format!("{:04}", x.iso_thursday().year)),
+            Some('g') => out.push_str(&format!("{:02}", x.iso_thursday().year % 100)),
+            Some('H') => out.push_str(&format!("{:02}", x.hour)),
+            Some('k') => out.push_str(&format!("{:2}", x.hour)),
+            Some(c @ ('I' | 'l')) => {
+                let hour = match x.hour {
+                    0 => 12,
+                    hour if hour > 12 => hour - 12,
+                    hour => hour,
+                };
+                if c == 'I' {
+                    out.push_str(&format!("{hour:02}"));
+                } else {
+                    out.push_str(&format!("{hour:2}"));
+                }
+            }
+            Some('j') => out.push_str(&format!("{:03}", x.days_after_jan01() + 1)),
+            Some('J') => out.push_str(&real("%.16g", x.jd as f64 / DAY_MS as f64)?),
+            Some('m') => out.push_str(&format!("{:02}", x.month)),
+            Some('M') => out.push_str(&format!("{:02}", x.minute)),
+            Some('p') => out.push_str(if x.hour >= 12 { "PM" } else { "AM" }),
+            Some('P') => out.push_str(if x.hour >= 12 { "pm" } else { "am" }),
+            Some('R') => out.push_str(&format!("{:02}:{:02}", x.hour, x.minute)),
+            Some('s') if x.subsec => out.push_str(&real("%.3f", x.unix_subsec())?),
+            Some('s') => out.push_str(&x.unix_seconds().to_string()),
+            Some('S') => out.push_str(&format!("{:02}", x.second as i32)),
+            Some('T') => out.push_str(&format!(
+                "{:02}:{:02}:{:02}",
+                x.hour, x.minute, x.second as i32
+            )),
+            Some('u') => match x.days_after_sunday() {
+                0 => out.push('7'),
+                day => out.push_str(&day.to_string()),
+            },
+            Some('w') => out.push_str(&x.days_after_sunday().to_string()),
+            Some('U') => out.push_str(&format!(
+                "{:02}",
+                (x.days_after_jan01() - x.days_after_sunday() + 7) / 7
+            )),
+            Some('V') => out.push_str(&format!(
+                "{:02}",
+                x.iso_thursday().days_after_jan01() / 7 + 1
+            )),
+            Some('W') => out.push_str(&format!(
+                "{:02}",
+                (x.days_after_jan01() - x.days_after_monday() + 7) / 7
+            )),
+            Some('Y') => out.push_str(&format!("{:04}", x.year)),
+            Some('%') => out.push('%'),
+            _ => return Ok(None),
+        }
+    }
+
+    Ok(Some(out))
+}
+
+/// Describes the time from the second argument to the first as `±YYYY-MM-DD HH:MM:SS.SSS`,
+/// counting whole years and months first.
+fn timediff(values: &[SerialValue], now: Option<i64>) -> anyhow::Result<Option<String>> {
+    let (Some(mut d1), Some(mut d2)) = (
+        date_time(&values[..1], now)?,
+        date_time(&values[1..2], now)?,
+    ) else {
+        return Ok(None);
+    };
+    d1.compute_ymd_hms();
+    d2.compute_ymd_hms();
+
+    let recompute = |d: &mut DateTime| {
+        d.valid_jd = false;
+        d.compute_jd();
+    };
+    let (sign, mut years, mut months);
+    if d1.jd >= d2.jd {
+        sign = '+';
+        years = d1.year - d2.year;
+        if years != 0 {
+            d2.year = d1.year;
+            recompute(&mut d2);
+        }
+        months = d1.month - d2.month;
+        if months < 0 {
+            years -= 1;
+            months += 12;
+        }
+        if months != 0 {
+            d2.month = d1.month;
+            recompute(&mut d2);
+        }
+        while d1.jd < d2.jd {
+            months -= 1;
+            if months < 0 {
+                months = 11;
+                years -= 1;
+            }
+            d2.month -= 1;
+            if d2.month < 1 {
+                d2.month = 12;
+                d2.year -= 1;
+            }
+            recompute(&mut d2);
+        }
+        d1.jd -= d2.jd;
+    } else {
+        sign = '-';
+        years = d2.year - d1.year;
+        if years != 0 {
+            d2.year = d1.year;
+            recompute(&mut d2);
+        }
+        months = d2.month - d1.month;
+        if months < 0 {
+            years -= 1;
+            months += 12;
+        }
+        if months != 0 {
+            d2.month = d1.month;
+            recompute(&mut d2);
+        }
+        while d1.jd > d2.jd {
+            months -= 1;
+            if months < 0 {
+                months = 11;
+                years -= 1;
+            }
+            d2.month += 1;
+            if d2.month > 12 {
+                d2.month = 1;
+                d2.year += 1;
+            }
+            recompute(&mut d2);
+        }
+        d1.jd = d2.jd - d1.jd;
+    }
+    // Shown as a time after 0000-01-01 00:00:00, so the day of the month counts from 1.
+    d1.jd += 148_699_540_800_000;
+    d1.clear_fields();
+    d1.compute_ymd_hms();
+
+    Ok(Some(format!(
+        "{sign}{years:04}-{months:02}-{:02} {:02}:{:02}:{}",
+        d1.day - 1,
+        d1.hour,
+        d1.minute,
+        printf("%06.3f", &[SerialValue::Float64(d1.second)])?
+    )))
+}
@@ -0,0 +1,255 @@
+use serde::{
+    de::{
+        self, value::StrDeserializer, DeserializeOwned, Expected, IntoDeserializer, Unexpected,
+        Visitor,
+    },
+    forward_to_deserialize_any, Deserialize,
+};
+
+use crate::{
+    column::SerialValue,
+    row::{Row, Rows},
+};
+
+/// Why a row could not be deserialized.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Error(String);
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+
+    /// A value is only read as unit when it is NULL, so a unit that does not fit is reported
+    /// as NULL.
+    fn invalid_type(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        match unexp {
+            Unexpected::Unit => Self(format!("invalid type: NULL, expected {exp}")),
+            unexp => Self(format!("invalid type: {unexp}, expected {exp}")),
+        }
+    }
+}
+
+/// Deserializes a row into a struct whose fields are named after its columns, or into a tuple
+/// or sequence of its values in order.
+pub fn from_row<'r, T: Deserialize<'r>>(row: &'r Row) -> anyhow::Result<T> {
+    Ok(T::deserialize(RowDeserializer { row })?)
+}
+
+/// Deserializes each row, as [`from_row`] does.
+pub fn from_rows<'s, T: DeserializeOwned + 's>(
+    rows: Rows<'s>,
+) -> impl Iterator<Item = anyhow::Result<T>> + 's {
+    rows.map(|row| from_row(&row?))
+}
+
+struct RowDeserializer<'r> {
+    row: &'r Row,
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Columns {
+            row: self.row,
+            fields: &[],
+            next: 0,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(Columns {
+            row: self.row,
+            fields,
+            next: 0,
+        })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Columns {
+            row: self.row,
+            fields: &[],
+            next: 0,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+/// The columns of a row, read by name as a map or by position as a sequence.
+struct Columns<'r> {
+    row: &'r Row,
+    /// The fields of the struct being read, which columns are matched to in any case.
+    fields: &'static [&'static str],
+    next: usize,
+}
+
+impl Columns<'_> {
+    /// Names the column that failed in the error of its value.
+    fn column_error(&self, err: Error) -> Error {
+        Error(format!(
+            "column {:?}: {err}",
+            self.row.columns[self.next - 1]
+        ))
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Columns<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some(name) = self.row.columns.get(self.next) else {
+            return Ok(None);
+        };
+        let name = self
+            .fields
+            .iter()
+            .find(|field| field.eq_ignore_ascii_case(name))
+            .map_or(name.as_str(), |field| field);
+        let name: StrDeserializer<Error> = name.into_deserializer();
+        seed.deserialize(name).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = &self.row.values[self.next];
+        self.next += 1;
+        seed.deserialize(ValueDeserializer { value })
+            .map_err(|err| self.column_error(err))
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Columns<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some(value) = self.row.values.get(self.next) else {
+            return Ok(None);
+        };
+        self.next += 1;
+        seed.deserialize(ValueDeserializer { value })
+            .map(Some)
+            .map_err(|err| self.column_error(err))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.values.len() - self.next)
+    }
+}
+
+struct ValueDeserializer<'v> {
+    value: &'v SerialValue,
+}
+
+impl ValueDeserializer<'_> {
+    fn invalid_type(&self, expected: &str) -> Error {
+        let unexp = match self.value {
+            SerialValue::Null => Unexpected::Unit,
+            SerialValue::Float64(num) => Unexpected::Float(*num),
+            SerialValue::String(txt) => Unexpected::Str(txt),
+            SerialValue::Blob(bytes) => Unexpected::Bytes(bytes),
+            value => Unexpected::Signed(value.as_integer().unwrap_or_default()),
+        };
+        de::Error::invalid_type(unexp, &expected)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            SerialValue::Null => visitor.visit_unit(),
+            SerialValue::Float64(num) => visitor.visit_f64(*num),
+            SerialValue::String(txt) => visitor.visit_borrowed_str(txt),
+            SerialValue::Blob(bytes) => visitor.visit_borrowed_bytes(bytes),
+            value => visitor.visit_i64(value.as_integer().unwrap_or_default()),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.as_integer() {
+            Some(num) => visitor.visit_bool(num != 0),
+            None => Err(self.invalid_type("an integer")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            SerialValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            SerialValue::String(txt) => {
+                let variant: StrDeserializer<Error> = txt.as_str().into_deserializer();
+                visitor.visit_enum(variant)
+            }
+            _ => Err(self.invalid_type("a string")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,1425 @@
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+    rc::Rc,
+};
+
+use anyhow::bail;
+use sqlparser::{
+    ast::{
+        visit_expressions, visit_expressions_mut, BinaryOperator, Cte, Distinct, Expr, GroupByExpr,
+        Ident, OrderByExpr, Query, Select, SelectItem, SetExpr, Statement, TableFactor, Value,
+        VisitMut, VisitorMut,
+    },
+    dialect::GenericDialect,
+    parser::Parser,
+};
+
+use crate::{
+    aggregate::{
+        collect_aggregates, hash_aggregate, stream_aggregate, AggregateCall, AggregateFunc,
+    },
+    auto_index::AutoIndex,
+    collation::Collation,
+    column::{Affinity, SerialValue},
+    compound::compound_order,
+    cte::CteScope,
+    database::Database,
+    datetime,
+    explain::explain_query_plan,
+    expr::{collation, eval, reassociate_concat, ColumnName, Context, Scope, Subqueries},
+    hash_join::DEFAULT_MEMORY_BUDGET,
+    join::{conjuncts, Source, TableRef},
+    json,
+    order::{limit, limit_offset, ordinal, sort, strip_collate, SortTerm},
+    plan::{self, Access},
+    record::Record,
+    schema::{ColumnSchema, IndexSchema, Schema, TableSchema},
+    window::{collect_windows, compute_windows, WindowRow},
+};
+
+/// The columns and rows produced by a query.
+#[derive(Debug, Clone, Default)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<SerialValue>>,
+    /// The collating sequence of each column, which a compound select or an outer query
+    /// compares it with.
+    pub collations: Vec<Option<Collation>>,
+    /// The declared type of each column that is a table column as it is, None for the others.
+    pub decl_types: Vec<Option<String>>,
+}
+
+/// The rows of a query as they are produced, with the names and declared types of its columns.
+pub struct RowStream<'r> {
+    pub columns: Vec<String>,
+    pub decl_types: Vec<Option<String>>,
+    pub rows: Box<dyn Iterator<Item = anyhow::Result<Vec<SerialValue>>> + 'r>,
+}
+
+impl From<ResultSet> for RowStream<'_> {
+    fn from(result: ResultSet) -> Self {
+        Self {
+            columns: result.columns,
+            decl_types: result.decl_types,
+            rows: Box::new(result.rows.into_iter().map(Ok)),
+        }
+    }
+}
+
+/// Rows flowing between the stages of a query, with the names they can be referenced by.
+#[derive(Debug, Clone, Default)]
+pub struct Relation {
+    pub columns: Vec<ColumnName>,
+    pub rows: Vec<Vec<SerialValue>>,
+    /// True when the rows arrive ordered by the GROUP BY key, which allows streaming
+    /// aggregation.
+    pub grouped: bool,
+}
+
+/// Automatic indexes by table root page and key columns with their collating sequences. None
+/// marks an index that did not fit in the memory budget.
+type AutoIndexes = HashMap<(usize, Vec<(usize, Collation)>), Option<Rc<AutoIndex>>>;
+
+/// The choices the planner made for a statement, by the node of the statement they were made
+/// for. They are made the first time the statement runs, and a prepared statement keeps them
+/// for all of its runs.
+#[derive(Clone, Default)]
+pub(crate) struct Plans {
+    /// How each table of a FROM clause is read first.
+    pub accesses: HashMap<*const TableFactor, plan::Choice>,
+    /// The order the tables of each SELECT are joined in, None for the written order.
+    pub orders: HashMap<*const Select, Option<Vec<usize>>>,
+    /// The index each joined table is probed through.
+    pub probes: HashMap<*const TableFactor, String>,
+}
+
+#[derive(Clone)]
+pub struct Executor<'a> {
+    pub db: &'a Database,
+    pub schema: Rc<Schema>,
+    /// The number of bytes a hash join may hold in memory before spilling to disk.
+    pub memory_budget: usize,
+    /// Results of the uncorrelated subqueries of the statement being executed, with the query
+    /// they belong to. Aggregate and window arguments are copied on every run, and a later copy
+    /// of another subquery can take the address of one that was dropped, so a result is only
+    /// reused for the same query.
+    subquery_cache: RefCell<HashMap<*const Query, (Query, Rc<ResultSet>)>>,
+    /// Rows of the common table expressions of the statement being executed.
+    pub(crate) cte_cache: RefCell<HashMap<*const Cte, Rc<ResultSet>>>,
+    /// Automatic indexes built by the joins of the statement being executed.
+    auto_indexes: RefCell<AutoIndexes>,
+    /// The plan of the statement being executed, or of the prepared statement.
+    pub(crate) plans: RefCell<Plans>,
+}
+
+impl<'a> Executor<'a> {
+    pub fn new(db: &'a Database) -> anyhow::Result<Self> {
+        Ok(Self::with_schema(db, Rc::new(Schema::read(db)?)))
+    }
+
+    /// Creates an executor for a database whose schema has already been read.
+    pub fn with_schema(db: &'a Database, schema: Rc<Schema>) -> Self {
+        Self {
+            db,
+            schema,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            subquery_cache: RefCell::default(),
+            cte_cache: RefCell::default(),
+            auto_indexes: RefCell::default(),
+            plans: RefCell::default(),
+        }
+    }
+
+    pub fn execute(&self, sql: &str) -> anyhow::Result<ResultSet> {
+        if let Some(sql) = explain_query_plan(sql) {
+            return Ok(self.query_plan(sql)?.into());
+        }
+
+        self.execute_query(&parse_query(sql)?)
+    }
+
+    /// Runs a parsed statement from the start, as [`Executor::execute`] does.
+    pub fn execute_query(&self, query: &Query) -> anyhow::Result<ResultSet> {
+        self.plans.take();
+        self.execute_planned(query, &[])
+    }
+
+    /// Runs a statement with the values bound to its parameters, reading the tables the way
+    /// the earlier runs of the statement did.
+    pub(crate) fn execute_planned(
+        &self,
+        query: &Query,
+        parameters: &[SerialValue],
+    ) -> anyhow::Result<ResultSet> {
+        self.query(query, self.start(parameters))
+    }
+
+    /// Runs a statement like [`Executor::execute_planned`], but returns its rows as they are
+    /// taken. A select from a single table, subquery or function that is not sorted, grouped or
+    /// made distinct reads, filters and computes one row at a time; any other statement is run
+    /// to the end before its first row is returned.
+    pub(crate) fn stream_planned<'r>(
+        &'r self,
+        query: &'r Query,
+        parameters: &'r [SerialValue],
+    ) -> anyhow::Result<RowStream<'r>> {
+        let context = self.start(parameters);
+        if let Some(stream) = self.stream_select(query, context)? {
+            return Ok(stream);
+        }
+
+        Ok(self.query(query, context)?.into())
+    }
+
+    /// Clears what the previous run of a statement left behind and returns the context of the
+    /// next one.
+    fn start<'r>(&'r self, parameters: &'r [SerialValue]) -> Context<'r> {
+        self.subquery_cache.borrow_mut().clear();
+        self.cte_cache.borrow_mut().clear();
+        self.auto_indexes.borrow_mut().clear();
+
+        Context {
+            subqueries: Some(self),
+            now: Some(datetime::current_time()),
+            parameters: Some(parameters),
+            ..Context::default()
+        }
+    }
+
+    /// Returns the rows of a select that can be produced one at a time, or None when the
+    /// select has to see all of its rows first.
+    fn stream_select<'r>(
+        &'r self,
+        query: &'r Query,
+        context: Context<'r>,
+    ) -> anyhow::Result<Option<RowStream<'r>>> {
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return Ok(None);
+        };
+        let [table_with_joins] = select.from.as_slice() else {
+            return Ok(None);
+        };
+        if query.with.is_some()
+            || !query.order_by.is_empty()
+            || select.distinct.is_some()
+            || select.having.is_some()
+            || !group_by(select).is_empty()
+            || !table_with_joins.joins.is_empty()
+        {
+            return Ok(None);
+        }
+        let items = select.projection.iter().filter_map(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+            _ => None,
+        });
+        if !collect_aggregates(items.clone())?.is_empty()
+            || !collect_windows(items, &select.named_window)?.is_empty()
+        {
+            return Ok(None);
+        }
+
+        let (columns, rows): (_, Box<dyn Iterator<Item = _>>) = match self
+            .source(&table_with_joins.relation, context)?
+        {
+            Source::Table(table_ref) => {
+                let (access, _) = self.table_access(select, &[], &[], &[], &table_ref, context)?;
+                let table = table_ref.table;
+                let rows: Box<dyn Iterator<Item = _>> = match access {
+                    Access::Scan => {
+                        Box::new(self.db.table_cursor(table.root_page).map(move |row| {
+                            let (rowid, record) = row?;
+                            Ok(table_row(table, rowid, &record))
+                        }))
+                    }
+                    access => Box::new(self.read_access(table, &access)?.into_iter().map(Ok)),
+                };
+                (table_ref.columns(), rows)
+            }
+            Source::Derived(relation) => (
+                relation.columns,
+                Box::new(relation.rows.into_iter().map(Ok)),
+            ),
+            Source::Function(function) => {
+                let relation = function.relation(context)?;
+                (
+                    relation.columns,
+                    Box::new(relation.rows.into_iter().map(Ok)),
+                )
+            }
+        };
+
+        let projection = result_columns(select, &columns)?;
+        let scope = Scope::new(&columns, &[]);
+        for column in &projection {
+            collation(&column.expr, &scope)?;
+        }
+        let (limit, offset) = limit_offset(query, context)?;
+        // Rows that fail are not counted by OFFSET, so their errors are not skipped.
+        let mut skipped = 0;
+
+        let stream = RowStream {
+            columns: projection
+                .iter()
+                .map(|column| column.name.clone())
+                .collect(),
+            decl_types: projection
+                .iter()
+                .map(|column| column.decl_type.clone())
+                .collect(),
+            rows: Box::new(
+                rows.filter_map(move |row| {
+                    let row = match row {
+                        Ok(row) => row,
+                        Err(err) => return Some(Err(err)),
+                    };
+                    let scope = Scope::within(context, &columns, &row);
+                    if let Some(selection) = &select.selection {
+                        match eval(selection, &scope) {
+                            Ok(value) if value.as_bool() == Some(true) => {}
+                            Ok(_) => return None,
+                            Err(err) => return Some(Err(err)),
+                        }
+                    }
+                    Some(
+                        projection
+                            .iter()
+                            .map(|column| eval(&column.expr, &scope))
+                            .collect(),
+                    )
+                })
+                .filter(move |row| {
+                    if row.is_err() || skipped == offset {
+                        return true;
+                    }
+                    skipped += 1;
+                    false
+                })
+                .take(limit.unwrap_or(usize::MAX)),
+            ),
+        };
+
+        Ok(Some(stream))
+    }
+
+    pub fn query(&self, query: &Query, context: Context) -> anyhow::Result<ResultSet> {
+        let Some(with) = &query.with else {
+            return self.query_body(query, context);
+        };
+
+        let ctes = CteScope {
+            ctes: &with.cte_tables,
+            visible: with.cte_tables.len(),
+            working: None,
+            parent: context.ctes,
+        };
+        self.query_body(
+            query,
+            Context {
+                ctes: Some(&ctes),
+                ..context
+            },
+        )
+    }
+
+    /// Runs a query without its WITH clause, then sorts and limits its rows.
+    fn query_body(&self, query: &Query, context: Context) -> anyhow::Result<ResultSet> {
+        let mut result = match query.body.as_ref() {
+            SetExpr::Select(select) => self.select(select, &query.order_by, context)?,
+            body => {
+                let mut result = self.set_expr(body, context)?;
+                let terms = compound_order(&result, &query.order_by)?;
+                sort(&mut result.rows, &terms);
+                result
+            }
+        };
+
+        result.rows = limit(result.rows, query, context)?;
+        Ok(result)
+    }
+
+    pub fn set_expr(&self, body: &SetExpr, context: Context) -> anyhow::Result<ResultSet> {
+        match body {
+            SetExpr::Select(select) => self.select(select, &[], context),
+            SetExpr::Query(query) => self.query(query, context),
+            SetExpr::SetOperation { .. } => self.compound(body, context),
+            body => bail!("unsupported query: {body}"),
+        }
+    }
+
+    fn select(
+        &self,
+        select: &Select,
+        order_by: &[OrderByExpr],
+        context: Context,
+    ) -> anyhow::Result<ResultSet> {
+        // Wildcards only expand to column references, so the aggregate and window calls are
+        // known before the FROM clause is read.
+        let items = select.projection.iter().filter_map(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+            _ => None,
+        });
+        let order_exprs = order_by.iter().map(|order| &order.expr);
+        let calls = collect_aggregates(
+            items
+                .clone()
+                .chain(order_exprs.clone())
+                .chain(select.having.as_ref()),
+        )?;
+        let windows = collect_windows(items.chain(order_exprs), &select.named_window)?;
+        let group_by = group_by(select);
+        let is_aggregate = !calls.is_empty() || !group_by.is_empty();
+
+        let mut source = self.from(
+            select,
+            order_by,
+            if is_aggregate { group_by } else { &[] },
+            &calls,
+            context,
+        )?;
+
+        let projection = result_columns(select, &source.columns)?;
+        let columns = projection
+            .iter()
+            .map(|column| column.name.clone())
+            .collect();
+        let decl_types = projection
+            .iter()
+            .map(|column| column.decl_type.clone())
+            .collect();
+        let mut exprs: Vec<&Expr> = projection.iter().map(|column| &column.expr).collect();
+        let ncolumns = exprs.len();
+
+        // ORDER BY terms that name a result column sort by it. The others are computed along
+        // with the result columns and dropped after sorting.
+        let mut order_columns = Vec::new();
+        for (i, order) in order_by.iter().enumerate() {
+            let (expr, collation) = strip_collate(&order.expr)?;
+            let column = match ordinal(expr, i, ncolumns)? {
+                Some(column) => column,
+                None => match alias_position(&projection, expr) {
+                    Some(column) => column,
+                    None => {
+                        exprs.push(&order.expr);
+                        exprs.len() - 1
+                    }
+                },
+            };
+            order_columns.push((column, collation));
+        }
+
+        let scope = Scope::new(&source.columns, &[]);
+        let mut collations = exprs
+            .iter()
+            .map(|expr| collation(expr, &scope))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if let Some(selection) = &select.selection {
+            let mut rows = Vec::new();
+            for row in source.rows {
+                let scope = Scope::within(context, &source.columns, &row);
+                if eval(selection, &scope)?.as_bool() == Some(true) {
+                    rows.push(row);
+                }
+            }
+            source.rows = rows;
+        }
+
+        let mut staged = if is_aggregate {
+            self.aggregate(select, &projection, &mut source, group_by, &calls, context)?
+        } else {
+            if select.having.is_some() {
+                bail!("a GROUP BY clause is required before HAVING");
+            }
+            std::mem::take(&mut source.rows)
+                .into_iter()
+                .map(|row| (row, HashMap::new()))
+                .collect()
+        };
+
+        if !windows.is_empty() {
+            compute_windows(&windows, &source.columns, &mut staged, context)?;
+        }
+
+        let mut rows = Vec::new();
+        for (row, results) in &staged {
+            let scope = Scope {
+                columns: &source.columns,
+                row,
+                aggregates: Some(results),
+                context,
+            };
+            rows.push(
+                exprs
+                    .iter()
+                    .map(|expr| eval(expr, &scope))
+                    .collect::<anyhow::Result<_>>()?,
+            );
+        }
+
+        match &select.distinct {
+            None => {}
+            Some(Distinct::Distinct) => rows = distinct(rows, &collations[..ncolumns]),
+            Some(Distinct::On(_)) => bail!("DISTINCT ON is not supported"),
+        }
+
+        let terms: Vec<SortTerm> = order_by
+            .iter()
+            .zip(order_columns)
+            .map(|(order, (column, collation))| {
+                let collation = collation.or(collations[column]).unwrap_or_default();
+                SortTerm::new(column, order, collation)
+            })
+            .collect();
+        sort(&mut rows, &terms);
+        for row in &mut rows {
+            row.truncate(ncolumns);
+        }
+        collations.truncate(ncolumns);
+
+        Ok(ResultSet {
+            columns,
+            rows,
+            collations,
+            decl_types,
+        })
+    }
+
+    fn aggregate(
+        &self,
+        select: &Select,
+        projection: &[ResultColumn],
+        source: &mut Relation,
+        group_by: &[Expr],
+        calls: &[AggregateCall],
+        context: Context,
+    ) -> anyhow::Result<Vec<WindowRow>> {
+        // GROUP BY may refer to result columns by their position, and GROUP BY and HAVING by
+        // their alias.
+        let group_by = group_by
+            .iter()
+            .map(|expr| match expr {
+                Expr::Value(Value::Number(num, _)) => match num.parse::<usize>() {
+                    Ok(n) if (1..=projection.len()).contains(&n) => {
+                        Ok(projection[n - 1].expr.clone())
+                    }
+                    _ => bail!(
+                        "GROUP BY term out of range - should be between 1 and {}",
+                        projection.len()
+                    ),
+                },
+                expr => Ok(resolve_aliases(expr, projection, &source.columns)),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let having = select
+            .having
+            .as_ref()
+            .map(|having| resolve_aliases(having, projection, &source.columns));
+
+        let rows = std::mem::take(&mut source.rows);
+        let groups = if source.grouped {
+            stream_aggregate(&source.columns, rows, &group_by, calls, context)?
+        } else {
+            hash_aggregate(&source.columns, rows, &group_by, calls, context)?
+        };
+
+        let mut rows = Vec::new();
+        for group in groups {
+            let aggregates = group.results(calls)?;
+            let scope = Scope {
+                columns: &source.columns,
+                row: &group.row,
+                aggregates: Some(&aggregates),
+                context,
+            };
+
+            if let Some(having) = &having {
+                if eval(having, &scope)?.as_bool() != Some(true) {
+                    continue;
+                }
+            }
+
+            rows.push((group.row, aggregates));
+        }
+
+        Ok(rows)
+    }
+
+    /// Reads the FROM clause. When `group_by` names columns that lead an index, the rows are
+    /// read in index order so they can be aggregated as a stream. When an index holds every
+    /// column of the table the query uses, the rows are built from the index alone. When the
+    /// only aggregate is `min()` or `max()` of the rowid or of an indexed column, only the row
+    /// it picks is read.
+    fn from(
+        &self,
+        select: &Select,
+        order_by: &[OrderByExpr],
+        group_by: &[Expr],
+        calls: &[AggregateCall],
+        context: Context,
+    ) -> anyhow::Result<Relation> {
+        let table_ref = match select.from.as_slice() {
+            [] => {
+                return Ok(Relation {
+                    columns: Vec::new(),
+                    rows: vec![Vec::new()],
+                    grouped: true,
+                })
+            }
+            [table_with_joins] if table_with_joins.joins.is_empty() => {
+                match self.source(&table_with_joins.relation, context)? {
+                    Source::Table(table_ref) => table_ref,
+                    Source::Derived(relation) => return Ok(relation),
+                    Source::Function(function) => return function.relation(context),
+                }
+            }
+            _ => return self.join_tables(select, context),
+        };
+
+        let columns = table_ref.columns();
+        let (access, grouped) =
+            self.table_access(select, order_by, group_by, calls, &table_ref, context)?;
+
+        Ok(Relation {
+            rows: self.read_access(table_ref.table, &access)?,
+            columns,
+            grouped,
+        })
+    }
+
+    /// Chooses how to read the only table of a FROM clause, and tells whether the rows come
+    /// ordered by the GROUP BY key.
+    pub fn table_access<'s>(
+        &'s self,
+        select: &Select,
+        order_by: &[OrderByExpr],
+        group_by: &[Expr],
+        calls: &[AggregateCall],
+        table_ref: &TableRef<'s>,
+        context: Context,
+    ) -> anyhow::Result<(Access<'s>, bool)> {
+        let table = table_ref.table;
+        let terms = select.selection.as_ref().map(conjuncts).unwrap_or_default();
+        let used = used_columns(select, order_by, &table_ref.columns());
+        let used = used.as_deref();
+        let group_columns = self.group_columns(table_ref, group_by);
+
+        let choose = || {
+            if let (None, [], [call]) = (&select.selection, group_by, calls) {
+                let max = match call.func {
+                    AggregateFunc::Min => Some(false),
+                    AggregateFunc::Max => Some(true),
+                    _ => None,
+                };
+                let access = match (max, call.args.as_slice(), &call.filter) {
+                    (Some(max), [arg], None) => {
+                        plan::min_max(&self.schema, table_ref, arg, max, used)
+                    }
+                    _ => None,
+                };
+                if let Some(access) = access {
+                    return Ok(access);
+                }
+            }
+
+            plan::access(
+                &self.schema,
+                table_ref,
+                &terms,
+                used,
+                group_columns.as_deref(),
+                context,
+            )
+        };
+
+        let access = self.planned_access(
+            &select.from[0].relation,
+            table_ref,
+            &terms,
+            used,
+            context,
+            choose,
+        )?;
+        let grouped = group_columns
+            .as_deref()
+            .is_some_and(|group| plan::group_order(table, &access, group));
+        Ok((access, grouped))
+    }
+
+    /// Chooses how to read the table of `factor` with `choose` the first time the statement
+    /// runs, from the shape of its terms. Every run, the first included, reads it that way with
+    /// the keys the terms give for the values bound to the parameters.
+    pub fn planned_access<'s>(
+        &'s self,
+        factor: &TableFactor,
+        table_ref: &TableRef<'s>,
+        terms: &[&Expr],
+        used: Option<&[usize]>,
+        context: Context,
+        choose: impl FnOnce() -> anyhow::Result<Access<'s>>,
+    ) -> anyhow::Result<Access<'s>> {
+        let key = factor as *const TableFactor;
+        let planned = self.plans.borrow().accesses.get(&key).cloned();
+        let choice = match planned {
+            Some(choice) => choice,
+            None => {
+                let choice = choose()?.choice();
+                self.plans.borrow_mut().accesses.insert(key, choice.clone());
+                choice
+            }
+        };
+
+        plan::bind(&self.schema, table_ref, terms, used, &choice, context)
+    }
+
+    /// Reads the rows of the table of `factor`. The planner looks for terms of the WHERE
+    /// clause that let it read only the rows that can match, by rowid or through an index. The
+    /// filter itself is still applied to the rows afterwards.
+    pub fn read_table(
+        &self,
+        factor: &TableFactor,
+        table_ref: &TableRef,
+        terms: &[&Expr],
+        context: Context,
+    ) -> anyhow::Result<Vec<Vec<SerialValue>>> {
+        let access = self.join_access(factor, table_ref, terms, context)?;
+
+        self.read_access(table_ref.table, &access)
+    }
+
+    /// Chooses how to read the table a join starts with.
+    pub fn join_access<'s>(
+        &'s self,
+        factor: &TableFactor,
+        table_ref: &TableRef<'s>,
+        terms: &[&Expr],
+        context: Context,
+    ) -> anyhow::Result<Access<'s>> {
+        self.planned_access(factor, table_ref, terms, None, context, || {
+            plan::access(&self.schema, table_ref, terms, None, None, context)
+        })
+    }
+
+    /// Finds the rowids of the rows an access path reads, without reading the rows when an
+    /// index or the rowids themselves tell.
+    fn access_rowids(&self, table: &TableSchema, access: &Access) -> anyhow::Result<Vec<i64>> {
+        Ok(match access {
+            Access::Rowids(rowids) => rowids.clone(),
+            Access::Index(seek) if seek.finds_nothing() => Vec::new(),
+            Access::Index(seek) => self
+                .db
+                .search_index_range(seek.index.root_page, &|entry| seek.position(entry))?
+                .iter()
+                .filter_map(|entry| entry.columns.last()?.data().as_integer())
+                .collect(),
+            // The rowid is the last column of a table row.
+            access => self
+                .read_access(table, access)?
+                .iter()
+                .filter_map(|row| row.last()?.as_integer())
+                .collect(),
+        })
+    }
+
+    /// Returns the automatic index of `table` on `columns`, each compared with its collating
+    /// sequence, reading the table to build it the first time the statement needs it. Returns
+    /// None when its rows take more than the memory budget.
+    pub fn auto_index(
+        &self,
+        table: &TableSchema,
+        columns: Vec<(usize, Collation)>,
+    ) -> anyhow::Result<Option<Rc<AutoIndex>>> {
+        let key = (table.root_page, columns);
+        if let Some(index) = self.auto_indexes.borrow().get(&key) {
+            return Ok(index.clone());
+        }
+
+        let rows = self
+            .db
+            .read_table(table.root_page)?
+            .into_iter()
+            .map(|(rowid, record)| table_row(table, rowid, &record))
+            .collect();
+        let index = AutoIndex::build(rows, &key.1, self.memory_budget).map(Rc::new);
+        self.auto_indexes.borrow_mut().insert(key, index.clone());
+
+        Ok(index)
+    }
+
+    /// Reads the rows of a table the way the planner chose. Rows found through an index come
+    /// in index order, the others in rowid order.
+    fn read_access(
+        &self,
+        table: &TableSchema,
+        access: &Access,
+    ) -> anyhow::Result<Vec<Vec<SerialValue>>> {
+        let records = match access {
+            Access::Scan => self.db.read_table(table.root_page)?,
+            Access::Rowids(rowids) => self.db.read_ids_from_table(table.root_page, rowids)?,
+            Access::RowidRange(rowids) => {
+                self.db.read_table_range(table.root_page, rowids.clone())?
+            }
+            Access::IndexOrder(index) => self.read_table_by_index(table, index)?,
+            Access::IndexScan(index) => {
+                return Ok(self
+                    .db
+                    .scan_index(index.root_page)?
+                    .iter()
+                    .map(|entry| index_row(table, index, entry))
+                    .collect())
+            }
+            Access::RowidEdge { last } => self
+                .db
+                .table_edge(table.root_page, *last)?
+                .into_iter()
+                .collect(),
+            Access::IndexEdge {
+                index,
+                last,
+                covering,
+            } => {
+                let entry = if *last {
+                    self.db.last_index_entry(index.root_page)?
+                } else {
+                    // NULLs sort first in an index, and min() skips them unless every value
+                    // is NULL.
+                    match self
+                        .db
+                        .first_index_entry(
+                            index.root_page,
+                            &|entry| match entry.columns.first() {
+                                Some(column) if !column.data().is_null() => Ordering::Equal,
+                                _ => Ordering::Less,
+                            },
+                        )? {
+                        Some(entry) => Some(entry),
+                        None => self
+                            .db
+                            .first_index_entry(index.root_page, &|_| Ordering::Equal)?,
+                    }
+                };
+
+                if *covering {
+                    return Ok(entry
+                        .iter()
+                        .map(|entry| index_row(table, index, entry))
+                        .collect());
+                }
+                let rowids: Vec<i64> = entry
+                    .iter()
+                    .filter_map(|entry| entry.columns.last()?.data().as_integer())
+                    .collect();
+                self.db.read_ids_from_table(table.root_page, &rowids)?
+            }
+            Access::MultiIndex { branches, .. } => {
+                let mut rowids = Vec::new();
+                for branch in branches {
+                    rowids.extend(self.access_rowids(table, branch)?);
+                }
+                rowids.sort_unstable();
+                rowids.dedup();
+                self.db.read_ids_from_table(table.root_page, &rowids)?
+            }
+            Access::Index(seek) if seek.finds_nothing() => Vec::new(),
+            Access::Index(seek) => {
+                let entries = self
+                    .db
+                    .search_index_range(seek.index.root_page, &|entry| seek.position(entry))?;
+                if seek.covering {
+                    return Ok(entries
+                        .iter()
+                        .map(|entry| index_row(table, seek.index, entry))
+                        .collect());
+                }
+
+                let rowids: Vec<i64> = entries
+                    .iter()
+                    .filter_map(|entry| entry.columns.last()?.data().as_integer())
+                    .collect();
+                self.db.read_ids_in_order(table.root_page, &rowids)?
+            }
+        };
+
+        Ok(records
+            .into_iter()
+            .map(|(rowid, record)| table_row(table, rowid, &record))
+            .collect())
+    }
+
+    /// Returns the positions of the table columns named by `group_by`, if it only names columns.
+    fn group_columns(&self, table_ref: &TableRef, group_by: &[Expr]) -> Option<Vec<usize>> {
+        let table = table_ref.table;
+        if group_by.is_empty() {
+            return None;
+        }
+
+        group_by
+            .iter()
+            .map(|expr| match expr {
+                Expr::Identifier(ident) => table.column_index(&ident.value),
+                Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+                    if idents[0].value.eq_ignore_ascii_case(&table_ref.qualifier) {
+                        table.column_index(&idents[1].value)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Reads a table in the order of one of its indexes, looking up the rowid of each index
+    /// entry in the table.
+    fn read_table_by_index(
+        &self,
+        table: &TableSchema,
+        index: &IndexSchema,
+    ) -> anyhow::Result<Vec<(i64, Record)>> {
+        let rowids: Vec<i64> = self
+            .db
+            .scan_index(index.root_page)?
+            .iter()
+            .filter_map(|entry| entry.columns.last()?.data().as_integer())
+            .collect();
+
+        self.db.read_ids_in_order(table.root_page, &rowids)
+    }
+}
+
+/// Parses a statement, which must be a single SELECT.
+pub fn parse_query(sql: &str) -> anyhow::Result<Query> {
+    let mut statements = Parser::parse_sql(&GenericDialect {}, sql)?;
+    let _ = visit_expressions_mut(&mut statements, |expr| {
+        match expr {
+            Expr::JsonAccess { .. } => {
+                *expr = json::reassociate(std::mem::replace(expr, Expr::Value(Value::Null)));
+            }
+            Expr::BinaryOp {
+                op: BinaryOperator::StringConcat,
+                ..
+            } => {
+                *expr = reassociate_concat(std::mem::replace(expr, Expr::Value(Value::Null)));
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    match statements.pop() {
+        Some(Statement::Query(query)) if statements.is_empty() => Ok(*query),
+        Some(_) if statements.is_empty() => bail!("only SELECT statements are supported"),
+        _ => bail!("expected exactly one statement"),
+    }
+}
+
+/// Builds a row like [`table_row`] from an index entry, which holds the indexed columns
+/// followed by the rowid. The columns that are not in the index are NULL.
+pub fn index_row(table: &TableSchema, index: &IndexSchema, entry: &Record) -> Vec<SerialValue> {
+    let rowid = entry
+        .columns
+        .last()
+        .and_then(|column| column.data().as_integer())
+        .unwrap_or_default();
+
+    let mut row = vec![SerialValue::Null; table.columns.len()];
+    for (column, value) in index.columns.iter().zip(&entry.columns) {
+        if let Some(i) = table.column_index(&column.name) {
+            row[i] = stored_value(&table.columns[i], value.data());
+        }
+    }
+    if let Some(i) = table.rowid_alias {
+        row[i] = SerialValue::I64(rowid);
+    }
+    row.push(SerialValue::I64(rowid));
+
+    row
+}
+
+/// The positions among `columns` of the columns a SELECT refers to anywhere, including its
+/// subqueries. `*` and `t.*` count as the columns they expand to. Returns None when a name is
+/// ambiguous or a wildcard names another table.
+fn used_columns(
+    select: &Select,
+    order_by: &[OrderByExpr],
+    columns: &[ColumnName],
+) -> Option<Vec<usize>> {
+    let mut used = Vec::new();
+    for item in &select.projection {
+        let qualifier = match item {
+            SelectItem::Wildcard(_) => None,
+            SelectItem::QualifiedWildcard(name, _) => Some(name.to_string()),
+            _ => continue,
+        };
+        let expanded = columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| !column.hidden);
+        for (i, column) in expanded {
+            match (&qualifier, &column.table) {
+                (None, _) => used.push(i),
+                (Some(qualifier), Some(table)) if qualifier.eq_ignore_ascii_case(table) => {
+                    used.push(i)
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    let scope = Scope::new(columns, &[]);
+    let mut visit = |expr: &Expr| {
+        let resolved = match expr {
+            Expr::Identifier(ident) => scope.resolve(None, &ident.value),
+            Expr::CompoundIdentifier(idents) if idents.len() >= 2 => scope.resolve(
+                Some(&idents[idents.len() - 2].value),
+                &idents[idents.len() - 1].value,
+            ),
+            _ => return ControlFlow::Continue(()),
+        };
+        match resolved {
+            Ok(Some(i)) => {
+                used.push(i);
+                ControlFlow::Continue(())
+            }
+            Ok(None) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    };
+
+    let flow = visit_expressions(select, &mut visit);
+    if flow.is_break() {
+        return None;
+    }
+    for order in order_by {
+        if visit_expressions(&order.expr, &mut visit).is_break() {
+            return None;
+        }
+    }
+
+    used.sort_unstable();
+    used.dedup();
+    Some(used)
+}
+
+/// Builds a row with the declared columns of the table followed by the rowid.
+pub fn table_row(table: &TableSchema, rowid: i64, record: &Record) -> Vec<SerialValue> {
+    let mut row: Vec<SerialValue> = (0..table.columns.len())
+        .map(|i| {
+            if table.rowid_alias == Some(i) {
+                SerialValue::I64(rowid)
+            } else {
+                record
+                    .columns
+                    .get(i)
+                    .map(|column| stored_value(&table.columns[i], column.data()))
+                    .unwrap_or(SerialValue::Null)
+            }
+        })
+        .collect();
+    row.push(SerialValue::I64(rowid));
+
+    row
+}
+
+/// The value a column holds as read from a record. SQLite writes REAL values that have no
+/// fractional part as integers to save space, so they are turned back into doubles here.
+fn stored_value(column: &ColumnSchema, value: &SerialValue) -> SerialValue {
+    match column.affinity {
+        Affinity::Real if value.as_integer().is_some() => value.apply_affinity(Affinity::Real),
+        _ => value.clone(),
+    }
+}
+
+/// Removes rows whose first values, one for each of `collations`, duplicate those of an earlier
+/// row. Values are compared the way SQLite compares them, so `1` and `1.0` are duplicates while
+/// `1` and `'1'` are not, NULLs are equal to each other and text is equal under the collating
+/// sequence of its column.
+pub fn distinct(
+    rows: Vec<Vec<SerialValue>>,
+    collations: &[Option<Collation>],
+) -> Vec<Vec<SerialValue>> {
+    let mut seen = HashSet::new();
+    rows.into_iter()
+        .filter(|row| {
+            seen.insert(
+                row.iter()
+                    .zip(collations)
+                    .map(|(value, collation)| collation.unwrap_or_default().key(value))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+/// Returns the result column whose alias an ORDER BY, GROUP BY or HAVING term is.
+fn alias_position(projection: &[ResultColumn], expr: &Expr) -> Option<usize> {
+    let Expr::Identifier(ident) = expr else {
+        return None;
+    };
+
+    projection
+        .iter()
+        .position(|column| column.aliased && column.name.eq_ignore_ascii_case(&ident.value))
+}
+
+/// Replaces the names in `expr` that are the alias of a result column, and not a column of
+/// `columns`, with the aliased expression. Subqueries keep their own names.
+fn resolve_aliases(expr: &Expr, projection: &[ResultColumn], columns: &[ColumnName]) -> Expr {
+    struct AliasVisitor<'a> {
+        projection: &'a [ResultColumn],
+        scope: Scope<'a>,
+        query_depth: usize,
+    }
+
+    impl VisitorMut for AliasVisitor<'_> {
+        type Break = ();
+
+        fn pre_visit_query(&mut self, _query: &mut Query) -> ControlFlow<Self::Break> {
+            self.query_depth += 1;
+            ControlFlow::Continue(())
+        }
+
+        fn post_visit_query(&mut self, _query: &mut Query) -> ControlFlow<Self::Break> {
+            self.query_depth -= 1;
+            ControlFlow::Continue(())
+        }
+
+        // Replacing after the children are visited keeps the aliased expression from being
+        // searched for aliases in turn.
+        fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+            if let (Expr::Identifier(ident), 0) = (&*expr, self.query_depth) {
+                if !matches!(self.scope.resolve(None, &ident.value), Ok(Some(_))) {
+                    if let Some(i) = alias_position(self.projection, expr) {
+                        *expr = self.projection[i].expr.clone();
+                    }
+                }
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut expr = expr.clone();
+    let _ = expr.visit(&mut AliasVisitor {
+        projection,
+        scope: Scope::new(columns, &[]),
+        query_depth: 0,
+    });
+
+    expr
+}
+
+pub fn group_by(select: &Select) -> &[Expr] {
+    match &select.group_by {
+        GroupByExpr::Expressions(exprs) => exprs,
+        GroupByExpr::All => &[],
+    }
+}
+
+/// A column of the result of a select, with the name SQLite gives it.
+pub struct ResultColumn {
+    pub expr: Expr,
+    pub name: String,
+    /// True when the name is an alias given with AS, which ORDER BY may refer to.
+    aliased: bool,
+    /// The declared type of the table column the expression reads as it is.
+    pub decl_type: Option<String>,
+}
+
+/// Expands the wildcards of the projection into references to the columns of `source` and
+/// names every result column. A column reference is named after the column; any other
+/// expression is named by its text.
+pub fn result_columns(select: &Select, source: &[ColumnName]) -> anyhow::Result<Vec<ResultColumn>> {
+    let mut columns = Vec::new();
+    for item in &select.projection {
+        let (expr, alias) = match item {
+            SelectItem::UnnamedExpr(expr) => (expr, None),
+            SelectItem::ExprWithAlias { expr, alias } => (expr, Some(alias)),
+            SelectItem::Wildcard(_) => {
+                if select.from.is_empty() {
+                    bail!("no tables specified");
+                }
+                // The right-hand copies of USING and NATURAL join columns are left out.
+                columns.extend(
+                    source
+                        .iter()
+                        .filter(|column| !column.hidden && !column.shadowed)
+                        .map(ResultColumn::reference),
+                );
+                continue;
+            }
+            SelectItem::QualifiedWildcard(name, _) => {
+                let table = name.0.last().map(|ident| ident.value.as_str());
+                let mut found = source
+                    .iter()
+                    .filter(|column| {
+                        !column.hidden
+                            && column
+                                .table
+                                .as_deref()
+                                .zip(table)
+                                .is_some_and(|(a, b)| a.eq_ignore_ascii_case(b))
+                    })
+                    .peekable();
+                if found.peek().is_none() {
+                    bail!("no such table: {name}");
+                }
+                columns.extend(found.map(ResultColumn::reference));
+                continue;
+            }
+        };
+
+        let column = referenced_column(expr, source);
+        let name = match alias {
+            Some(alias) => alias.value.clone(),
+            None => column
+                .map(|column| column.name.clone())
+                .unwrap_or_else(|| expr.to_string()),
+        };
+        columns.push(ResultColumn {
+            expr: expr.clone(),
+            name,
+            aliased: alias.is_some(),
+            decl_type: column.and_then(|column| column.decl_type.clone()),
+        });
+    }
+
+    Ok(columns)
+}
+
+impl ResultColumn {
+    fn reference(column: &ColumnName) -> Self {
+        let mut idents: Vec<Ident> = column.table.iter().map(Ident::new).collect();
+        idents.push(Ident::new(&column.name));
+
+        Self {
+            expr: Expr::CompoundIdentifier(idents),
+            name: column.name.clone(),
+            aliased: false,
+            decl_type: column.decl_type.clone(),
+        }
+    }
+}
+
+/// The column of `source` an expression refers to, if it is a plain column reference.
+fn referenced_column<'c>(expr: &Expr, source: &'c [ColumnName]) -> Option<&'c ColumnName> {
+    let (table, name) = match expr {
+        Expr::Identifier(name) => (None, name),
+        Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [.., table, name] => (Some(table.value.as_str()), name),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let i = Scope::new(source, &[]).resolve(table, &name.value).ok()??;
+    Some(&source[i])
+}
+
+impl Subqueries for Executor<'_> {
+    /// Runs a subquery with the row it appears in as its outer row. Results of subqueries that
+    /// never looked at that row are kept and reused until the statement finishes.
+    fn run(&self, query: &Query, scope: &Scope) -> anyhow::Result<Rc<ResultSet>> {
+        let key = query as *const Query;
+        if let Some((cached, result)) = self.subquery_cache.borrow().get(&key) {
+            if cached == query {
+                return Ok(result.clone());
+            }
+        }
+
+        let correlated = Cell::new(false);
+        let context = Context {
+            subqueries: Some(self),
+            outer: Some(scope),
+            correlated: Some(&correlated),
+            ctes: scope.context.ctes,
+            now: scope.context.now,
+            parameters: scope.context.parameters,
+        };
+        let result = Rc::new(self.query(query, context)?);
+
+        if !correlated.get() {
+            self.subquery_cache
+                .borrow_mut()
+                .insert(key, (query.clone(), result.clone()));
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use sqlparser::{dialect::GenericDialect, parser::Parser};
+
+    use crate::{
+        column::SerialValue,
+        database::Database,
+        executor::{distinct, Executor},
+        expr::Context,
+        join::{conjuncts, TableRef},
+        plan::{self, Access},
+    };
+
+    #[test]
+    fn test_distinct_compares_typed_values() {
+        let rows = vec![
+            vec![SerialValue::I8(1)],
+            vec![SerialValue::String("1".to_string())],
+            vec![SerialValue::Float64(1.0)],
+            vec![SerialValue::Null],
+            vec![SerialValue::I64(1)],
+            vec![SerialValue::Null],
+        ];
+
+        assert_eq!(
+            distinct(rows, &[None]),
+            vec![
+                vec![SerialValue::I8(1)],
+                vec![SerialValue::String("1".to_string())],
+                vec![SerialValue::Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_result_column_names() {
+        let db = Database::read_file("library.db").expect("read library.db");
+        let executor = Executor::new(&db).expect("read schema");
+        let result = executor
+            .execute(
+                "SELECT B.ID, title AS t, b.id + 1, count(*), a.* FROM books AS b, authors AS a",
+            )
+            .expect("execute");
+        assert_eq!(
+            result.columns,
+            vec!["id", "t", "b.id + 1", "count(*)", "id", "name", "country"]
+        );
+
+        let result = executor
+            .execute("SELECT * FROM (SELECT 1 AS a, 2 AS a, 3 AS A)")
+            .expect("execute");
+        assert_eq!(result.columns, vec!["a", "a:1", "A:2"]);
+    }
+
+    #[test]
+    fn test_access_paths() {
+        let db = Database::read_file("library.db").expect("read library.db");
+        let executor = Executor::new(&db).expect("read schema");
+        let table = TableRef {
+            table: executor.schema.table("readings").expect("readings table"),
+            qualifier: "readings".to_string(),
+        };
+        let access = |selection: &str| {
+            let expr = Parser::new(&GenericDialect)
+                .try_with_sql(selection)
+                .and_then(|mut parser| parser.parse_expr())
+                .expect("parse");
+            plan::access(
+                &executor.schema,
+                &table,
+                &conjuncts(&expr),
+                None,
+                None,
+                Context::default(),
+            )
+            .expect("plan")
+        };
+
+        let Access::Index(seek) =
+            access("value > 100 AND 'sensor-3' = sensor AND value <= 120 AND value > 90")
+        else {
+            panic!("expected an index seek");
+        };
+        assert_eq!(seek.index.name, "idx_readings_sensor_value");
+        assert_eq!(seek.key, vec![SerialValue::String("sensor-3".to_string())]);
+        assert!(matches!(seek.low, Bound::Excluded(SerialValue::I64(100))));
+        assert!(matches!(seek.high, Bound::Included(SerialValue::I64(120))));
+
+        assert!(matches!(
+            access("sensor = 'sensor-3' AND id = 4294968461"),
+            Access::Rowids(rowids) if rowids == vec![4294968461]
+        ));
+        assert!(matches!(
+            access("rowid BETWEEN 10 AND 20 AND value > 5"),
+            Access::RowidRange(rowids) if rowids == (10..=20)
+        ));
+        let Access::Index(seek) = access("sensor LIKE 'Sensor-1%'") else {
+            panic!("expected an index range");
+        };
+        assert!(seek.key.is_empty());
+        assert!(matches!(seek.low, Bound::Included(SerialValue::String(low)) if low == "SENSOR-1"));
+        assert!(
+            matches!(seek.high, Bound::Excluded(SerialValue::String(high)) if high == "sensor-2")
+        );
+
+        assert!(matches!(access("value = 5"), Access::Scan));
+        assert!(matches!(
+            access("sensor = 'sensor-3' COLLATE NOCASE"),
+            Access::Scan
+        ));
+
+        let Access::MultiIndex { branches, .. } =
+            access("(sensor = 'sensor-3' AND value > 5) OR id < 10")
+        else {
+            panic!("expected a multi-index OR");
+        };
+        assert!(
+            matches!(&branches[0], Access::Index(seek) if seek.key.len() == 1 && matches!(seek.low, Bound::Excluded(_)))
+        );
+        assert!(matches!(&branches[1], Access::RowidRange(rowids) if *rowids.end() == 9));
+        assert!(matches!(
+            access("sensor = 'sensor-3' OR value = 5"),
+            Access::Scan
+        ));
+    }
+
+    #[test]
+    fn test_covering_index() {
+        let db = Database::read_file("library.db").expect("read library.db");
+        let executor = Executor::new(&db).expect("read schema");
+        let table = TableRef {
+            table: executor.schema.table("readings").expect("readings table"),
+            qualifier: "readings".to_string(),
+        };
+        let expr = Parser::new(&GenericDialect)
+            .try_with_sql("sensor = 'sensor-3'")
+            .and_then(|mut parser| parser.parse_expr())
+            .expect("parse");
+        let terms = conjuncts(&expr);
+
+        // id aliases the rowid, which every index entry ends with.
+        let access = plan::access(
+            &executor.schema,
+            &table,
+            &terms,
+            Some(&[0, 1, 2]),
+            None,
+            Context::default(),
+        );
+        assert!(matches!(access, Ok(Access::Index(seek)) if seek.covering));
+        let access = plan::access(
+            &executor.schema,
+            &table,
+            &terms,
+            None,
+            None,
+            Context::default(),
+        );
+        assert!(matches!(access, Ok(Access::Index(seek)) if !seek.covering));
+        let access = plan::access(
+            &executor.schema,
+            &table,
+            &[],
+            Some(&[]),
+            None,
+            Context::default(),
+        );
+        assert!(
+            matches!(access, Ok(Access::IndexScan(index)) if index.name == "idx_readings_sensor_value")
+        );
+    }
+}
@@ -0,0 +1,773 @@
+use std::{cell::Cell, collections::HashMap, fmt, ops::Bound};
+
+use anyhow::bail;
+use sqlparser::ast::{
+    Cte, Expr, JoinConstraint, JoinOperator, OrderByExpr, Query, Select, SelectItem, SetExpr,
+    SetOperator, SetQuantifier, TableFactor,
+};
+
+use crate::{
+    aggregate::{collect_aggregates, AggregateCall},
+    column::SerialValue,
+    compound::flatten,
+    cte::{find_cte, recursive_parts, rename, CteRef, CteScope},
+    executor::{group_by, parse_query, result_columns, Executor, ResultSet},
+    expr::{visit_outside_subqueries, ColumnName, Context, Scope},
+    join::{
+        conjuncts, derived_columns, hash_or_scan, join_kind, using_terms, Probe, Source, TableRef,
+    },
+    plan::Access,
+};
+
+/// One line of a query plan. Steps whose parent is 0 are at the top of the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStep {
+    pub id: usize,
+    pub parent: usize,
+    pub detail: String,
+}
+
+/// How a query is run, in the words of SQLite's EXPLAIN QUERY PLAN. It displays as the tree
+/// the sqlite3 shell prints.
+#[derive(Debug, Clone, Default)]
+pub struct QueryPlan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl QueryPlan {
+    fn add(&mut self, parent: usize, detail: impl Into<String>) -> usize {
+        let id = self.steps.len() + 1;
+        self.steps.push(PlanStep {
+            id,
+            parent,
+            detail: detail.into(),
+        });
+        id
+    }
+
+    /// Adds how a table is read. The branches of a multi-index OR each get their own step.
+    fn add_access(&mut self, parent: usize, name: &str, access: &Access) {
+        let id = self.add(parent, access_detail(name, access));
+        if let Access::MultiIndex { branches, .. } = access {
+            for (i, branch) in branches.iter().enumerate() {
+                let index = self.add(id, format!("INDEX {}", i + 1));
+                self.add(index, access_detail(name, branch));
+            }
+        }
+    }
+
+    fn write_children(&self, f: &mut fmt::Formatter, parent: usize, prefix: &str) -> fmt::Result {
+        let children: Vec<&PlanStep> = self
+            .steps
+            .iter()
+            .filter(|step| step.parent == parent)
+            .collect();
+
+        for (i, step) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let (branch, indent) = if last { ("`--", "   ") } else { ("|--", "|  ") };
+            writeln!(f, "{prefix}{branch}{}", step.detail)?;
+            self.write_children(f, step.id, &format!("{prefix}{indent}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "QUERY PLAN")?;
+        self.write_children(f, 0, "")
+    }
+}
+
+/// The rows SQLite returns for EXPLAIN QUERY PLAN.
+impl From<QueryPlan> for ResultSet {
+    fn from(plan: QueryPlan) -> Self {
+        let columns: Vec<String> = ["id", "parent", "notused", "detail"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        Self {
+            collations: vec![None; columns.len()],
+            decl_types: vec![None; columns.len()],
+            columns,
+            rows: plan
+                .steps
+                .into_iter()
+                .map(|step| {
+                    vec![
+                        SerialValue::I64(step.id as i64),
+                        SerialValue::I64(step.parent as i64),
+                        SerialValue::I64(0),
+                        SerialValue::String(step.detail),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Returns the statement after `EXPLAIN QUERY PLAN`, if `sql` starts with those keywords.
+pub fn explain_query_plan(sql: &str) -> Option<&str> {
+    let mut rest = sql.trim_start();
+    for keyword in ["EXPLAIN", "QUERY", "PLAN"] {
+        let word = rest.get(..keyword.len())?;
+        rest = &rest[keyword.len()..];
+        if !word.eq_ignore_ascii_case(keyword) || !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        rest = rest.trim_start();
+    }
+
+    Some(rest)
+}
+
+impl Executor<'_> {
+    /// Describes how a SELECT would be run, without running it. The statement may start with
+    /// `EXPLAIN QUERY PLAN`.
+    pub fn query_plan(&self, sql: &str) -> anyhow::Result<QueryPlan> {
+        self.explain_query(&parse_query(explain_query_plan(sql).unwrap_or(sql))?)
+    }
+
+    /// Describes how a parsed SELECT would be run.
+    pub fn explain_query(&self, query: &Query) -> anyhow::Result<QueryPlan> {
+        self.plans.take();
+        self.explain_planned(query, &[])
+    }
+
+    /// Describes how a statement runs with the values bound to its parameters. The choices
+    /// made by the earlier runs of the statement are kept, and the statement runs with those
+    /// made here.
+    pub(crate) fn explain_planned(
+        &self,
+        query: &Query,
+        parameters: &[SerialValue],
+    ) -> anyhow::Result<QueryPlan> {
+        let mut explainer = Explainer {
+            executor: self,
+            plan: QueryPlan::default(),
+            subqueries: 0,
+            materialized: HashMap::new(),
+        };
+        let context = Context {
+            parameters: Some(parameters),
+            ..Context::default()
+        };
+        explainer.query(0, query, context, None)?;
+
+        Ok(explainer.plan)
+    }
+}
+
+/// Walks a query the way the executor runs it, and asks the executor for the same decisions
+/// about how to read each table.
+struct Explainer<'e, 'a> {
+    executor: &'e Executor<'a>,
+    plan: QueryPlan,
+    /// The number of subqueries described so far, which numbers the next one.
+    subqueries: usize,
+    /// The columns of the CTEs materialized so far, which later references only scan.
+    materialized: HashMap<*const Cte, Vec<String>>,
+}
+
+/// The columns of the query a subquery appears in, and whether the subquery refers to them.
+struct Outer<'o> {
+    columns: &'o [ColumnName],
+    correlated: Cell<bool>,
+}
+
+/// A table of the FROM clause, as far as the plan needs to know it.
+enum Input<'s> {
+    Table(TableRef<'s>),
+    /// A subquery, common table expression or table-valued function, with the step that reads
+    /// its rows.
+    Other {
+        scan: String,
+        columns: Vec<ColumnName>,
+    },
+}
+
+impl<'e> Explainer<'e, '_> {
+    /// Describes a query under `parent` and returns the names of its result columns.
+    fn query(
+        &mut self,
+        parent: usize,
+        query: &Query,
+        context: Context,
+        outer: Option<&Outer>,
+    ) -> anyhow::Result<Vec<String>> {
+        let Some(with) = &query.with else {
+            return self.query_body(parent, query, context, outer);
+        };
+
+        let ctes = CteScope {
+            ctes: &with.cte_tables,
+            visible: with.cte_tables.len(),
+            working: None,
+            parent: context.ctes,
+        };
+        self.query_body(
+            parent,
+            query,
+            Context {
+                ctes: Some(&ctes),
+                ..context
+            },
+            outer,
+        )
+    }
+
+    fn query_body(
+        &mut self,
+        parent: usize,
+        query: &Query,
+        context: Context,
+        outer: Option<&Outer>,
+    ) -> anyhow::Result<Vec<String>> {
+        match query.body.as_ref() {
+            SetExpr::Select(select) => self.select(parent, select, &query.order_by, context, outer),
+            body => {
+                let columns = self.set_expr(parent, body, context, outer)?;
+                if !query.order_by.is_empty() {
+                    self.plan.add(parent, "USE TEMP B-TREE FOR ORDER BY");
+                }
+                Ok(columns)
+            }
+        }
+    }
+
+    fn set_expr(
+        &mut self,
+        parent: usize,
+        body: &SetExpr,
+        context: Context,
+        outer: Option<&Outer>,
+    ) -> anyhow::Result<Vec<String>> {
+        match body {
+            SetExpr::Select(select) => self.select(parent, select, &[], context, outer),
+            SetExpr::Query(query) => self.query(parent, query, context, outer),
+            SetExpr::SetOperation { .. } => {
+                let compound = self.plan.add(parent, "COMPOUND QUERY");
+                let mut operands = Vec::new();
+                let first = flatten(body, &mut operands);
+
+                let step = self.plan.add(compound, "LEFT-MOST SUBQUERY");
+                let columns = self.set_expr(step, first, context, outer)?;
+                for (op, set_quantifier, right) in operands {
+                    let detail = match (op, set_quantifier) {
+                        (SetOperator::Union, SetQuantifier::All) => "UNION ALL".to_string(),
+                        (op, _) => format!("{op} USING TEMP B-TREE"),
+                    };
+                    let step = self.plan.add(compound, detail);
+                    self.set_expr(step, right, context, outer)?;
+                }
+
+                Ok(columns)
+            }
+            body => bail!("unsupported query: {body}"),
+        }
+    }
+
+    fn select(
+        &mut self,
+        parent: usize,
+        select: &Select,
+        order_by: &[OrderByExpr],
+        context: Context,
+        outer: Option<&Outer>,
+    ) -> anyhow::Result<Vec<String>> {
+        let items = select.projection.iter().filter_map(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+            _ => None,
+        });
+        let order_exprs = order_by.iter().map(|order| &order.expr);
+        let calls = collect_aggregates(
+            items
+                .clone()
+                .chain(order_exprs.clone())
+                .chain(select.having.as_ref()),
+        )?;
+        let group_by = group_by(select);
+        let is_aggregate = !calls.is_empty() || !group_by.is_empty();
+
+        let (columns, grouped) = self.from(
+            parent,
+            select,
+            order_by,
+            if is_aggregate { group_by } else { &[] },
+            &calls,
+            context,
+        )?;
+
+        let mut exprs: Vec<&Expr> = items.chain(order_exprs).collect();
+        exprs.extend(select.selection.as_ref());
+        exprs.extend(group_by);
+        exprs.extend(select.having.as_ref());
+        for join in select.from.iter().flat_map(|table| &table.joins) {
+            if let Ok((_, Some(JoinConstraint::On(expr)))) = join_kind(&join.join_operator) {
+                exprs.push(expr);
+            }
+        }
+
+        // A column that is not found in the FROM clause belongs to the enclosing query.
+        if let Some(outer) = outer {
+            visit_outside_subqueries(&exprs, |expr| {
+                let (table, name) = match expr {
+                    Expr::Identifier(ident) => (None, &ident.value),
+                    Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+                        (Some(idents[0].value.as_str()), &idents[1].value)
+                    }
+                    _ => return,
+                };
+                if matches!(Scope::new(&columns, &[]).resolve(table, name), Ok(None))
+                    && matches!(
+                        Scope::new(outer.columns, &[]).resolve(table, name),
+                        Ok(Some(_))
+                    )
+                {
+                    outer.correlated.set(true);
+                }
+            });
+        }
+
+        self.subqueries(parent, &exprs, &columns, context)?;
+
+        if is_aggregate && !group_by.is_empty() && !grouped {
+            self.plan.add(parent, "USE TEMP B-TREE FOR GROUP BY");
+        }
+        if select.distinct.is_some() {
+            self.plan.add(parent, "USE TEMP B-TREE FOR DISTINCT");
+        }
+        if !order_by.is_empty() {
+            self.plan.add(parent, "USE TEMP B-TREE FOR ORDER BY");
+        }
+
+        Ok(result_columns(select, &columns)?
+            .into_iter()
+            .map(|column| column.name)
+            .collect())
+    }
+
+    /// Describes the subqueries of a select's expressions. A subquery that refers to a column
+    /// of the select is correlated, and runs again for every row.
+    fn subqueries(
+        &mut self,
+        parent: usize,
+        exprs: &[&Expr],
+        columns: &[ColumnName],
+        context: Context,
+    ) -> anyhow::Result<()> {
+        let mut found = Vec::new();
+        visit_outside_subqueries(exprs, |expr| match expr {
+            Expr::Subquery(query)
+            | Expr::Exists {
+                subquery: query, ..
+            } => found.push(("SCALAR", query.clone())),
+            Expr::InSubquery { subquery, .. } => found.push(("LIST", subquery.clone())),
+            _ => {}
+        });
+
+        for (kind, query) in found {
+            self.subqueries += 1;
+            let n = self.subqueries;
+            let step = self.plan.add(parent, "");
+
+            let outer = Outer {
+                columns,
+                correlated: Cell::new(false),
+            };
+            // The subquery is planned against a row of the select, whose values only count by
+            // their shape, as when it runs.
+            let row = vec![SerialValue::Null; columns.len()];
+            let scope = Scope::within(context, columns, &row);
+            let context = Context {
+                outer: Some(&scope),
+                ..context
+            };
+            self.query(step, &query, context, Some(&outer))?;
+
+            let correlated = if outer.correlated.get() {
+                "CORRELATED "
+            } else {
+                ""
+            };
+            self.plan.steps[step - 1].detail = format!("{correlated}{kind} SUBQUERY {n}");
+        }
+
+        Ok(())
+    }
+
+    /// Describes the FROM clause, and returns its columns and whether its rows come ordered
+    /// by the GROUP BY key.
+    fn from(
+        &mut self,
+        parent: usize,
+        select: &Select,
+        order_by: &[OrderByExpr],
+        group_by: &[Expr],
+        calls: &[AggregateCall],
+        context: Context,
+    ) -> anyhow::Result<(Vec<ColumnName>, bool)> {
+        match select.from.as_slice() {
+            [] => {
+                self.plan.add(parent, "SCAN CONSTANT ROW");
+                Ok((Vec::new(), true))
+            }
+            [table_with_joins] if table_with_joins.joins.is_empty() => {
+                match self.source(parent, &table_with_joins.relation, context)? {
+                    Input::Table(table_ref) => {
+                        let (access, grouped) = self
+                            .executor
+                            .table_access(select, order_by, group_by, calls, &table_ref, context)?;
+                        self.plan.add_access(parent, &table_ref.qualifier, &access);
+                        Ok((table_ref.columns(), grouped))
+                    }
+                    Input::Other { scan, columns } => {
+                        self.plan.add(parent, scan);
+                        Ok((columns, false))
+                    }
+                }
+            }
+            _ => Ok((self.join_tables(parent, select, context)?, false)),
+        }
+    }
+
+    /// Resolves a table of the FROM clause. Subqueries and common table expressions are
+    /// materialized, and their steps are described.
+    fn source(
+        &mut self,
+        parent: usize,
+        factor: &TableFactor,
+        context: Context,
+    ) -> anyhow::Result<Input<'e>> {
+        match factor {
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => {
+                let name = match alias {
+                    Some(alias) => alias.name.value.clone(),
+                    None => {
+                        self.subqueries += 1;
+                        format!("(subquery-{})", self.subqueries)
+                    }
+                };
+                let step = self.plan.add(parent, format!("MATERIALIZE {name}"));
+                let names = self.query(step, subquery, context, None)?;
+
+                Ok(Input::Other {
+                    scan: format!("SCAN {name}"),
+                    columns: derived_columns(names, &[], &[], alias.as_ref(), None)?,
+                })
+            }
+            TableFactor::Table { args: Some(_), .. } => {
+                let Source::Function(function) = self.executor.source(factor, context)? else {
+                    bail!("unsupported table: {factor}");
+                };
+
+                Ok(Input::Other {
+                    scan: format!("SCAN {} VIRTUAL TABLE", function.qualifier),
+                    columns: function.columns(),
+                })
+            }
+            TableFactor::Table { name, alias, .. } => {
+                let cte_name = name
+                    .0
+                    .last()
+                    .map(|ident| ident.value.as_str())
+                    .unwrap_or_default();
+                let names = match find_cte(cte_name, context) {
+                    Some(CteRef::Working(working)) => working.columns.clone(),
+                    Some(CteRef::Defined(ctes, i)) => self.cte(parent, ctes, i, context)?,
+                    None => return Ok(Input::Table(self.executor.table_ref(factor)?)),
+                };
+                let qualifier = alias
+                    .as_ref()
+                    .map_or(cte_name, |alias| alias.name.value.as_str());
+
+                Ok(Input::Other {
+                    scan: format!("SCAN {qualifier}"),
+                    columns: derived_columns(names, &[], &[], alias.as_ref(), Some(cte_name))?,
+                })
+            }
+            factor => bail!("unsupported table: {factor}"),
+        }
+    }
+
+    /// Describes how a CTE is materialized, and returns the names of its columns. A recursive
+    /// CTE runs its initial select once and its recursive select for every row.
+    fn cte(
+        &mut self,
+        parent: usize,
+        ctes: &CteScope,
+        i: usize,
+        context: Context,
+    ) -> anyhow::Result<Vec<String>> {
+        let cte = &ctes.ctes[i];
+        if let Some(columns) = self.materialized.get(&(cte as *const Cte)) {
+            return Ok(columns.clone());
+        }
+        let name = cte.alias.name.value.as_str();
+        let step = self.plan.add(parent, format!("MATERIALIZE {name}"));
+
+        let before = CteScope {
+            ctes: ctes.ctes,
+            visible: i,
+            working: None,
+            parent: ctes.parent,
+        };
+        let before_context = Context {
+            ctes: Some(&before),
+            ..context
+        };
+
+        let Some((initial, recursive, _)) = recursive_parts(cte) else {
+            let columns = self.query(step, &cte.query, before_context, None)?;
+            let columns = rename(cte, named(columns))?.columns;
+            self.materialized.insert(cte, columns.clone());
+            return Ok(columns);
+        };
+
+        let setup = self.plan.add(step, "SETUP");
+        let columns = self.set_expr(setup, initial, before_context, None)?;
+        let working = rename(cte, named(columns))?;
+
+        let scope = CteScope {
+            ctes: ctes.ctes,
+            visible: i + 1,
+            working: Some((name, &working)),
+            parent: ctes.parent,
+        };
+        let step = self.plan.add(step, "RECURSIVE STEP");
+        self.set_expr(
+            step,
+            recursive,
+            Context {
+                ctes: Some(&scope),
+                ..context
+            },
+            None,
+        )?;
+
+        self.materialized.insert(cte, working.columns.clone());
+        Ok(working.columns)
+    }
+
+    /// Describes the joins of the FROM clause in the order the executor joins them, and
+    /// returns their columns.
+    fn join_tables(
+        &mut self,
+        parent: usize,
+        select: &Select,
+        context: Context,
+    ) -> anyhow::Result<Vec<ColumnName>> {
+        if let Some(order) = self.executor.join_order(select, context)? {
+            let mut columns: Option<Vec<ColumnName>> = None;
+            for &i in &order.order {
+                columns = Some(match columns {
+                    None => {
+                        let table_ref = self.executor.table_ref(order.factors[i])?;
+                        let access = self.executor.join_access(
+                            order.factors[i],
+                            &table_ref,
+                            &order.terms,
+                            context,
+                        )?;
+                        self.plan.add_access(parent, &table_ref.qualifier, &access);
+                        table_ref.columns()
+                    }
+                    Some(left) => self.join(
+                        parent,
+                        left,
+                        order.factors[i],
+                        &JoinOperator::CrossJoin,
+                        &order.terms,
+                        context,
+                    )?,
+                });
+            }
+            return Ok(columns.unwrap_or_default());
+        }
+
+        let where_terms = select.selection.as_ref().map(conjuncts).unwrap_or_default();
+
+        let mut columns: Option<Vec<ColumnName>> = None;
+        for table_with_joins in &select.from {
+            columns = Some(match columns {
+                None => match self.source(parent, &table_with_joins.relation, context)? {
+                    Input::Table(table_ref) => {
+                        let access = self.executor.join_access(
+                            &table_with_joins.relation,
+                            &table_ref,
+                            &where_terms,
+                            context,
+                        )?;
+                        self.plan.add_access(parent, &table_ref.qualifier, &access);
+                        table_ref.columns()
+                    }
+                    Input::Other { scan, columns } => {
+                        self.plan.add(parent, scan);
+                        columns
+                    }
+                },
+                Some(left) => self.join(
+                    parent,
+                    left,
+                    &table_with_joins.relation,
+                    &JoinOperator::CrossJoin,
+                    &where_terms,
+                    context,
+                )?,
+            });
+
+            for join in &table_with_joins.joins {
+                let left = columns.take().unwrap_or_default();
+                columns = Some(self.join(
+                    parent,
+                    left,
+                    &join.relation,
+                    &join.join_operator,
+                    &where_terms,
+                    context,
+                )?);
+            }
+        }
+
+        Ok(columns.unwrap_or_default())
+    }
+
+    /// Describes how the rows of a table are found for each left row of a join.
+    fn join(
+        &mut self,
+        parent: usize,
+        left: Vec<ColumnName>,
+        factor: &TableFactor,
+        operator: &JoinOperator,
+        where_terms: &[&Expr],
+        context: Context,
+    ) -> anyhow::Result<Vec<ColumnName>> {
+        let (outer, constraint) = join_kind(operator)?;
+
+        let right = self.source(parent, factor, context)?;
+        let mut right_columns = match &right {
+            Input::Table(table) => table.columns(),
+            Input::Other { columns, .. } => columns.clone(),
+        };
+        let using_terms = using_terms(&left, &mut right_columns, constraint)?;
+
+        let mut terms: Vec<&Expr> = using_terms.iter().collect();
+        if let Some(JoinConstraint::On(expr)) = constraint {
+            terms.extend(conjuncts(expr));
+        }
+        if !outer {
+            terms.extend(where_terms);
+        }
+
+        let mut detail = match &right {
+            Input::Table(table) => {
+                let name = &table.qualifier;
+                match self.executor.planned_probe(
+                    factor,
+                    table,
+                    &left,
+                    &right_columns,
+                    &terms,
+                    context,
+                ) {
+                    Probe::Rowid(..) => {
+                        format!("SEARCH {name} USING INTEGER PRIMARY KEY (rowid=?)")
+                    }
+                    Probe::Index(_, index, _) => format!(
+                        "SEARCH {name} USING INDEX {} ({}=?)",
+                        index.name,
+                        index
+                            .columns
+                            .first()
+                            .map(|column| column.name.as_str())
+                            .unwrap_or_default()
+                    ),
+                    Probe::AutoIndex(table, keys) => format!(
+                        "SEARCH {name} USING AUTOMATIC COVERING INDEX ({})",
+                        keys.iter()
+                            .map(|(i, _)| format!("{}=?", table.columns[*i].name))
+                            .collect::<Vec<_>>()
+                            .join(" AND ")
+                    ),
+                    Probe::Hash(_) => format!("SCAN {name} FOR HASH JOIN"),
+                    Probe::Scan | Probe::Function(_) => format!("SCAN {name}"),
+                }
+            }
+            Input::Other { scan, .. } => match hash_or_scan(&left, &right_columns, &terms) {
+                Probe::Hash(_) => format!("{scan} FOR HASH JOIN"),
+                _ => scan.clone(),
+            },
+        };
+        if outer {
+            detail.push_str(" LEFT-JOIN");
+        }
+        self.plan.add(parent, detail);
+
+        let mut columns = left;
+        columns.extend(right_columns);
+        Ok(columns)
+    }
+}
+
+/// Describes how a table is read, the way SQLite does.
+fn access_detail(name: &str, access: &Access) -> String {
+    match access {
+        Access::Scan => format!("SCAN {name}"),
+        Access::Rowids(_) => format!("SEARCH {name} USING INTEGER PRIMARY KEY (rowid=?)"),
+        Access::RowidRange(rowids) => {
+            let mut terms = Vec::new();
+            if *rowids.start() != i64::MIN {
+                terms.push("rowid>?");
+            }
+            if *rowids.end() != i64::MAX {
+                terms.push("rowid<?");
+            }
+            format!(
+                "SEARCH {name} USING INTEGER PRIMARY KEY ({})",
+                terms.join(" AND ")
+            )
+        }
+        Access::Index(seek) => {
+            let mut terms: Vec<String> = seek.index.columns[..seek.key.len()]
+                .iter()
+                .map(|column| format!("{}=?", column.name))
+                .collect();
+            if let Some(column) = seek.index.columns.get(seek.key.len()) {
+                if !matches!(seek.low, Bound::Unbounded) {
+                    terms.push(format!("{}>?", column.name));
+                }
+                if !matches!(seek.high, Bound::Unbounded) {
+                    terms.push(format!("{}<?", column.name));
+                }
+            }
+            let covering = if seek.covering { "COVERING " } else { "" };
+            format!(
+                "SEARCH {name} USING {covering}INDEX {} ({})",
+                seek.index.name,
+                terms.join(" AND ")
+            )
+        }
+        Access::IndexScan(index) => format!("SCAN {name} USING COVERING INDEX {}", index.name),
+        Access::IndexOrder(index) => format!("SCAN {name} USING INDEX {}", index.name),
+        Access::RowidEdge { .. } => format!("SEARCH {name}"),
+        Access::IndexEdge {
+            index, covering, ..
+        } => {
+            let covering = if *covering { "COVERING " } else { "" };
+            format!("SEARCH {name} USING {covering}INDEX {}", index.name)
+        }
+        Access::MultiIndex { .. } => "MULTI-INDEX OR".to_string(),
+    }
+}
+
+fn named(columns: Vec<String>) -> ResultSet {
+    ResultSet {
+        columns,
+        ..ResultSet::default()
+    }
+}
@@ -0,0 +1,746 @@
+use std::{cell::Cell, cmp::Ordering, collections::HashMap, ops::ControlFlow, rc::Rc};
+
+use anyhow::bail;
+use sqlparser::ast::{
+    visit_expressions, BinaryOperator, Expr, Ident, Query, UnaryOperator, Value, Visit, Visitor,
+};
+
+use crate::{
+    collation::Collation,
+    column::{real_to_integer, Affinity, SerialValue},
+    cte::CteScope,
+    executor::ResultSet,
+    function, json,
+};
+
+/// A column of an intermediate result, optionally qualified by the table it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnName {
+    pub table: Option<String>,
+    pub name: String,
+    /// Hidden columns such as the rowid can be referenced by name but are not part of `*`.
+    pub hidden: bool,
+    /// The right-hand copy of a USING or NATURAL join column, which is only found when it is
+    /// qualified by its table.
+    pub shadowed: bool,
+    /// The collating sequence the column was declared with, BINARY when it names none. Results
+    /// of other expressions and the rowid have none, so a comparison looks for one elsewhere.
+    pub collation: Option<Collation>,
+    /// The affinity of the column's declared type, which comparisons convert operands to.
+    pub affinity: Option<Affinity>,
+    /// The type the column was declared with, as written. Columns declared without one and
+    /// results of other expressions have none.
+    pub decl_type: Option<String>,
+}
+
+impl ColumnName {
+    pub fn new(table: Option<&str>, name: &str) -> Self {
+        Self {
+            table: table.map(|table| table.to_string()),
+            name: name.to_string(),
+            hidden: false,
+            shadowed: false,
+            collation: None,
+            affinity: None,
+            decl_type: None,
+        }
+    }
+
+    pub fn rowid(table: Option<&str>) -> Self {
+        Self {
+            hidden: true,
+            affinity: Some(Affinity::Integer),
+            ..Self::new(table, "rowid")
+        }
+    }
+}
+
+/// Runs the subqueries found in expressions.
+pub trait Subqueries {
+    /// Returns the result of `query` for the row of `scope`, which correlated subqueries refer
+    /// to.
+    fn run(&self, query: &Query, scope: &Scope) -> anyhow::Result<Rc<ResultSet>>;
+}
+
+/// What an expression can see besides its own row.
+#[derive(Clone, Copy, Default)]
+pub struct Context<'a> {
+    pub subqueries: Option<&'a dyn Subqueries>,
+    /// The row of the enclosing query when evaluating a subquery.
+    pub outer: Option<&'a Scope<'a>>,
+    /// Set when a column is found in `outer`, which means the subquery is correlated.
+    pub correlated: Option<&'a Cell<bool>>,
+    /// The common table expressions the query can refer to.
+    pub ctes: Option<&'a CteScope<'a>>,
+    /// The time the statement started, as a Julian day number in milliseconds. Every 'now' in
+    /// the statement refers to it.
+    pub now: Option<i64>,
+    /// The values bound to the parameters of a prepared statement, which numbers its
+    /// parameters `?1`, `?2` and so on.
+    pub parameters: Option<&'a [SerialValue]>,
+}
+
+/// The row an expression is evaluated against.
+pub struct Scope<'a> {
+    pub columns: &'a [ColumnName],
+    pub row: &'a [SerialValue],
+    /// Results of the aggregate calls of the current group, keyed by the text of the call.
+    pub aggregates: Option<&'a HashMap<String, SerialValue>>,
+    pub context: Context<'a>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(columns: &'a [ColumnName], row: &'a [SerialValue]) -> Self {
+        Self::within(Context::default(), columns, row)
+    }
+
+    pub fn within(context: Context<'a>, columns: &'a [ColumnName], row: &'a [SerialValue]) -> Self {
+        Self {
+            columns,
+            row,
+            aggregates: None,
+            context,
+        }
+    }
+
+    /// Finds the position of a possibly qualified column.
+    pub fn resolve(&self, table: Option<&str>, name: &str) -> anyhow::Result<Option<usize>> {
+        let matches_table = |column: &ColumnName| match (table, &column.table) {
+            (None, _) => true,
+            (Some(table), Some(column_table)) => table.eq_ignore_ascii_case(column_table),
+            (Some(_), None) => false,
+        };
+
+        let mut found = self.columns.iter().enumerate().filter(|(_, column)| {
+            !column.hidden
+                && (table.is_some() || !column.shadowed)
+                && column.name.eq_ignore_ascii_case(name)
+                && matches_table(column)
+        });
+        if let Some((i, _)) = found.next() {
+            if found.next().is_some() {
+                bail!("ambiguous column name: {name}");
+            }
+            return Ok(Some(i));
+        }
+
+        // Hidden columns are found by their own name, and the rowid by any of its aliases.
+        let mut found = self.columns.iter().enumerate().filter(|(_, column)| {
+            column.hidden
+                && (column.name.eq_ignore_ascii_case(name)
+                    || is_rowid_name(name) && is_rowid_name(&column.name))
+                && matches_table(column)
+        });
+        if let Some((i, _)) = found.next() {
+            if found.next().is_some() {
+                bail!("ambiguous column name: {name}");
+            }
+            return Ok(Some(i));
+        }
+
+        Ok(None)
+    }
+
+    fn column(&self, idents: &[Ident]) -> anyhow::Result<SerialValue> {
+        let (table, name) = match idents {
+            [name] => (None, name),
+            [.., table, name] => (Some(table.value.as_str()), name),
+            [] => bail!("empty column name"),
+        };
+
+        match self.lookup(table, &name.value)? {
+            Some(value) => Ok(value),
+            // Like SQLite, a double-quoted identifier that names no column is a string literal.
+            None if table.is_none() && name.quote_style == Some('"') => {
+                Ok(SerialValue::String(name.value.clone()))
+            }
+            None => match table {
+                Some(table) => bail!("no such column: {table}.{}", name.value),
+                None => bail!("no such column: {}", name.value),
+            },
+        }
+    }
+
+    /// Finds the value of a column in this row or, failing that, in the rows of the enclosing
+    /// queries.
+    fn lookup(&self, table: Option<&str>, name: &str) -> anyhow::Result<Option<SerialValue>> {
+        if let Some(i) = self.resolve(table, name)? {
+            return Ok(Some(self.row.get(i).cloned().unwrap_or(SerialValue::Null)));
+        }
+
+        let Some(outer) = self.context.outer else {
+            return Ok(None);
+        };
+        let value = outer.lookup(table, name)?;
+        if value.is_some() {
+            if let Some(correlated) = self.context.correlated {
+                correlated.set(true);
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn subquery(&self, query: &Query) -> anyhow::Result<Rc<ResultSet>> {
+        match self.context.subqueries {
+            Some(subqueries) => subqueries.run(query, self),
+            None => bail!("subqueries are not supported here"),
+        }
+    }
+
+    /// Runs a subquery that must return a single column.
+    fn single_column(&self, query: &Query) -> anyhow::Result<Rc<ResultSet>> {
+        let result = self.subquery(query)?;
+        if result.columns.len() != 1 {
+            bail!(
+                "sub-select returns {} columns - expected 1",
+                result.columns.len()
+            );
+        }
+
+        Ok(result)
+    }
+}
+
+pub fn is_rowid_name(name: &str) -> bool {
+    ["rowid", "oid", "_rowid_"]
+        .iter()
+        .any(|alias| alias.eq_ignore_ascii_case(name))
+}
+
+pub fn eval(expr: &Expr, scope: &Scope) -> anyhow::Result<SerialValue> {
+    match expr {
+        Expr::Identifier(ident) => scope.column(std::slice::from_ref(ident)),
+        Expr::CompoundIdentifier(idents) => scope.column(idents),
+        Expr::Value(Value::Placeholder(placeholder)) => Ok(parameter(placeholder, scope.context)),
+        Expr::Value(value) => literal(value),
+        Expr::Nested(expr) => eval(expr, scope),
+        Expr::Collate { expr, collation } => {
+            Collation::from_name(&collation.to_string())?;
+            eval(expr, scope)
+        }
+        Expr::IsNull(expr) => Ok(eval(expr, scope)?.is_null().into()),
+        Expr::IsNotNull(expr) => Ok((!eval(expr, scope)?.is_null()).into()),
+        Expr::UnaryOp { op, expr } => {
+            // The magnitude of the smallest integer only fits once it is negated.
+            if let (UnaryOperator::Minus, Expr::Value(Value::Number(num, _))) = (op, &**expr) {
+                if num == "9223372036854775808" {
+                    return Ok(SerialValue::I64(i64::MIN));
+                }
+            }
+
+            let value = eval(expr, scope)?;
+            match op {
+                UnaryOperator::Not => Ok(match value.as_bool() {
+                    Some(value) => (!value).into(),
+                    None => SerialValue::Null,
+                }),
+                UnaryOperator::Plus => Ok(value),
+                UnaryOperator::Minus => Ok(arithmetic(
+                    &BinaryOperator::Minus,
+                    SerialValue::I64(0),
+                    value,
+                )),
+                op => bail!("unsupported operator: {op}"),
+            }
+        }
+        Expr::Cast {
+            expr, data_type, ..
+        } => Ok(eval(expr, scope)?.cast(Affinity::from_type_name(&data_type.to_string()))),
+        Expr::BinaryOp { left, op, right } => binary_op(left, op, right, scope),
+        Expr::Subquery(query) => Ok(scope
+            .single_column(query)?
+            .rows
+            .first()
+            .and_then(|row| row.first())
+            .cloned()
+            .unwrap_or(SerialValue::Null)),
+        Expr::Exists { subquery, negated } => {
+            Ok((scope.subquery(subquery)?.rows.is_empty() == *negated).into())
+        }
+        Expr::InSubquery {
+            expr,
+            subquery,
+            negated,
+        } => {
+            let value = eval(expr, scope)?;
+            let result = scope.single_column(subquery)?;
+            let collation = collation(expr, scope)?
+                .or(result.collations[0])
+                .unwrap_or_default();
+            let (_, list_affinity) = comparison_affinities(affinity(expr, scope), None);
+            let list: Vec<SerialValue> = result
+                .rows
+                .iter()
+                .filter_map(|row| row.first())
+                .map(|element| convert(element.clone(), list_affinity))
+                .collect();
+            let found = contains(&value, list.iter(), collation);
+            Ok(negate(found, *negated))
+        }
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => {
+            let value = eval(expr, scope)?;
+            let (_, list_affinity) = comparison_affinities(affinity(expr, scope), None);
+            let list = list
+                .iter()
+                .map(|expr| Ok(convert(eval(expr, scope)?, list_affinity)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let collation = collation(expr, scope)?.unwrap_or_default();
+            Ok(negate(contains(&value, list.iter(), collation), *negated))
+        }
+        // `x BETWEEN low AND high` is `x >= low AND x <= high`.
+        Expr::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => {
+            let low = binary_op(expr, &BinaryOperator::GtEq, low, scope)?.as_bool();
+            let high = binary_op(expr, &BinaryOperator::LtEq, high, scope)?.as_bool();
+            let within = match (low, high) {
+                (Some(false), _) | (_, Some(false)) => false.into(),
+                (Some(true), Some(true)) => true.into(),
+                _ => SerialValue::Null,
+            };
+            Ok(negate(within, *negated))
+        }
+        Expr::Like {
+            negated,
+            expr,
+            pattern,
+            escape_char,
+        } => {
+            let value = eval(expr, scope)?;
+            let pattern = eval(pattern, scope)?;
+            if value.is_null() || pattern.is_null() {
+                return Ok(SerialValue::Null);
+            }
+
+            let pattern: Vec<char> = pattern.display().chars().collect();
+            let text: Vec<char> = value.display().chars().collect();
+            Ok((like(&pattern, &text, *escape_char) != *negated).into())
+        }
+        Expr::Function(function) => {
+            if let Some(value) = scope
+                .aggregates
+                .and_then(|aggregates| aggregates.get(&function.to_string()))
+            {
+                return Ok(value.clone());
+            }
+
+            function::call(function, scope)
+        }
+        Expr::JsonAccess {
+            left,
+            operator,
+            right,
+        } => Ok(json::access(left, operator, right, scope)?.value),
+        Expr::Trim { .. } | Expr::Substring { .. } => function::special(expr, scope),
+        expr => bail!("unsupported expression: {expr}"),
+    }
+}
+
+/// Matches text against a `LIKE` pattern, where `%` matches any run of characters and `_` any
+/// single character. ASCII letters match either case, and the escape character makes the next
+/// character of the pattern match only itself.
+fn like(pattern: &[char], text: &[char], escape: Option<char>) -> bool {
+    let (mut p, mut t) = (0, 0);
+    while let Some(&c) = pattern.get(p) {
+        if Some(c) == escape {
+            match (pattern.get(p + 1), text.get(t)) {
+                (Some(literal), Some(next)) if literal.eq_ignore_ascii_case(next) => {
+                    p += 2;
+                    t += 1;
+                    continue;
+                }
+                _ => return false,
+            }
+        }
+
+        match c {
+            '%' => {
+                let rest = &pattern[p + 1..];
+                return (t..=text.len()).any(|start| like(rest, &text[start..], escape));
+            }
+            '_' if t < text.len() => {}
+            c if text.get(t).is_some_and(|next| c.eq_ignore_ascii_case(next)) => {}
+            _ => return false,
+        }
+        p += 1;
+        t += 1;
+    }
+
+    t == text.len()
+}
+
+/// The result of `value IN (list)`: true when some element equals the value, NULL when the
+/// value or an element is NULL and none is equal, false otherwise. An empty list contains
+/// nothing, not even NULL.
+fn contains<'v>(
+    value: &SerialValue,
+    list: impl Iterator<Item = &'v SerialValue>,
+    collation: Collation,
+) -> SerialValue {
+    let mut saw_null = false;
+    let mut empty = true;
+    for element in list {
+        empty = false;
+        if element.is_null() {
+            saw_null = true;
+        } else if !value.is_null() && collation.compare(value, element) == Ordering::Equal {
+            return true.into();
+        }
+    }
+
+    if empty {
+        false.into()
+    } else if value.is_null() || saw_null {
+        SerialValue::Null
+    } else {
+        false.into()
+    }
+}
+
+fn negate(value: SerialValue, negated: bool) -> SerialValue {
+    match value.as_bool() {
+        Some(value) => (value != negated).into(),
+        None => SerialValue::Null,
+    }
+}
+
+fn binary_op(
+    left: &Expr,
+    op: &BinaryOperator,
+    right: &Expr,
+    scope: &Scope,
+) -> anyhow::Result<SerialValue> {
+    match op {
+        BinaryOperator::And => {
+            let left = eval(left, scope)?.as_bool();
+            if left == Some(false) {
+                return Ok(false.into());
+            }
+            Ok(match (left, eval(right, scope)?.as_bool()) {
+                (_, Some(false)) => false.into(),
+                (Some(true), Some(true)) => true.into(),
+                _ => SerialValue::Null,
+            })
+        }
+        BinaryOperator::Or => {
+            let left = eval(left, scope)?.as_bool();
+            if left == Some(true) {
+                return Ok(true.into());
+            }
+            Ok(match (left, eval(right, scope)?.as_bool()) {
+                (_, Some(true)) => true.into(),
+                (Some(false), Some(false)) => false.into(),
+                _ => SerialValue::Null,
+            })
+        }
+        BinaryOperator::Eq
+        | BinaryOperator::NotEq
+        | BinaryOperator::Lt
+        | BinaryOperator::LtEq
+        | BinaryOperator::Gt
+        | BinaryOperator::GtEq => {
+            let collation = comparison_collation(left, right, scope)?;
+            let (left_affinity, right_affinity) =
+                comparison_affinities(affinity(left, scope), affinity(right, scope));
+            let left = convert(eval(left, scope)?, left_affinity);
+            let right = convert(eval(right, scope)?, right_affinity);
+            if left.is_null() || right.is_null() {
+                return Ok(SerialValue::Null);
+            }
+
+            let ordering = collation.compare(&left, &right);
+            let result = match op {
+                BinaryOperator::Eq => ordering == Ordering::Equal,
+                BinaryOperator::NotEq => ordering != Ordering::Equal,
+                BinaryOperator::Lt => ordering == Ordering::Less,
+                BinaryOperator::LtEq => ordering != Ordering::Greater,
+                BinaryOperator::Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            };
+            Ok(result.into())
+        }
+        BinaryOperator::Plus
+        | BinaryOperator::Minus
+        | BinaryOperator::Multiply
+        | BinaryOperator::Divide
+        | BinaryOperator::Modulo => Ok(arithmetic(op, eval(left, scope)?, eval(right, scope)?)),
+        BinaryOperator::StringConcat => {
+            let left = eval(left, scope)?;
+            let right = eval(right, scope)?;
+            if left.is_null() || right.is_null() {
+                return Ok(SerialValue::Null);
+            }
+            Ok(SerialValue::String(left.display() + &right.display()))
+        }
+        op => bail!("unsupported operator: {op}"),
+    }
+}
+
+/// Applies an arithmetic operator the way SQLite does. Both operands are converted to numbers
+/// first. Integer arithmetic that overflows is redone with doubles, and a division or
+/// remainder by zero is NULL, as is a result that is not a number.
+fn arithmetic(op: &BinaryOperator, left: SerialValue, right: SerialValue) -> SerialValue {
+    let (left, right) = (left.to_numeric(), right.to_numeric());
+    if left.is_null() || right.is_null() {
+        return SerialValue::Null;
+    }
+
+    if let (Some(a), Some(b)) = (left.as_integer(), right.as_integer()) {
+        let result = match op {
+            BinaryOperator::Plus => a.checked_add(b),
+            BinaryOperator::Minus => a.checked_sub(b),
+            BinaryOperator::Multiply => a.checked_mul(b),
+            BinaryOperator::Divide if b == 0 => return SerialValue::Null,
+            BinaryOperator::Divide => a.checked_div(b),
+            BinaryOperator::Modulo if b == 0 => return SerialValue::Null,
+            _ => Some(a % if b == -1 { 1 } else { b }),
+        };
+        if let Some(result) = result {
+            return SerialValue::I64(result);
+        }
+    }
+
+    let (a, b) = (left.to_real(), right.to_real());
+    let result = match op {
+        BinaryOperator::Plus => a + b,
+        BinaryOperator::Minus => a - b,
+        BinaryOperator::Multiply => a * b,
+        BinaryOperator::Divide if b == 0.0 => return SerialValue::Null,
+        BinaryOperator::Divide => a / b,
+        // The remainder of doubles is taken of their integer parts.
+        _ => {
+            let (a, b) = (real_to_integer(a), real_to_integer(b));
+            match b {
+                0 => return SerialValue::Null,
+                -1 => 0.0,
+                b => (a % b) as f64,
+            }
+        }
+    };
+
+    if result.is_nan() {
+        SerialValue::Null
+    } else {
+        SerialValue::Float64(result)
+    }
+}
+
+/// Gives `||` the precedence SQLite does. The parser binds it as loosely as `*`, `/` and `%`,
+/// so `a * b || c` would concatenate the product; SQLite concatenates `b || c` first.
+pub fn reassociate_concat(expr: Expr) -> Expr {
+    let Expr::BinaryOp {
+        left,
+        op: BinaryOperator::StringConcat,
+        right,
+    } = expr
+    else {
+        return expr;
+    };
+
+    match *left {
+        Expr::BinaryOp {
+            left: outer,
+            op: op @ (BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo),
+            right: inner,
+        } => Expr::BinaryOp {
+            left: outer,
+            op,
+            right: Box::new(reassociate_concat(Expr::BinaryOp {
+                left: inner,
+                op: BinaryOperator::StringConcat,
+                right,
+            })),
+        },
+        left => Expr::BinaryOp {
+            left: Box::new(left),
+            op: BinaryOperator::StringConcat,
+            right,
+        },
+    }
+}
+
+/// Returns the collating sequence an expression brings to a comparison: the one named by a
+/// COLLATE operator, or the one a column was declared with, which is BINARY when it names none.
+pub fn collation(expr: &Expr, scope: &Scope) -> anyhow::Result<Option<Collation>> {
+    let (table, name) = match expr {
+        Expr::Collate { collation, .. } => {
+            return Collation::from_name(&collation.to_string()).map(Some)
+        }
+        Expr::Nested(expr) => return self::collation(expr, scope),
+        Expr::Identifier(name) => (None, name),
+        Expr::CompoundIdentifier(idents) if idents.len() >= 2 => (
+            Some(idents[idents.len() - 2].value.as_str()),
+            &idents[idents.len() - 1],
+        ),
+        _ => return Ok(None),
+    };
+
+    Ok(scope
+        .resolve(table, &name.value)
+        .ok()
+        .flatten()
+        .and_then(|i| scope.columns[i].collation))
+}
+
+/// Returns the collating sequence named by a COLLATE operator on the expression.
+pub fn explicit_collation(expr: &Expr) -> anyhow::Result<Option<Collation>> {
+    match expr {
+        Expr::Collate { collation, .. } => Collation::from_name(&collation.to_string()).map(Some),
+        Expr::Nested(expr) => explicit_collation(expr),
+        _ => Ok(None),
+    }
+}
+
+/// The collating sequence a comparison of `left` and `right` uses. A COLLATE operator wins,
+/// on the left before the right, then the collation of a column operand, again on the left
+/// first.
+pub fn comparison_collation(left: &Expr, right: &Expr, scope: &Scope) -> anyhow::Result<Collation> {
+    if let Some(collation) = explicit_collation(left)?.or(explicit_collation(right)?) {
+        return Ok(collation);
+    }
+
+    Ok(collation(left, scope)?
+        .or(collation(right, scope)?)
+        .unwrap_or_default())
+}
+
+/// The affinity an expression has in a comparison: columns have the affinity of their declared
+/// type and `CAST(x AS type)` that of the type. BLOB affinity counts as none.
+pub fn affinity(expr: &Expr, scope: &Scope) -> Option<Affinity> {
+    let (table, name) = match expr {
+        Expr::Cast { data_type, .. } => {
+            return Some(Affinity::from_type_name(&data_type.to_string()))
+                .filter(|affinity| *affinity != Affinity::Blob)
+        }
+        Expr::Nested(expr) | Expr::Collate { expr, .. } => return affinity(expr, scope),
+        Expr::Identifier(name) => (None, name),
+        Expr::CompoundIdentifier(idents) if idents.len() >= 2 => (
+            Some(idents[idents.len() - 2].value.as_str()),
+            &idents[idents.len() - 1],
+        ),
+        _ => return None,
+    };
+
+    scope
+        .resolve(table, &name.value)
+        .ok()
+        .flatten()
+        .and_then(|i| scope.columns[i].affinity)
+        .filter(|affinity| *affinity != Affinity::Blob)
+}
+
+/// The affinities SQLite converts the left and right operands of a comparison to. A numeric
+/// operand makes the other one NUMERIC, and a TEXT operand makes one without affinity TEXT.
+pub fn comparison_affinities(
+    left: Option<Affinity>,
+    right: Option<Affinity>,
+) -> (Option<Affinity>, Option<Affinity>) {
+    let numeric = |affinity| {
+        matches!(
+            affinity,
+            Some(Affinity::Integer | Affinity::Real | Affinity::Numeric)
+        )
+    };
+    match (left, right) {
+        _ if numeric(left) && !numeric(right) => (None, Some(Affinity::Numeric)),
+        _ if numeric(right) && !numeric(left) => (Some(Affinity::Numeric), None),
+        (Some(Affinity::Text), None) => (None, Some(Affinity::Text)),
+        (None, Some(Affinity::Text)) => (Some(Affinity::Text), None),
+        _ => (None, None),
+    }
+}
+
+fn convert(value: SerialValue, affinity: Option<Affinity>) -> SerialValue {
+    match affinity {
+        Some(affinity) => value.apply_affinity(affinity),
+        None => value,
+    }
+}
+
+/// The value bound to a parameter. A parameter that is not bound is NULL.
+fn parameter(placeholder: &str, context: Context) -> SerialValue {
+    placeholder
+        .strip_prefix('?')
+        .and_then(|number| number.parse::<usize>().ok())
+        .and_then(|number| context.parameters?.get(number.checked_sub(1)?))
+        .cloned()
+        .unwrap_or(SerialValue::Null)
+}
+
+/// Tells whether an expression refers to a parameter, whose value changes between the runs of
+/// a prepared statement.
+pub fn has_parameter(expr: &Expr) -> bool {
+    let flow = visit_expressions(expr, |expr| match expr {
+        Expr::Value(Value::Placeholder(_)) => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    });
+
+    flow.is_break()
+}
+
+/// Calls `f` with the expressions in `exprs` and their subexpressions, except those inside
+/// subqueries. The subqueries themselves are visited.
+pub fn visit_outside_subqueries(exprs: &[&Expr], f: impl FnMut(&Expr)) {
+    struct OuterVisitor<F> {
+        query_depth: usize,
+        f: F,
+    }
+
+    impl<F: FnMut(&Expr)> Visitor for OuterVisitor<F> {
+        type Break = ();
+
+        fn pre_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+            self.query_depth += 1;
+            ControlFlow::Continue(())
+        }
+
+        fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+            self.query_depth -= 1;
+            ControlFlow::Continue(())
+        }
+
+        fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+            if self.query_depth == 0 {
+                (self.f)(expr);
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut visitor = OuterVisitor { query_depth: 0, f };
+    for expr in exprs {
+        let _ = expr.visit(&mut visitor);
+    }
+}
+
+pub fn literal(value: &Value) -> anyhow::Result<SerialValue> {
+    match value {
+        Value::Number(num, _) => Ok(match num.parse::<i64>() {
+            Ok(num) => SerialValue::I64(num),
+            Err(_) => SerialValue::Float64(num.parse()?),
+        }),
+        Value::SingleQuotedString(txt) => Ok(SerialValue::String(txt.clone())),
+        Value::DoubleQuotedString(txt) => Ok(SerialValue::String(txt.clone())),
+        Value::Boolean(value) => Ok((*value).into()),
+        Value::Null => Ok(SerialValue::Null),
+        Value::HexStringLiteral(hex) => {
+            if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                bail!("unrecognized token: \"X'{hex}'\"");
+            }
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<_, _>>()?;
+            Ok(SerialValue::Blob(bytes))
+        }
+        value => bail!("unsupported literal: {value}"),
+    }
+}
@@ -0,0 +1,457 @@
+use std::{
+    cmp::Ordering,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    ops::RangeInclusive,
+};
+
+use anyhow::bail;
+use sqlparser::ast::{Expr, Function, FunctionArg, FunctionArgExpr, TrimWhereField};
+
+use crate::{
+    aggregate::AggregateFunc,
+    collation::Collation,
+    column::SerialValue,
+    datetime,
+    expr::{collation, eval, Scope},
+    json,
+    printf::{check_length, fixed, printf, quote_real},
+};
+
+/// The arguments of a call, which must be plain expressions.
+pub fn arguments(function: &Function) -> anyhow::Result<Vec<&Expr>> {
+    function
+        .args
+        .iter()
+        .map(|arg| match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr),
+            _ => bail!("wrong arguments to function {}()", function.name),
+        })
+        .collect()
+}
+
+/// Calls a scalar function. Aggregate and window calls are computed before the expression
+/// is evaluated; reaching one here means it was used where it is not allowed.
+pub fn call(function: &Function, scope: &Scope) -> anyhow::Result<SerialValue> {
+    let name = function.name.to_string().to_lowercase();
+    if function.over.is_some() {
+        bail!("misuse of window function {}()", function.name);
+    }
+    if AggregateFunc::lookup(&name, function.args.len()).is_some() {
+        bail!("misuse of aggregate: {}()", function.name);
+    }
+
+    if json::is_function(&name) {
+        return Ok(json::call(function, scope)?.value);
+    }
+
+    let args = arguments(function)?;
+    let arity = |nargs: RangeInclusive<usize>| -> anyhow::Result<()> {
+        if !nargs.contains(&args.len()) {
+            bail!("wrong number of arguments to function {}()", function.name);
+        }
+        Ok(())
+    };
+
+    // These only evaluate the arguments they need.
+    match name.as_str() {
+        "coalesce" | "ifnull" => {
+            arity(if name == "ifnull" {
+                2..=2
+            } else {
+                2..=usize::MAX
+            })?;
+            for arg in &args {
+                let value = eval(arg, scope)?;
+                if !value.is_null() {
+                    return Ok(value);
+                }
+            }
+            return Ok(SerialValue::Null);
+        }
+        "iif" => {
+            arity(2..=3)?;
+            return if eval(args[0], scope)?.as_bool() == Some(true) {
+                eval(args[1], scope)
+            } else if let Some(otherwise) = args.get(2) {
+                eval(otherwise, scope)
+            } else {
+                Ok(SerialValue::Null)
+            };
+        }
+        _ => {}
+    }
+
+    let values = args
+        .iter()
+        .map(|arg| eval(arg, scope))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let null = values.iter().any(SerialValue::is_null);
+
+    Ok(match name.as_str() {
+        "length" => {
+            arity(1..=1)?;
+            match &values[0] {
+                SerialValue::Null => SerialValue::Null,
+                SerialValue::Blob(bytes) => SerialValue::I64(bytes.len() as i64),
+                value => SerialValue::I64(text(value).chars().count() as i64),
+            }
+        }
+        "upper" | "lower" => {
+            arity(1..=1)?;
+            match &values[0] {
+                SerialValue::Null => SerialValue::Null,
+                value if name == "upper" => SerialValue::String(text(value).to_ascii_uppercase()),
+                value => SerialValue::String(text(value).to_ascii_lowercase()),
+            }
+        }
+        "substr" | "substring" => {
+            arity(2..=3)?;
+            if null {
+                SerialValue::Null
+            } else {
+                substr(&values)
+            }
+        }
+        "trim" | "ltrim" | "rtrim" => {
+            arity(1..=2)?;
+            trim(&name, &values)
+        }
+        "replace" => {
+            arity(3..=3)?;
+            if null {
+                SerialValue::Null
+            } else {
+                let (txt, pattern) = (text(&values[0]), text(&values[1]));
+                let replacement = text(&values[2]);
+                if pattern.is_empty() {
+                    SerialValue::String(txt)
+                } else {
+                    let count = txt.matches(&pattern).count();
+                    check_length(
+                        (txt.len() - count * pattern.len())
+                            .saturating_add(count.saturating_mul(replacement.len())),
+                    )?;
+                    SerialValue::String(txt.replace(&pattern, &replacement))
+                }
+            }
+        }
+        "instr" => {
+            arity(2..=2)?;
+            match (&values[0], &values[1]) {
+                _ if null => SerialValue::Null,
+                (SerialValue::Blob(haystack), SerialValue::Blob(needle)) => {
+                    let position = if needle.is_empty() {
+                        Some(0)
+                    } else {
+                        haystack
+                            .windows(needle.len())
+                            .position(|window| window == needle.as_slice())
+                    };
+                    SerialValue::I64(position.map_or(0, |i| i as i64 + 1))
+                }
+                (haystack, needle) => {
+                    let (haystack, needle) = (text(haystack), text(needle));
+                    let position = haystack
+                        .find(&needle)
+                        .map_or(0, |i| haystack[..i].chars().count() as i64 + 1);
+                    SerialValue::I64(position)
+                }
+            }
+        }
+        "nullif" => {
+            arity(2..=2)?;
+            let collation = collation_of(&args, scope)?;
+            if collation.compare(&values[0], &values[1]) == Ordering::Equal {
+                SerialValue::Null
+            } else {
+                values[0].clone()
+            }
+        }
+        "abs" => {
+            arity(1..=1)?;
+            match &values[0] {
+                SerialValue::Null => SerialValue::Null,
+                SerialValue::Float64(num) => SerialValue::Float64(num.abs()),
+                value => match value.as_integer() {
+                    Some(num) => match num.checked_abs() {
+                        Some(num) => SerialValue::I64(num),
+                        None => bail!("integer overflow"),
+                    },
+                    None => SerialValue::Float64(value.to_real().abs()),
+                },
+            }
+        }
+        "round" => {
+            arity(1..=2)?;
+            if null {
+                SerialValue::Null
+            } else {
+                let digits = values.get(1).map_or(0, |n| n.to_integer().clamp(0, 30));
+                SerialValue::Float64(round(values[0].to_real(), digits as usize))
+            }
+        }
+        "typeof" => {
+            arity(1..=1)?;
+            SerialValue::String(type_name(&values[0]).to_string())
+        }
+        "hex" => {
+            arity(1..=1)?;
+            let bytes = match &values[0] {
+                SerialValue::Null => Vec::new(),
+                SerialValue::Blob(bytes) => bytes.clone(),
+                value => text(value).into_bytes(),
+            };
+            SerialValue::String(bytes.iter().map(|byte| format!("{byte:02X}")).collect())
+        }
+        "quote" => {
+            arity(1..=1)?;
+            SerialValue::String(quote(&values[0]))
+        }
+        "printf" | "format" => {
+            arity(1..=usize::MAX)?;
+            match &values[0] {
+                SerialValue::Null => SerialValue::Null,
+                format => SerialValue::String(printf(&text(format), &values[1..])?),
+            }
+        }
+        "char" => SerialValue::String(
+            values
+                .iter()
+                .map(|value| {
+                    u32::try_from(value.to_integer())
+                        .ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER)
+                })
+                .collect(),
+        ),
+        "unicode" => {
+            arity(1..=1)?;
+            match &values[0] {
+                SerialValue::Null => SerialValue::Null,
+                value => match text(value).chars().next() {
+                    Some(c) => SerialValue::I64(c as i64),
+                    None => SerialValue::Null,
+                },
+            }
+        }
+        "zeroblob" => {
+            arity(1..=1)?;
+            let len = values[0].to_integer().max(0) as usize;
+            check_length(len)?;
+            SerialValue::Blob(vec![0; len])
+        }
+        "random" => {
+            arity(0..=0)?;
+            SerialValue::I64(RandomState::new().build_hasher().finish() as i64)
+        }
+        "min" | "max" => {
+            arity(2..=usize::MAX)?;
+            if null {
+                SerialValue::Null
+            } else {
+                let collation = collation_of(&args, scope)?;
+                let wanted = if name == "min" {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                };
+                let mut best = &values[0];
+                for value in &values[1..] {
+                    if collation.compare(value, best) == wanted {
+                        best = value;
+                    }
+                }
+                best.clone()
+            }
+        }
+        "date" | "time" | "datetime" | "julianday" | "unixepoch" => {
+            datetime::call(&name, &values, scope.context.now)?
+        }
+        "strftime" => {
+            arity(1..=usize::MAX)?;
+            datetime::call(&name, &values, scope.context.now)?
+        }
+        "timediff" => {
+            arity(2..=2)?;
+            datetime::call(&name, &values, scope.context.now)?
+        }
+        "current_date" | "current_time" | "current_timestamp" => {
+            arity(0..=0)?;
+            datetime::call(&name, &[], scope.context.now)?
+        }
+        _ => bail!("no such function: {}", function.name),
+    })
+}
+
+/// `TRIM(...)` and `SUBSTRING(...)` have their own syntax, which the parser reads as
+/// dedicated expressions rather than function calls.
+pub fn special(expr: &Expr, scope: &Scope) -> anyhow::Result<SerialValue> {
+    match expr {
+        Expr::Trim {
+            expr,
+            trim_where,
+            trim_what,
+            trim_characters,
+        } => {
+            let name = match trim_where {
+                Some(TrimWhereField::Leading) => "ltrim",
+                Some(TrimWhereField::Trailing) => "rtrim",
+                _ => "trim",
+            };
+            let mut values = vec![eval(expr, scope)?];
+            match (trim_what.as_deref(), trim_characters.as_deref()) {
+                (Some(what), _) | (None, Some([what])) => values.push(eval(what, scope)?),
+                (None, None) => {}
+                (None, Some(_)) => bail!("wrong number of arguments to function trim()"),
+            }
+            Ok(trim(name, &values))
+        }
+        Expr::Substring {
+            expr,
+            substring_from,
+            substring_for,
+            ..
+        } => {
+            let mut values = vec![
+                eval(expr, scope)?,
+                match substring_from {
+                    Some(from) => eval(from, scope)?,
+                    None => SerialValue::I64(1),
+                },
+            ];
+            if let Some(length) = substring_for {
+                values.push(eval(length, scope)?);
+            }
+            if values.iter().any(SerialValue::is_null) {
+                Ok(SerialValue::Null)
+            } else {
+                Ok(substr(&values))
+            }
+        }
+        expr => bail!("unsupported expression: {expr}"),
+    }
+}
+
+/// `trim()`, `ltrim()` and `rtrim()` remove the characters of their second argument, or
+/// spaces, from the ends of the first.
+fn trim(name: &str, values: &[SerialValue]) -> SerialValue {
+    if values.iter().any(SerialValue::is_null) {
+        return SerialValue::Null;
+    }
+
+    let set: Vec<char> = match values.get(1) {
+        Some(set) => text(set).chars().collect(),
+        None => vec![' '],
+    };
+    let txt = text(&values[0]);
+    let trimmed = match name {
+        "ltrim" => txt.trim_start_matches(set.as_slice()),
+        "rtrim" => txt.trim_end_matches(set.as_slice()),
+        _ => txt.trim_matches(set.as_slice()),
+    };
+
+    SerialValue::String(trimmed.to_string())
+}
+
+/// Converts a value to TEXT the way SQLite does when a function expects text.
+fn text(value: &SerialValue) -> String {
+    value.display()
+}
+
+pub fn type_name(value: &SerialValue) -> &'static str {
+    match value {
+        SerialValue::Null => "null",
+        SerialValue::Float64(_) => "real",
+        SerialValue::String(_) => "text",
+        SerialValue::Blob(_) => "blob",
+        _ => "integer",
+    }
+}
+
+/// The collation of the leftmost argument that has one.
+fn collation_of(args: &[&Expr], scope: &Scope) -> anyhow::Result<Collation> {
+    for arg in args {
+        if let Some(collation) = collation(arg, scope)? {
+            return Ok(collation);
+        }
+    }
+
+    Ok(Collation::default())
+}
+
+/// `substr(X, Y, Z)` counts characters of TEXT and bytes of a BLOB, from 1. A negative `Y`
+/// counts from the end and a negative `Z` takes the characters before `Y`.
+fn substr(values: &[SerialValue]) -> SerialValue {
+    let chars: Vec<char> = match &values[0] {
+        SerialValue::Blob(_) => Vec::new(),
+        value => text(value).chars().collect(),
+    };
+    let len = match &values[0] {
+        SerialValue::Blob(bytes) => bytes.len(),
+        _ => chars.len(),
+    } as i64;
+
+    let mut start = values[1].to_integer();
+    let mut count = match values.get(2) {
+        Some(count) => count.to_integer(),
+        None => i64::MAX / 2,
+    };
+    let negative = count < 0;
+    count = count.saturating_abs();
+
+    if start < 0 {
+        start += len;
+        if start < 0 {
+            count = (count + start).max(0);
+            start = 0;
+        }
+    } else if start > 0 {
+        start -= 1;
+    } else if count > 0 {
+        count -= 1;
+    }
+    if negative {
+        start -= count;
+        if start < 0 {
+            count += start;
+            start = 0;
+        }
+    }
+
+    let start = start.clamp(0, len) as usize;
+    let end = (start as i64).saturating_add(count.max(0)).min(len) as usize;
+    match &values[0] {
+        SerialValue::Blob(bytes) => SerialValue::Blob(bytes[start..end].to_vec()),
+        _ => SerialValue::String(chars[start..end].iter().collect()),
+    }
+}
+
+/// Rounds half away from zero. Doubles too large to have a fraction are returned unchanged.
+fn round(num: f64, digits: usize) -> f64 {
+    if !num.is_finite() || num.abs() >= 4503599627370496.0 {
+        return num;
+    }
+
+    let rounded: f64 = fixed(num, digits).parse().unwrap_or_default();
+    if num < 0.0 {
+        -rounded
+    } else {
+        rounded
+    }
+}
+
+/// Renders a value as an SQL literal.
+pub fn quote(value: &SerialValue) -> String {
+    match value {
+        SerialValue::Null => "NULL".to_string(),
+        SerialValue::Float64(num) => quote_real(*num),
+        SerialValue::String(txt) => format!("'{}'", txt.replace('\'', "''")),
+        SerialValue::Blob(bytes) => {
+            let hex: String = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+            format!("X'{hex}'")
+        }
+        value => value.display(),
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{BufReader, BufWriter, ErrorKind, Read, Seek, Write},
+};
+
+use anyhow::bail;
+
+use crate::{
+    collation::Collation,
+    column::{SerialValue, ValueKey},
+};
+
+/// How many bytes of build rows a hash join keeps in memory before it partitions its inputs to
+/// disk.
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+/// How many partitions the inputs are split into each time the build rows outgrow the budget.
+const PARTITIONS: usize = 16;
+
+/// How many times a partition that is still too large is split again. Past that its keys are
+/// likely all equal, so it is joined in memory regardless.
+const MAX_DEPTH: u32 = 3;
+
+/// A row of a join input together with the values of its join key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyedRow {
+    pub key: Vec<SerialValue>,
+    pub row: Vec<SerialValue>,
+}
+
+/// An equi-join that hashes the right input and probes it with the left one, so the caller
+/// passes the smaller input on the right. The right rows are collected as they arrive; once they
+/// outgrow `memory_budget`, both inputs are split into partitions by the hash of their keys and
+/// written to temporary files, then joined one partition at a time.
+pub struct HashJoin {
+    /// Emit left rows without a match, padded by the caller.
+    pub outer: bool,
+    pub memory_budget: usize,
+    /// The collating sequence each key value is compared with.
+    pub collations: Vec<Collation>,
+}
+
+impl HashJoin {
+    /// Calls `emit` with every pair of rows whose keys are equal. `emit` returns whether the pair
+    /// was kept; for an outer join, left rows without a kept pair are passed to `emit` alone.
+    /// Keys containing NULL never match. Returns the most bytes of right rows that were hashed
+    /// at once.
+    pub fn run<L, R, F>(&self, left: L, right: R, mut emit: F) -> anyhow::Result<usize>
+    where
+        L: IntoIterator<Item = anyhow::Result<KeyedRow>>,
+        R: IntoIterator<Item = anyhow::Result<KeyedRow>>,
+        F: FnMut(&[SerialValue], Option<&[SerialValue]>) -> anyhow::Result<bool>,
+    {
+        self.join(left, right, 0, &mut emit)
+    }
+
+    fn join<L, R, F>(&self, left: L, right: R, depth: u32, emit: &mut F) -> anyhow::Result<usize>
+    where
+        L: IntoIterator<Item = anyhow::Result<KeyedRow>>,
+        R: IntoIterator<Item = anyhow::Result<KeyedRow>>,
+        F: FnMut(&[SerialValue], Option<&[SerialValue]>) -> anyhow::Result<bool>,
+    {
+        let mut right = right.into_iter();
+        let mut build = Vec::new();
+        let mut size = 0;
+        for row in right.by_ref() {
+            let row = row?;
+            size += values_size(&row.key) + values_size(&row.row);
+            build.push(row);
+            if size > self.memory_budget && depth < MAX_DEPTH {
+                break;
+            }
+        }
+
+        if size <= self.memory_budget || depth >= MAX_DEPTH {
+            self.probe(&build, left, emit)?;
+            return Ok(size);
+        }
+
+        let mut right_partitions = Partitions::new(depth)?;
+        for row in build.into_iter().map(Ok).chain(right) {
+            let row = row?;
+            // A right row with a NULL key never matches, so it is dropped.
+            if let Some(key) = hash_key(&row.key, &self.collations) {
+                right_partitions.write(Some(&key), &row)?;
+            }
+        }
+        let mut left_partitions = Partitions::new(depth)?;
+        for row in left {
+            let row = row?;
+            left_partitions.write(hash_key(&row.key, &self.collations).as_ref(), &row)?;
+        }
+
+        let mut hashed = 0;
+        for (left, right) in left_partitions
+            .finish()?
+            .into_iter()
+            .zip(right_partitions.finish()?)
+        {
+            hashed = hashed.max(self.join(left.rows(), right.rows(), depth + 1, emit)?);
+        }
+
+        Ok(hashed)
+    }
+
+    fn probe<L, F>(&self, build: &[KeyedRow], left: L, emit: &mut F) -> anyhow::Result<()>
+    where
+        L: IntoIterator<Item = anyhow::Result<KeyedRow>>,
+        F: FnMut(&[SerialValue], Option<&[SerialValue]>) -> anyhow::Result<bool>,
+    {
+        let mut table: HashMap<Vec<ValueKey>, Vec<usize>> = HashMap::new();
+        for (i, row) in build.iter().enumerate() {
+            if let Some(key) = hash_key(&row.key, &self.collations) {
+                table.entry(key).or_default().push(i);
+            }
+        }
+
+        for left_row in left {
+            let left_row = left_row?;
+            let mut matched = false;
+            if let Some(positions) =
+                hash_key(&left_row.key, &self.collations).and_then(|key| table.get(&key))
+            {
+                for &i in positions {
+                    matched |= emit(&left_row.row, Some(&build[i].row))?;
+                }
+            }
+
+            if self.outer && !matched {
+                emit(&left_row.row, None)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The hashable form of a key, in which values equal under their collating sequence are equal.
+/// Values past the end of `collations` compare under BINARY. Returns None for a key containing
+/// NULL.
+pub fn hash_key(key: &[SerialValue], collations: &[Collation]) -> Option<Vec<ValueKey>> {
+    key.iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let collation = collations.get(i).copied().unwrap_or_default();
+            (!value.is_null()).then(|| collation.key(value))
+        })
+        .collect()
+}
+
+/// Estimates how many bytes a row of values takes in memory.
+pub fn values_size(values: &[SerialValue]) -> usize {
+    values
+        .iter()
+        .map(|value| {
+            std::mem::size_of::<SerialValue>()
+                + match value {
+                    SerialValue::String(txt) => txt.len(),
+                    SerialValue::Blob(bytes) => bytes.len(),
+                    _ => 0,
+                }
+        })
+        .sum()
+}
+
+/// The temporary files one input is split into, chosen by the hash of the key so that equal
+/// keys of both inputs end up in partitions with the same number. Rows whose key has a NULL go
+/// to the first partition. The files are created in the system's temporary directory with
+/// unpredictable names and removed once they are dropped.
+struct Partitions {
+    depth: u32,
+    writers: Vec<BufWriter<File>>,
+}
+
+impl Partitions {
+    fn new(depth: u32) -> anyhow::Result<Self> {
+        let writers = (0..PARTITIONS)
+            .map(|_| Ok(BufWriter::new(tempfile::tempfile()?)))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { depth, writers })
+    }
+
+    fn write(&mut self, key: Option<&Vec<ValueKey>>, row: &KeyedRow) -> anyhow::Result<()> {
+        let partition = match key {
+            Some(key) => {
+                // Each level of partitioning hashes differently, so that a partition that is
+                // split again spreads over the new partitions.
+                let mut hasher = DefaultHasher::new();
+                self.depth.hash(&mut hasher);
+                key.hash(&mut hasher);
+                hasher.finish() as usize % PARTITIONS
+            }
+            None => 0,
+        };
+
+        write_row(&mut self.writers[partition], row)
+    }
+
+    fn finish(self) -> anyhow::Result<Vec<SpillFile>> {
+        self.writers
+            .into_iter()
+            .map(|writer| {
+                let mut file = writer.into_inner()?;
+                file.rewind()?;
+                Ok(SpillFile(file))
+            })
+            .collect()
+    }
+}
+
+/// A temporary file holding one partition of a join input.
+struct SpillFile(File);
+
+impl SpillFile {
+    /// Reads the rows back one at a time.
+    fn rows(self) -> impl Iterator<Item = anyhow::Result<KeyedRow>> {
+        let mut reader = BufReader::new(self.0);
+        std::iter::from_fn(move || read_row(&mut reader).transpose())
+    }
+}
+
+fn write_row(writer: &mut impl Write, row: &KeyedRow) -> anyhow::Result<()> {
+    writer.write_all(&(row.key.len() as u32).to_le_bytes())?;
+    writer.write_all(&(row.row.len() as u32).to_le_bytes())?;
+    for value in row.key.iter().chain(&row.row) {
+        match value {
+            SerialValue::Null => writer.write_all(&[0])?,
+            SerialValue::Float64(num) => {
+                writer.write_all(&[2])?;
+                writer.write_all(&num.to_le_bytes())?;
+            }
+            SerialValue::String(txt) => {
+                writer.write_all(&[3])?;
+                writer.write_all(&(txt.len() as u32).to_le_bytes())?;
+                writer.write_all(txt.as_bytes())?;
+            }
+            SerialValue::Blob(bytes) => {
+                writer.write_all(&[4])?;
+                writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+                writer.write_all(bytes)?;
+            }
+            value => {
+                writer.write_all(&[1])?;
+                writer.write_all(&value.as_integer().unwrap_or_default().to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_row(reader: &mut impl Read) -> anyhow::Result<Option<KeyedRow>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let key_len = u32::from_le_bytes(len) as usize;
+    reader.read_exact(&mut len)?;
+    let row_len = u32::from_le_bytes(len) as usize;
+
+    let mut values = Vec::with_capacity(key_len + row_len);
+    for _ in 0..key_len + row_len {
+        values.push(read_value(reader)?);
+    }
+    let row = values.split_off(key_len);
+
+    Ok(Some(KeyedRow { key: values, row }))
+}
+
+fn read_value(reader: &mut impl Read) -> anyhow::Result<SerialValue> {
+    let mut tag = [0; 1];
+    reader.read_exact(&mut tag)?;
+
+    let mut num = [0; 8];
+    let mut len = [0; 4];
+    Ok(match tag[0] {
+        0 => SerialValue::Null,
+        1 => {
+            reader.read_exact(&mut num)?;
+            SerialValue::I64(i64::from_le_bytes(num))
+        }
+        2 => {
+            reader.read_exact(&mut num)?;
+            SerialValue::Float64(f64::from_le_bytes(num))
+        }
+        3 | 4 => {
+            reader.read_exact(&mut len)?;
+            let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+            reader.read_exact(&mut bytes)?;
+            if tag[0] == 3 {
+                SerialValue::String(String::from_utf8(bytes)?)
+            } else {
+                SerialValue::Blob(bytes)
+            }
+        }
+        tag => bail!("invalid value tag {tag} in spill file"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        collation::Collation,
+        column::SerialValue,
+        hash_join::{HashJoin, KeyedRow},
+    };
+
+    fn rows(keys: &[Option<SerialValue>]) -> impl Iterator<Item = anyhow::Result<KeyedRow>> + '_ {
+        keys.iter().enumerate().map(|(i, key)| {
+            Ok(KeyedRow {
+                key: vec![key.clone().unwrap_or(SerialValue::Null)],
+                row: vec![
+                    SerialValue::I64(i as i64),
+                    SerialValue::String(format!("row {i}")),
+                ],
+            })
+        })
+    }
+
+    fn integers(keys: impl IntoIterator<Item = Option<i64>>) -> Vec<Option<SerialValue>> {
+        keys.into_iter()
+            .map(|key| key.map(SerialValue::I64))
+            .collect()
+    }
+
+    /// Returns the pairs of row numbers that were joined and the most bytes that were hashed.
+    fn join(
+        join: &HashJoin,
+        left: &[Option<SerialValue>],
+        right: &[Option<SerialValue>],
+    ) -> (Vec<(i64, i64)>, usize) {
+        let mut pairs = Vec::new();
+        let hashed = join
+            .run(rows(left), rows(right), |left, right| {
+                let right = right.map_or(-1, |right| right[0].as_integer().unwrap_or_default());
+                pairs.push((left[0].as_integer().unwrap_or_default(), right));
+                Ok(true)
+            })
+            .unwrap();
+
+        pairs.sort_unstable();
+        (pairs, hashed)
+    }
+
+    #[test]
+    fn test_hash_join_spills_to_disk() {
+        let left = integers((0..500).map(|i| Some(i % 50)));
+        let right = integers((0..200).map(|i| Some(i % 70)).chain([None]));
+
+        let in_memory = HashJoin {
+            outer: true,
+            memory_budget: usize::MAX,
+            collations: Vec::new(),
+        };
+        let spilled = HashJoin {
+            outer: true,
+            memory_budget: 1024,
+            collations: Vec::new(),
+        };
+
+        let (expected, hashed) = join(&in_memory, &left, &right);
+        assert_eq!(expected.len(), 500 * 3);
+        assert!(hashed > 1024);
+
+        // Only one partition of the right rows is hashed at a time, within the budget.
+        let (pairs, hashed) = join(&spilled, &left, &right);
+        assert_eq!(pairs, expected);
+        assert!(hashed > 0 && hashed <= 1024);
+        assert_eq!(join(&spilled, &right, &left).0.len(), 500 * 3 + 50 + 1);
+    }
+
+    #[test]
+    fn test_hash_join_left_outer_keeps_unmatched_rows() {
+        let join = HashJoin {
+            outer: true,
+            memory_budget: usize::MAX,
+            collations: Vec::new(),
+        };
+
+        assert_eq!(
+            self::join(
+                &join,
+                &integers([Some(1), None, Some(3)]),
+                &integers([Some(3), Some(1), Some(1), None])
+            )
+            .0,
+            vec![(0, 1), (0, 2), (1, -1), (2, 0)]
+        );
+    }
+
+    #[test]
+    fn test_hash_join_compares_keys_with_collation() {
+        let text = |txt: &str| Some(SerialValue::String(txt.to_string()));
+        let left = [text("abc"), text("ABD")];
+        let right = [text("ABC"), text("abc "), text("abd")];
+        let join = |collation| HashJoin {
+            outer: false,
+            memory_budget: usize::MAX,
+            collations: vec![collation],
+        };
+
+        assert_eq!(
+            self::join(&join(Collation::Binary), &left, &right).0,
+            vec![]
+        );
+        assert_eq!(
+            self::join(&join(Collation::NoCase), &left, &right).0,
+            vec![(0, 0), (1, 2)]
+        );
+        assert_eq!(
+            self::join(&join(Collation::RTrim), &left, &right).0,
+            vec![(0, 1)]
+        );
+    }
+}
@@ -0,0 +1,1261 @@
+use std::{ops::ControlFlow, rc::Rc};
+
+use anyhow::bail;
+use sqlparser::ast::{
+    visit_expressions, BinaryOperator, Expr, FunctionArg, FunctionArgExpr, Ident, JoinConstraint,
+    JoinOperator, ObjectName, Select, TableAlias, TableFactor,
+};
+
+use crate::{
+    collation::Collation,
+    column::{Affinity, SerialValue},
+    cte::find_cte,
+    executor::{table_row, Executor, Relation},
+    expr::{
+        affinity, comparison_affinities, comparison_collation, eval, ColumnName, Context, Scope,
+    },
+    hash_join::{HashJoin, KeyedRow},
+    json, plan,
+    schema::{ColumnSchema, IndexColumn, IndexSchema, TableSchema},
+};
+
+/// The number of rows a table that ANALYZE has not counted is assumed to hold, as SQLite
+/// assumes.
+const UNANALYZED_ROWS: f64 = 1_048_576.0;
+
+/// A table named in the FROM clause together with the name its columns are qualified by.
+pub struct TableRef<'s> {
+    pub table: &'s TableSchema,
+    pub qualifier: String,
+}
+
+impl TableRef<'_> {
+    /// The declared columns of the table followed by its hidden rowid.
+    pub fn columns(&self) -> Vec<ColumnName> {
+        let mut columns: Vec<ColumnName> = self
+            .table
+            .columns
+            .iter()
+            .map(|column| ColumnName {
+                collation: Some(
+                    column
+                        .collation
+                        .as_deref()
+                        .and_then(|name| Collation::from_name(name).ok())
+                        .unwrap_or_default(),
+                ),
+                affinity: Some(column.affinity),
+                decl_type: (!column.type_name.is_empty()).then(|| column.type_name.clone()),
+                ..ColumnName::new(Some(&self.qualifier), &column.name)
+            })
+            .collect();
+        columns.push(ColumnName::rowid(Some(&self.qualifier)));
+
+        columns
+    }
+}
+
+/// A table-valued function of the FROM clause, such as `json_each()`.
+#[derive(Debug, Clone)]
+pub struct TableFunction {
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub qualifier: String,
+}
+
+impl TableFunction {
+    pub fn columns(&self) -> Vec<ColumnName> {
+        json::each_columns(&self.qualifier)
+    }
+
+    /// Calls the function. The arguments may refer to the columns of `scope`, which holds the
+    /// row of the tables to the left.
+    pub fn rows(&self, scope: &Scope) -> anyhow::Result<Vec<Vec<SerialValue>>> {
+        let values = self
+            .args
+            .iter()
+            .map(|arg| eval(arg, scope))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        json::each(&self.name, &values)
+    }
+
+    pub fn relation(&self, context: Context) -> anyhow::Result<Relation> {
+        Ok(Relation {
+            columns: self.columns(),
+            rows: self.rows(&Scope::within(context, &[], &[]))?,
+            grouped: false,
+        })
+    }
+}
+
+/// A table or subquery of the FROM clause.
+pub enum Source<'s> {
+    Table(TableRef<'s>),
+    /// The rows of a subquery, computed up front.
+    Derived(Relation),
+    /// A table-valued function, which is called again for every row on its left.
+    Function(TableFunction),
+}
+
+impl Source<'_> {
+    pub fn columns(&self) -> Vec<ColumnName> {
+        match self {
+            Source::Table(table) => table.columns(),
+            Source::Derived(relation) => relation.columns.clone(),
+            Source::Function(function) => function.columns(),
+        }
+    }
+}
+
+/// The most tables whose join orders are all compared.
+const MAX_REORDERED_TABLES: usize = 7;
+
+/// The tables of an inner join in the order the planner chose to join them.
+pub struct JoinOrder<'q> {
+    /// The tables in the order of the FROM clause.
+    pub factors: Vec<&'q TableFactor>,
+    /// The positions in `factors` in the order they are joined.
+    pub order: Vec<usize>,
+    /// The terms of the WHERE clause and of every ON constraint.
+    pub terms: Vec<&'q Expr>,
+    /// The ON constraint that joins each table in `factors`.
+    pub on: Vec<Option<&'q Expr>>,
+}
+
+/// How the rows of the right-hand table are found for each row on the left.
+pub enum Probe<'e> {
+    /// Every row of the table is read once and compared against each left row.
+    Scan,
+    /// The row whose rowid equals the key is looked up in the table b-tree.
+    Rowid(&'e TableSchema, ProbeKey<'e>),
+    /// The rows whose indexed column equals the key are looked up in the index b-tree.
+    Index(&'e TableSchema, &'e IndexSchema, ProbeKey<'e>),
+    /// The rows whose columns equal the keys are looked up in an automatic index of the table,
+    /// built on those columns when no index of the database leads with them.
+    AutoIndex(&'e TableSchema, Vec<(usize, ProbeKey<'e>)>),
+    /// The table is read once and hash joined on pairs of left and right key expressions.
+    Hash(Vec<HashKey<'e>>),
+    /// The table-valued function is called with the arguments of each left row.
+    Function(TableFunction),
+}
+
+/// The expression of the left rows whose value a probe looks up, with the affinity the
+/// comparison converts that value to and the collating sequence it compares with.
+#[derive(Clone, Copy)]
+pub struct ProbeKey<'e> {
+    pub expr: &'e Expr,
+    pub affinity: Option<Affinity>,
+    pub collation: Collation,
+}
+
+impl ProbeKey<'_> {
+    fn eval(&self, scope: &Scope) -> anyhow::Result<SerialValue> {
+        let value = eval(self.expr, scope)?;
+        Ok(match self.affinity {
+            Some(affinity) => value.apply_affinity(affinity),
+            None => value,
+        })
+    }
+}
+
+/// An equality between an expression of the left rows and one of the right rows that a hash
+/// join matches rows on. The keys are converted and compared the way the equality compares its
+/// operands.
+pub struct HashKey<'e> {
+    pub left: &'e Expr,
+    pub right: &'e Expr,
+    pub collation: Collation,
+    pub left_affinity: Option<Affinity>,
+    pub right_affinity: Option<Affinity>,
+}
+
+impl<'a> Executor<'a> {
+    pub fn table_ref(&self, factor: &TableFactor) -> anyhow::Result<TableRef<'_>> {
+        let TableFactor::Table { name, alias, .. } = factor else {
+            bail!("unsupported table: {factor}");
+        };
+
+        let tbl_name = name
+            .0
+            .last()
+            .map(|ident| ident.value.as_str())
+            .unwrap_or_default();
+        let Some(table) = self.schema.table(tbl_name) else {
+            bail!("no such table: {name}");
+        };
+
+        Ok(TableRef {
+            table,
+            qualifier: alias
+                .as_ref()
+                .map(|alias| alias.name.value.clone())
+                .unwrap_or_else(|| table.name.clone()),
+        })
+    }
+
+    /// Resolves a table of the FROM clause. Subqueries and common table expressions are run
+    /// right away.
+    pub fn source(&self, factor: &TableFactor, context: Context) -> anyhow::Result<Source<'_>> {
+        let (result, qualifier, alias) = match factor {
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => (self.query(subquery, context)?, None, alias),
+            TableFactor::Table {
+                name,
+                alias,
+                args: Some(args),
+                ..
+            } => return Ok(Source::Function(table_function(name, alias, args)?)),
+            TableFactor::Table { name, alias, .. } => {
+                let cte_name = name
+                    .0
+                    .last()
+                    .map(|ident| ident.value.as_str())
+                    .unwrap_or_default();
+                match self.cte(cte_name, context)? {
+                    Some(result) => (Rc::unwrap_or_clone(result), Some(cte_name), alias),
+                    None => return Ok(Source::Table(self.table_ref(factor)?)),
+                }
+            }
+            factor => bail!("unsupported table: {factor}"),
+        };
+
+        Ok(Source::Derived(Relation {
+            columns: derived_columns(
+                result.columns,
+                &result.collations,
+                &result.decl_types,
+                alias.as_ref(),
+                qualifier,
+            )?,
+            rows: result.rows,
+            grouped: false,
+        }))
+    }
+
+    /// Joins the tables of the FROM clause from left to right, unless statistics show that
+    /// another order of an inner join is cheaper. Comma joins are inner joins without a
+    /// constraint. The WHERE clause is not applied to the result, but its terms may be used to
+    /// look up the rows of inner joins.
+    pub fn join_tables(&self, select: &Select, context: Context) -> anyhow::Result<Relation> {
+        if let Some(order) = self.join_order(select, context)? {
+            return self.join_reordered(&order, context);
+        }
+
+        let where_terms = select.selection.as_ref().map(conjuncts).unwrap_or_default();
+
+        let mut relation: Option<Relation> = None;
+        for table_with_joins in &select.from {
+            relation = Some(match relation {
+                None => match self.source(&table_with_joins.relation, context)? {
+                    Source::Table(table) => {
+                        let rows = self.read_table(
+                            &table_with_joins.relation,
+                            &table,
+                            &where_terms,
+                            context,
+                        )?;
+                        Relation {
+                            columns: table.columns(),
+                            rows,
+                            grouped: false,
+                        }
+                    }
+                    Source::Derived(relation) => relation,
+                    Source::Function(function) => function.relation(context)?,
+                },
+                Some(left) => self.join(
+                    left,
+                    &table_with_joins.relation,
+                    &JoinOperator::CrossJoin,
+                    &where_terms,
+                    context,
+                )?,
+            });
+
+            for join in &table_with_joins.joins {
+                let left = relation.take().unwrap_or_default();
+                relation = Some(self.join(
+                    left,
+                    &join.relation,
+                    &join.join_operator,
+                    &where_terms,
+                    context,
+                )?);
+            }
+        }
+
+        Ok(relation.unwrap_or_default())
+    }
+
+    /// Joins the tables in the chosen order. Every term of the WHERE clause and the ON
+    /// constraints may look up rows, and the ON constraints are checked once all tables are
+    /// joined. The columns and rows are then put back in the order of the FROM clause.
+    fn join_reordered(&self, order: &JoinOrder, context: Context) -> anyhow::Result<Relation> {
+        let mut relation: Option<Relation> = None;
+        for &i in &order.order {
+            relation = Some(match relation {
+                None => {
+                    let table = self.table_ref(order.factors[i])?;
+                    Relation {
+                        columns: table.columns(),
+                        rows: self.read_table(order.factors[i], &table, &order.terms, context)?,
+                        grouped: false,
+                    }
+                }
+                Some(left) => self.join(
+                    left,
+                    order.factors[i],
+                    &JoinOperator::CrossJoin,
+                    &order.terms,
+                    context,
+                )?,
+            });
+        }
+        let relation = relation.unwrap_or_default();
+
+        // Where each table's columns start in the joined rows, in the order of the FROM clause.
+        let widths: Vec<usize> = order
+            .factors
+            .iter()
+            .map(|factor| Ok(self.table_ref(factor)?.columns().len()))
+            .collect::<anyhow::Result<_>>()?;
+        let mut starts = vec![0; widths.len()];
+        let mut start = 0;
+        for &i in &order.order {
+            starts[i] = start;
+            start += widths[i];
+        }
+        let positions: Vec<usize> = (0..widths.len())
+            .flat_map(|i| starts[i]..starts[i] + widths[i])
+            .collect();
+
+        let columns: Vec<ColumnName> = positions
+            .iter()
+            .map(|&i| relation.columns[i].clone())
+            .collect();
+        let mut rows = Vec::new();
+        'rows: for row in relation.rows {
+            let row: Vec<SerialValue> = positions.iter().map(|&i| row[i].clone()).collect();
+
+            // An ON constraint only sees the tables up to its own.
+            let mut end = 0;
+            for (width, on) in widths.iter().zip(&order.on) {
+                end += width;
+                let terms = on.map(conjuncts).unwrap_or_default();
+                if !satisfies(&terms, &columns[..end], &row[..end], context)? {
+                    continue 'rows;
+                }
+            }
+            rows.push(row);
+        }
+
+        Ok(Relation {
+            columns,
+            rows,
+            grouped: false,
+        })
+    }
+
+    /// Picks the cheapest order to join the tables of the FROM clause. Only inner joins of
+    /// tables that ANALYZE has seen are reordered. Returns None when the tables should be
+    /// joined as written.
+    pub fn join_order<'q>(
+        &self,
+        select: &'q Select,
+        context: Context,
+    ) -> anyhow::Result<Option<JoinOrder<'q>>> {
+        let mut factors = Vec::new();
+        let mut on = Vec::new();
+        let mut terms = select.selection.as_ref().map(conjuncts).unwrap_or_default();
+        for table_with_joins in &select.from {
+            factors.push(&table_with_joins.relation);
+            on.push(None);
+            for join in &table_with_joins.joins {
+                let constraint = match &join.join_operator {
+                    JoinOperator::Inner(JoinConstraint::On(expr)) => Some(expr),
+                    JoinOperator::Inner(JoinConstraint::None) => None,
+                    _ => return Ok(None),
+                };
+                factors.push(&join.relation);
+                on.push(constraint);
+                terms.extend(constraint.map(conjuncts).unwrap_or_default());
+            }
+        }
+        if !(2..=MAX_REORDERED_TABLES).contains(&factors.len()) {
+            return Ok(None);
+        }
+
+        let mut tables = Vec::new();
+        for factor in &factors {
+            let TableFactor::Table {
+                name, args: None, ..
+            } = factor
+            else {
+                return Ok(None);
+            };
+            let name = name.0.last().map(|ident| ident.value.as_str());
+            if find_cte(name.unwrap_or_default(), context).is_some() {
+                return Ok(None);
+            }
+            let table = self.table_ref(factor)?;
+            if table.table.row_estimate.is_none() {
+                return Ok(None);
+            }
+            tables.push(table);
+        }
+
+        // A prepared statement joins the tables in the order of its first run.
+        let key = select as *const Select;
+        let planned = self.plans.borrow().orders.get(&key).cloned();
+        let order = match planned {
+            Some(order) => order,
+            None => {
+                let order = self.cheapest_order(&tables, &terms, context)?;
+                self.plans.borrow_mut().orders.insert(key, order.clone());
+                order
+            }
+        };
+
+        Ok(order.map(|order| JoinOrder {
+            factors,
+            order,
+            terms,
+            on,
+        }))
+    }
+
+    /// Compares the cost of every order of the tables, and returns the cheapest one unless it
+    /// is the written order.
+    fn cheapest_order(
+        &self,
+        tables: &[TableRef],
+        terms: &[&Expr],
+        context: Context,
+    ) -> anyhow::Result<Option<Vec<usize>>> {
+        // The rows of each table that its own terms let through.
+        let filtered = tables
+            .iter()
+            .map(|table| plan::rows(&self.schema, table, terms, context))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut best: Option<(f64, Vec<usize>)> = None;
+        for order in permutations(tables.len()) {
+            let cost = self.join_cost(tables, &filtered, &order, terms, context)?;
+            if best.as_ref().is_none_or(|(best, _)| cost < *best) {
+                best = Some((cost, order));
+            }
+        }
+
+        Ok(best
+            .map(|(_, order)| order)
+            .filter(|order| !order.is_sorted()))
+    }
+
+    /// Estimates the cost of joining the tables in `order`, counting the rows and index entries
+    /// read the way [`plan::cost`] does.
+    fn join_cost(
+        &self,
+        tables: &[TableRef],
+        filtered: &[Option<f64>],
+        order: &[usize],
+        terms: &[&Expr],
+        context: Context,
+    ) -> anyhow::Result<f64> {
+        let mut cost = 0.0;
+        let mut rows = 1.0;
+        let mut left_columns = Vec::new();
+
+        for (step, &i) in order.iter().enumerate() {
+            let table = &tables[i];
+            let count = table.table.row_estimate.unwrap_or_default() as f64;
+            let search = plan::search_cost(table.table).unwrap_or_default();
+            let columns = table.columns();
+
+            let filtered = filtered[i].unwrap_or(count);
+            if step == 0 {
+                let access = plan::access(&self.schema, table, terms, None, None, context)?;
+                cost += plan::cost(table.table, &access).unwrap_or(count);
+                rows = filtered;
+            } else {
+                let selectivity = filtered / count.max(1.0);
+                match self.probe(table, &left_columns, &columns, terms, context, None) {
+                    Probe::Rowid(..) => {
+                        cost += rows * search;
+                        rows *= selectivity;
+                    }
+                    // A constant key finds the same entries, which its own terms let
+                    // through, for every left row.
+                    Probe::Index(_, index, key) => {
+                        let per_key = plan::probe_rows(index, &key, context);
+                        cost += rows * (search + per_key * (1.0 + search));
+                        if depends_only_on(key.expr, &[]) {
+                            rows *= filtered;
+                        } else {
+                            rows *= per_key * selectivity;
+                        }
+                    }
+                    // Building the index sorts the table once.
+                    Probe::AutoIndex(..) => {
+                        cost += (count + rows) * search;
+                        rows = rows.max(filtered);
+                    }
+                    Probe::Hash(_) => {
+                        cost += count + rows;
+                        rows = rows.max(filtered);
+                    }
+                    Probe::Scan | Probe::Function(_) => {
+                        cost += rows * count;
+                        rows *= filtered;
+                    }
+                }
+            }
+            left_columns.extend(columns);
+        }
+
+        Ok(cost)
+    }
+
+    /// Joins `left` with a table with nested loops. When an equality constraint names the rowid
+    /// or the first column of an index of the right table, that b-tree is searched for every
+    /// left row instead of scanning the table. Other equality constraints are hash joined.
+    fn join(
+        &self,
+        left: Relation,
+        factor: &TableFactor,
+        operator: &JoinOperator,
+        where_terms: &[&Expr],
+        context: Context,
+    ) -> anyhow::Result<Relation> {
+        let (outer, constraint) = join_kind(operator)?;
+
+        let right = self.source(factor, context)?;
+        let mut right_columns = right.columns();
+        let using_terms = using_terms(&left.columns, &mut right_columns, constraint)?;
+
+        let mut terms: Vec<&Expr> = using_terms.iter().collect();
+        if let Some(JoinConstraint::On(expr)) = constraint {
+            terms.extend(conjuncts(expr));
+        }
+
+        let mut columns = left.columns.clone();
+        columns.extend(right_columns.iter().cloned());
+
+        // Terms of the WHERE clause may only pick the rows of an inner join. For an outer join
+        // they decide whether the padded row survives.
+        let probe_terms = if outer {
+            terms.clone()
+        } else {
+            terms.iter().chain(where_terms).copied().collect()
+        };
+        let probe = match &right {
+            Source::Table(table) => self.planned_probe(
+                factor,
+                table,
+                &left.columns,
+                &right_columns,
+                &probe_terms,
+                context,
+            ),
+            Source::Derived(_) => hash_or_scan(&left.columns, &right_columns, &probe_terms),
+            Source::Function(function) => Probe::Function(function.clone()),
+        };
+
+        // An automatic index that outgrows the memory budget gives way to a hash join, which
+        // can spill to disk.
+        let mut auto_index = None;
+        if let Probe::AutoIndex(table, keys) = &probe {
+            let columns = keys.iter().map(|(i, key)| (*i, key.collation)).collect();
+            auto_index = self.auto_index(table, columns)?;
+        }
+        let probe = match probe {
+            Probe::AutoIndex(..) if auto_index.is_none() => {
+                hash_or_scan(&left.columns, &right_columns, &probe_terms)
+            }
+            probe => probe,
+        };
+
+        let mut rows = Vec::new();
+        if let Probe::Hash(keys) = &probe {
+            // The smaller input is hashed and the other one probes it. The left rows of an outer
+            // join are padded when they find no match, so they always probe.
+            let right_estimate = match &right {
+                Source::Table(table) => table
+                    .table
+                    .row_estimate
+                    .map_or(UNANALYZED_ROWS, |rows| rows as f64),
+                Source::Derived(relation) => relation.rows.len() as f64,
+                Source::Function(_) => 0.0,
+            };
+            let build_left = !outer && (left.rows.len() as f64) < right_estimate;
+
+            // The right rows are keyed as they are read, so the join can partition them to disk
+            // without holding them all.
+            let right_rows: Box<dyn Iterator<Item = anyhow::Result<Vec<SerialValue>>>> = match right
+            {
+                Source::Table(table) => {
+                    Box::new(self.db.table_cursor(table.table.root_page).map(move |row| {
+                        let (rowid, record) = row?;
+                        Ok(table_row(table.table, rowid, &record))
+                    }))
+                }
+                Source::Derived(relation) => Box::new(relation.rows.into_iter().map(Ok)),
+                Source::Function(_) => Box::new(std::iter::empty()),
+            };
+            // Each left row carries its position, so the joined rows can be put back in the
+            // order of the left rows when they are the ones hashed.
+            let left_width = left.columns.len();
+            let left_rows = keyed_rows(
+                left.rows.into_iter().enumerate().map(|(i, mut row)| {
+                    row.push(SerialValue::I64(i as i64));
+                    Ok(row)
+                }),
+                keys.iter().map(|key| (key.left, key.left_affinity)),
+                &left.columns,
+                context,
+            );
+            let right_rows = keyed_rows(
+                right_rows,
+                keys.iter().map(|key| (key.right, key.right_affinity)),
+                &right_columns,
+                context,
+            );
+
+            let join = HashJoin {
+                outer,
+                memory_budget: self.memory_budget,
+                collations: keys.iter().map(|key| key.collation).collect(),
+            };
+            let mut joined = Vec::new();
+            let mut emit = |left_row: &[SerialValue], right_row: Option<&[SerialValue]>| {
+                let position = left_row[left_width].as_integer().unwrap_or_default();
+                let mut row = left_row[..left_width].to_vec();
+                match right_row {
+                    Some(right_row) => {
+                        row.extend_from_slice(right_row);
+                        if !satisfies(&terms, &columns, &row, context)? {
+                            return Ok(false);
+                        }
+                    }
+                    None => row.resize(columns.len(), SerialValue::Null),
+                }
+                joined.push((position, row));
+                Ok(true)
+            };
+            if build_left {
+                join.run(right_rows, left_rows, |right_row, left_row| {
+                    emit(left_row.unwrap_or_default(), Some(right_row))
+                })?;
+                joined.sort_by_key(|(position, _)| *position);
+            } else {
+                join.run(left_rows, right_rows, emit)?;
+            }
+
+            return Ok(Relation {
+                columns,
+                rows: joined.into_iter().map(|(_, row)| row).collect(),
+                grouped: false,
+            });
+        }
+
+        let scanned = match (&probe, right) {
+            (
+                Probe::Rowid(..) | Probe::Index(..) | Probe::AutoIndex(..) | Probe::Function(_),
+                _,
+            ) => Vec::new(),
+            (_, Source::Table(table)) => self
+                .db
+                .read_table(table.table.root_page)?
+                .into_iter()
+                .map(|(rowid, record)| table_row(table.table, rowid, &record))
+                .collect(),
+            (_, Source::Derived(relation)) => relation.rows,
+            (_, Source::Function(_)) => Vec::new(),
+        };
+
+        for left_row in left.rows {
+            let found;
+            let candidates: &[Vec<SerialValue>] = match probe {
+                Probe::Scan | Probe::Hash(_) => &scanned,
+                Probe::Rowid(table, key) => {
+                    let key = key.eval(&Scope::within(context, &left.columns, &left_row))?;
+                    found = self.search_rowid(table, key)?;
+                    &found
+                }
+                Probe::Index(table, index, key) => {
+                    let value = key.eval(&Scope::within(context, &left.columns, &left_row))?;
+                    found = self.search_index(table, index, value, key.collation)?;
+                    &found
+                }
+                Probe::AutoIndex(_, ref keys) => {
+                    let scope = Scope::within(context, &left.columns, &left_row);
+                    let key = keys
+                        .iter()
+                        .map(|(_, key)| key.eval(&scope))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    auto_index.as_ref().map_or(&[], |index| index.search(&key))
+                }
+                Probe::Function(ref function) => {
+                    found = function.rows(&Scope::within(context, &left.columns, &left_row))?;
+                    &found
+                }
+            };
+
+            let mut matched = false;
+            for right_row in candidates {
+                let mut row = left_row.clone();
+                row.extend(right_row.iter().cloned());
+
+                if satisfies(&terms, &columns, &row, context)? {
+                    matched = true;
+                    rows.push(row);
+                }
+            }
+
+            if outer && !matched {
+                let mut row = left_row;
+                row.resize(columns.len(), SerialValue::Null);
+                rows.push(row);
+            }
+        }
+
+        Ok(Relation {
+            columns,
+            rows,
+            grouped: false,
+        })
+    }
+
+    /// Chooses how to find the rows of the table of `factor` for each left row with
+    /// [`Executor::probe`]. The later runs of a prepared statement probe the index chosen the
+    /// first time.
+    pub fn planned_probe<'e>(
+        &'e self,
+        factor: &TableFactor,
+        right: &TableRef<'e>,
+        left_columns: &[ColumnName],
+        right_columns: &[ColumnName],
+        terms: &[&'e Expr],
+        context: Context,
+    ) -> Probe<'e> {
+        let key = factor as *const TableFactor;
+        let planned = self.plans.borrow().probes.get(&key).cloned();
+        let probe = self.probe(
+            right,
+            left_columns,
+            right_columns,
+            terms,
+            context,
+            planned.as_deref(),
+        );
+        if let (None, Probe::Index(_, index, _)) = (&planned, &probe) {
+            self.plans
+                .borrow_mut()
+                .probes
+                .insert(key, index.name.clone());
+        }
+
+        probe
+    }
+
+    /// Looks for a `right_column = expr` term where `expr` only depends on the left rows and the
+    /// column is the rowid or leads an index of the right table. The index must be ordered by
+    /// the collating sequence the term compares with, and must hold every row the terms let
+    /// through if it is partial. The term must not convert the column's values. When `planned`
+    /// names an index, no other one is probed. Failing that, the right table is searched
+    /// through an automatic index on every column such a term compares.
+    pub fn probe<'e>(
+        &'e self,
+        right: &TableRef<'e>,
+        left_columns: &[ColumnName],
+        right_columns: &[ColumnName],
+        terms: &[&'e Expr],
+        context: Context,
+        planned: Option<&str>,
+    ) -> Probe<'e> {
+        let columns: Vec<ColumnName> = left_columns.iter().chain(right_columns).cloned().collect();
+        let scope = Scope::new(&columns, &[]);
+        let mut index_probe = None;
+        let mut auto_keys: Vec<(usize, ProbeKey)> = Vec::new();
+
+        for term in terms {
+            let Expr::BinaryOp {
+                left,
+                op: BinaryOperator::Eq,
+                right: other,
+            } = term
+            else {
+                continue;
+            };
+            // An unknown collating sequence is reported when the term is evaluated.
+            let Ok(collation) = comparison_collation(left, other, &scope) else {
+                continue;
+            };
+            let (left_affinity, other_affinity) =
+                comparison_affinities(affinity(left, &scope), affinity(other, &scope));
+
+            for (column, key, column_affinity, key_affinity) in [
+                (left, other, left_affinity, other_affinity),
+                (other, left, other_affinity, left_affinity),
+            ] {
+                let Some(i) = column_of(column, left_columns, right_columns) else {
+                    continue;
+                };
+                if !depends_only_on(key, left_columns) || column_affinity.is_some() {
+                    continue;
+                }
+                let probe_key = ProbeKey {
+                    expr: key,
+                    affinity: key_affinity,
+                    collation,
+                };
+
+                if right_columns[i].hidden || right.table.rowid_alias == Some(i) {
+                    return Probe::Rowid(right.table, probe_key);
+                }
+
+                // Of the indexes that can be probed, the one expected to find the fewest
+                // entries for each left row wins.
+                let column = &right.table.columns[i];
+                for index in self.schema.indexes_of(&right.table.name) {
+                    let usable = planned.is_none_or(|name| name == index.name)
+                        && index.columns.first().is_some_and(|index_column| {
+                            index_column.name.eq_ignore_ascii_case(&column.name)
+                                && index_collation(index_column, column) == Some(collation)
+                        })
+                        && plan::holds(right, index, terms, context);
+                    if !usable {
+                        continue;
+                    }
+                    let rows = plan::probe_rows(index, &probe_key, context);
+                    if index_probe.as_ref().is_none_or(|(best, _)| rows < *best) {
+                        index_probe = Some((rows, Probe::Index(right.table, index, probe_key)));
+                    }
+                }
+
+                // The automatic index is keyed under the collation of the term.
+                if auto_keys.iter().all(|(column, _)| *column != i) {
+                    auto_keys.push((i, probe_key));
+                }
+            }
+        }
+
+        match index_probe {
+            Some((_, probe)) => probe,
+            None if !auto_keys.is_empty() => Probe::AutoIndex(right.table, auto_keys),
+            None => hash_or_scan(left_columns, right_columns, terms),
+        }
+    }
+
+    fn search_rowid(
+        &self,
+        table: &TableSchema,
+        key: SerialValue,
+    ) -> anyhow::Result<Vec<Vec<SerialValue>>> {
+        // The key has been converted the way the comparison converts it, so only a whole number
+        // in range can equal a rowid.
+        let rowid = match key {
+            SerialValue::Float64(num)
+                if num.fract() == 0.0
+                    && (-9.223372036854776e18..9.223372036854776e18).contains(&num) =>
+            {
+                num as i64
+            }
+            key => match key.as_integer() {
+                Some(rowid) => rowid,
+                None => return Ok(Vec::new()),
+            },
+        };
+
+        Ok(self
+            .db
+            .read_ids_from_table(table.root_page, &[rowid])?
+            .into_iter()
+            .map(|(rowid, record)| table_row(table, rowid, &record))
+            .collect())
+    }
+
+    fn search_index(
+        &self,
+        table: &TableSchema,
+        index: &IndexSchema,
+        key: SerialValue,
+        collation: Collation,
+    ) -> anyhow::Result<Vec<Vec<SerialValue>>> {
+        if key.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let desc = index.columns.first().is_some_and(|column| column.desc);
+        let mut rowids: Vec<i64> = self
+            .db
+            .search_index(index.root_page, &[key], &[desc], &[collation])?
+            .iter()
+            .filter_map(|entry| entry.columns.last()?.data().as_integer())
+            .collect();
+        rowids.sort_unstable();
+        rowids.dedup();
+
+        Ok(self
+            .db
+            .read_ids_from_table(table.root_page, &rowids)?
+            .into_iter()
+            .map(|(rowid, record)| table_row(table, rowid, &record))
+            .collect())
+    }
+}
+
+/// The collating sequence the entries of an index column are ordered by: the one named in the
+/// index, or else the one the table column was declared with. None for an unknown name.
+pub fn index_collation(index_column: &IndexColumn, column: &ColumnSchema) -> Option<Collation> {
+    match index_column
+        .collation
+        .as_deref()
+        .or(column.collation.as_deref())
+    {
+        Some(name) => Collation::from_name(name).ok(),
+        None => Some(Collation::Binary),
+    }
+}
+
+/// Names the columns of a subquery or common table expression in the FROM clause, after the
+/// column list of its alias if it has one. Like table columns, they compare with BINARY unless
+/// their expression brings another collating sequence, and they keep the declared type of the
+/// table column they read.
+pub fn derived_columns(
+    names: Vec<String>,
+    collations: &[Option<Collation>],
+    decl_types: &[Option<String>],
+    alias: Option<&TableAlias>,
+    qualifier: Option<&str>,
+) -> anyhow::Result<Vec<ColumnName>> {
+    let qualifier = alias.map(|alias| alias.name.value.as_str()).or(qualifier);
+    let names = match alias {
+        Some(TableAlias { columns, .. }) if !columns.is_empty() => {
+            if columns.len() != names.len() {
+                bail!(
+                    "table {} has {} values for {} columns",
+                    qualifier.unwrap_or_default(),
+                    names.len(),
+                    columns.len()
+                );
+            }
+            columns.iter().map(|column| column.value.clone()).collect()
+        }
+        _ => unique_names(names),
+    };
+
+    Ok(names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| ColumnName {
+            collation: Some(collations.get(i).copied().flatten().unwrap_or_default()),
+            decl_type: decl_types.get(i).cloned().flatten(),
+            ..ColumnName::new(qualifier, name)
+        })
+        .collect())
+}
+
+/// Tells whether a join is an outer join, and returns its constraint.
+pub fn join_kind(operator: &JoinOperator) -> anyhow::Result<(bool, Option<&JoinConstraint>)> {
+    match operator {
+        JoinOperator::Inner(constraint) => Ok((false, Some(constraint))),
+        JoinOperator::LeftOuter(constraint) => Ok((true, Some(constraint))),
+        JoinOperator::CrossJoin => Ok((false, None)),
+        JoinOperator::RightOuter(_) | JoinOperator::FullOuter(_) => {
+            bail!("RIGHT and FULL OUTER JOINs are not supported")
+        }
+        operator => bail!("unsupported join: {operator:?}"),
+    }
+}
+
+/// USING and NATURAL compare the columns of the same name on both sides. Returns those
+/// comparisons and marks the right-hand copies as shadowed, since they can then only be
+/// referenced through their table.
+pub fn using_terms(
+    left_columns: &[ColumnName],
+    right_columns: &mut [ColumnName],
+    constraint: Option<&JoinConstraint>,
+) -> anyhow::Result<Vec<Expr>> {
+    let shared: Vec<String> = match constraint {
+        Some(JoinConstraint::Using(idents)) => {
+            idents.iter().map(|ident| ident.value.clone()).collect()
+        }
+        Some(JoinConstraint::Natural) => right_columns
+            .iter()
+            .filter(|column| !column.hidden)
+            .filter(|column| {
+                left_columns.iter().any(|left| {
+                    !left.hidden && !left.shadowed && left.name.eq_ignore_ascii_case(&column.name)
+                })
+            })
+            .map(|column| column.name.clone())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut terms = Vec::new();
+    for name in &shared {
+        let left_column = Scope::new(left_columns, &[]).resolve(None, name)?;
+        let right_column = right_columns
+            .iter()
+            .position(|column| !column.hidden && column.name.eq_ignore_ascii_case(name));
+        let (Some(left_column), Some(right_column)) = (left_column, right_column) else {
+            bail!("cannot join using column {name} - column not present in both tables");
+        };
+
+        right_columns[right_column].shadowed = true;
+        terms.push(Expr::BinaryOp {
+            left: Box::new(qualified(&left_columns[left_column])),
+            op: BinaryOperator::Eq,
+            right: Box::new(qualified(&right_columns[right_column])),
+        });
+    }
+
+    Ok(terms)
+}
+
+/// Makes the column names of a subquery unique the way SQLite does, by appending `:N` to the
+/// later copies of a name.
+fn unique_names(names: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(names.len());
+    for mut name in names {
+        let mut count = 0;
+        while unique.iter().any(|other| other.eq_ignore_ascii_case(&name)) {
+            let base = match name.rsplit_once(':') {
+                Some((base, suffix)) if suffix.bytes().all(|b| b.is_ascii_digit()) => base,
+                _ => &name,
+            };
+            count += 1;
+            name = format!("{base}:{count}");
+        }
+        unique.push(name);
+    }
+
+    unique
+}
+
+/// Resolves a call in the FROM clause, which must name a table-valued function.
+fn table_function(
+    name: &ObjectName,
+    alias: &Option<TableAlias>,
+    args: &[FunctionArg],
+) -> anyhow::Result<TableFunction> {
+    let function = name.to_string().to_lowercase();
+    if !json::is_table_function(&function) {
+        bail!("no such table: {name}");
+    }
+    if args.len() > 2 {
+        bail!("too many arguments on {function}() - max 2");
+    }
+
+    let args = args
+        .iter()
+        .map(|arg| match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr.clone()),
+            _ => bail!("wrong arguments to function {name}()"),
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let qualifier = match alias {
+        Some(alias) => alias.name.value.clone(),
+        None => function.clone(),
+    };
+
+    Ok(TableFunction {
+        name: function,
+        args,
+        qualifier,
+    })
+}
+
+/// Hash joins on the equalities between an expression of the left rows and one of the right
+/// rows. Without any, every pair of rows is compared.
+pub fn hash_or_scan<'e>(
+    left_columns: &[ColumnName],
+    right_columns: &[ColumnName],
+    terms: &[&'e Expr],
+) -> Probe<'e> {
+    let columns: Vec<ColumnName> = left_columns.iter().chain(right_columns).cloned().collect();
+    let scope = Scope::new(&columns, &[]);
+    let mut keys = Vec::new();
+    for term in terms {
+        let Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right,
+        } = term
+        else {
+            continue;
+        };
+        // An unknown collating sequence is reported when the term is evaluated.
+        let Ok(collation) = comparison_collation(left, right, &scope) else {
+            continue;
+        };
+        let (left_affinity, right_affinity) =
+            comparison_affinities(affinity(left, &scope), affinity(right, &scope));
+
+        for (left, right, left_affinity, right_affinity) in [
+            (left, right, left_affinity, right_affinity),
+            (right, left, right_affinity, left_affinity),
+        ] {
+            if depends_only_on(left, left_columns)
+                && !depends_only_on(left, right_columns)
+                && depends_only_on(right, right_columns)
+                && !depends_only_on(right, left_columns)
+            {
+                keys.push(HashKey {
+                    left,
+                    right,
+                    collation,
+                    left_affinity,
+                    right_affinity,
+                });
+                break;
+            }
+        }
+    }
+
+    if keys.is_empty() {
+        Probe::Scan
+    } else {
+        Probe::Hash(keys)
+    }
+}
+
+/// Lists the orders of `n` items, starting with the order they are in.
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![Vec::new()];
+    }
+
+    let mut orders = Vec::new();
+    for first in 0..n {
+        for rest in permutations(n - 1) {
+            let mut order = vec![first];
+            order.extend(rest.into_iter().map(|i| if i < first { i } else { i + 1 }));
+            orders.push(order);
+        }
+    }
+
+    orders
+}
+
+/// Checks that every term is true for the row.
+fn satisfies(
+    terms: &[&Expr],
+    columns: &[ColumnName],
+    row: &[SerialValue],
+    context: Context,
+) -> anyhow::Result<bool> {
+    let scope = Scope::within(context, columns, row);
+    for term in terms {
+        if eval(term, &scope)?.as_bool() != Some(true) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Pairs each row with the values of its keys, converted to the affinity the comparison
+/// applies to them.
+fn keyed_rows<'e, 'c>(
+    rows: impl IntoIterator<Item = anyhow::Result<Vec<SerialValue>>> + 'c,
+    keys: impl Iterator<Item = (&'e Expr, Option<Affinity>)> + Clone + 'c,
+    columns: &'c [ColumnName],
+    context: Context<'c>,
+) -> impl Iterator<Item = anyhow::Result<KeyedRow>> + 'c
+where
+    'e: 'c,
+{
+    rows.into_iter().map(move |row| {
+        let row = row?;
+        let scope = Scope::within(context, columns, &row);
+        let key = keys
+            .clone()
+            .map(|(key, affinity)| {
+                let value = eval(key, &scope)?;
+                Ok(match affinity {
+                    Some(affinity) => value.apply_affinity(affinity),
+                    None => value,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(KeyedRow { key, row })
+    })
+}
+
+/// Splits an expression into the terms of its top-level ANDs.
+pub fn conjuncts(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut terms = conjuncts(left);
+            terms.extend(conjuncts(right));
+            terms
+        }
+        Expr::Nested(expr) => conjuncts(expr),
+        expr => vec![expr],
+    }
+}
+
+/// Returns the position among `right_columns` of a column reference that can only mean a
+/// column of the right table.
+pub fn column_of(
+    expr: &Expr,
+    left_columns: &[ColumnName],
+    right_columns: &[ColumnName],
+) -> Option<usize> {
+    let (table, name) = match expr {
+        Expr::Identifier(ident) => (None, ident),
+        Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+            (Some(idents[0].value.as_str()), &idents[1])
+        }
+        _ => return None,
+    };
+
+    // An unqualified name that also matches on the left is ambiguous.
+    if !matches!(
+        Scope::new(left_columns, &[]).resolve(table, &name.value),
+        Ok(None)
+    ) {
+        return None;
+    }
+
+    Scope::new(right_columns, &[])
+        .resolve(table, &name.value)
+        .ok()
+        .flatten()
+}
+
+/// Checks that every column referenced by `expr` is one of `columns`.
+pub fn depends_only_on(expr: &Expr, columns: &[ColumnName]) -> bool {
+    let scope = Scope::new(columns, &[]);
+    let flow = visit_expressions(expr, |expr| {
+        let resolved = match expr {
+            Expr::Identifier(ident) => scope.resolve(None, &ident.value),
+            Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+                scope.resolve(Some(&idents[0].value), &idents[1].value)
+            }
+            Expr::CompoundIdentifier(_) | Expr::Subquery(_) | Expr::Exists { .. } => Ok(None),
+            _ => return ControlFlow::Continue(()),
+        };
+
+        match resolved {
+            Ok(Some(_)) => ControlFlow::Continue(()),
+            _ => ControlFlow::Break(()),
+        }
+    });
+
+    flow.is_continue()
+}
+
+fn qualified(column: &ColumnName) -> Expr {
+    match &column.table {
+        Some(table) => Expr::CompoundIdentifier(vec![Ident::new(table), Ident::new(&column.name)]),
+        None => Expr::Identifier(Ident::new(&column.name)),
+    }
+}
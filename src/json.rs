@@ -0,0 +1,1740 @@
+use std::{borrow::Cow, ops::RangeInclusive};
+
+use anyhow::bail;
+use sqlparser::ast::{Expr, Function, JsonOperator};
+
+use crate::{
+    column::{format_real, numeric_prefix, SerialValue},
+    expr::{eval, ColumnName, Scope},
+    function::arguments,
+};
+
+/// The deepest nesting of arrays and objects the parser accepts.
+const MAX_DEPTH: usize = 1000;
+
+/// The scalar functions, which take their arguments through [`eval_arg`].
+const FUNCTIONS: [&str; 11] = [
+    "json",
+    "json_array",
+    "json_array_length",
+    "json_extract",
+    "json_insert",
+    "json_object",
+    "json_remove",
+    "json_replace",
+    "json_set",
+    "json_type",
+    "json_valid",
+];
+
+/// The columns of `json_each()` and `json_tree()`. The last two hold the arguments.
+const EACH_COLUMNS: [&str; 10] = [
+    "key", "value", "type", "atom", "id", "parent", "fullkey", "path", "json", "root",
+];
+
+pub fn is_function(name: &str) -> bool {
+    FUNCTIONS.contains(&name)
+}
+
+pub fn is_table_function(name: &str) -> bool {
+    matches!(name, "json_each" | "json_tree")
+}
+
+fn arity(name: &str) -> RangeInclusive<usize> {
+    match name {
+        "json" => 1..=1,
+        "json_array_length" | "json_type" | "json_valid" => 1..=2,
+        _ => 0..=usize::MAX,
+    }
+}
+
+/// A value together with whether it holds JSON. SQLite marks the results of the JSON
+/// functions with a subtype so that another JSON function embeds them instead of quoting them
+/// as strings.
+#[derive(Debug, Clone)]
+pub struct Subtyped {
+    pub value: SerialValue,
+    pub json: bool,
+}
+
+impl Subtyped {
+    fn plain(value: SerialValue) -> Self {
+        Self { value, json: false }
+    }
+
+    fn json(value: SerialValue) -> Self {
+        Self { value, json: true }
+    }
+
+    /// The value as JSON text, the way `json_array()` renders its arguments.
+    pub fn to_json_text(&self) -> anyhow::Result<String> {
+        Ok(from_sql(self, true)?.to_text())
+    }
+}
+
+/// How the text of a string is stored. Like SQLite, strings keep the escapes they were
+/// written with, so that `json()` returns them unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Escapes {
+    /// Nothing is escaped and nothing needs to be.
+    None,
+    /// The escapes of standard JSON.
+    Json,
+    /// JSON5 escapes, or characters that standard JSON requires to be escaped.
+    Json5,
+    /// Unescaped text, such as a SQL string, which is escaped when it is rendered.
+    Raw,
+}
+
+#[derive(Debug, Clone)]
+struct JsonText {
+    raw: String,
+    escapes: Escapes,
+}
+
+impl JsonText {
+    fn raw(txt: &str) -> Self {
+        Self {
+            raw: txt.to_string(),
+            escapes: Escapes::Raw,
+        }
+    }
+
+    /// The text with its escapes resolved.
+    fn decode(&self) -> Cow<'_, str> {
+        match self.escapes {
+            Escapes::None | Escapes::Raw => Cow::Borrowed(&self.raw),
+            Escapes::Json | Escapes::Json5 => Cow::Owned(unescape(&self.raw)),
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        out.push('"');
+        match self.escapes {
+            Escapes::None | Escapes::Json => out.push_str(&self.raw),
+            Escapes::Json5 => render_json5_text(&self.raw, out),
+            Escapes::Raw => escape(&self.raw, out),
+        }
+        out.push('"');
+    }
+
+    fn size(&self) -> usize {
+        node_size(self.raw.len())
+    }
+}
+
+/// A parsed JSON document. Numbers keep the text they were written with.
+#[derive(Debug, Clone)]
+enum Json {
+    Null,
+    True,
+    False,
+    Integer(String),
+    Real(String),
+    Text(JsonText),
+    Array(Vec<Json>),
+    Object(Vec<(JsonText, Json)>),
+}
+
+impl Json {
+    fn is_container(&self) -> bool {
+        matches!(self, Json::Array(_) | Json::Object(_))
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::True => "true",
+            Json::False => "false",
+            Json::Integer(_) => "integer",
+            Json::Real(_) => "real",
+            Json::Text(_) => "text",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        }
+    }
+
+    /// Renders the document as minified JSON text. JSON5 extensions come out as standard JSON.
+    fn render(&self, out: &mut String) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::True => out.push_str("true"),
+            Json::False => out.push_str("false"),
+            Json::Integer(txt) => render_integer(txt, out),
+            Json::Real(txt) => render_real(txt, out),
+            Json::Text(txt) => txt.render(out),
+            Json::Array(elements) => {
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    element.render(out);
+                }
+                out.push(']');
+            }
+            Json::Object(members) => {
+                out.push('{');
+                for (i, (label, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    label.render(out);
+                    out.push(':');
+                    value.render(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.render(&mut out);
+        out
+    }
+
+    /// The SQL value of an element: numbers and strings as themselves, booleans as 1 and 0,
+    /// and arrays and objects as JSON text.
+    fn to_sql(&self) -> SerialValue {
+        match self {
+            Json::Null => SerialValue::Null,
+            Json::True => SerialValue::I64(1),
+            Json::False => SerialValue::I64(0),
+            Json::Integer(txt) => integer_value(txt),
+            Json::Real(txt) => SerialValue::Float64(real_value(txt)),
+            Json::Text(txt) => SerialValue::String(txt.decode().into_owned()),
+            json => SerialValue::String(json.to_text()),
+        }
+    }
+
+    /// The number of bytes of the node's content in SQLite's binary JSONB encoding, which the
+    /// `id` column of `json_each()` and `json_tree()` counts in.
+    fn payload(&self) -> usize {
+        match self {
+            Json::Null | Json::True | Json::False => 0,
+            Json::Integer(txt) | Json::Real(txt) => txt.len(),
+            Json::Text(txt) => txt.raw.len(),
+            Json::Array(elements) => elements.iter().map(Json::size).sum(),
+            Json::Object(members) => members
+                .iter()
+                .map(|(label, value)| label.size() + value.size())
+                .sum(),
+        }
+    }
+
+    fn size(&self) -> usize {
+        node_size(self.payload())
+    }
+}
+
+/// A JSONB node header is one byte for up to 11 bytes of content, followed by the size in 1,
+/// 2 or 4 bytes when it is larger.
+fn header_size(payload: usize) -> usize {
+    match payload {
+        0..=11 => 1,
+        12..=0xff => 2,
+        0x100..=0xffff => 3,
+        _ => 5,
+    }
+}
+
+fn node_size(payload: usize) -> usize {
+    header_size(payload) + payload
+}
+
+/// Hexadecimal integers are rendered in decimal.
+fn render_integer(txt: &str, out: &mut String) {
+    let (sign, digits) = match txt.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", txt),
+    };
+    let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    else {
+        out.push_str(txt);
+        return;
+    };
+
+    out.push_str(sign);
+    match u64::from_str_radix(hex, 16) {
+        Ok(num) => out.push_str(&num.to_string()),
+        Err(_) => out.push_str("9.0e999"),
+    }
+}
+
+/// A decimal point gets a digit on both sides, as standard JSON requires.
+fn render_real(txt: &str, out: &mut String) {
+    let (sign, digits) = match txt.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", txt),
+    };
+    out.push_str(sign);
+    if digits.starts_with('.') {
+        out.push('0');
+    }
+
+    let bytes = digits.as_bytes();
+    for (i, c) in digits.char_indices() {
+        out.push(c);
+        if c == '.' && !bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+            out.push('0');
+        }
+    }
+}
+
+fn integer_value(txt: &str) -> SerialValue {
+    let (negative, digits) = match txt.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, txt),
+    };
+    let parsed = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).map(|num| num as i64).ok(),
+        None => digits.parse::<i64>().ok(),
+    };
+
+    match parsed {
+        Some(num) if negative => SerialValue::I64(num.wrapping_neg()),
+        Some(num) => SerialValue::I64(num),
+        None if negative && digits == "9223372036854775808" => SerialValue::I64(i64::MIN),
+        None => SerialValue::Float64(real_value(txt)),
+    }
+}
+
+fn real_value(txt: &str) -> f64 {
+    txt.parse()
+        .unwrap_or_else(|_| numeric_prefix(txt.as_bytes()).1)
+}
+
+/// Escapes the text of a SQL string for a JSON string literal.
+fn escape(txt: &str, out: &mut String) {
+    for c in txt.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => control_char(c as u8, out),
+            c => out.push(c),
+        }
+    }
+}
+
+fn control_char(c: u8, out: &mut String) {
+    match c {
+        b'\x08' => out.push_str("\\b"),
+        b'\t' => out.push_str("\\t"),
+        b'\n' => out.push_str("\\n"),
+        b'\x0c' => out.push_str("\\f"),
+        b'\r' => out.push_str("\\r"),
+        c => out.push_str(&format!("\\u{:04x}", c)),
+    }
+}
+
+/// Rewrites a string with JSON5 escapes using standard JSON escapes only.
+fn render_json5_text(raw: &str, out: &mut String) {
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            c if (c as u32) < 0x20 => control_char(c as u8, out),
+            '\\' => match chars.next() {
+                Some('\'') => out.push('\''),
+                Some('v') => out.push_str("\\u000b"),
+                Some('0') => out.push_str("\\u0000"),
+                Some('x') => {
+                    out.push_str("\\u00");
+                    out.extend(chars.by_ref().take(2));
+                }
+                // An escaped line break continues the string on the next line.
+                Some('\r') => {
+                    chars.next_if_eq(&'\n');
+                }
+                Some('\n' | '\u{2028}' | '\u{2029}') => {}
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+}
+
+fn hex_digits(chars: &[char]) -> Option<u32> {
+    let digits: String = chars.iter().collect();
+    if chars.iter().all(char::is_ascii_hexdigit) {
+        u32::from_str_radix(&digits, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Resolves the escapes of a JSON or JSON5 string.
+fn unescape(raw: &str) -> String {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut out = String::with_capacity(raw.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '\\' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let escaped = chars.get(i + 1).copied();
+        i += 2;
+        match escaped {
+            Some('u') => {
+                let Some(high) = chars.get(i..i + 4).and_then(hex_digits) else {
+                    out.push(char::REPLACEMENT_CHARACTER);
+                    break;
+                };
+                i += 4;
+                let low = match chars.get(i..i + 6) {
+                    Some(['\\', 'u', digits @ ..]) if high & 0xfc00 == 0xd800 => {
+                        hex_digits(digits).filter(|low| low & 0xfc00 == 0xdc00)
+                    }
+                    _ => None,
+                };
+                let code = match low {
+                    Some(low) => {
+                        i += 6;
+                        ((high & 0x3ff) << 10) + (low & 0x3ff) + 0x10000
+                    }
+                    None => high,
+                };
+                out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some('b') => out.push('\x08'),
+            Some('f') => out.push('\x0c'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('v') => out.push('\x0b'),
+            Some('0') => out.push('\0'),
+            Some('x') => {
+                let code = chars.get(i..i + 2).and_then(hex_digits);
+                i += 2;
+                out.push(
+                    code.and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER),
+                );
+            }
+            Some('\r') => {
+                if chars.get(i) == Some(&'\n') {
+                    i += 1;
+                }
+            }
+            Some('\n' | '\u{2028}' | '\u{2029}') => {}
+            Some(c @ ('\'' | '"' | '/' | '\\')) => out.push(c),
+            _ => out.push(char::REPLACEMENT_CHARACTER),
+        }
+    }
+
+    out
+}
+
+/// What the parser found at a position: a value and the position after it, or one of the
+/// punctuation characters that separate and close values.
+enum Parsed {
+    Value(Json, usize),
+    Punct(u8, usize),
+    End,
+    Error,
+}
+
+/// A port of SQLite's JSON parser, which also accepts JSON5.
+struct Parser<'t> {
+    z: &'t [u8],
+    /// Set when the text uses JSON5 extensions.
+    json5: bool,
+    depth: usize,
+}
+
+fn is_space(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || c == b'$' || c >= 0x80
+}
+
+fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c >= 0x80
+}
+
+fn is_hex4(z: &[u8]) -> bool {
+    z.len() >= 4 && z[..4].iter().all(u8::is_ascii_hexdigit)
+}
+
+impl Parser<'_> {
+    fn at(&self, i: usize) -> u8 {
+        self.z.get(i).copied().unwrap_or(0)
+    }
+
+    fn starts_with_ignore_case(&self, i: usize, prefix: &str) -> bool {
+        self.z
+            .get(i..i + prefix.len())
+            .is_some_and(|z| z.eq_ignore_ascii_case(prefix.as_bytes()))
+    }
+
+    fn text(&self, from: usize, to: usize) -> String {
+        String::from_utf8_lossy(&self.z[from..to]).into_owned()
+    }
+
+    /// The length of the JSON5 whitespace and comments at `i`.
+    fn json5_space(&self, i: usize) -> usize {
+        let mut n = i;
+        loop {
+            match self.at(n) {
+                0x09..=0x0d | b' ' => n += 1,
+                b'/' if self.at(n + 1) == b'*' && self.at(n + 2) != 0 => {
+                    let mut j = n + 3;
+                    while self.at(j) != b'/' || self.at(j - 1) != b'*' {
+                        if self.at(j) == 0 {
+                            return n - i;
+                        }
+                        j += 1;
+                    }
+                    n = j + 1;
+                }
+                b'/' if self.at(n + 1) == b'/' => {
+                    let mut j = n + 2;
+                    while self.at(j) != 0 && self.at(j) != b'\n' && self.at(j) != b'\r' {
+                        if self.z[j..].starts_with("\u{2028}".as_bytes())
+                            || self.z[j..].starts_with("\u{2029}".as_bytes())
+                        {
+                            j += 2;
+                            break;
+                        }
+                        j += 1;
+                    }
+                    n = if self.at(j) != 0 { j + 1 } else { j };
+                }
+                0xc2 if self.at(n + 1) == 0xa0 => n += 2,
+                0xe1 if self.at(n + 1) == 0x9a && self.at(n + 2) == 0x80 => n += 3,
+                0xe2 if self.at(n + 1) == 0x80
+                    && matches!(self.at(n + 2), 0x80..=0x8a | 0xa8 | 0xa9 | 0xaf) =>
+                {
+                    n += 3
+                }
+                0xe2 if self.at(n + 1) == 0x81 && self.at(n + 2) == 0x9f => n += 3,
+                0xe3 if self.at(n + 1) == 0x80 && self.at(n + 2) == 0x80 => n += 3,
+                0xef if self.at(n + 1) == 0xbb && self.at(n + 2) == 0xbf => n += 3,
+                _ => return n - i,
+            }
+        }
+    }
+
+    fn value(&mut self, mut i: usize) -> Parsed {
+        loop {
+            match self.at(i) {
+                c if is_space(c) => i += 1,
+                b'/' | 0x0b | 0x0c | 0xc2 | 0xe1 | 0xe2 | 0xe3 | 0xef => {
+                    let n = self.json5_space(i);
+                    if n == 0 {
+                        return Parsed::Error;
+                    }
+                    i += n;
+                    self.json5 = true;
+                }
+                _ => break,
+            }
+        }
+
+        match self.at(i) {
+            b'{' => self.object(i),
+            b'[' => self.array(i),
+            b'"' | b'\'' => self.string(i),
+            b't' => self.keyword(i, "true", Json::True),
+            b'f' => self.keyword(i, "false", Json::False),
+            b'n' if self.z[i..].starts_with(b"null") && !self.at(i + 4).is_ascii_alphanumeric() => {
+                Parsed::Value(Json::Null, i + 4)
+            }
+            b'+' | b'.' | b'-' | b'0'..=b'9' => self.number(i),
+            c @ (b'}' | b']' | b',' | b':') => Parsed::Punct(c, i),
+            0 if i >= self.z.len() => Parsed::End,
+            _ => self.nan_or_infinity(i),
+        }
+    }
+
+    fn keyword(&self, i: usize, keyword: &str, json: Json) -> Parsed {
+        let end = i + keyword.len();
+        if self.z[i..].starts_with(keyword.as_bytes()) && !self.at(end).is_ascii_alphanumeric() {
+            Parsed::Value(json, end)
+        } else {
+            Parsed::Error
+        }
+    }
+
+    fn nan_or_infinity(&mut self, i: usize) -> Parsed {
+        for (name, json) in [
+            ("inf", Json::Real("9e999".to_string())),
+            ("infinity", Json::Real("9e999".to_string())),
+            ("nan", Json::Null),
+            ("qnan", Json::Null),
+            ("snan", Json::Null),
+        ] {
+            let end = i + name.len();
+            if self.starts_with_ignore_case(i, name) && !self.at(end).is_ascii_alphanumeric() {
+                self.json5 = true;
+                return Parsed::Value(json, end);
+            }
+        }
+
+        Parsed::Error
+    }
+
+    fn object(&mut self, i: usize) -> Parsed {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Parsed::Error;
+        }
+
+        let mut members = Vec::new();
+        let mut j = i + 1;
+        loop {
+            let (label, next) = match self.value(j) {
+                Parsed::Value(Json::Text(label), next) => (label, next),
+                Parsed::Value(..) => return Parsed::Error,
+                Parsed::Punct(b'}', at) => {
+                    // A trailing comma.
+                    if !members.is_empty() {
+                        self.json5 = true;
+                    }
+                    j = at;
+                    break;
+                }
+                // JSON5 allows identifiers as labels.
+                _ => {
+                    let start = j + self.json5_space(j);
+                    if !is_ident_start(self.at(start)) {
+                        return Parsed::Error;
+                    }
+                    let mut end = start + 1;
+                    while is_ident(self.at(end)) && self.json5_space(end) == 0 {
+                        end += 1;
+                    }
+                    self.json5 = true;
+                    let label = JsonText {
+                        raw: self.text(start, end),
+                        escapes: Escapes::None,
+                    };
+                    (label, end)
+                }
+            };
+
+            j = next;
+            if self.at(j) == b':' {
+                j += 1;
+            } else {
+                while is_space(self.at(j)) {
+                    j += 1;
+                }
+                if self.at(j) == b':' {
+                    j += 1;
+                } else {
+                    match self.value(j) {
+                        Parsed::Punct(b':', at) => j = at + 1,
+                        _ => return Parsed::Error,
+                    }
+                }
+            }
+
+            let Parsed::Value(value, next) = self.value(j) else {
+                return Parsed::Error;
+            };
+            members.push((label, value));
+
+            j = next;
+            while is_space(self.at(j)) {
+                j += 1;
+            }
+            match self.at(j) {
+                b',' => j += 1,
+                b'}' => break,
+                _ => match self.value(j) {
+                    Parsed::Punct(b',', at) => j = at + 1,
+                    Parsed::Punct(b'}', at) => {
+                        j = at;
+                        break;
+                    }
+                    _ => return Parsed::Error,
+                },
+            }
+        }
+
+        self.depth -= 1;
+        Parsed::Value(Json::Object(members), j + 1)
+    }
+
+    fn array(&mut self, i: usize) -> Parsed {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Parsed::Error;
+        }
+
+        let mut elements = Vec::new();
+        let mut j = i + 1;
+        loop {
+            match self.value(j) {
+                Parsed::Value(element, next) => {
+                    elements.push(element);
+                    j = next;
+                }
+                Parsed::Punct(b']', at) => {
+                    // A trailing comma.
+                    if !elements.is_empty() {
+                        self.json5 = true;
+                    }
+                    j = at;
+                    break;
+                }
+                _ => return Parsed::Error,
+            }
+
+            while is_space(self.at(j)) {
+                j += 1;
+            }
+            match self.at(j) {
+                b',' => j += 1,
+                b']' => break,
+                _ => match self.value(j) {
+                    Parsed::Punct(b',', at) => j = at + 1,
+                    Parsed::Punct(b']', at) => {
+                        j = at;
+                        break;
+                    }
+                    _ => return Parsed::Error,
+                },
+            }
+        }
+
+        self.depth -= 1;
+        Parsed::Value(Json::Array(elements), j + 1)
+    }
+
+    fn string(&mut self, i: usize) -> Parsed {
+        let delimiter = self.z[i];
+        let mut escapes = Escapes::None;
+        if delimiter == b'\'' {
+            self.json5 = true;
+        }
+
+        let mut j = i + 1;
+        loop {
+            let c = self.at(j);
+            if c == delimiter {
+                break;
+            }
+            match c {
+                b'\\' => {
+                    j += 1;
+                    let c = self.at(j);
+                    let rest = &self.z[j + 1..];
+                    if matches!(c, b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't')
+                        || (c == b'u' && is_hex4(rest))
+                    {
+                        if escapes == Escapes::None {
+                            escapes = Escapes::Json;
+                        }
+                    } else if matches!(c, b'\'' | b'v' | b'\n')
+                        || (c == b'0' && !self.at(j + 1).is_ascii_digit())
+                        || (c == 0xe2
+                            && self.at(j + 1) == 0x80
+                            && matches!(self.at(j + 2), 0xa8 | 0xa9))
+                        || (c == b'x'
+                            && rest.len() >= 2
+                            && rest[..2].iter().all(u8::is_ascii_hexdigit))
+                    {
+                        escapes = Escapes::Json5;
+                        self.json5 = true;
+                    } else if c == b'\r' {
+                        if self.at(j + 1) == b'\n' {
+                            j += 1;
+                        }
+                        escapes = Escapes::Json5;
+                        self.json5 = true;
+                    } else {
+                        return Parsed::Error;
+                    }
+                }
+                0 if j >= self.z.len() => return Parsed::Error,
+                // Control characters must be escaped in standard JSON, and double quotes in
+                // single-quoted strings when they are rendered.
+                c if c <= 0x1f => {
+                    escapes = Escapes::Json5;
+                    self.json5 = true;
+                }
+                b'"' => escapes = Escapes::Json5,
+                _ => {}
+            }
+            j += 1;
+        }
+
+        let txt = JsonText {
+            raw: self.text(i + 1, j),
+            escapes,
+        };
+        Parsed::Value(Json::Text(txt), j + 1)
+    }
+
+    fn number(&mut self, i: usize) -> Parsed {
+        let c = self.at(i);
+        // Whether the number uses JSON5 syntax and whether it is a real.
+        let mut json5;
+        let mut real = false;
+
+        let finish = |parser: &mut Self, j: usize, json5: bool, real: bool| {
+            parser.json5 |= json5;
+            let start = if parser.at(i) == b'+' { i + 1 } else { i };
+            let txt = parser.text(start, j);
+            Parsed::Value(
+                if real {
+                    Json::Real(txt)
+                } else {
+                    Json::Integer(txt)
+                },
+                j,
+            )
+        };
+        let hex_end = |parser: &Self, mut j: usize| {
+            while parser.at(j).is_ascii_hexdigit() {
+                j += 1;
+            }
+            j
+        };
+
+        if c == b'.' {
+            if !self.at(i + 1).is_ascii_digit() {
+                return Parsed::Error;
+            }
+            json5 = true;
+            real = true;
+        } else {
+            json5 = c == b'+';
+            if c == b'0' {
+                if matches!(self.at(i + 1), b'x' | b'X') && self.at(i + 2).is_ascii_hexdigit() {
+                    let j = hex_end(self, i + 3);
+                    return finish(self, j, true, false);
+                } else if self.at(i + 1).is_ascii_digit() {
+                    return Parsed::Error;
+                }
+            } else if c == b'+' || c == b'-' {
+                let next = self.at(i + 1);
+                if !next.is_ascii_digit() {
+                    if self.starts_with_ignore_case(i + 1, "inf") {
+                        self.json5 = true;
+                        let txt = if c == b'-' { "-9e999" } else { "9e999" };
+                        let len = if self.starts_with_ignore_case(i + 4, "inity") {
+                            9
+                        } else {
+                            4
+                        };
+                        return Parsed::Value(Json::Real(txt.to_string()), i + len);
+                    }
+                    if next != b'.' {
+                        return Parsed::Error;
+                    }
+                    json5 = true;
+                } else if next == b'0' {
+                    if self.at(i + 2).is_ascii_digit() {
+                        return Parsed::Error;
+                    }
+                    if matches!(self.at(i + 2), b'x' | b'X') && self.at(i + 3).is_ascii_hexdigit() {
+                        let j = hex_end(self, i + 4);
+                        return finish(self, j, true, false);
+                    }
+                }
+            }
+        }
+
+        // A decimal point without a digit after it is JSON5.
+        let dangling_point = |parser: &Self, j: usize| {
+            parser.at(j - 1) == b'.' && j >= i + 2 && parser.at(j - 2).is_ascii_digit()
+        };
+        let mut seen_exponent = false;
+        let mut j = i + 1;
+        loop {
+            match self.at(j) {
+                c if c.is_ascii_digit() => {}
+                b'.' => {
+                    if real {
+                        return Parsed::Error;
+                    }
+                    real = true;
+                }
+                b'e' | b'E' => {
+                    if self.at(j - 1) < b'0' {
+                        if !dangling_point(self, j) {
+                            return Parsed::Error;
+                        }
+                        json5 = true;
+                    }
+                    if seen_exponent {
+                        return Parsed::Error;
+                    }
+                    real = true;
+                    seen_exponent = true;
+                    if matches!(self.at(j + 1), b'+' | b'-') {
+                        j += 1;
+                    }
+                    if !self.at(j + 1).is_ascii_digit() {
+                        return Parsed::Error;
+                    }
+                }
+                _ => break,
+            }
+            j += 1;
+        }
+
+        if self.at(j - 1) < b'0' {
+            if !dangling_point(self, j) {
+                return Parsed::Error;
+            }
+            json5 = true;
+        }
+
+        finish(self, j, json5, real)
+    }
+}
+
+/// Parses JSON text. Also returns whether the text used any JSON5 extensions.
+fn parse(txt: &str) -> Option<(Json, bool)> {
+    let mut parser = Parser {
+        z: txt.as_bytes(),
+        json5: false,
+        depth: 0,
+    };
+    let Parsed::Value(json, mut i) = parser.value(0) else {
+        return None;
+    };
+
+    while is_space(parser.at(i)) {
+        i += 1;
+    }
+    if i < parser.z.len() {
+        i += parser.json5_space(i);
+        if i < parser.z.len() {
+            return None;
+        }
+        parser.json5 = true;
+    }
+
+    Some((json, parser.json5))
+}
+
+/// Parses the JSON argument of a function. NULL has no document.
+fn parse_arg(value: &SerialValue) -> anyhow::Result<Option<Json>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    match parse(&value.display()) {
+        Some((json, _)) => Ok(Some(json)),
+        None => bail!("malformed JSON"),
+    }
+}
+
+/// Converts a SQL value to JSON. Text is a string unless it holds JSON. Infinite reals come
+/// out as a number too large for a double, written the way `printf()` writes them when
+/// rendering to `text`.
+fn from_sql(arg: &Subtyped, text: bool) -> anyhow::Result<Json> {
+    Ok(match &arg.value {
+        SerialValue::Null => Json::Null,
+        SerialValue::Float64(num) if num.is_nan() => Json::Null,
+        SerialValue::Float64(num) if num.is_infinite() => {
+            let sign = if *num < 0.0 { "-" } else { "" };
+            let magnitude = if text { "9.0e+999" } else { "9e999" };
+            Json::Real(format!("{sign}{magnitude}"))
+        }
+        SerialValue::Float64(num) => Json::Real(format_real(*num)),
+        SerialValue::String(txt) if arg.json => match parse(txt) {
+            Some((json, _)) => json,
+            None => bail!("malformed JSON"),
+        },
+        SerialValue::String(txt) => Json::Text(JsonText::raw(txt)),
+        SerialValue::Blob(_) => bail!("JSON cannot hold BLOB values"),
+        value => Json::Integer(value.display()),
+    })
+}
+
+struct BadPath;
+
+/// The label a path step names, as written in the path.
+struct PathKey<'p> {
+    txt: &'p str,
+    /// False when the label was quoted and contains escapes.
+    raw: bool,
+}
+
+impl PathKey<'_> {
+    fn matches(&self, label: &JsonText) -> bool {
+        match self.raw {
+            true => label.decode() == self.txt,
+            false => label.decode() == unescape(self.txt),
+        }
+    }
+
+    fn to_label(&self) -> JsonText {
+        JsonText {
+            raw: self.txt.to_string(),
+            escapes: if self.raw {
+                Escapes::Raw
+            } else {
+                Escapes::Json5
+            },
+        }
+    }
+}
+
+/// Parses the label of a `.label` or `."label"` step. `path` follows the dot.
+fn parse_key(path: &str) -> Result<(PathKey<'_>, &str), BadPath> {
+    if let Some(quoted) = path.strip_prefix('"') {
+        let end = quoted.find('"').ok_or(BadPath)?;
+        let txt = &quoted[..end];
+        return Ok((
+            PathKey {
+                txt,
+                raw: !txt.contains('\\'),
+            },
+            &quoted[end + 1..],
+        ));
+    }
+
+    let end = path.find(['.', '[']).unwrap_or(path.len());
+    if end == 0 {
+        return Err(BadPath);
+    }
+    Ok((
+        PathKey {
+            txt: &path[..end],
+            raw: true,
+        },
+        &path[end..],
+    ))
+}
+
+/// Parses an `[N]`, `[#]` or `[#-N]` step of an array of `len` elements. `[#-N]` past the
+/// start of the array finds nothing.
+fn parse_index(path: &str, len: usize) -> Result<Option<(usize, &str)>, BadPath> {
+    let bytes = path.as_bytes();
+    let at = |i: usize| bytes.get(i).copied().unwrap_or(0);
+    let digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let number = |from: usize, n: usize| path[from..from + n].parse().unwrap_or(usize::MAX);
+
+    let n = digits(1);
+    if n > 0 && at(1 + n) == b']' {
+        return Ok(Some((number(1, n), &path[n + 2..])));
+    }
+    if at(1) != b'#' {
+        return Err(BadPath);
+    }
+
+    let mut index = len;
+    let mut i = 2;
+    if at(2) == b'-' && at(3).is_ascii_digit() {
+        let n = digits(3);
+        let back = number(3, n);
+        if back > len {
+            return Ok(None);
+        }
+        index = len - back;
+        i = 3 + n;
+    }
+    if at(i) != b']' {
+        return Err(BadPath);
+    }
+
+    Ok(Some((index, &path[i + 1..])))
+}
+
+/// A node found by a path, with the offsets SQLite's JSONB encoding gives the node and, for
+/// an object member, its label.
+struct Found<'j> {
+    node: &'j Json,
+    offset: usize,
+    label: Option<usize>,
+    /// Where the last step starts in the path.
+    last_step: usize,
+}
+
+/// Follows a path from the root of a document. Steps are parsed as they are taken, so the
+/// rest of a path that leads nowhere is never checked.
+fn lookup<'j>(root: &'j Json, path: &str) -> Result<Option<Found<'j>>, BadPath> {
+    let Some(mut rest) = path.strip_prefix('$') else {
+        return Err(BadPath);
+    };
+
+    let mut found = Found {
+        node: root,
+        offset: 0,
+        label: None,
+        last_step: 0,
+    };
+    while !rest.is_empty() {
+        let last_step = path.len() - rest.len();
+        let start = found.offset + header_size(found.node.payload());
+
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let (key, after) = parse_key(after_dot)?;
+            let Json::Object(members) = found.node else {
+                return Ok(None);
+            };
+
+            let mut offset = start;
+            let mut member = None;
+            for (label, value) in members {
+                if key.matches(label) {
+                    member = Some((label, value));
+                    break;
+                }
+                offset += label.size() + value.size();
+            }
+            let Some((label, value)) = member else {
+                return Ok(None);
+            };
+            found = Found {
+                node: value,
+                offset: offset + label.size(),
+                label: Some(offset),
+                last_step,
+            };
+            rest = after;
+        } else if rest.starts_with('[') {
+            let Json::Array(elements) = found.node else {
+                return Ok(None);
+            };
+            let Some((index, after)) = parse_index(rest, elements.len())? else {
+                return Ok(None);
+            };
+            let Some(element) = elements.get(index) else {
+                return Ok(None);
+            };
+            found = Found {
+                node: element,
+                offset: start + elements[..index].iter().map(Json::size).sum::<usize>(),
+                label: None,
+                last_step,
+            };
+            rest = after;
+        } else {
+            return Err(BadPath);
+        }
+    }
+
+    Ok(Some(found))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Remove,
+    Replace,
+    Insert,
+    Set,
+}
+
+enum Edited {
+    Done,
+    NotFound,
+    /// The node the path ends at is to be removed from its parent.
+    Remove,
+}
+
+/// Applies an edit at the end of a path. `json_insert()` and `json_set()` create the missing
+/// members of objects and append to arrays.
+fn edit(node: &mut Json, path: &str, edit_kind: Edit, value: &Json) -> Result<Edited, BadPath> {
+    if path.is_empty() {
+        match edit_kind {
+            Edit::Remove => return Ok(Edited::Remove),
+            Edit::Insert => {}
+            Edit::Replace | Edit::Set => *node = value.clone(),
+        }
+        return Ok(Edited::Done);
+    }
+
+    let creates = matches!(edit_kind, Edit::Insert | Edit::Set);
+    if let Some(after_dot) = path.strip_prefix('.') {
+        let (key, rest) = parse_key(after_dot)?;
+        let Json::Object(members) = node else {
+            return Ok(Edited::NotFound);
+        };
+
+        if let Some(i) = members.iter().position(|(label, _)| key.matches(label)) {
+            return Ok(match edit(&mut members[i].1, rest, edit_kind, value)? {
+                Edited::Remove => {
+                    members.remove(i);
+                    Edited::Done
+                }
+                edited => edited,
+            });
+        }
+        if creates {
+            if let Some(created) = create(rest, edit_kind, value)? {
+                members.push((key.to_label(), created));
+                return Ok(Edited::Done);
+            }
+        }
+        Ok(Edited::NotFound)
+    } else if path.starts_with('[') {
+        let Json::Array(elements) = node else {
+            return Ok(Edited::NotFound);
+        };
+        let Some((index, rest)) = parse_index(path, elements.len())? else {
+            return Ok(Edited::NotFound);
+        };
+
+        if index < elements.len() {
+            return Ok(match edit(&mut elements[index], rest, edit_kind, value)? {
+                Edited::Remove => {
+                    elements.remove(index);
+                    Edited::Done
+                }
+                edited => edited,
+            });
+        }
+        if creates && index == elements.len() {
+            if let Some(created) = create(rest, edit_kind, value)? {
+                elements.push(created);
+                return Ok(Edited::Done);
+            }
+        }
+        Ok(Edited::NotFound)
+    } else {
+        Err(BadPath)
+    }
+}
+
+/// Builds the objects and arrays the rest of a path goes through, ending in `value`.
+fn create(path: &str, edit_kind: Edit, value: &Json) -> Result<Option<Json>, BadPath> {
+    if path.is_empty() {
+        return Ok(Some(value.clone()));
+    }
+
+    let mut node = if path.starts_with('.') {
+        Json::Object(Vec::new())
+    } else {
+        Json::Array(Vec::new())
+    };
+    Ok(match edit(&mut node, path, edit_kind, value)? {
+        Edited::Done => Some(node),
+        _ => None,
+    })
+}
+
+fn bad_path(path: &str) -> anyhow::Error {
+    anyhow::anyhow!("bad JSON path: '{}'", path.replace('\'', "''"))
+}
+
+/// Evaluates an argument of a JSON function, noting whether it is the result of another
+/// JSON function.
+pub fn eval_arg(expr: &Expr, scope: &Scope) -> anyhow::Result<Subtyped> {
+    match expr {
+        Expr::Nested(expr) => eval_arg(expr, scope),
+        Expr::JsonAccess {
+            left,
+            operator,
+            right,
+        } => access(left, operator, right, scope),
+        Expr::Function(function) if function.over.is_none() => {
+            let name = function.name.to_string().to_lowercase();
+            if let Some(value) = scope
+                .aggregates
+                .and_then(|aggregates| aggregates.get(&function.to_string()))
+            {
+                let json = matches!(name.as_str(), "json_group_array" | "json_group_object");
+                return Ok(Subtyped {
+                    value: value.clone(),
+                    json,
+                });
+            }
+
+            if is_function(&name) {
+                call(function, scope)
+            } else {
+                Ok(Subtyped::plain(eval(expr, scope)?))
+            }
+        }
+        expr => Ok(Subtyped::plain(eval(expr, scope)?)),
+    }
+}
+
+/// Calls one of the scalar JSON functions.
+pub fn call(function: &Function, scope: &Scope) -> anyhow::Result<Subtyped> {
+    let name = function.name.to_string().to_lowercase();
+    let args = arguments(function)?;
+    if !arity(&name).contains(&args.len()) {
+        bail!("wrong number of arguments to function {}()", function.name);
+    }
+    let args = args
+        .iter()
+        .map(|arg| eval_arg(arg, scope))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let values: Vec<SerialValue> = args.iter().map(|arg| arg.value.clone()).collect();
+    let null = Subtyped::plain(SerialValue::Null);
+
+    Ok(match name.as_str() {
+        "json" => match parse_arg(&values[0])? {
+            Some(json) => Subtyped::json(SerialValue::String(json.to_text())),
+            None => null,
+        },
+        "json_array" => {
+            let elements = args
+                .iter()
+                .map(|arg| from_sql(arg, true))
+                .collect::<anyhow::Result<_>>()?;
+            Subtyped::json(SerialValue::String(Json::Array(elements).to_text()))
+        }
+        "json_object" => {
+            if args.len() % 2 != 0 {
+                bail!("json_object() requires an even number of arguments");
+            }
+            let mut members = Vec::new();
+            for pair in args.chunks(2) {
+                let SerialValue::String(label) = &pair[0].value else {
+                    bail!("json_object() labels must be TEXT");
+                };
+                members.push((JsonText::raw(label), from_sql(&pair[1], true)?));
+            }
+            Subtyped::json(SerialValue::String(Json::Object(members).to_text()))
+        }
+        "json_extract" => extract(&values, Extract::Function)?,
+        "json_type" | "json_array_length" => {
+            let Some(json) = parse_arg(&values[0])? else {
+                return Ok(null);
+            };
+            let node = match values.get(1) {
+                None => &json,
+                Some(SerialValue::Null) => return Ok(null),
+                Some(path) => {
+                    let path = path.display();
+                    match lookup(&json, &path).map_err(|_| bad_path(&path))? {
+                        Some(found) => found.node,
+                        None => return Ok(null),
+                    }
+                }
+            };
+            Subtyped::plain(match (name.as_str(), node) {
+                ("json_type", node) => SerialValue::String(node.type_name().to_string()),
+                (_, Json::Array(elements)) => SerialValue::I64(elements.len() as i64),
+                _ => SerialValue::I64(0),
+            })
+        }
+        "json_valid" => {
+            let flags = match values.get(1) {
+                Some(flags) => flags.to_integer(),
+                None => 1,
+            };
+            if !(1..=15).contains(&flags) {
+                bail!("FLAGS parameter to json_valid() must be between 1 and 15");
+            }
+            Subtyped::plain(match &values[0] {
+                SerialValue::Null => SerialValue::Null,
+                _ if flags & 0x03 == 0 => false.into(),
+                value => match parse(&value.display()) {
+                    Some((_, json5)) => (flags & 0x02 != 0 || !json5).into(),
+                    None => false.into(),
+                },
+            })
+        }
+        "json_remove" => {
+            let Some(mut json) = values.first().map(parse_arg).transpose()?.flatten() else {
+                return Ok(null);
+            };
+            for path in &values[1..] {
+                let path = match path {
+                    SerialValue::Null => return Ok(null),
+                    path => path.display(),
+                };
+                if path == "$" {
+                    return Ok(null);
+                }
+                if !path.starts_with('$') {
+                    return Err(bad_path(&path));
+                }
+                edit(&mut json, &path[1..], Edit::Remove, &Json::Null)
+                    .map_err(|_| bad_path(&path))?;
+            }
+            Subtyped::json(SerialValue::String(json.to_text()))
+        }
+        _ => {
+            let edit_kind = match name.as_str() {
+                "json_insert" => Edit::Insert,
+                "json_replace" => Edit::Replace,
+                _ => Edit::Set,
+            };
+            let Some(mut json) = values.first().map(parse_arg).transpose()?.flatten() else {
+                return Ok(null);
+            };
+            if args.len() % 2 == 0 {
+                bail!("{name}() needs an odd number of arguments");
+            }
+            for pair in args[1..].chunks(2) {
+                let path = match &pair[0].value {
+                    SerialValue::Null => continue,
+                    path => path.display(),
+                };
+                if !path.starts_with('$') {
+                    return Err(bad_path(&path));
+                }
+                let value = from_sql(&pair[1], false)?;
+                edit(&mut json, &path[1..], edit_kind, &value).map_err(|_| bad_path(&path))?;
+            }
+            Subtyped::json(SerialValue::String(json.to_text()))
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Extract {
+    /// `json_extract()` returns SQL values, and arrays and objects as JSON.
+    Function,
+    /// `->` returns JSON.
+    Json,
+    /// `->>` returns SQL values.
+    Sql,
+}
+
+/// The `->` and `->>` operators.
+pub fn access(
+    left: &Expr,
+    operator: &JsonOperator,
+    right: &Expr,
+    scope: &Scope,
+) -> anyhow::Result<Subtyped> {
+    let mode = match operator {
+        JsonOperator::Arrow => Extract::Json,
+        JsonOperator::LongArrow => Extract::Sql,
+        operator => bail!("unsupported operator: {operator}"),
+    };
+
+    extract(&[eval(left, scope)?, eval(right, scope)?], mode)
+}
+
+/// The operators also take a label or an array index instead of a path.
+fn abbreviated_path(path: &SerialValue) -> String {
+    let txt = path.display();
+    if path.as_integer().is_some() {
+        if txt.starts_with('-') {
+            format!("$[#{txt}]")
+        } else {
+            format!("$[{txt}]")
+        }
+    } else if txt.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_') {
+        format!("$.{txt}")
+    } else if txt.starts_with('[') && txt.len() >= 3 && txt.ends_with(']') {
+        format!("${txt}")
+    } else {
+        format!("$.\"{txt}\"")
+    }
+}
+
+/// Looks up the paths that follow the document in `values`. With more than one path the
+/// results are collected into a JSON array.
+fn extract(values: &[SerialValue], mode: Extract) -> anyhow::Result<Subtyped> {
+    let null = Subtyped::plain(SerialValue::Null);
+    if values.len() < 2 {
+        return Ok(null);
+    }
+    let Some(json) = parse_arg(&values[0])? else {
+        return Ok(null);
+    };
+
+    let mut results = Vec::new();
+    for path in &values[1..] {
+        if path.is_null() {
+            return Ok(null);
+        }
+        let txt = path.display();
+        let full = if mode != Extract::Function && !txt.starts_with('$') {
+            abbreviated_path(path)
+        } else {
+            txt.clone()
+        };
+        let found = lookup(&json, &full).map_err(|_| bad_path(&txt))?;
+        results.push(found.map(|found| found.node));
+    }
+
+    if let [found] = results.as_slice() {
+        let Some(node) = found else {
+            return Ok(null);
+        };
+        return Ok(match mode {
+            Extract::Json => Subtyped::json(SerialValue::String(node.to_text())),
+            _ => Subtyped {
+                value: node.to_sql(),
+                json: mode == Extract::Function && node.is_container(),
+            },
+        });
+    }
+
+    let elements: Vec<String> = results
+        .iter()
+        .map(|found| match found {
+            Some(node) => node.to_text(),
+            None => "null".to_string(),
+        })
+        .collect();
+    Ok(Subtyped::json(SerialValue::String(format!(
+        "[{}]",
+        elements.join(",")
+    ))))
+}
+
+/// The columns of `json_each()` and `json_tree()` followed by their hidden columns and rowid.
+pub fn each_columns(qualifier: &str) -> Vec<ColumnName> {
+    let mut columns: Vec<ColumnName> = EACH_COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(i, name)| ColumnName {
+            hidden: i >= 8,
+            ..ColumnName::new(Some(qualifier), name)
+        })
+        .collect();
+    columns.push(ColumnName::rowid(Some(qualifier)));
+
+    columns
+}
+
+/// The step a member adds to the path of its container. Labels that are not alphanumeric are
+/// quoted.
+fn path_step(label: &JsonText) -> String {
+    let raw = &label.raw;
+    let plain = raw.starts_with(|c: char| c.is_ascii_alphabetic())
+        && raw.bytes().all(|c| c.is_ascii_alphanumeric());
+    if plain {
+        format!(".{raw}")
+    } else {
+        format!(".\"{raw}\"")
+    }
+}
+
+struct EachRows<'a> {
+    rows: Vec<Vec<SerialValue>>,
+    input: &'a SerialValue,
+    root: &'a str,
+    recursive: bool,
+}
+
+impl EachRows<'_> {
+    fn push(
+        &mut self,
+        key: SerialValue,
+        node: &Json,
+        id: usize,
+        parent: Option<usize>,
+        fullkey: String,
+        path: &str,
+    ) {
+        let value = node.to_sql();
+        let atom = if node.is_container() {
+            SerialValue::Null
+        } else {
+            value.clone()
+        };
+        let rowid = self.rows.len() as i64;
+        self.rows.push(vec![
+            key,
+            value,
+            SerialValue::String(node.type_name().to_string()),
+            atom,
+            SerialValue::I64(id as i64),
+            parent.map_or(SerialValue::Null, |parent| SerialValue::I64(parent as i64)),
+            SerialValue::String(fullkey),
+            SerialValue::String(path.to_string()),
+            self.input.clone(),
+            SerialValue::String(self.root.to_string()),
+            SerialValue::I64(rowid),
+        ]);
+    }
+
+    /// Adds a row for every element or member of a container. `json_tree()` descends into
+    /// the ones that are containers themselves.
+    fn children(&mut self, node: &Json, offset: usize, id: usize, fullkey: &str) {
+        let parent = self.recursive.then_some(id);
+        let mut child = offset + header_size(node.payload());
+        match node {
+            Json::Array(elements) => {
+                for (i, element) in elements.iter().enumerate() {
+                    let key = format!("{fullkey}[{i}]");
+                    self.push(
+                        SerialValue::I64(i as i64),
+                        element,
+                        child,
+                        parent,
+                        key.clone(),
+                        fullkey,
+                    );
+                    if self.recursive && element.is_container() {
+                        self.children(element, child, child, &key);
+                    }
+                    child += element.size();
+                }
+            }
+            Json::Object(members) => {
+                for (label, value) in members {
+                    let key = format!("{fullkey}{}", path_step(label));
+                    let name = SerialValue::String(label.decode().into_owned());
+                    self.push(name, value, child, parent, key.clone(), fullkey);
+                    if self.recursive && value.is_container() {
+                        self.children(value, child + label.size(), child, &key);
+                    }
+                    child += label.size() + value.size();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The rows of `json_each()` or `json_tree()`. `json_each()` lists the elements or members
+/// of the container at the root path; `json_tree()` walks everything below it, starting with
+/// the root itself.
+pub fn each(name: &str, values: &[SerialValue]) -> anyhow::Result<Vec<Vec<SerialValue>>> {
+    let Some(input) = values.first() else {
+        return Ok(Vec::new());
+    };
+    let Some(json) = parse_arg(input)? else {
+        return Ok(Vec::new());
+    };
+    let root = match values.get(1) {
+        None => "$".to_string(),
+        Some(SerialValue::Null) => return Ok(Vec::new()),
+        Some(path) => path.display(),
+    };
+    let Some(found) = lookup(&json, &root).map_err(|_| bad_path(&root))? else {
+        return Ok(Vec::new());
+    };
+
+    let mut rows = EachRows {
+        rows: Vec::new(),
+        input,
+        root: &root,
+        recursive: name == "json_tree",
+    };
+    let id = found.label.unwrap_or(found.offset);
+    if rows.recursive {
+        // The key of the root is the last step of the root path.
+        let (key, path) = match root.get(found.last_step..) {
+            _ if found.last_step == 0 => (SerialValue::Null, root.as_str()),
+            Some(step) => {
+                let key = if let Some(index) = step.strip_prefix('[') {
+                    let digits: String = index.chars().take_while(char::is_ascii_digit).collect();
+                    SerialValue::I64(digits.parse().unwrap_or_default())
+                } else if let Some(quoted) = step.strip_prefix(".\"") {
+                    SerialValue::String(quoted.trim_end_matches('"').to_string())
+                } else {
+                    SerialValue::String(step[1..].to_string())
+                };
+                (key, &root[..found.last_step])
+            }
+            None => (SerialValue::Null, root.as_str()),
+        };
+        rows.push(key, found.node, id, None, root.clone(), path);
+        rows.children(found.node, found.offset, id, &root);
+    } else if found.node.is_container() {
+        rows.children(found.node, found.offset, id, &root);
+    } else {
+        rows.push(SerialValue::Null, found.node, id, None, root.clone(), &root);
+    }
+
+    Ok(rows.rows)
+}
+
+/// Gives `->` and `->>` the precedence SQLite does. The parser takes everything after the
+/// operator as its right operand, so `x -> 'a' = 1` would compare `'a' = 1`. The operator
+/// binds as tightly as `||` instead, which puts it below only COLLATE and unary operators.
+pub fn reassociate(expr: Expr) -> Expr {
+    let Expr::JsonAccess {
+        left,
+        operator,
+        right,
+    } = expr
+    else {
+        return expr;
+    };
+    let access = |right: Box<Expr>| {
+        Box::new(reassociate(Expr::JsonAccess {
+            left: left.clone(),
+            operator,
+            right,
+        }))
+    };
+
+    match *right {
+        Expr::BinaryOp {
+            left: inner,
+            op,
+            right,
+        } => Expr::BinaryOp {
+            left: access(inner),
+            op,
+            right,
+        },
+        Expr::JsonAccess {
+            left: inner,
+            operator,
+            right,
+        } => Expr::JsonAccess {
+            left: access(inner),
+            operator,
+            right,
+        },
+        Expr::IsNull(inner) => Expr::IsNull(access(inner)),
+        Expr::IsNotNull(inner) => Expr::IsNotNull(access(inner)),
+        Expr::InList {
+            expr: inner,
+            list,
+            negated,
+        } => Expr::InList {
+            expr: access(inner),
+            list,
+            negated,
+        },
+        Expr::InSubquery {
+            expr: inner,
+            subquery,
+            negated,
+        } => Expr::InSubquery {
+            expr: access(inner),
+            subquery,
+            negated,
+        },
+        Expr::Between {
+            expr: inner,
+            negated,
+            low,
+            high,
+        } => Expr::Between {
+            expr: access(inner),
+            negated,
+            low,
+            high,
+        },
+        Expr::Like {
+            negated,
+            expr: inner,
+            pattern,
+            escape_char,
+        } => Expr::Like {
+            negated,
+            expr: access(inner),
+            pattern,
+            escape_char,
+        },
+        right => Expr::JsonAccess {
+            left,
+            operator,
+            right: Box::new(right),
+        },
+    }
+}
@@ -1,24 +1,55 @@
+pub mod aggregate;
+pub mod auto_index;
 pub mod cell;
+pub mod collation;
 pub mod column;
+pub mod compound;
+pub mod connection;
+pub mod cte;
 pub mod database;
+pub mod datetime;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod executor;
+pub mod explain;
+pub mod expr;
+pub mod function;
+pub mod hash_join;
+pub mod join;
+pub mod json;
+pub mod order;
 pub mod page;
+pub mod plan;
+pub mod printf;
 pub mod record;
-pub mod sql;
+pub mod row;
+pub mod schema;
+pub mod statement;
+pub mod types;
+pub mod window;
 
+pub use connection::Connection;
+pub use row::{Column, Row, RowIndex, Rows};
+pub use statement::{Params, Statement};
+pub use types::{FromSql, ToSql};
+
+/// Decodes a varint of 1 to 9 bytes. The first 8 bytes give 7 bits each, and their high bit
+/// tells whether another byte follows. A 9th byte gives all of its 8 bits, which is how
+/// negative numbers and the largest ones are stored.
 pub fn decode_varint(bytes: &[u8]) -> anyhow::Result<(i64, usize)> {
     let mut result = 0;
     let mut bytes_read = 0;
 
-    for (i, &byte) in bytes.iter().take(8).enumerate() {
-        bytes_read += 1;
+    for (i, &byte) in bytes.iter().take(9).enumerate() {
+        bytes_read = i + 1;
 
-        let has_more = i != 8 && (byte & 0b10000000 != 0);
+        if i == 8 {
+            result = (result << 8) | (byte as i64);
+            break;
+        }
 
-        if has_more {
-            result = (result << 7) | ((byte & 0b01111111) as i64);
-        } else {
-            result = (result << 7) | (byte as i64);
-            bytes_read = i + 1;
+        result = (result << 7) | ((byte & 0b01111111) as i64);
+        if byte & 0b10000000 == 0 {
             break;
         }
     }
@@ -38,4 +69,19 @@ mod tests {
 
         assert_eq!(val, 129);
     }
+
+    #[test]
+    fn test_decode_nine_byte_varint() {
+        let varint_bytes = vec![0xff; 9];
+
+        let (val, len) = decode_varint(&varint_bytes).unwrap();
+
+        assert_eq!((val, len), (-1, 9));
+
+        let varint_bytes = vec![0xc0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+
+        let (val, len) = decode_varint(&varint_bytes).unwrap();
+
+        assert_eq!((val, len), (i64::MIN, 9));
+    }
 }
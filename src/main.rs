@@ -1,8 +1,7 @@
 use std::path::PathBuf;
-use std::{collections::HashSet, str::FromStr};
 
-use clap::{Parser, Subcommand};
-use rust_sqlite::{column::SerialValue, database::Database, sql::Sql};
+use clap::{Parser, Subcommand, ValueEnum};
+use rust_sqlite::{column::SerialValue, Connection};
 
 #[derive(Parser)]
 struct Cli {
@@ -22,6 +21,20 @@ enum Commands {
 
     #[clap(name = ".query")]
     Query { db: PathBuf, statement: String },
+
+    /// Show the query plan of the statement before its result
+    #[clap(name = ".eqp")]
+    Eqp {
+        mode: Toggle,
+        db: PathBuf,
+        statement: String,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Toggle {
+    On,
+    Off,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -29,166 +42,48 @@ fn main() -> anyhow::Result<()> {
 
     match cli.command {
         Commands::DbInfo { db } => {
-            let db = Database::read_file(db)?;
-            println!("database page size: {}", db.page_size());
+            let conn = Connection::open(db)?;
+            println!("database page size: {}", conn.page_size());
 
-            if let Some(first_page) = db.pages.get(0) {
-                println!("number of tables: {}", first_page.btree_header.ncells());
+            if let Some(count) = conn.schema_cell_count() {
+                println!("number of tables: {count}");
             }
         }
         Commands::Tables { db } => {
-            let db = Database::read_file(db)?;
-            match db.pages.get(0) {
-                Some(first_page) => {
-                    let mut tables = String::new();
-                    for i in 0..first_page.btree_header.ncells() {
-                        if let Ok((_, Some(record))) = first_page.read_cell(i) {
-                            if let SerialValue::String(ref str) = record.columns[0].data() {
-                                if str != "table" {
-                                    continue;
-                                }
-                            }
-
-                            let tbl_name = match record.columns[2].data() {
-                                SerialValue::String(ref str) => {
-                                    if str == "sqlite_sequence" {
-                                        continue;
-                                    }
-                                    str
-                                }
-                                _ => "",
-                            };
-
-                            tables.push_str(&format!("{} ", tbl_name));
-                        };
-                    }
-                    println!("{tables}");
-                }
-                None => eprintln!("can not read first page"),
-            }
+            let conn = Connection::open(db)?;
+            let tables: String = conn.table_names().map(|name| format!("{name} ")).collect();
+            println!("{tables}");
         }
-        Commands::Query { db, statement } => {
-            let db = Database::read_file(db)?;
-
-            match statement {
-                stmt if stmt.to_lowercase().starts_with("select count(*)") => {
-                    let select_statement = Sql::from_str(&stmt)?;
-
-                    if let Some(first_page) = db.pages.get(0) {
-                        for i in 0..first_page.btree_header.ncells() {
-                            if let Ok((_, Some(record))) = first_page.read_cell(i) {
-                                if let SerialValue::String(ref str) = record.columns[0].data() {
-                                    if str != "table" {
-                                        continue;
-                                    }
-                                }
-
-                                if let SerialValue::String(str) = record.columns[2].data() {
-                                    match str.as_str() {
-                                        "sqlite_sequence" => {
-                                            continue;
-                                        }
-                                        t_name => {
-                                            if select_statement.tbl_name == t_name {
-                                                if let SerialValue::I8(num) =
-                                                    record.columns[3].data()
-                                                {
-                                                    if let Some(page) =
-                                                        db.pages.get(*num as usize - 1)
-                                                    {
-                                                        let cell_len = page.cell_offsets.len();
-                                                        println!("{:?}", cell_len);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                stmt if stmt.to_lowercase().starts_with("select") => {
-                    let select_statement = Sql::from_str(&stmt)?;
-
-                    if let Some(first_page) = db.pages.get(0) {
-                        for i in (0..first_page.btree_header.ncells()).rev() {
-                            if let (_, Some(record)) = first_page.read_cell(i)? {
-                                let mut rowids = HashSet::new();
-
-                                if let SerialValue::String(str) = record.columns[0].data() {
-                                    if str.as_str() == "index" {
-                                        let index_statement =
-                                            Sql::from_str(&record.columns[4].data().display())?;
-                                        if let SerialValue::I8(num) = record.columns[3].data() {
-                                            db.read_index(
-                                                *num as usize,
-                                                &index_statement,
-                                                &select_statement,
-                                                &mut rowids,
-                                            );
-                                        }
-                                        continue;
-                                    }
-                                }
-
-                                let mut rowids: Vec<i64> = rowids.into_iter().collect();
-                                rowids.sort_unstable();
-
-                                if let SerialValue::String(str) = record.columns[2].data() {
-                                    match str.as_str() {
-                                        "sqlite_sequence" => {
-                                            continue;
-                                        }
-                                        t_name => {
-                                            if select_statement.tbl_name == t_name {
-                                                if let SerialValue::I8(num) =
-                                                    record.columns[3].data()
-                                                {
-                                                    let create_statement = Sql::from_str(
-                                                        &record.columns[4].data().display(),
-                                                    )?;
-
-                                                    let fields = select_statement
-                                                        .get_fields(&create_statement);
+        Commands::Query { db, statement } => query(db, &statement, false)?,
+        Commands::Eqp {
+            mode,
+            db,
+            statement,
+        } => query(db, &statement, mode == Toggle::On)?,
+    }
+    Ok(())
+}
 
-                                                    let mut row_set = HashSet::new();
-                                                    let mut rowid_set = HashSet::new();
+/// Runs a statement and prints its rows. EXPLAIN QUERY PLAN, or `eqp`, prints the plan as a
+/// tree the way the sqlite3 shell does.
+fn query(db: PathBuf, statement: &str, eqp: bool) -> anyhow::Result<()> {
+    let conn = Connection::open(db)?;
+    let mut statement = conn.prepare(statement)?;
 
-                                                    if rowids.is_empty() {
-                                                        db.read_table(
-                                                            *num as usize,
-                                                            &select_statement,
-                                                            fields,
-                                                            &mut row_set,
-                                                            &mut rowid_set,
-                                                        );
-                                                    } else {
-                                                        db.read_ids_from_table(
-                                                            *num as usize,
-                                                            &select_statement,
-                                                            fields,
-                                                            &mut row_set,
-                                                            &mut rowid_set,
-                                                            &rowids,
-                                                        );
-                                                    }
+    if eqp || statement.is_explain() {
+        print!("{}", statement.query_plan()?);
+    }
+    if statement.is_explain() {
+        return Ok(());
+    }
 
-                                                    row_set
-                                                        .iter()
-                                                        .for_each(|str| println!("{str}"));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                _ => todo!(),
-            }
-        }
+    for row in statement.query(())? {
+        let row = row?;
+        let values = (0..row.column_count())
+            .map(|i| row.get_ref(i).map(SerialValue::display))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        println!("{}", values.join("|"));
     }
+
     Ok(())
 }
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+use anyhow::bail;
+use sqlparser::ast::{Expr, OrderByExpr, Query, Value};
+
+use crate::{
+    collation::Collation,
+    column::SerialValue,
+    expr::{eval, Context, Scope},
+};
+
+/// One ORDER BY term, resolved to a column of the rows being sorted.
+#[derive(Debug, Clone, Copy)]
+pub struct SortTerm {
+    pub column: usize,
+    pub desc: bool,
+    /// NULLs sort first in ascending order and last in descending order unless NULLS FIRST or
+    /// NULLS LAST says otherwise.
+    pub nulls_first: bool,
+    pub collation: Collation,
+}
+
+impl SortTerm {
+    pub fn new(column: usize, order: &OrderByExpr, collation: Collation) -> Self {
+        let desc = order.asc == Some(false);
+
+        Self {
+            column,
+            desc,
+            nulls_first: order.nulls_first.unwrap_or(!desc),
+            collation,
+        }
+    }
+
+    /// Sorts by a column in ascending order, the way the result of a compound SELECT is
+    /// ordered.
+    pub fn ascending(column: usize, collation: Collation) -> Self {
+        Self {
+            column,
+            desc: false,
+            nulls_first: true,
+            collation,
+        }
+    }
+
+    pub fn compare(&self, a: &[SerialValue], b: &[SerialValue]) -> Ordering {
+        self.compare_values(&a[self.column], &b[self.column])
+    }
+
+    pub fn compare_values(&self, a: &SerialValue, b: &SerialValue) -> Ordering {
+        match (a.is_null(), b.is_null()) {
+            (false, false) if self.desc => self.collation.compare(a, b).reverse(),
+            (false, false) => self.collation.compare(a, b),
+            (a_null, b_null) if a_null == b_null => Ordering::Equal,
+            (a_null, _) if a_null == self.nulls_first => Ordering::Less,
+            _ => Ordering::Greater,
+        }
+    }
+}
+
+/// Sorts the rows by the terms, keeping rows that compare equal in their original order.
+pub fn sort(rows: &mut [Vec<SerialValue>], terms: &[SortTerm]) {
+    if terms.is_empty() {
+        return;
+    }
+
+    rows.sort_by(|a, b| {
+        terms
+            .iter()
+            .map(|term| term.compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Splits the COLLATE operator off an ORDER BY term.
+pub fn strip_collate(expr: &Expr) -> anyhow::Result<(&Expr, Option<Collation>)> {
+    match expr {
+        Expr::Collate { expr, collation } => {
+            Ok((expr, Some(Collation::from_name(&collation.to_string())?)))
+        }
+        expr => Ok((expr, None)),
+    }
+}
+
+/// Returns the result column an ORDER BY term refers to by its number. `i` is the position of
+/// the term, for error messages.
+pub fn ordinal(expr: &Expr, i: usize, ncolumns: usize) -> anyhow::Result<Option<usize>> {
+    let Expr::Value(Value::Number(num, _)) = expr else {
+        return Ok(None);
+    };
+    let Ok(n) = num.parse::<i64>() else {
+        return Ok(None);
+    };
+
+    if n < 1 || n as usize > ncolumns {
+        bail!(
+            "{} ORDER BY term out of range - should be between 1 and {ncolumns}",
+            nth(i + 1)
+        );
+    }
+
+    Ok(Some(n as usize - 1))
+}
+
+/// Formats `1` as "1st", `2` as "2nd" and so on.
+pub fn nth(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+
+    format!("{n}{suffix}")
+}
+
+/// Applies the LIMIT and OFFSET of a query to its rows. A negative limit means no limit.
+pub fn limit(
+    rows: Vec<Vec<SerialValue>>,
+    query: &Query,
+    context: Context,
+) -> anyhow::Result<Vec<Vec<SerialValue>>> {
+    let (limit, offset) = limit_offset(query, context)?;
+
+    let rows = rows.into_iter().skip(offset);
+    Ok(match limit {
+        Some(limit) => rows.take(limit).collect(),
+        None => rows.collect(),
+    })
+}
+
+/// Evaluates the LIMIT and OFFSET of a query. The limit is None when there is none or when it
+/// is negative.
+pub fn limit_offset(query: &Query, context: Context) -> anyhow::Result<(Option<usize>, usize)> {
+    let limit = match &query.limit {
+        Some(expr) => constant_integer(expr, context)?,
+        None => -1,
+    };
+    let offset = match &query.offset {
+        Some(offset) => constant_integer(&offset.value, context)?.max(0) as usize,
+        None => 0,
+    };
+
+    Ok((usize::try_from(limit).ok(), offset))
+}
+
+fn constant_integer(expr: &Expr, context: Context) -> anyhow::Result<i64> {
+    let value = eval(expr, &Scope::within(context, &[], &[]))?;
+    match value.numeric_affinity().as_integer() {
+        Some(num) => Ok(num),
+        None => bail!("datatype mismatch"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::nth;
+
+    #[test]
+    fn test_nth() {
+        let formatted: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 101, 111]
+            .into_iter()
+            .map(nth)
+            .collect();
+
+        assert_eq!(
+            formatted,
+            [
+                "1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "101st",
+                "111th"
+            ]
+        );
+    }
+}
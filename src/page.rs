@@ -1,6 +1,6 @@
 use anyhow::{bail, Context};
 
-use crate::{database::DbHeader, decode_varint, record::Record};
+use crate::{cell::Cell, database::DbHeader, decode_varint, record::Record};
 
 #[derive(Debug, Clone)]
 pub struct Page {
@@ -48,6 +48,14 @@ impl Page {
         &self.btree_header.page_type
     }
 
+    /// Parses every cell on the page in cell pointer order.
+    pub fn cells(&self) -> anyhow::Result<Vec<Cell>> {
+        self.cell_offsets
+            .iter()
+            .map(|offset| Cell::from_bytes(self.page_type(), *offset as usize, &self.buffer))
+            .collect()
+    }
+
     pub fn read_cell(&self, i: u16) -> anyhow::Result<(Option<i64>, Option<Record>)> {
         if i >= self.btree_header.ncells {
             bail!("Cell index out of range");
@@ -59,12 +67,12 @@ impl Page {
             PageType::LeafTable => {
                 let mut idx = offset;
 
-                let (npayload, bytes_read) = decode_varint(&self.buffer[idx..idx + 9])
-                    .context("decode varint for payload size")?;
+                let (npayload, bytes_read) =
+                    decode_varint(&self.buffer[idx..]).context("decode varint for payload size")?;
                 idx += bytes_read;
 
-                let (rowid, bytes_read) = decode_varint(&self.buffer[idx..idx + 9])
-                    .context("decode varint for payload size")?;
+                let (rowid, bytes_read) =
+                    decode_varint(&self.buffer[idx..]).context("decode varint for payload size")?;
                 idx += bytes_read;
 
                 let end = idx + npayload as usize;
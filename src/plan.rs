@@ -0,0 +1,1517 @@
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    ops::{Bound, ControlFlow, RangeInclusive},
+};
+
+use sqlparser::ast::{visit_expressions, BinaryOperator, Expr, Query, Visit};
+
+use crate::{
+    collation::Collation,
+    column::{Affinity, SerialValue},
+    database::compare_index_key,
+    expr::{
+        affinity, comparison_affinities, comparison_collation, eval, has_parameter,
+        visit_outside_subqueries, ColumnName, Context, Scope,
+    },
+    join::{column_of, conjuncts, depends_only_on, index_collation, ProbeKey, TableRef},
+    record::Record,
+    schema::{IndexSchema, IndexStats, Schema, TableSchema},
+};
+
+/// How the rows of a table are read.
+#[derive(Debug, Clone)]
+pub enum Access<'s> {
+    /// Every row of the table b-tree is read.
+    Scan,
+    /// The rows with these rowids, sorted and without duplicates, are looked up in the table
+    /// b-tree.
+    Rowids(Vec<i64>),
+    /// The rows whose rowid lies in the range are read from the table b-tree.
+    RowidRange(RangeInclusive<i64>),
+    /// The rowids are found in an index, and the rows are then looked up in the table b-tree.
+    Index(IndexSeek<'s>),
+    /// Every entry of an index that holds all the columns the query uses is read instead of
+    /// the table.
+    IndexScan(&'s IndexSchema),
+    /// Every row of the table is read in the order of an index.
+    IndexOrder(&'s IndexSchema),
+    /// Only the first or the last row of the table b-tree is read, for `min()` or `max()` of
+    /// the rowid.
+    RowidEdge { last: bool },
+    /// Only the first entry of an index whose leading column is not NULL, or the last entry,
+    /// is read, for `min()` or `max()` of that column. A covering index answers without the
+    /// table.
+    IndexEdge {
+        index: &'s IndexSchema,
+        last: bool,
+        covering: bool,
+    },
+    /// Each branch of the OR term at position `term` of the WHERE clause finds its rowids by
+    /// rowid or through an index. The rowids are merged, and the rows are then looked up in the
+    /// table b-tree.
+    MultiIndex {
+        term: usize,
+        branches: Vec<Access<'s>>,
+    },
+}
+
+/// The way of reading a table that the planner chose, without the keys it searches for. It
+/// depends on the shape of the terms but not on the values bound to the parameters, so a
+/// prepared statement keeps it, and [`bind`] takes the keys from the values each time the
+/// statement runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Choice {
+    Scan,
+    /// A list or a range of rowids.
+    Rowids,
+    Index(String),
+    IndexScan(String),
+    IndexOrder(String),
+    RowidEdge {
+        last: bool,
+    },
+    IndexEdge {
+        index: String,
+        last: bool,
+        covering: bool,
+    },
+    MultiIndex {
+        term: usize,
+        branches: Vec<Choice>,
+    },
+}
+
+impl Access<'_> {
+    pub fn choice(&self) -> Choice {
+        match self {
+            Access::Scan => Choice::Scan,
+            Access::Rowids(_) | Access::RowidRange(_) => Choice::Rowids,
+            Access::Index(seek) => Choice::Index(seek.index.name.clone()),
+            Access::IndexScan(index) => Choice::IndexScan(index.name.clone()),
+            Access::IndexOrder(index) => Choice::IndexOrder(index.name.clone()),
+            Access::RowidEdge { last } => Choice::RowidEdge { last: *last },
+            Access::IndexEdge {
+                index,
+                last,
+                covering,
+            } => Choice::IndexEdge {
+                index: index.name.clone(),
+                last: *last,
+                covering: *covering,
+            },
+            Access::MultiIndex { term, branches } => Choice::MultiIndex {
+                term: *term,
+                branches: branches.iter().map(Access::choice).collect(),
+            },
+        }
+    }
+}
+
+/// The entries of an index whose leading columns equal `key` and whose next column lies
+/// between `low` and `high`.
+#[derive(Debug, Clone)]
+pub struct IndexSeek<'s> {
+    pub index: &'s IndexSchema,
+    pub key: Vec<SerialValue>,
+    pub low: Bound<SerialValue>,
+    pub high: Bound<SerialValue>,
+    /// The sort order of each column of the index.
+    pub desc: Vec<bool>,
+    /// The collating sequence each column of the index is ordered by, up to the column the
+    /// range is on.
+    pub collations: Vec<Collation>,
+    /// Set when the index holds every column the query uses, so the rows are built from the
+    /// entries without reading the table.
+    pub covering: bool,
+    /// Set when the keys and bounds are the values searched for. While planning, a key that a
+    /// parameter gives is only a stand-in, which the samples of the index cannot count.
+    pub known: bool,
+}
+
+impl IndexSeek<'_> {
+    /// A comparison with NULL is never true, so a seek with a NULL key or bound finds nothing.
+    pub fn finds_nothing(&self) -> bool {
+        let null = |bound: &Bound<SerialValue>| matches!(bound, Bound::Included(value) | Bound::Excluded(value) if value.is_null());
+        self.key.iter().any(SerialValue::is_null) || null(&self.low) || null(&self.high)
+    }
+
+    fn has_range(&self) -> bool {
+        !matches!(
+            (&self.low, &self.high),
+            (Bound::Unbounded, Bound::Unbounded)
+        )
+    }
+
+    /// Tells whether an entry sorts before the entries the seek finds, among them or after them,
+    /// in index order. NULL is never in a range.
+    pub fn position(&self, entry: &Record) -> Ordering {
+        let ordering = compare_index_key(entry, &self.key, &self.desc, &self.collations);
+        if ordering.is_ne() || !self.has_range() {
+            return ordering;
+        }
+
+        let Some(value) = entry
+            .columns
+            .get(self.key.len())
+            .map(|column| column.data())
+        else {
+            return Ordering::Less;
+        };
+        let collation = self
+            .collations
+            .get(self.key.len())
+            .copied()
+            .unwrap_or_default();
+        let below = value.is_null()
+            || match &self.low {
+                Bound::Included(low) => collation.compare(value, low).is_lt(),
+                Bound::Excluded(low) => collation.compare(value, low).is_le(),
+                Bound::Unbounded => false,
+            };
+        let above = match &self.high {
+            Bound::Included(high) => collation.compare(value, high).is_gt(),
+            Bound::Excluded(high) => collation.compare(value, high).is_ge(),
+            Bound::Unbounded => false,
+        };
+
+        let ordering = if below {
+            Ordering::Less
+        } else if above {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        };
+        if self.desc.get(self.key.len()).copied().unwrap_or_default() {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// A term of the WHERE clause that compares a column of the table with a constant, written
+/// with the column on the left.
+struct Constraint {
+    column: usize,
+    op: BinaryOperator,
+    /// None while planning when the key is not known: a parameter, or NULL.
+    key: Option<SerialValue>,
+    collation: Collation,
+}
+
+/// Which keys the constraints take. A plan is chosen from the keys written in the statement
+/// only, so that it holds whatever values are bound to the parameters; it is then run with the
+/// values.
+#[derive(Clone, Copy, PartialEq)]
+enum Keys {
+    Written,
+    Bound,
+}
+
+/// Chooses how to read the rows of `table` from the terms of the WHERE clause. A lookup by
+/// rowid is always taken. When ANALYZE recorded statistics for the table, the other ways are
+/// compared by their estimated [`cost`]. Otherwise an index whose leading columns are compared
+/// for equality is preferred, with a range on the column after them, then a range of rowids,
+/// then a range of an index's first column, then an OR term whose branches can all use the
+/// rowid or an index. Failing those, the table is scanned, or the smallest index that holds
+/// every column in `used`, the columns the query refers to. Every row that passes the terms is
+/// read, but the terms still have to be applied to the rows.
+///
+/// When the rows are grouped by the table columns in `group`, reading them in that order saves
+/// sorting them. An index that leads with the columns is compared with the other ways at the
+/// cost of the sort they need, or without statistics is taken unless an index seek by equality
+/// is at hand.
+///
+/// Keys that parameters give, and NULL, only count by their shape: a key that a parameter
+/// gives for an index is a stand-in, so the access is only good for its [`Access::choice`],
+/// which [`bind`] runs.
+pub fn access<'s>(
+    schema: &'s Schema,
+    table: &TableRef,
+    terms: &[&Expr],
+    used: Option<&[usize]>,
+    group: Option<&[usize]>,
+    context: Context,
+) -> anyhow::Result<Access<'s>> {
+    Ok(choose(
+        table,
+        candidates(schema, table, terms, used, group, context)?,
+        group,
+    ))
+}
+
+/// Reads the table the way [`access`] chose, with the keys the terms give now. A key may come
+/// from the parameters of a prepared statement, bound in `context`. When the terms no longer
+/// give a key for the chosen index, the table is scanned, which the terms still filter.
+pub fn bind<'s>(
+    schema: &'s Schema,
+    table: &TableRef,
+    terms: &[&Expr],
+    used: Option<&[usize]>,
+    choice: &Choice,
+    context: Context,
+) -> anyhow::Result<Access<'s>> {
+    let columns = table.columns();
+    let constraints = constraints(table, &columns, terms, context, Keys::Bound)?;
+    let index = |name: &str| {
+        schema
+            .indexes_of(&table.table.name)
+            .find(|index| index.name == name)
+    };
+
+    Ok(match choice {
+        Choice::Scan => Access::Scan,
+        Choice::Rowids => {
+            let is_rowid = |i: usize| columns[i].hidden || table.table.rowid_alias == Some(i);
+            let rowid_constraints: Vec<&Constraint> = constraints
+                .iter()
+                .filter(|constraint| is_rowid(constraint.column))
+                .collect();
+            rowid_access(
+                &columns,
+                &is_rowid,
+                &rowid_constraints,
+                terms,
+                context,
+                Keys::Bound,
+            )?
+        }
+        Choice::Index(name) => {
+            match index(name).and_then(|index| index_seek(table, index, &constraints)) {
+                Some(mut seek) => {
+                    seek.covering = used.is_some_and(|used| covers(table.table, seek.index, used));
+                    Access::Index(seek)
+                }
+                None => Access::Scan,
+            }
+        }
+        Choice::IndexScan(name) => index(name).map_or(Access::Scan, Access::IndexScan),
+        Choice::IndexOrder(name) => index(name).map_or(Access::Scan, Access::IndexOrder),
+        Choice::RowidEdge { last } => Access::RowidEdge { last: *last },
+        Choice::IndexEdge {
+            index: name,
+            last,
+            covering,
+        } => match index(name) {
+            Some(index) => Access::IndexEdge {
+                index,
+                last: *last,
+                covering: *covering,
+            },
+            None => Access::Scan,
+        },
+        Choice::MultiIndex { term, branches } => {
+            let Some(or_term) = terms.get(*term) else {
+                return Ok(Access::Scan);
+            };
+            let mut accesses = Vec::new();
+            for (branch, choice) in disjuncts(or_term).into_iter().zip(branches) {
+                let access = bind(
+                    schema,
+                    table,
+                    &branch_terms(terms, *term, branch),
+                    None,
+                    choice,
+                    context,
+                )?;
+                if !matches!(
+                    access,
+                    Access::Rowids(_) | Access::RowidRange(_) | Access::Index(_)
+                ) {
+                    return Ok(Access::Scan);
+                }
+                accesses.push(access);
+            }
+            Access::MultiIndex {
+                term: *term,
+                branches: accesses,
+            }
+        }
+    })
+}
+
+/// The terms a branch of the OR term at position `term` is searched with: its own, and the
+/// terms ANDed with the OR.
+fn branch_terms<'e>(terms: &[&'e Expr], term: usize, branch: &'e Expr) -> Vec<&'e Expr> {
+    let mut branch_terms = conjuncts(branch);
+    branch_terms.extend(
+        terms
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != term)
+            .map(|(_, other)| *other),
+    );
+    branch_terms
+}
+
+fn choose<'s>(
+    table: &TableRef,
+    candidates: Vec<Access<'s>>,
+    group: Option<&[usize]>,
+) -> Access<'s> {
+    let ordered =
+        |access: &Access| group.is_some_and(|group| group_order(table.table, access, group));
+    if table.table.row_estimate.is_some() {
+        let cheapest = candidates
+            .into_iter()
+            .map(|access| {
+                let mut cost = cost(table.table, &access).unwrap_or(f64::INFINITY);
+                if group.is_some() && !ordered(&access) {
+                    cost += estimate(table.table, &access).map_or(0.0, sort_cost);
+                }
+                (cost, access)
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        return cheapest.map_or(Access::Scan, |(_, access)| access);
+    }
+
+    let mut best: Option<(usize, IndexSeek)> = None;
+    let mut rowid_range = None;
+    let mut multi_index = None;
+    let mut index_scan = None;
+    let mut group_scan = None;
+    for access in candidates {
+        match access {
+            Access::Rowids(_) => return access,
+            Access::RowidRange(_) => rowid_range = Some(access),
+            Access::MultiIndex { .. } if multi_index.is_none() => multi_index = Some(access),
+            Access::IndexScan(_) | Access::IndexOrder(_) if ordered(&access) => {
+                group_scan = Some(access)
+            }
+            Access::IndexScan(_) => index_scan = Some(access),
+            Access::Index(seek) => {
+                let score = 4 * seek.key.len()
+                    + 2 * usize::from(seek.has_range())
+                    + usize::from(seek.covering);
+                if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                    best = Some((score, seek));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match (best, rowid_range) {
+        (Some((_, seek)), _) if !seek.key.is_empty() => Access::Index(seek),
+        (Some((_, seek)), _)
+            if group.is_some_and(|group| leads_with(table.table, seek.index, group)) =>
+        {
+            Access::Index(seek)
+        }
+        _ if group_scan.is_some() => group_scan.unwrap_or(Access::Scan),
+        (_, Some(rowid_range)) => rowid_range,
+        (Some((_, seek)), _) => Access::Index(seek),
+        (None, None) => multi_index.or(index_scan).unwrap_or(Access::Scan),
+    }
+}
+
+/// Estimates how many rows of an analyzed table the terms of the WHERE clause let through, from
+/// the most selective of the ways to read them. Each term on a column that neither the rowid
+/// nor the first column of an index can count is assumed to keep a quarter of the rows.
+pub fn rows(
+    schema: &Schema,
+    table: &TableRef,
+    terms: &[&Expr],
+    context: Context,
+) -> anyhow::Result<Option<f64>> {
+    let Some(rows) = candidates(schema, table, terms, None, None, context)?
+        .iter()
+        .filter_map(|access| estimate(table.table, access))
+        .min_by(f64::total_cmp)
+    else {
+        return Ok(None);
+    };
+
+    let columns = table.columns();
+    let uncounted = constraints(table, &columns, terms, context, Keys::Written)?
+        .iter()
+        .filter(|constraint| {
+            let column = &columns[constraint.column];
+            !column.hidden
+                && table.table.rowid_alias != Some(constraint.column)
+                && !schema.indexes_of(&table.table.name).any(|index| {
+                    index
+                        .columns
+                        .first()
+                        .is_some_and(|first| first.name.eq_ignore_ascii_case(&column.name))
+                })
+        })
+        .count();
+
+    Ok(Some(rows * RANGE_SELECTIVITY.powi(uncounted as i32)))
+}
+
+/// Lists the ways to read the rows that can pass the terms: a scan of the table, a scan of the
+/// smallest covering index, a range of rowids, the seeks of every usable index, the OR terms
+/// whose branches can all be searched and a scan in the order of an index that leads with the
+/// columns in `group`. A lookup by rowid is the only way when the terms allow one. A partial
+/// index is only used when the terms imply its WHERE clause. Only the keys written in the
+/// statement are looked at.
+fn candidates<'s>(
+    schema: &'s Schema,
+    table: &TableRef,
+    terms: &[&Expr],
+    used: Option<&[usize]>,
+    group: Option<&[usize]>,
+    context: Context,
+) -> anyhow::Result<Vec<Access<'s>>> {
+    let columns = table.columns();
+    let constraints = constraints(table, &columns, terms, context, Keys::Written)?;
+
+    let is_rowid = |i: usize| columns[i].hidden || table.table.rowid_alias == Some(i);
+    let rowid_constraints: Vec<&Constraint> = constraints
+        .iter()
+        .filter(|constraint| is_rowid(constraint.column))
+        .collect();
+    let mut ways = vec![Access::Scan];
+    match rowid_access(
+        &columns,
+        &is_rowid,
+        &rowid_constraints,
+        terms,
+        context,
+        Keys::Written,
+    )? {
+        rowids @ Access::Rowids(_) => return Ok(vec![rowids]),
+        rowids @ Access::RowidRange(_) => ways.push(rowids),
+        _ => {}
+    }
+
+    let covering = |index: &IndexSchema| used.is_some_and(|used| covers(table.table, index, used));
+    let indexes: Vec<&IndexSchema> = schema
+        .indexes_of(&table.table.name)
+        .filter(|index| holds(table, index, terms, context))
+        .collect();
+    ways.extend(
+        indexes
+            .iter()
+            .filter(|index| covering(index))
+            .min_by_key(|index| index.columns.len())
+            .map(|index| Access::IndexScan(index)),
+    );
+    let group_index = group.and_then(|group| {
+        indexes
+            .iter()
+            .find(|index| leads_with(table.table, index, group))
+    });
+    if let Some(index) = group_index {
+        ways.push(if covering(index) {
+            Access::IndexScan(index)
+        } else {
+            Access::IndexOrder(index)
+        });
+    }
+    for index in indexes {
+        if let Some(mut seek) = index_seek(table, index, &constraints) {
+            seek.covering = covering(index);
+            ways.push(Access::Index(seek));
+        }
+    }
+
+    for (i, term) in terms.iter().enumerate() {
+        let branches = disjuncts(term);
+        if branches.len() < 2 {
+            continue;
+        }
+
+        let mut accesses = Vec::new();
+        for &branch in &branches {
+            let access = choose(
+                table,
+                candidates(
+                    schema,
+                    table,
+                    &branch_terms(terms, i, branch),
+                    None,
+                    None,
+                    context,
+                )?,
+                None,
+            );
+            if !matches!(
+                access,
+                Access::Rowids(_) | Access::RowidRange(_) | Access::Index(_)
+            ) {
+                break;
+            }
+            accesses.push(access);
+        }
+        if accesses.len() == branches.len() {
+            ways.push(Access::MultiIndex {
+                term: i,
+                branches: accesses,
+            });
+        }
+    }
+
+    Ok(ways)
+}
+
+/// Splits an expression into the terms of its top-level ORs.
+fn disjuncts(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Or,
+            right,
+        } => {
+            let mut terms = disjuncts(left);
+            terms.extend(disjuncts(right));
+            terms
+        }
+        Expr::Nested(expr) => disjuncts(expr),
+        expr => vec![expr],
+    }
+}
+
+/// The share of rows each bound of a range is assumed to keep when no samples tell better,
+/// the same guess SQLite makes.
+const RANGE_SELECTIVITY: f64 = 0.25;
+
+/// The number of entries an index without statistics is assumed to hold for each key.
+const ROWS_PER_KEY: f64 = 10.0;
+
+/// Estimates how many rows or index entries an access path reads, from the statistics that
+/// ANALYZE stored. Returns None when the table has not been analyzed.
+pub fn estimate(table: &TableSchema, access: &Access) -> Option<f64> {
+    let rows = table.row_estimate? as f64;
+
+    Some(match access {
+        Access::Scan | Access::IndexScan(_) | Access::IndexOrder(_) => rows,
+        Access::Rowids(rowids) => rowids.len() as f64,
+        Access::RowidRange(rowids) => {
+            let bounds =
+                i32::from(*rowids.start() != i64::MIN) + i32::from(*rowids.end() != i64::MAX);
+            let width = (*rowids.end() as f64 - *rowids.start() as f64 + 1.0).max(0.0);
+            (rows * RANGE_SELECTIVITY.powi(bounds)).min(width)
+        }
+        Access::Index(seek) => seek_estimate(seek, rows),
+        Access::RowidEdge { .. } | Access::IndexEdge { .. } => 1.0,
+        Access::MultiIndex { branches, .. } => branches
+            .iter()
+            .filter_map(|branch| estimate(table, branch))
+            .sum::<f64>()
+            .min(rows),
+    })
+}
+
+/// Estimates the cost of an access path as the number of rows and index entries it visits.
+/// Each search of a b-tree, including every lookup of a row by its rowid, costs about the
+/// logarithm of the number of rows. Returns None when the table has not been analyzed.
+pub fn cost(table: &TableSchema, access: &Access) -> Option<f64> {
+    let rows = estimate(table, access)?;
+    let search = search_cost(table)?;
+
+    Some(match access {
+        // A scan descends to the first leaf like a search does, so a search never costs more
+        // than the scan it narrows.
+        Access::Scan => search + rows,
+        Access::IndexScan(index) => search + rows * entry_size(table, index),
+        Access::IndexOrder(_) => rows * (1.0 + search),
+        Access::Rowids(_) => rows * search,
+        Access::RowidRange(_) | Access::RowidEdge { .. } => search + rows,
+        Access::Index(seek) if seek.covering => search + rows * entry_size(table, seek.index),
+        Access::Index(_) | Access::IndexEdge { .. } => search + rows * (1.0 + search),
+        // Each branch only reads rowids, then the merged rowids are looked up once.
+        Access::MultiIndex { branches, .. } => {
+            branches
+                .iter()
+                .filter_map(|branch| Some(search + estimate(table, branch)?))
+                .sum::<f64>()
+                + rows * search
+        }
+    })
+}
+
+/// The cost of sorting `rows` rows, for a GROUP BY that does not get them in order.
+fn sort_cost(rows: f64) -> f64 {
+    rows * rows.max(2.0).log2()
+}
+
+/// Tells whether an access reads the rows with equal values of the columns in `group` next to
+/// each other: a single row, the rowid in rowid order, or the leading columns of an index, in
+/// any order, in index order.
+pub fn group_order(table: &TableSchema, access: &Access, group: &[usize]) -> bool {
+    match access {
+        Access::Rowids(rowids) if rowids.len() <= 1 => true,
+        Access::Scan | Access::Rowids(_) | Access::RowidRange(_) => {
+            matches!(group, [column] if Some(*column) == table.rowid_alias)
+        }
+        Access::IndexScan(index) | Access::IndexOrder(index) => leads_with(table, index, group),
+        Access::Index(seek) => leads_with(table, seek.index, group),
+        _ => false,
+    }
+}
+
+/// Tells whether the leading columns of an index are exactly the columns in `group`, in any
+/// order, each ordered by BINARY.
+fn leads_with(table: &TableSchema, index: &IndexSchema, group: &[usize]) -> bool {
+    index.columns.len() >= group.len()
+        && index.columns[..group.len()].iter().all(|column| {
+            column.collation.is_none()
+                && table
+                    .column_index(&column.name)
+                    .is_some_and(|i| group.contains(&i))
+        })
+}
+
+/// The size of an entry of `index` relative to a row of `table`. Index entries are smaller
+/// than rows, so fewer pages hold them.
+fn entry_size(table: &TableSchema, index: &IndexSchema) -> f64 {
+    (index.columns.len() + 1) as f64 / (table.columns.len() + 1) as f64
+}
+
+/// The cost of one search of the b-tree of an analyzed table.
+pub fn search_cost(table: &TableSchema) -> Option<f64> {
+    Some((table.row_estimate? as f64).max(2.0).log2())
+}
+
+/// Estimates how many entries of an index share a value of its first column.
+pub fn rows_per_key(index: &IndexSchema) -> f64 {
+    match &index.stats {
+        Some(stats) => stats
+            .per_key
+            .first()
+            .map_or(ROWS_PER_KEY, |&count| count as f64),
+        None if index.unique && index.columns.len() == 1 => 1.0,
+        None => ROWS_PER_KEY,
+    }
+}
+
+/// Estimates the number of entries an index seek finds. An equality key that matches a sample
+/// counts the sample's equal entries, otherwise the average per key. A range of the first
+/// column is counted between the samples around its bounds.
+fn seek_estimate(seek: &IndexSeek, rows: f64) -> f64 {
+    let n = seek.key.len();
+    let bounds = i32::from(!matches!(seek.low, Bound::Unbounded))
+        + i32::from(!matches!(seek.high, Bound::Unbounded));
+
+    let Some(stats) = &seek.index.stats else {
+        let per_key = if seek.index.unique && n == seek.index.columns.len() {
+            1.0
+        } else {
+            ROWS_PER_KEY
+        };
+        let rows = if n == 0 { rows } else { per_key.min(rows) };
+        return rows * RANGE_SELECTIVITY.powi(bounds);
+    };
+
+    let equal = if n == 0 {
+        stats.rows as f64
+    } else {
+        sampled_equal(stats, &seek.key, &seek.collations)
+            .filter(|_| seek.known)
+            .or(stats.per_key.get(n - 1).copied())
+            .map_or(rows, |count| count as f64)
+    };
+
+    let collation = seek.collations.first().copied().unwrap_or_default();
+    match sampled_range(stats, &seek.low, &seek.high, collation) {
+        Some(count) if n == 0 && bounds > 0 && seek.known => count,
+        _ => equal * RANGE_SELECTIVITY.powi(bounds),
+    }
+}
+
+/// Estimates how many entries of an index a join probe of its first column finds for each row
+/// on the left. A key written in the statement that matches a sample counts the sample's equal
+/// entries.
+pub fn probe_rows(index: &IndexSchema, probe: &ProbeKey, context: Context) -> f64 {
+    let key = constant(probe.expr, &[], context, Keys::Written)
+        .ok()
+        .flatten()
+        .flatten()
+        .map(|value| match probe.affinity {
+            Some(affinity) => value.apply_affinity(affinity),
+            None => value,
+        });
+    match (&index.stats, key) {
+        (Some(stats), Some(key)) => sampled_equal(stats, &[key], &[probe.collation])
+            .map_or_else(|| rows_per_key(index), |count| count as f64),
+        _ => rows_per_key(index),
+    }
+}
+
+/// The number of entries equal to `key` on its prefix of the index, if a sample holds it. Each
+/// column compares under its collating sequence in `collations`.
+fn sampled_equal(stats: &IndexStats, key: &[SerialValue], collations: &[Collation]) -> Option<u64> {
+    let n = key.len();
+    stats
+        .samples
+        .iter()
+        .find(|sample| {
+            sample.key.len() >= n
+                && sample.key[..n]
+                    .iter()
+                    .zip(key)
+                    .enumerate()
+                    .all(|(i, (a, b))| {
+                        let collation = collations.get(i).copied().unwrap_or_default();
+                        collation.compare(a, b).is_eq()
+                    })
+        })
+        .and_then(|sample| sample.eq.get(n - 1))
+        .copied()
+}
+
+/// Counts the entries whose first column lies between `low` and `high` under `collation` from
+/// the samples, as the difference of the positions of the two bounds in the index.
+fn sampled_range(
+    stats: &IndexStats,
+    low: &Bound<SerialValue>,
+    high: &Bound<SerialValue>,
+    collation: Collation,
+) -> Option<f64> {
+    if stats.samples.is_empty() {
+        return None;
+    }
+
+    // The number of entries before a bound, counting those equal to it if `equal` is set.
+    let position = |value: &SerialValue, equal: bool| {
+        let mut position = 0;
+        for sample in &stats.samples {
+            let (Some(key), Some(&eq), Some(&lt)) =
+                (sample.key.first(), sample.eq.first(), sample.lt.first())
+            else {
+                continue;
+            };
+            match collation.compare(key, value) {
+                Ordering::Less => position = lt + eq,
+                Ordering::Equal => {
+                    position = if equal { lt + eq } else { lt };
+                    break;
+                }
+                Ordering::Greater => break,
+            }
+        }
+        position
+    };
+
+    let start = match low {
+        Bound::Included(value) => position(value, false),
+        Bound::Excluded(value) => position(value, true),
+        Bound::Unbounded => 0,
+    };
+    let end = match high {
+        Bound::Included(value) => position(value, true),
+        Bound::Excluded(value) => position(value, false),
+        Bound::Unbounded => stats.rows,
+    };
+
+    Some(end.saturating_sub(start) as f64)
+}
+
+/// Chooses how to find the row that `min(arg)` or `max(arg)` picks, for a query whose only
+/// aggregate it is and that has no WHERE or GROUP BY clause. The rowid is at an end of the
+/// table b-tree, and a column that leads an ascending index is at an end of the index. The
+/// index covers the query when it holds every column in `used`.
+pub fn min_max<'s>(
+    schema: &'s Schema,
+    table: &TableRef,
+    arg: &Expr,
+    max: bool,
+    used: Option<&[usize]>,
+) -> Option<Access<'s>> {
+    let columns = table.columns();
+    let i = column_of(arg, &[], &columns)?;
+    if columns[i].hidden || table.table.rowid_alias == Some(i) {
+        return Some(Access::RowidEdge { last: max });
+    }
+
+    // The aggregate compares with the collation of the column, so the index must sort by it.
+    // Without a WHERE clause, a partial index misses rows.
+    let column = &table.table.columns[i];
+    let collation = column.collation.as_deref().unwrap_or("binary");
+    schema.indexes_of(&table.table.name).find_map(|index| {
+        if index.predicate.is_some() {
+            return None;
+        }
+        let first = index.columns.first()?;
+        let sorts_by_column = first.name.eq_ignore_ascii_case(&column.name)
+            && !first.desc
+            && first
+                .collation
+                .as_deref()
+                .unwrap_or(collation)
+                .eq_ignore_ascii_case(collation);
+        sorts_by_column.then(|| Access::IndexEdge {
+            index,
+            last: max,
+            covering: used.is_some_and(|used| covers(table.table, index, used)),
+        })
+    })
+}
+
+/// Tells whether an index holds every row that can pass the terms. A partial index only holds
+/// the rows its WHERE clause is true for, so each term of that clause must be one of the
+/// terms, or follow from a comparison of the same column with a constant: `a > 5` follows from
+/// `a = 7` or `a >= 6`, and `a IS NOT NULL` from any such comparison.
+pub fn holds(table: &TableRef, index: &IndexSchema, terms: &[&Expr], context: Context) -> bool {
+    let Some(predicate) = &index.predicate else {
+        return true;
+    };
+    let columns = table.columns();
+    let Ok(known) = constraints(table, &columns, terms, context, Keys::Written) else {
+        return false;
+    };
+    let follows = |column: usize, test: &dyn Fn(&Constraint, &SerialValue) -> bool| {
+        known.iter().any(|constraint| {
+            constraint.column == column
+                && constraint
+                    .key
+                    .as_ref()
+                    .is_some_and(|key| !key.is_null() && test(constraint, key))
+        })
+    };
+
+    conjuncts(predicate).into_iter().all(|required| {
+        if terms.contains(&required) {
+            return true;
+        }
+        match required {
+            Expr::IsNotNull(expr) => {
+                column_of(expr, &[], &columns).is_some_and(|column| follows(column, &|_, _| true))
+            }
+            Expr::BinaryOp { .. } => {
+                let Ok(required) =
+                    constraints(table, &columns, &[required], context, Keys::Written)
+                else {
+                    return false;
+                };
+                let [Constraint {
+                    column,
+                    op,
+                    key: Some(bound),
+                    collation,
+                }] = required.as_slice()
+                else {
+                    return false;
+                };
+                follows(*column, &|constraint, key| {
+                    constraint.collation == *collation
+                        && implies(&constraint.op, key, op, bound, *collation)
+                })
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Tells whether every value that satisfies `value op key` also satisfies
+/// `value required bound`, both compared under `collation`.
+fn implies(
+    op: &BinaryOperator,
+    key: &SerialValue,
+    required: &BinaryOperator,
+    bound: &SerialValue,
+    collation: Collation,
+) -> bool {
+    use BinaryOperator::{Eq, Gt, GtEq, Lt, LtEq};
+
+    let ordering = collation.compare(key, bound);
+    match (op, required) {
+        (Eq, Eq) => ordering.is_eq(),
+        (Eq, Lt) => ordering.is_lt(),
+        (Eq, LtEq) => ordering.is_le(),
+        (Eq, Gt) => ordering.is_gt(),
+        (Eq, GtEq) => ordering.is_ge(),
+        (Gt | GtEq, Gt | GtEq) => {
+            ordering.is_gt() || ordering.is_eq() && (*op == Gt || *required == GtEq)
+        }
+        (Lt | LtEq, Lt | LtEq) => {
+            ordering.is_lt() || ordering.is_eq() && (*op == Lt || *required == LtEq)
+        }
+        _ => false,
+    }
+}
+
+/// Checks that an index holds every column in `used`. The rowid, and the column that aliases
+/// it, are part of every index entry.
+pub fn covers(table: &TableSchema, index: &IndexSchema, used: &[usize]) -> bool {
+    used.iter().all(|&i| {
+        i >= table.columns.len()
+            || table.rowid_alias == Some(i)
+            || index
+                .columns
+                .iter()
+                .any(|column| table.column_index(&column.name) == Some(i))
+    })
+}
+
+/// Collects the comparisons between a column of the table and a constant. `BETWEEN` counts as
+/// two comparisons, and so does a `LIKE` whose pattern starts with a fixed prefix.
+fn constraints(
+    table: &TableRef,
+    columns: &[ColumnName],
+    terms: &[&Expr],
+    context: Context,
+    keys: Keys,
+) -> anyhow::Result<Vec<Constraint>> {
+    let scope = Scope::new(columns, &[]);
+    // The key is converted the way the comparison converts it, so that it is found among the
+    // stored values. A column whose own values would be converted cannot be searched.
+    let comparison_key =
+        |column: &Expr, key: &Expr| -> anyhow::Result<Option<Option<SerialValue>>> {
+            let (column_affinity, key_affinity) =
+                comparison_affinities(affinity(column, &scope), affinity(key, &scope));
+            if column_affinity.is_some() {
+                return Ok(None);
+            }
+            Ok(constant(key, columns, context, keys)?.map(|key| {
+                key.map(|key| match key_affinity {
+                    Some(affinity) => key.apply_affinity(affinity),
+                    None => key,
+                })
+            }))
+        };
+    let mut constraints = Vec::new();
+
+    for term in terms {
+        match term {
+            Expr::BinaryOp { left, op, right } => {
+                let Some(flipped) = flip(op) else {
+                    continue;
+                };
+                let (column, op, column_expr, key) =
+                    if let Some(column) = column_of(left, &[], columns) {
+                        (column, op.clone(), left, right)
+                    } else if let Some(column) = column_of(right, &[], columns) {
+                        (column, flipped, right, left)
+                    } else {
+                        continue;
+                    };
+
+                if let Some(key) = comparison_key(column_expr, key)? {
+                    constraints.push(Constraint {
+                        column,
+                        op,
+                        key,
+                        collation: comparison_collation(left, right, &scope)?,
+                    });
+                }
+            }
+            Expr::Between {
+                expr,
+                negated: false,
+                low,
+                high,
+            } => {
+                let Some(column) = column_of(expr, &[], columns) else {
+                    continue;
+                };
+                let (Some(low_key), Some(high_key)) =
+                    (comparison_key(expr, low)?, comparison_key(expr, high)?)
+                else {
+                    continue;
+                };
+
+                constraints.push(Constraint {
+                    column,
+                    op: BinaryOperator::GtEq,
+                    key: low_key,
+                    collation: comparison_collation(expr, low, &scope)?,
+                });
+                constraints.push(Constraint {
+                    column,
+                    op: BinaryOperator::LtEq,
+                    key: high_key,
+                    collation: comparison_collation(expr, high, &scope)?,
+                });
+            }
+            Expr::Like {
+                negated: false,
+                expr,
+                pattern,
+                escape_char: None,
+            } => {
+                let Some(column) = column_of(expr, &[], columns) else {
+                    continue;
+                };
+                // Numbers in other columns match as text but are not stored among the text.
+                let text_column = table.table.columns.get(column).is_some_and(|column| {
+                    Affinity::from_type_name(&column.type_name) == Affinity::Text
+                });
+                let Some(Some(SerialValue::String(pattern))) =
+                    constant(pattern, columns, context, keys)?
+                else {
+                    continue;
+                };
+
+                if let (true, Some((low, high))) = (text_column, like_range(&pattern)) {
+                    constraints.push(Constraint {
+                        column,
+                        op: BinaryOperator::GtEq,
+                        key: Some(SerialValue::String(low)),
+                        collation: Collation::Binary,
+                    });
+                    if let Some(high) = high {
+                        constraints.push(Constraint {
+                            column,
+                            op: BinaryOperator::Lt,
+                            key: Some(SerialValue::String(high)),
+                            collation: Collation::Binary,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(constraints)
+}
+
+/// The text that every string matching a `LIKE` pattern sorts at or after, and the text it
+/// sorts before, if any. LIKE ignores the case of ASCII letters, and upper case letters sort
+/// before lower case ones, so the range runs from the upper case prefix up to the lower case
+/// prefix whose last character is incremented. A pattern that starts with a wildcard has no
+/// range.
+fn like_range(pattern: &str) -> Option<(String, Option<String>)> {
+    let prefix: String = pattern
+        .chars()
+        .take_while(|c| !matches!(c, '%' | '_'))
+        .collect();
+    if prefix.is_empty() {
+        return None;
+    }
+
+    let mut high = prefix.to_ascii_lowercase();
+    let last = high.pop()?;
+    let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+    let high = next.map(|next| {
+        high.push(next);
+        high
+    });
+
+    Some((prefix.to_ascii_uppercase(), high))
+}
+
+/// Evaluates an expression that does not refer to any column of the table, with the values
+/// bound to the parameters and the row of the enclosing query in `context`. Returns None when
+/// it refers to a column of the table, and Some(None) for a key that is not known while
+/// planning: one that refers to a parameter, to the enclosing query or to a subquery, or NULL.
+fn constant(
+    expr: &Expr,
+    columns: &[ColumnName],
+    context: Context,
+    keys: Keys,
+) -> anyhow::Result<Option<Option<SerialValue>>> {
+    if has_subquery(expr) {
+        return Ok(subquery_key(expr, columns, context, keys));
+    }
+    if !depends_only_on(expr, &[]) {
+        if !refers_to_outer(expr, columns, context) {
+            return Ok(None);
+        }
+        if keys == Keys::Written {
+            return Ok(Some(None));
+        }
+    }
+    if keys == Keys::Written && has_parameter(expr) {
+        return Ok(Some(None));
+    }
+    let value = eval(expr, &Scope::within(context, &[], &[]))?;
+    Ok(Some(
+        (keys == Keys::Bound || !value.is_null()).then_some(value),
+    ))
+}
+
+fn has_subquery(expr: &Expr) -> bool {
+    let flow = visit_expressions(expr, |expr| match expr {
+        Expr::Subquery(_) | Expr::InSubquery { .. } | Expr::Exists { .. } => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    });
+
+    flow.is_break()
+}
+
+/// A key that holds subqueries, which is only known when the statement runs. Outside its
+/// subqueries it may not refer to any column, and the subqueries may not refer to the table
+/// or to an enclosing query. Since that is only found out by running them, every such key
+/// counts while planning, and one that turns out to be correlated gives no key.
+fn subquery_key(
+    expr: &Expr,
+    columns: &[ColumnName],
+    context: Context,
+    keys: Keys,
+) -> Option<Option<SerialValue>> {
+    let mut refers = false;
+    visit_outside_subqueries(&[expr], |expr| {
+        refers |= matches!(expr, Expr::Identifier(_) | Expr::CompoundIdentifier(_));
+    });
+    if refers || names_table(expr, columns) {
+        return None;
+    }
+    // Without a way to run subqueries, as when a plan is only described, the key stays unknown.
+    if keys == Keys::Written || context.subqueries.is_none() {
+        return Some(None);
+    }
+
+    uncorrelated(columns, context, |scope| eval(expr, scope)).map(Some)
+}
+
+/// Checks for a column qualified by the name of the table, which a subquery is taken to refer
+/// to without running it.
+fn names_table(node: &impl Visit, columns: &[ColumnName]) -> bool {
+    let scope = Scope::new(columns, &[]);
+    let flow = visit_expressions(node, |expr| match expr {
+        Expr::CompoundIdentifier(idents)
+            if idents.len() == 2
+                && matches!(
+                    scope.resolve(Some(&idents[0].value), &idents[1].value),
+                    Ok(Some(_))
+                ) =>
+        {
+            ControlFlow::Break(())
+        }
+        _ => ControlFlow::Continue(()),
+    });
+
+    flow.is_break()
+}
+
+/// Runs `f` in a scope whose enclosing row is a row of NULLs for the table's `columns`, with
+/// no enclosing query beyond it. Returns None when a subquery that `f` runs refers to that row,
+/// or when `f` fails, as it does for a subquery that refers to an enclosing query. Results of
+/// the subqueries that refer to neither are kept for the filter to reuse.
+fn uncorrelated<T>(
+    columns: &[ColumnName],
+    context: Context,
+    f: impl FnOnce(&Scope) -> anyhow::Result<T>,
+) -> Option<T> {
+    let row = vec![SerialValue::Null; columns.len()];
+    let table = Scope::within(
+        Context {
+            outer: None,
+            correlated: None,
+            ..context
+        },
+        columns,
+        &row,
+    );
+    let correlated = Cell::new(false);
+    let scope = Scope::within(
+        Context {
+            outer: Some(&table),
+            correlated: Some(&correlated),
+            ..context
+        },
+        &[],
+        &[],
+    );
+
+    let value = f(&scope).ok()?;
+    (!correlated.get()).then_some(value)
+}
+
+/// Checks that every column an expression refers to belongs to an enclosing query rather than
+/// to the table, so that a correlated subquery can search the table with it.
+fn refers_to_outer(expr: &Expr, columns: &[ColumnName], context: Context) -> bool {
+    let scope = Scope::new(columns, &[]);
+    let flow = visit_expressions(expr, |expr| {
+        let (table, name) = match expr {
+            Expr::Identifier(ident) => (None, &ident.value),
+            Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+                (Some(idents[0].value.as_str()), &idents[1].value)
+            }
+            Expr::CompoundIdentifier(_) | Expr::Subquery(_) | Expr::Exists { .. } => {
+                return ControlFlow::Break(())
+            }
+            _ => return ControlFlow::Continue(()),
+        };
+        if !matches!(scope.resolve(table, name), Ok(None)) {
+            return ControlFlow::Break(());
+        }
+
+        let mut outer = context.outer;
+        while let Some(scope) = outer {
+            if matches!(scope.resolve(table, name), Ok(Some(_))) {
+                return ControlFlow::Continue(());
+            }
+            outer = scope.context.outer;
+        }
+        ControlFlow::Break(())
+    });
+
+    flow.is_continue()
+}
+
+/// The values of the single column of an `IN (SELECT ...)` subquery, as keys. While planning
+/// or describing the plan, the subquery stands for as many unknown keys as an index finds
+/// for one key. Returns None
+/// when it does not have one column or refers to the table or to an enclosing query.
+fn subquery_values(
+    query: &Query,
+    columns: &[ColumnName],
+    context: Context,
+    keys: Keys,
+) -> Option<Vec<Option<SerialValue>>> {
+    if names_table(query, columns) {
+        return None;
+    }
+    let subqueries = match context.subqueries {
+        Some(subqueries) if keys == Keys::Bound => subqueries,
+        _ => return Some(vec![None; ROWS_PER_KEY as usize]),
+    };
+
+    let result = uncorrelated(columns, context, |scope| subqueries.run(query, scope))?;
+    if result.columns.len() != 1 {
+        return None;
+    }
+
+    Some(result.rows.iter().map(|row| row.first().cloned()).collect())
+}
+
+/// Narrows the rowids allowed by the constraints and the `IN (...)` terms on the rowid down to
+/// a list or a range.
+fn rowid_access(
+    columns: &[ColumnName],
+    is_rowid: &dyn Fn(usize) -> bool,
+    constraints: &[&Constraint],
+    terms: &[&Expr],
+    context: Context,
+    keys: Keys,
+) -> anyhow::Result<Access<'static>> {
+    let mut range = i64::MIN..=i64::MAX;
+    for constraint in constraints {
+        let rowids = match &constraint.key {
+            Some(key) => rowid_range(&constraint.op, key),
+            None => unknown_rowid_range(&constraint.op),
+        };
+        range = intersect(range, rowids);
+    }
+
+    let mut points: Option<Vec<i64>> = None;
+    for term in terms {
+        let values = match term {
+            Expr::InList {
+                expr,
+                list,
+                negated: false,
+            } if column_of(expr, &[], columns).is_some_and(is_rowid) => list
+                .iter()
+                .map(|key| constant(key, columns, context, keys))
+                .collect::<anyhow::Result<Option<Vec<_>>>>()?,
+            Expr::InSubquery {
+                expr,
+                subquery,
+                negated: false,
+            } if column_of(expr, &[], columns).is_some_and(is_rowid) => {
+                subquery_values(subquery, columns, context, keys)
+            }
+            _ => continue,
+        };
+        let Some(values) = values else {
+            continue;
+        };
+
+        // Keys that are not known stand for as many rowids.
+        let rowids: Vec<i64> = match values.iter().cloned().collect::<Option<Vec<_>>>() {
+            Some(values) => values
+                .iter()
+                .flat_map(|key| rowid_range(&BinaryOperator::Eq, key))
+                .collect(),
+            None => (0..values.len() as i64).collect(),
+        };
+        points = Some(match points {
+            Some(points) => points
+                .into_iter()
+                .filter(|id| rowids.contains(id))
+                .collect(),
+            None => rowids,
+        });
+    }
+
+    Ok(match points {
+        Some(mut points) => {
+            points.retain(|id| range.contains(id));
+            points.sort_unstable();
+            points.dedup();
+            Access::Rowids(points)
+        }
+        None if range.start() == range.end() => Access::Rowids(vec![*range.start()]),
+        // An equality that nothing matches, such as with NULL, still looks up one rowid.
+        None if range.is_empty()
+            && constraints
+                .iter()
+                .any(|constraint| constraint.op == BinaryOperator::Eq) =>
+        {
+            Access::Rowids(Vec::new())
+        }
+        None if !constraints.is_empty() => Access::RowidRange(range),
+        None => Access::Scan,
+    })
+}
+
+/// Matches the columns of an index from the left with equality constraints, and the column
+/// after them with range constraints. A comparison qualifies only under the collating sequence
+/// the index column is ordered by, since the index is searched in that order.
+fn index_seek<'s>(
+    table: &TableRef,
+    index: &'s IndexSchema,
+    constraints: &[Constraint],
+) -> Option<IndexSeek<'s>> {
+    let mut seek = IndexSeek {
+        index,
+        key: Vec::new(),
+        low: Bound::Unbounded,
+        high: Bound::Unbounded,
+        desc: index.columns.iter().map(|column| column.desc).collect(),
+        collations: Vec::new(),
+        covering: false,
+        known: true,
+    };
+
+    for index_column in &index.columns {
+        let Some(column) = table.table.column_index(&index_column.name) else {
+            break;
+        };
+        let Some(collation) = index_collation(index_column, &table.table.columns[column]) else {
+            break;
+        };
+        seek.collations.push(collation);
+
+        let usable: Vec<&Constraint> = constraints
+            .iter()
+            .filter(|constraint| constraint.column == column && constraint.collation == collation)
+            .collect();
+        if let Some(constraint) = usable
+            .iter()
+            .find(|constraint| constraint.op == BinaryOperator::Eq)
+        {
+            seek.known &= constraint.key.is_some();
+            seek.key
+                .push(constraint.key.clone().unwrap_or(SerialValue::Null));
+            continue;
+        }
+
+        for constraint in usable {
+            // A bound that is not known stands in for one on the same side.
+            let Some(key) = &constraint.key else {
+                seek.known = false;
+                let bound = match constraint.op {
+                    BinaryOperator::Gt | BinaryOperator::GtEq => &mut seek.low,
+                    _ => &mut seek.high,
+                };
+                if matches!(bound, Bound::Unbounded) {
+                    *bound = Bound::Included(SerialValue::Null);
+                }
+                continue;
+            };
+            match constraint.op {
+                BinaryOperator::Gt => tighten(
+                    &mut seek.low,
+                    Bound::Excluded(key),
+                    Ordering::Greater,
+                    collation,
+                ),
+                BinaryOperator::GtEq => tighten(
+                    &mut seek.low,
+                    Bound::Included(key),
+                    Ordering::Greater,
+                    collation,
+                ),
+                BinaryOperator::Lt => tighten(
+                    &mut seek.high,
+                    Bound::Excluded(key),
+                    Ordering::Less,
+                    collation,
+                ),
+                _ => tighten(
+                    &mut seek.high,
+                    Bound::Included(key),
+                    Ordering::Less,
+                    collation,
+                ),
+            }
+        }
+        break;
+    }
+
+    (!seek.key.is_empty() || seek.has_range()).then_some(seek)
+}
+
+/// Replaces `bound` by `new` when `new` lets fewer values through. `inward` is the direction
+/// in which the bound narrows the range.
+fn tighten(
+    bound: &mut Bound<SerialValue>,
+    new: Bound<&SerialValue>,
+    inward: Ordering,
+    collation: Collation,
+) {
+    let tighter = match (&*bound, new) {
+        (_, Bound::Unbounded) => false,
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(current) | Bound::Excluded(current), Bound::Included(key)) => {
+            collation.compare(key, current) == inward
+        }
+        (Bound::Included(current) | Bound::Excluded(current), Bound::Excluded(key)) => {
+            collation.compare(key, current) != inward.reverse()
+        }
+    };
+    if tighter {
+        *bound = new.cloned();
+    }
+}
+
+/// The operator that compares the same way with its operands swapped, for the comparisons.
+fn flip(op: &BinaryOperator) -> Option<BinaryOperator> {
+    Some(match op {
+        BinaryOperator::Eq => BinaryOperator::Eq,
+        BinaryOperator::Lt => BinaryOperator::Gt,
+        BinaryOperator::LtEq => BinaryOperator::GtEq,
+        BinaryOperator::Gt => BinaryOperator::Lt,
+        BinaryOperator::GtEq => BinaryOperator::LtEq,
+        _ => return None,
+    })
+}
+
+/// The rowids that satisfy `rowid op key`, compared the way the WHERE clause compares them. A
+/// REAL key may let one rowid too many through at the ends of the 64-bit range.
+fn rowid_range(op: &BinaryOperator, key: &SerialValue) -> RangeInclusive<i64> {
+    const EMPTY: RangeInclusive<i64> = RangeInclusive::new(1, 0);
+    const ALL: RangeInclusive<i64> = i64::MIN..=i64::MAX;
+
+    if let Some(key) = key.as_integer() {
+        return match op {
+            BinaryOperator::Eq => key..=key,
+            BinaryOperator::Lt => key.checked_sub(1).map_or(EMPTY, |key| i64::MIN..=key),
+            BinaryOperator::LtEq => i64::MIN..=key,
+            BinaryOperator::Gt => key.checked_add(1).map_or(EMPTY, |key| key..=i64::MAX),
+            _ => key..=i64::MAX,
+        };
+    }
+
+    match key {
+        SerialValue::Float64(num) if num.is_nan() => EMPTY,
+        // Casts from f64 saturate at the ends of the i64 range.
+        SerialValue::Float64(num) => match op {
+            BinaryOperator::Eq if num.fract() == 0.0 => *num as i64..=*num as i64,
+            BinaryOperator::Eq => EMPTY,
+            BinaryOperator::Lt => i64::MIN..=(num.ceil() - 1.0) as i64,
+            BinaryOperator::LtEq => i64::MIN..=num.floor() as i64,
+            BinaryOperator::Gt => (num.floor() + 1.0) as i64..=i64::MAX,
+            _ => num.ceil() as i64..=i64::MAX,
+        },
+        // Every number sorts before TEXT and BLOB values, and NULL compares with nothing.
+        SerialValue::Null => EMPTY,
+        _ => match op {
+            BinaryOperator::Lt | BinaryOperator::LtEq => ALL,
+            _ => EMPTY,
+        },
+    }
+}
+
+/// Stands for the rowids that `rowid op key` lets through while the key is not known: one
+/// rowid, or a range bounded on the same side.
+fn unknown_rowid_range(op: &BinaryOperator) -> RangeInclusive<i64> {
+    match op {
+        BinaryOperator::Eq => 0..=0,
+        BinaryOperator::Lt | BinaryOperator::LtEq => i64::MIN..=i64::MAX - 1,
+        _ => i64::MIN + 1..=i64::MAX,
+    }
+}
+
+fn intersect(a: RangeInclusive<i64>, b: RangeInclusive<i64>) -> RangeInclusive<i64> {
+    *a.start().max(b.start())..=*a.end().min(b.end())
+}
@@ -0,0 +1,434 @@
+use std::{iter::Peekable, str::Chars};
+
+use anyhow::bail;
+
+use crate::column::{format_real, SerialValue};
+
+/// The longest string or blob a function may produce, as in SQLite's `SQLITE_MAX_LENGTH`.
+pub const MAX_LENGTH: usize = 1_000_000_000;
+
+/// The most digits a floating point conversion writes after the decimal point, as in
+/// SQLite's `SQLITE_FP_PRECISION_LIMIT`.
+const FP_PRECISION_LIMIT: usize = 100_000_000;
+
+/// Fails with SQLite's error when a result of `len` bytes would be too long.
+pub fn check_length(len: usize) -> anyhow::Result<()> {
+    if len > MAX_LENGTH {
+        bail!("string or blob too big");
+    }
+
+    Ok(())
+}
+
+/// The decimal digits of the magnitude of a double: `digits[0].digits[1..] × 10^exponent`.
+/// Enough digits are kept that rounding them matches rounding the exact binary value.
+#[derive(Debug, Clone)]
+struct Decimal {
+    digits: Vec<u8>,
+    exponent: i32,
+}
+
+impl Decimal {
+    fn new(num: f64) -> Self {
+        let scientific = format!("{:.50e}", num.abs());
+        let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+
+        Self {
+            digits: mantissa
+                .bytes()
+                .filter(u8::is_ascii_digit)
+                .map(|digit| digit - b'0')
+                .collect(),
+            exponent: exponent.parse().unwrap_or_default(),
+        }
+    }
+
+    /// Rounds half away from zero, keeping `keep` significant digits.
+    fn round(&mut self, keep: i32) {
+        if keep < 0 {
+            self.digits = vec![0];
+            return;
+        }
+
+        let keep = keep as usize;
+        if self.digits.len() <= keep {
+            self.digits.resize(keep.max(1), 0);
+            return;
+        }
+
+        let round_up = self.digits[keep] >= 5;
+        self.digits.truncate(keep);
+        if round_up {
+            let mut i = keep;
+            loop {
+                if i == 0 {
+                    self.digits.insert(0, 1);
+                    self.exponent += 1;
+                    break;
+                }
+                i -= 1;
+                if self.digits[i] == 9 {
+                    self.digits[i] = 0;
+                } else {
+                    self.digits[i] += 1;
+                    break;
+                }
+            }
+        }
+        if self.digits.is_empty() {
+            self.digits.push(0);
+        }
+    }
+
+    /// The digit for 10^`power`.
+    fn digit(&self, power: i32) -> char {
+        let index = self.exponent - power;
+        match usize::try_from(index).ok().and_then(|i| self.digits.get(i)) {
+            Some(digit) => (b'0' + digit) as char,
+            None => '0',
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.digits.iter().all(|digit| *digit == 0)
+    }
+}
+
+/// Formats the magnitude of `num` with `precision` digits after the decimal point, rounding
+/// half away from zero.
+pub fn fixed(num: f64, precision: usize) -> String {
+    let mut decimal = Decimal::new(num);
+    decimal.round(decimal.exponent + 1 + precision as i32);
+    if decimal.is_zero() {
+        decimal.exponent = 0;
+    }
+
+    let mut out = String::new();
+    for power in (0..=decimal.exponent.max(0)).rev() {
+        out.push(decimal.digit(power));
+    }
+    if precision > 0 {
+        out.push('.');
+        for power in 1..=precision as i32 {
+            out.push(decimal.digit(-power));
+        }
+    }
+
+    out
+}
+
+/// Formats the magnitude of `num` as a mantissa with `precision` digits after the decimal
+/// point, and returns it with the decimal exponent.
+fn scientific(num: f64, precision: usize) -> (String, i32) {
+    let mut decimal = Decimal::new(num);
+    if num == 0.0 {
+        decimal.exponent = 0;
+    }
+    decimal.round(precision as i32 + 1);
+
+    let mut mantissa = String::new();
+    mantissa.push(decimal.digit(decimal.exponent));
+    if precision > 0 {
+        mantissa.push('.');
+        for i in 1..=precision as i32 {
+            mantissa.push(decimal.digit(decimal.exponent - i));
+        }
+    }
+
+    (mantissa, decimal.exponent)
+}
+
+fn exponent_suffix(exponent: i32, upper: bool) -> String {
+    let sign = if exponent < 0 { '-' } else { '+' };
+    let e = if upper { 'E' } else { 'e' };
+    format!("{e}{sign}{:02}", exponent.abs())
+}
+
+/// Renders a REAL for `quote()`: as `%!.15g` when that reads back as the same value,
+/// otherwise with up to 19 significant digits.
+pub fn quote_real(num: f64) -> String {
+    let short = format_real(num);
+    if !num.is_finite() || short.parse::<f64>() == Ok(num) {
+        return short;
+    }
+
+    let decimal = Decimal::new(num);
+    let digits: String = decimal
+        .digits
+        .iter()
+        .take(19)
+        .map(|digit| (b'0' + digit) as char)
+        .collect();
+    let fraction = digits[1..].trim_end_matches('0');
+    let fraction = if fraction.is_empty() { "0" } else { fraction };
+    let sign = if num < 0.0 { "-" } else { "" };
+
+    format!(
+        "{sign}{}.{fraction}{}",
+        &digits[..1],
+        exponent_suffix(decimal.exponent, false)
+    )
+}
+
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alternate: bool,
+    thousands: bool,
+    bang: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn sign(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.plus {
+            "+"
+        } else if self.space {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// Pads a converted value to the field width. Zero padding goes between the prefix, such
+    /// as the sign, and the digits.
+    fn pad(&self, prefix: &str, body: &str, numeric: bool) -> anyhow::Result<String> {
+        let len = prefix.chars().count() + body.chars().count();
+        let fill = self.width.saturating_sub(len);
+        check_length(prefix.len() + body.len() + fill)?;
+
+        Ok(if self.left {
+            format!("{prefix}{body}{}", " ".repeat(fill))
+        } else if self.zero && numeric {
+            format!("{prefix}{}{body}", "0".repeat(fill))
+        } else {
+            format!("{}{prefix}{body}", " ".repeat(fill))
+        })
+    }
+}
+
+/// Reads a width or precision written in the format, clamped to `i32::MAX` as SQLite does.
+fn digits(chars: &mut Peekable<Chars>) -> usize {
+    let mut n = 0usize;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        n = n.saturating_mul(10).saturating_add(digit as usize);
+        chars.next();
+    }
+
+    n.min(i32::MAX as usize)
+}
+
+/// Reads a width or precision given by `*`. SQLite takes it as a C `int`, uses its magnitude,
+/// and treats `i32::MIN`, which has none, as missing.
+fn star(value: &SerialValue) -> (bool, Option<usize>) {
+    let n = value.to_integer() as i32;
+    let magnitude = (n != i32::MIN).then(|| n.unsigned_abs() as usize);
+
+    (n < 0, magnitude)
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+fn text(value: &SerialValue) -> String {
+    match value {
+        SerialValue::Null => String::new(),
+        value => value.display(),
+    }
+}
+
+/// Formats the arguments the way SQLite's `printf()` does. Missing arguments count as NULL.
+/// Fails when the result would be longer than [`MAX_LENGTH`].
+pub fn printf(format: &str, args: &[SerialValue]) -> anyhow::Result<String> {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut next_arg = || args.next().cloned().unwrap_or(SerialValue::Null);
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '0' => spec.zero = true,
+                '#' => spec.alternate = true,
+                ',' => spec.thousands = true,
+                '!' => spec.bang = true,
+                _ => break,
+            }
+            chars.next();
+        }
+
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            let (negative, width) = star(&next_arg());
+            spec.left |= negative;
+            spec.width = width.unwrap_or_default();
+        } else {
+            spec.width = digits(&mut chars);
+        }
+
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            spec.precision = if chars.peek() == Some(&'*') {
+                chars.next();
+                star(&next_arg()).1
+            } else {
+                Some(digits(&mut chars))
+            };
+        }
+
+        while matches!(chars.peek(), Some('l' | 'h')) {
+            chars.next();
+        }
+
+        let Some(conversion) = chars.next() else {
+            break;
+        };
+        let formatted = match conversion {
+            '%' => "%".to_string(),
+            'd' | 'i' => {
+                let num = next_arg().to_integer();
+                let mut digits = num.unsigned_abs().to_string();
+                if let Some(precision) = spec.precision {
+                    check_length(precision)?;
+                    digits = format!("{digits:0>precision$}");
+                }
+                if spec.thousands {
+                    digits = group_thousands(&digits);
+                }
+                spec.pad(spec.sign(num < 0), &digits, true)?
+            }
+            'u' | 'x' | 'X' | 'o' | 'p' => {
+                let num = next_arg().to_integer() as u64;
+                let (digits, prefix) = match conversion {
+                    'x' => (format!("{num:x}"), "0x"),
+                    'X' | 'p' => (format!("{num:X}"), "0X"),
+                    'o' => (format!("{num:o}"), "0"),
+                    _ => (num.to_string(), ""),
+                };
+                let prefix = if spec.alternate && num != 0 {
+                    prefix
+                } else {
+                    ""
+                };
+                spec.pad(prefix, &digits, true)?
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => real(&spec, conversion, next_arg().to_real())?,
+            's' | 'z' => {
+                let txt = text(&next_arg());
+                let txt = match spec.precision {
+                    Some(precision) => txt.chars().take(precision).collect(),
+                    None => txt,
+                };
+                spec.pad("", &txt, false)?
+            }
+            'c' => {
+                let txt = text(&next_arg());
+                let c: String = txt.chars().take(1).collect();
+                let count = spec.precision.unwrap_or(1).max(1);
+                check_length(c.len() * count)?;
+                spec.pad("", &c.repeat(count), false)?
+            }
+            'q' | 'Q' | 'w' => {
+                let value = next_arg();
+                let quote = if conversion == 'w' { "\"" } else { "'" };
+                let txt = match (&value, conversion) {
+                    (SerialValue::Null, 'Q') => "NULL".to_string(),
+                    (SerialValue::Null, _) => "(NULL)".to_string(),
+                    (value, 'Q') => {
+                        format!("'{}'", text(value).replace('\'', "''"))
+                    }
+                    (value, _) => text(value).replace(quote, &quote.repeat(2)),
+                };
+                spec.pad("", &txt, false)?
+            }
+            _ => String::new(),
+        };
+        check_length(out.len() + formatted.len())?;
+        out.push_str(&formatted);
+    }
+
+    Ok(out)
+}
+
+fn real(spec: &Spec, conversion: char, num: f64) -> anyhow::Result<String> {
+    let sign = spec.sign(num.is_sign_negative() && num != 0.0);
+    if num.is_nan() {
+        return spec.pad("", "NaN", false);
+    }
+    if num.is_infinite() {
+        return spec.pad(sign, "Inf", false);
+    }
+
+    let precision = spec.precision.unwrap_or(6).min(FP_PRECISION_LIMIT);
+    let upper = conversion.is_ascii_uppercase();
+    let mut body = match conversion.to_ascii_lowercase() {
+        'f' => fixed(num, precision),
+        'e' => {
+            let (mantissa, exponent) = scientific(num, precision);
+            format!("{mantissa}{}", exponent_suffix(exponent, upper))
+        }
+        _ => {
+            let precision = precision.max(1);
+            let (_, exponent) = scientific(num, precision - 1);
+            let mut body = if exponent < -4 || exponent >= precision as i32 {
+                let (mantissa, exponent) = scientific(num, precision - 1);
+                let mantissa = trim_zeros(&mantissa, spec);
+                format!("{mantissa}{}", exponent_suffix(exponent, upper))
+            } else {
+                let fixed = fixed(num, (precision as i32 - 1 - exponent).max(0) as usize);
+                trim_zeros(&fixed, spec)
+            };
+            if spec.bang && !body.contains('.') && !body.contains(['e', 'E']) {
+                body.push_str(".0");
+            }
+            body
+        }
+    };
+    if spec.alternate && !body.contains('.') {
+        body = match body.find(['e', 'E']) {
+            Some(i) => format!("{}.{}", &body[..i], &body[i..]),
+            None => format!("{body}."),
+        };
+    }
+
+    spec.pad(sign, &body, true)
+}
+
+/// `%g` drops trailing zeros of the fraction unless the `#` flag is given. The `!` flag keeps
+/// one digit after the decimal point.
+fn trim_zeros(num: &str, spec: &Spec) -> String {
+    if spec.alternate || !num.contains('.') {
+        return num.to_string();
+    }
+
+    let trimmed = num.trim_end_matches('0');
+    match trimmed.strip_suffix('.') {
+        Some(integer) if spec.bang => format!("{integer}.0"),
+        Some(integer) => integer.to_string(),
+        None => trimmed.to_string(),
+    }
+}
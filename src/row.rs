@@ -0,0 +1,140 @@
+use std::rc::Rc;
+
+use anyhow::{bail, Context};
+
+use crate::{column::SerialValue, executor::RowStream, types::FromSql};
+
+/// The rows of a statement, in order, together with the names and declared types of its
+/// columns. Each row is produced when it is taken, so reading one can fail.
+pub struct Rows<'s> {
+    columns: Rc<[String]>,
+    decl_types: Vec<Option<String>>,
+    rows: Box<dyn Iterator<Item = anyhow::Result<Vec<SerialValue>>> + 's>,
+}
+
+/// A column of the rows of a statement.
+pub struct Column<'r> {
+    name: &'r str,
+    decl_type: Option<&'r str>,
+}
+
+/// A result row whose columns can be read by position or by name.
+pub struct Row {
+    pub(crate) columns: Rc<[String]>,
+    pub(crate) values: Vec<SerialValue>,
+}
+
+/// A way to name a column of a row: its position, counting from 0, or its name, in any case.
+pub trait RowIndex {
+    fn index(&self, columns: &[String]) -> anyhow::Result<usize>;
+}
+
+impl RowIndex for usize {
+    fn index(&self, columns: &[String]) -> anyhow::Result<usize> {
+        if *self >= columns.len() {
+            bail!("invalid column index {self}");
+        }
+        Ok(*self)
+    }
+}
+
+impl RowIndex for &str {
+    fn index(&self, columns: &[String]) -> anyhow::Result<usize> {
+        match columns
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(self))
+        {
+            Some(i) => Ok(i),
+            None => bail!("invalid column name: {self}"),
+        }
+    }
+}
+
+impl<'s> From<RowStream<'s>> for Rows<'s> {
+    fn from(stream: RowStream<'s>) -> Self {
+        Self {
+            columns: stream.columns.into(),
+            decl_types: stream.decl_types,
+            rows: stream.rows,
+        }
+    }
+}
+
+impl Column<'_> {
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The type the column was declared with, when the result column is a table column as it
+    /// is, as SQLite's `sqlite3_column_decltype()` gives it.
+    pub fn decl_type(&self) -> Option<&str> {
+        self.decl_type
+    }
+}
+
+impl Rows<'_> {
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(String::as_str).collect()
+    }
+
+    pub fn column_name(&self, index: usize) -> anyhow::Result<&str> {
+        Ok(&self.columns[index.index(&self.columns)?])
+    }
+
+    pub fn column_index(&self, name: &str) -> anyhow::Result<usize> {
+        name.index(&self.columns)
+    }
+
+    /// The declared type of a column, None when it is not a table column as it is or the
+    /// column was declared without one.
+    pub fn column_decltype(&self, index: impl RowIndex) -> anyhow::Result<Option<&str>> {
+        let i = index.index(&self.columns)?;
+        Ok(self.decl_types.get(i).and_then(Option::as_deref))
+    }
+
+    /// The name and declared type of every column.
+    pub fn columns(&self) -> Vec<Column<'_>> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Column {
+                name,
+                decl_type: self.decl_types.get(i).and_then(Option::as_deref),
+            })
+            .collect()
+    }
+}
+
+impl Iterator for Rows<'_> {
+    type Item = anyhow::Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let values = self.rows.next()?;
+        Some(values.map(|values| Row {
+            columns: Rc::clone(&self.columns),
+            values,
+        }))
+    }
+}
+
+impl Row {
+    /// Reads a column as `T`, failing when its value has another type or does not fit.
+    pub fn get<T: FromSql>(&self, index: impl RowIndex) -> anyhow::Result<T> {
+        let i = index.index(&self.columns)?;
+        T::column_result(&self.values[i])
+            .with_context(|| format!("read column {:?}", self.columns[i]))
+    }
+
+    /// The value of a column as it was stored or computed.
+    pub fn get_ref(&self, index: impl RowIndex) -> anyhow::Result<&SerialValue> {
+        Ok(&self.values[index.index(&self.columns)?])
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+}
@@ -0,0 +1,565 @@
+use anyhow::{bail, Context};
+use sqlparser::{
+    ast::{Expr, Statement},
+    dialect::GenericDialect,
+    parser::Parser,
+};
+
+use crate::{
+    column::{Affinity, SerialValue},
+    database::Database,
+    record::Record,
+};
+
+/// The tables and indexes described by the `sqlite_schema` table on page 1.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    pub tables: Vec<TableSchema>,
+    pub indexes: Vec<IndexSchema>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableSchema {
+    pub name: String,
+    pub root_page: usize,
+    pub columns: Vec<ColumnSchema>,
+    /// Position of the INTEGER PRIMARY KEY column, which is stored as NULL and aliases the rowid.
+    pub rowid_alias: Option<usize>,
+    /// Column lists of the PRIMARY KEY and UNIQUE constraints in declaration order. These back
+    /// the `sqlite_autoindex_<table>_<N>` indexes.
+    pub unique_constraints: Vec<Vec<String>>,
+    pub sql: String,
+    /// The number of rows ANALYZE counted, from `sqlite_stat1`. None when the table has not
+    /// been analyzed.
+    pub row_estimate: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub type_name: String,
+    /// The collating sequence named by a COLLATE constraint, in upper case.
+    pub collation: Option<String>,
+    /// The affinity of the declared type, which values are converted to when they are read.
+    pub affinity: Affinity,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexSchema {
+    pub name: String,
+    pub tbl_name: String,
+    pub root_page: usize,
+    pub columns: Vec<IndexColumn>,
+    pub unique: bool,
+    /// The WHERE clause of a partial index, which only holds the rows it is true for.
+    pub predicate: Option<Expr>,
+    pub stats: Option<IndexStats>,
+}
+
+/// What ANALYZE recorded about an index in `sqlite_stat1` and `sqlite_stat4`.
+#[derive(Debug, Clone, Default)]
+pub struct IndexStats {
+    /// The number of entries in the index.
+    pub rows: u64,
+    /// The average number of entries that share their values of the first N columns, for N
+    /// from 1 up to the number of indexed columns.
+    pub per_key: Vec<u64>,
+    /// Entries sampled from the index, in index order.
+    pub samples: Vec<IndexSample>,
+}
+
+/// An index entry from `sqlite_stat4`. The counts are given for each prefix of the indexed
+/// columns followed by the rowid, from the first column alone to the whole entry.
+#[derive(Debug, Clone)]
+pub struct IndexSample {
+    pub key: Vec<SerialValue>,
+    /// The number of entries that equal the sample on the prefix.
+    pub eq: Vec<u64>,
+    /// The number of entries that sort before the sample on the prefix.
+    pub lt: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexColumn {
+    pub name: String,
+    pub desc: bool,
+    pub collation: Option<String>,
+}
+
+impl Schema {
+    pub fn read(db: &Database) -> anyhow::Result<Self> {
+        let mut schema = Self::default();
+        let mut index_rows = Vec::new();
+
+        for (_rowid, record) in db.read_table(1)? {
+            let root_page = record
+                .columns
+                .get(3)
+                .and_then(|column| column.data().as_integer())
+                .unwrap_or_default() as usize;
+
+            match text(&record, 0).as_deref() {
+                Some("table") if root_page > 0 => {
+                    let sql = text(&record, 4).unwrap_or_default();
+                    let mut table = parse_create_table(&sql)
+                        .with_context(|| format!("parse schema of table {:?}", text(&record, 1)))?;
+                    table.name = text(&record, 1).unwrap_or(table.name);
+                    table.root_page = root_page;
+                    schema.tables.push(table);
+                }
+                Some("index") if root_page > 0 => {
+                    index_rows.push((
+                        text(&record, 1),
+                        text(&record, 2),
+                        root_page,
+                        text(&record, 4),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        for (name, tbl_name, root_page, sql) in index_rows {
+            let name = name.unwrap_or_default();
+            let tbl_name = tbl_name.unwrap_or_default();
+
+            let (columns, unique, predicate) = match sql {
+                Some(sql) => parse_create_index(&sql)?,
+                None => {
+                    // Automatic indexes have no SQL. Their columns come from the Nth UNIQUE or
+                    // PRIMARY KEY constraint of the table they belong to.
+                    let n: usize = name
+                        .rsplit('_')
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .unwrap_or_default();
+                    let columns = schema
+                        .table(&tbl_name)
+                        .and_then(|table| table.unique_constraints.get(n.wrapping_sub(1)))
+                        .cloned()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|name| IndexColumn {
+                            name,
+                            desc: false,
+                            collation: None,
+                        })
+                        .collect();
+                    (columns, true, None)
+                }
+            };
+
+            schema.indexes.push(IndexSchema {
+                name,
+                tbl_name,
+                root_page,
+                columns,
+                unique,
+                predicate,
+                stats: None,
+            });
+        }
+
+        schema.read_stats(db)?;
+
+        Ok(schema)
+    }
+
+    /// Reads the statistics that ANALYZE stores in `sqlite_stat1` and `sqlite_stat4`, if the
+    /// database has them. A row of `sqlite_stat1` holds the number of entries of an index and
+    /// the average number of entries per distinct key prefix, or only the number of rows of
+    /// a table without indexes.
+    fn read_stats(&mut self, db: &Database) -> anyhow::Result<()> {
+        let Some(stat1) = self.table("sqlite_stat1").map(|table| table.root_page) else {
+            return Ok(());
+        };
+
+        for (_rowid, record) in db.read_table(stat1)? {
+            let (Some(tbl), Some(stat)) = (text(&record, 0), text(&record, 2)) else {
+                continue;
+            };
+            // Options such as `unordered` or `sz=N` may follow the numbers.
+            let numbers: Vec<u64> = stat
+                .split_whitespace()
+                .map_while(|number| number.parse().ok())
+                .collect();
+            let Some(&rows) = numbers.first() else {
+                continue;
+            };
+
+            let index = text(&record, 1).and_then(|idx| {
+                self.indexes
+                    .iter_mut()
+                    .find(|index| index.name.eq_ignore_ascii_case(&idx))
+            });
+            if let Some(index) = index {
+                index.stats = Some(IndexStats {
+                    rows,
+                    per_key: numbers[1..].to_vec(),
+                    samples: Vec::new(),
+                });
+            }
+
+            let table = self
+                .tables
+                .iter_mut()
+                .find(|table| table.name.eq_ignore_ascii_case(&tbl));
+            if let Some(table) = table {
+                table.row_estimate = Some(table.row_estimate.unwrap_or_default().max(rows));
+            }
+        }
+
+        let Some(stat4) = self.table("sqlite_stat4").map(|table| table.root_page) else {
+            return Ok(());
+        };
+
+        for (_rowid, record) in db.read_table(stat4)? {
+            let stats = text(&record, 1).and_then(|idx| {
+                self.indexes
+                    .iter_mut()
+                    .find(|index| index.name.eq_ignore_ascii_case(&idx))
+                    .and_then(|index| index.stats.as_mut())
+            });
+            let Some(stats) = stats else {
+                continue;
+            };
+            let Some(SerialValue::Blob(sample)) = record.columns.get(5).map(|column| column.data())
+            else {
+                continue;
+            };
+            let Ok(sample) = Record::new(sample) else {
+                continue;
+            };
+
+            let counts = |i: usize| -> Vec<u64> {
+                text(&record, i)
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .filter_map(|number| number.parse().ok())
+                    .collect()
+            };
+            stats.samples.push(IndexSample {
+                key: sample
+                    .columns
+                    .iter()
+                    .map(|column| column.data().clone())
+                    .collect(),
+                eq: counts(2),
+                lt: counts(3),
+            });
+        }
+
+        for stats in self
+            .indexes
+            .iter_mut()
+            .filter_map(|index| index.stats.as_mut())
+        {
+            stats
+                .samples
+                .sort_by_key(|sample| sample.lt.last().copied().unwrap_or_default());
+        }
+
+        Ok(())
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables
+            .iter()
+            .find(|table| table.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn indexes_of(&self, tbl_name: &str) -> impl Iterator<Item = &IndexSchema> {
+        let tbl_name = tbl_name.to_string();
+        self.indexes
+            .iter()
+            .filter(move |index| index.tbl_name.eq_ignore_ascii_case(&tbl_name))
+    }
+}
+
+impl TableSchema {
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Parses a CREATE INDEX statement into its key columns, whether it is UNIQUE and the WHERE
+/// clause of a partial index.
+fn parse_create_index(sql: &str) -> anyhow::Result<(Vec<IndexColumn>, bool, Option<Expr>)> {
+    let statements = Parser::parse_sql(&GenericDialect {}, sql)?;
+    let Some(Statement::CreateIndex {
+        columns,
+        unique,
+        predicate,
+        ..
+    }) = statements.first()
+    else {
+        bail!("not a CREATE INDEX statement: {sql}");
+    };
+
+    let columns = columns
+        .iter()
+        .map(|column| {
+            let (name, collation) = match &column.expr {
+                Expr::Collate { expr, collation } => {
+                    (expr.to_string(), Some(collation.to_string().to_uppercase()))
+                }
+                expr => (expr.to_string(), None),
+            };
+
+            IndexColumn {
+                name: unquote(&name),
+                desc: column.asc == Some(false),
+                collation,
+            }
+        })
+        .collect();
+
+    Ok((columns, *unique, predicate.clone()))
+}
+
+const CONSTRAINT_KEYWORDS: [&str; 11] = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+];
+
+/// Parses a CREATE TABLE statement.
+///
+/// SQLite allows columns without a declared type, e.g. `CREATE TABLE sqlite_sequence(name,seq)`,
+/// which `sqlparser` rejects, so the column definitions are split up by hand.
+fn parse_create_table(sql: &str) -> anyhow::Result<TableSchema> {
+    let tokens = tokenize(sql);
+    let Some(open) = tokens.iter().position(|token| token == "(") else {
+        bail!("not a CREATE TABLE statement: {sql}");
+    };
+    let name = tokens[..open]
+        .last()
+        .map(|name| unquote(name))
+        .unwrap_or_default();
+
+    let mut definitions: Vec<Vec<String>> = vec![Vec::new()];
+    let mut depth = 0;
+    for token in &tokens[open + 1..] {
+        match token.as_str() {
+            "(" => depth += 1,
+            ")" if depth == 0 => break,
+            ")" => depth -= 1,
+            "," if depth == 0 => {
+                definitions.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(definition) = definitions.last_mut() {
+            definition.push(token.clone());
+        }
+    }
+
+    let mut columns: Vec<ColumnSchema> = Vec::new();
+    let mut primary_key: Option<Vec<String>> = None;
+    let mut unique_constraints = Vec::new();
+    let mut integer_key: Option<usize> = None;
+
+    for definition in definitions.into_iter().filter(|tokens| !tokens.is_empty()) {
+        let keyword = definition[0].to_uppercase();
+        if matches!(
+            keyword.as_str(),
+            "CONSTRAINT" | "PRIMARY" | "UNIQUE" | "CHECK" | "FOREIGN"
+        ) {
+            let upper: Vec<String> = definition.iter().map(|t| t.to_uppercase()).collect();
+            let is_primary = upper.iter().any(|t| t == "PRIMARY");
+            if is_primary || upper.iter().any(|t| t == "UNIQUE") {
+                let key_columns = parenthesized_names(&definition);
+                if is_primary {
+                    primary_key = Some(key_columns.clone());
+                }
+                unique_constraints.push(key_columns);
+            }
+            continue;
+        }
+
+        let name = unquote(&definition[0]);
+        let type_end = definition[1..]
+            .iter()
+            .position(|token| CONSTRAINT_KEYWORDS.contains(&token.to_uppercase().as_str()))
+            .map(|i| i + 1)
+            .unwrap_or(definition.len());
+        let type_name = join_type_name(&definition[1..type_end]);
+
+        let upper: Vec<String> = definition[type_end..]
+            .iter()
+            .map(|t| t.to_uppercase())
+            .collect();
+        if let Some(i) = upper.iter().position(|t| t == "PRIMARY") {
+            let desc = upper.get(i + 2).is_some_and(|t| t == "DESC");
+            if type_name.eq_ignore_ascii_case("INTEGER") && !desc {
+                integer_key = Some(columns.len());
+            }
+            primary_key = Some(vec![name.clone()]);
+            unique_constraints.push(vec![name.clone()]);
+        } else if upper.iter().any(|t| t == "UNIQUE") {
+            unique_constraints.push(vec![name.clone()]);
+        }
+
+        let collation = upper
+            .iter()
+            .position(|t| t == "COLLATE")
+            .and_then(|i| definition.get(type_end + i + 1))
+            .map(|name| unquote(name).to_uppercase());
+
+        columns.push(ColumnSchema {
+            name,
+            affinity: Affinity::from_type_name(&type_name),
+            type_name,
+            collation,
+        });
+    }
+
+    // A table-level PRIMARY KEY on a single INTEGER column also aliases the rowid.
+    let rowid_alias = match &primary_key {
+        Some(key) if key.len() == 1 => columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(&key[0]))
+            .filter(|&i| {
+                integer_key == Some(i) || columns[i].type_name.eq_ignore_ascii_case("INTEGER")
+            }),
+        _ => None,
+    };
+
+    // The rowid alias is the b-tree key itself, so it has no automatic index.
+    if let Some(alias) = rowid_alias {
+        let alias = &columns[alias].name;
+        unique_constraints.retain(|key| !(key.len() == 1 && key[0].eq_ignore_ascii_case(alias)));
+    }
+
+    Ok(TableSchema {
+        name,
+        root_page: 0,
+        columns,
+        rowid_alias,
+        unique_constraints,
+        sql: sql.to_string(),
+        row_estimate: None,
+    })
+}
+
+/// Returns the text in column `i` of a record.
+fn text(record: &Record, i: usize) -> Option<String> {
+    match record.columns.get(i).map(|column| column.data()) {
+        Some(SerialValue::String(str)) => Some(str.clone()),
+        _ => None,
+    }
+}
+
+/// Returns the names listed in the first parenthesized group of a table constraint.
+fn parenthesized_names(tokens: &[String]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut inside = false;
+    let mut expect_name = true;
+
+    for token in tokens {
+        match token.as_str() {
+            "(" if !inside => inside = true,
+            ")" if inside => break,
+            "," if inside => expect_name = true,
+            token if inside && expect_name => {
+                names.push(unquote(token));
+                expect_name = false;
+            }
+            _ => {}
+        }
+    }
+
+    names
+}
+
+fn join_type_name(tokens: &[String]) -> String {
+    let mut type_name = String::new();
+    for token in tokens {
+        if !type_name.is_empty()
+            && !matches!(token.as_str(), "(" | ")" | ",")
+            && !type_name.ends_with('(')
+        {
+            type_name.push(' ');
+        }
+        type_name.push_str(token);
+    }
+
+    type_name
+}
+
+/// Splits SQL into identifiers, quoted names and strings, numbers and single punctuation.
+fn tokenize(sql: &str) -> Vec<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        if c.is_whitespace() {
+            idx += 1;
+        } else if c == '-' && chars.get(idx + 1) == Some(&'-') {
+            while idx < chars.len() && chars[idx] != '\n' {
+                idx += 1;
+            }
+        } else if c == '/' && chars.get(idx + 1) == Some(&'*') {
+            idx += 2;
+            while idx < chars.len() && !(chars[idx] == '*' && chars.get(idx + 1) == Some(&'/')) {
+                idx += 1;
+            }
+            idx += 2;
+        } else if matches!(c, '"' | '\'' | '`' | '[') {
+            let close = if c == '[' { ']' } else { c };
+            let start = idx;
+            idx += 1;
+            while idx < chars.len() {
+                if chars[idx] == close {
+                    if close != ']' && chars.get(idx + 1) == Some(&close) {
+                        idx += 2;
+                        continue;
+                    }
+                    break;
+                }
+                idx += 1;
+            }
+            idx += 1;
+            tokens.push(chars[start..idx.min(chars.len())].iter().collect());
+        } else if c.is_alphanumeric() || c == '_' || c == '$' {
+            let start = idx;
+            while idx < chars.len()
+                && (chars[idx].is_alphanumeric() || chars[idx] == '_' || chars[idx] == '$')
+            {
+                idx += 1;
+            }
+            tokens.push(chars[start..idx].iter().collect());
+        } else {
+            tokens.push(c.to_string());
+            idx += 1;
+        }
+    }
+
+    tokens
+}
+
+/// Removes SQL identifier quoting.
+pub fn unquote(name: &str) -> String {
+    let mut chars = name.chars();
+    match (chars.next(), name.chars().last()) {
+        (Some(open @ ('"' | '`' | '\'')), Some(close)) if open == close && name.len() >= 2 => {
+            let doubled = format!("{open}{open}");
+            name[1..name.len() - 1].replace(&doubled, &open.to_string())
+        }
+        (Some('['), Some(']')) => name[1..name.len() - 1].to_string(),
+        _ => name.to_string(),
+    }
+}
@@ -0,0 +1,335 @@
+use std::ops::ControlFlow;
+
+use anyhow::bail;
+use sqlparser::ast::{visit_expressions_mut, Expr, Ident, Query, SelectItem, SetExpr, Value};
+
+use crate::{
+    column::SerialValue,
+    executor::{parse_query, Executor, ResultSet},
+    explain::{explain_query_plan, QueryPlan},
+    expr::has_parameter,
+    row::{Row, Rows},
+    types::ToSql,
+};
+
+/// The largest parameter number, as in SQLite.
+const MAX_PARAMETER: usize = 32766;
+
+/// A SELECT that is parsed once and then run any number of times with the values bound to its
+/// parameters: `?`, `?NNN`, `:name`, `@name` and `$name`. The first run, or the first query
+/// plan asked for, chooses how to read the tables, and every later run reads them the same way.
+/// The values are read as the statement runs and never pass through SQL text. Parameters that
+/// are not bound are NULL.
+pub struct Statement<'a> {
+    /// Keeps the plan of the statement between runs.
+    executor: Executor<'a>,
+    /// The statement with each parameter written as `?NNN`, its number.
+    query: Query,
+    /// Set when the statement starts with `EXPLAIN QUERY PLAN`.
+    explain: bool,
+    /// The name of each parameter by its number minus one. `?` and `?NNN` have no name.
+    names: Vec<Option<String>>,
+    values: Vec<SerialValue>,
+}
+
+impl<'a> Executor<'a> {
+    /// Parses a statement so that it can be run many times with different parameters.
+    pub fn prepare(&self, sql: &str) -> anyhow::Result<Statement<'a>> {
+        let (sql, explain) = match explain_query_plan(sql) {
+            Some(sql) => (sql, true),
+            None => (sql, false),
+        };
+        let mut query = parse_query(sql)?;
+        let (names, slots) = number_parameters(&mut query)?;
+        name_parameter_columns(&mut query.body);
+        write_parameter_numbers(&mut query, &slots);
+
+        let executor = self.clone();
+        executor.plans.take();
+
+        Ok(Statement {
+            executor,
+            query,
+            explain,
+            values: vec![SerialValue::Null; names.len()],
+            names,
+        })
+    }
+}
+
+impl Statement<'_> {
+    /// The largest parameter number of the statement.
+    pub fn parameter_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The number of the parameter called `name`, including its `:`, `@` or `$` prefix.
+    pub fn parameter_index(&self, name: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .position(|other| other.as_deref() == Some(name))
+            .map(|i| i + 1)
+    }
+
+    /// The name of parameter number `index`, if it has one.
+    pub fn parameter_name(&self, index: usize) -> Option<&str> {
+        self.names.get(index.checked_sub(1)?)?.as_deref()
+    }
+
+    /// Binds a value to parameter number `index`, counting from 1.
+    pub fn bind(&mut self, index: usize, value: impl Into<SerialValue>) -> anyhow::Result<()> {
+        let Some(slot) = index.checked_sub(1).and_then(|i| self.values.get_mut(i)) else {
+            bail!("parameter index {index} out of range");
+        };
+        *slot = value.into();
+
+        Ok(())
+    }
+
+    /// Binds a value to the parameter called `name`.
+    pub fn bind_name(&mut self, name: &str, value: impl Into<SerialValue>) -> anyhow::Result<()> {
+        let Some(index) = self.parameter_index(name) else {
+            bail!("no such parameter: {name}");
+        };
+
+        self.bind(index, value)
+    }
+
+    /// Sets every parameter back to NULL.
+    pub fn clear_bindings(&mut self) {
+        self.values.fill(SerialValue::Null);
+    }
+
+    /// True when the statement starts with `EXPLAIN QUERY PLAN`.
+    pub fn is_explain(&self) -> bool {
+        self.explain
+    }
+
+    /// Runs the statement with the values bound so far. The tables are read the way the
+    /// statement chose the first time, and the keys searched for come from the values.
+    pub fn execute(&self) -> anyhow::Result<ResultSet> {
+        if self.explain {
+            return Ok(self.query_plan()?.into());
+        }
+        self.executor.execute_planned(&self.query, &self.values)
+    }
+
+    /// The plan the statement runs with. Before the first run, the plan is chosen with the
+    /// values bound so far.
+    pub fn query_plan(&self) -> anyhow::Result<QueryPlan> {
+        self.executor.explain_planned(&self.query, &self.values)
+    }
+
+    /// Binds `params` and runs the statement, returning its rows as they are produced.
+    pub fn query(&mut self, params: impl Params) -> anyhow::Result<Rows<'_>> {
+        params.bind_to(self)?;
+
+        let stream = if self.explain {
+            ResultSet::from(self.query_plan()?).into()
+        } else {
+            self.executor.stream_planned(&self.query, &self.values)?
+        };
+        Ok(stream.into())
+    }
+
+    /// Binds `params`, runs the statement and converts each of its rows with `f`.
+    pub fn query_map<'s, T, F>(
+        &'s mut self,
+        params: impl Params,
+        mut f: F,
+    ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<T>> + 's>
+    where
+        F: FnMut(&Row) -> anyhow::Result<T> + 's,
+        T: 's,
+    {
+        Ok(self.query(params)?.map(move |row| f(&row?)))
+    }
+
+    /// Binds `params`, runs the statement and converts its first row with `f`.
+    pub fn query_row<T, F>(&mut self, params: impl Params, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&Row) -> anyhow::Result<T>,
+    {
+        match self.query(params)?.next() {
+            Some(row) => f(&row?),
+            None => bail!("query returned no rows"),
+        }
+    }
+}
+
+/// Values to bind to the parameters of a statement before it runs. A list binds the
+/// parameters by number and must have one value for each of them; pairs of a name and a value
+/// bind by name; `()` binds nothing and keeps the values bound so far.
+pub trait Params {
+    fn bind_to(self, statement: &mut Statement) -> anyhow::Result<()>;
+}
+
+impl Params for () {
+    fn bind_to(self, _statement: &mut Statement) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: ToSql> Params for &[T] {
+    fn bind_to(self, statement: &mut Statement) -> anyhow::Result<()> {
+        if self.len() != statement.parameter_count() {
+            bail!(
+                "wrong number of parameters: expected {}, got {}",
+                statement.parameter_count(),
+                self.len()
+            );
+        }
+        for (i, value) in self.iter().enumerate() {
+            statement.bind(i + 1, value.to_sql()?)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: ToSql, const N: usize> Params for [T; N] {
+    fn bind_to(self, statement: &mut Statement) -> anyhow::Result<()> {
+        self.as_slice().bind_to(statement)
+    }
+}
+
+impl Params for &[(&str, &dyn ToSql)] {
+    fn bind_to(self, statement: &mut Statement) -> anyhow::Result<()> {
+        for (name, value) in self {
+            statement.bind_name(name, value.to_sql()?)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Params for [(&str, &dyn ToSql); N] {
+    fn bind_to(self, statement: &mut Statement) -> anyhow::Result<()> {
+        self.as_slice().bind_to(statement)
+    }
+}
+
+/// Builds the values of positional parameters from values of different types.
+#[macro_export]
+macro_rules! params {
+    () => {
+        &[] as &[&dyn $crate::types::ToSql]
+    };
+    ($($value:expr),+ $(,)?) => {
+        [$(&$value as &dyn $crate::types::ToSql),+]
+    };
+}
+
+/// Builds the values of named parameters: `named_params! { ":id": 1, ":name": "x" }`.
+#[macro_export]
+macro_rules! named_params {
+    () => {
+        &[] as &[(&str, &dyn $crate::types::ToSql)]
+    };
+    ($($name:literal: $value:expr),+ $(,)?) => {
+        [$(($name, &$value as &dyn $crate::types::ToSql)),+]
+    };
+}
+
+/// Numbers the parameters the way SQLite does: `?NNN` is number NNN, a name keeps the number
+/// it got first, and `?` or a new name takes the number after the largest so far. `@name`,
+/// which parses as an identifier, becomes a placeholder. Returns the name of each number and
+/// the number of each placeholder.
+fn number_parameters(query: &mut Query) -> anyhow::Result<(Vec<Option<String>>, Vec<usize>)> {
+    let mut names: Vec<Option<String>> = Vec::new();
+    let mut slots = Vec::new();
+
+    let flow = visit_expressions_mut(query, |expr| {
+        if let Expr::Identifier(Ident {
+            value,
+            quote_style: None,
+        }) = expr
+        {
+            if value.len() > 1 && value.starts_with('@') {
+                *expr = Expr::Value(Value::Placeholder(value.clone()));
+            }
+        }
+        let Expr::Value(Value::Placeholder(placeholder)) = expr else {
+            return ControlFlow::Continue(());
+        };
+
+        let n = if placeholder == "?" {
+            names.push(None);
+            names.len()
+        } else if let Some(number) = placeholder.strip_prefix('?') {
+            match number.parse::<usize>() {
+                Ok(n) if (1..=MAX_PARAMETER).contains(&n) => {
+                    if n > names.len() {
+                        names.resize(n, None);
+                    }
+                    n
+                }
+                _ => {
+                    return ControlFlow::Break(anyhow::anyhow!(
+                        "variable number must be between ?1 and ?{MAX_PARAMETER}"
+                    ))
+                }
+            }
+        } else {
+            match names
+                .iter()
+                .position(|name| name.as_deref() == Some(placeholder.as_str()))
+            {
+                Some(i) => i + 1,
+                None => {
+                    names.push(Some(placeholder.clone()));
+                    names.len()
+                }
+            }
+        };
+        if n > MAX_PARAMETER {
+            return ControlFlow::Break(anyhow::anyhow!("too many SQL variables"));
+        }
+
+        slots.push(n);
+        ControlFlow::Continue(())
+    });
+
+    match flow {
+        ControlFlow::Break(err) => Err(err),
+        ControlFlow::Continue(()) => Ok((names, slots)),
+    }
+}
+
+/// Names a result column that holds a parameter after the text of its expression, as if no
+/// value had replaced the parameter.
+fn name_parameter_columns(body: &mut SetExpr) {
+    match body {
+        SetExpr::Select(select) => {
+            for item in &mut select.projection {
+                let SelectItem::UnnamedExpr(expr) = item else {
+                    continue;
+                };
+                if !has_parameter(expr) {
+                    continue;
+                }
+
+                let alias = Ident::new(expr.to_string());
+                let expr = std::mem::replace(expr, Expr::Value(Value::Null));
+                *item = SelectItem::ExprWithAlias { expr, alias };
+            }
+        }
+        SetExpr::SetOperation { left, .. } => name_parameter_columns(left),
+        SetExpr::Query(query) => name_parameter_columns(&mut query.body),
+        _ => {}
+    }
+}
+
+/// Writes each placeholder as the number `slots` gives it, in the order they are visited, so
+/// that it reads the value bound to that number.
+fn write_parameter_numbers(query: &mut Query, slots: &[usize]) {
+    let mut slots = slots.iter();
+    let _ = visit_expressions_mut(query, |expr| {
+        if let Expr::Value(Value::Placeholder(placeholder)) = expr {
+            if let Some(n) = slots.next() {
+                *placeholder = format!("?{n}");
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
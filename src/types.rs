@@ -0,0 +1,163 @@
+use anyhow::bail;
+
+use crate::{column::SerialValue, function::type_name};
+
+/// A Rust value that can be bound to a parameter.
+pub trait ToSql {
+    fn to_sql(&self) -> anyhow::Result<SerialValue>;
+}
+
+/// A Rust value that a column of a result row can be read as.
+pub trait FromSql: Sized {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self>;
+}
+
+impl<T: ToSql + ?Sized> ToSql for &T {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        (**self).to_sql()
+    }
+}
+
+impl<T: ToSql> ToSql for Option<T> {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        match self {
+            Some(value) => value.to_sql(),
+            None => Ok(SerialValue::Null),
+        }
+    }
+}
+
+impl ToSql for SerialValue {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok(self.clone())
+    }
+}
+
+impl ToSql for bool {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok((*self).into())
+    }
+}
+
+macro_rules! integer_to_sql {
+    ($($ty:ty),*) => {
+        $(impl ToSql for $ty {
+            fn to_sql(&self) -> anyhow::Result<SerialValue> {
+                match i64::try_from(*self) {
+                    Ok(num) => Ok(SerialValue::I64(num)),
+                    Err(_) => bail!("integer {self} is too large"),
+                }
+            }
+        })*
+    };
+}
+
+integer_to_sql!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl ToSql for f32 {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok(SerialValue::Float64(*self as f64))
+    }
+}
+
+impl ToSql for f64 {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok(SerialValue::Float64(*self))
+    }
+}
+
+impl ToSql for str {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok(self.into())
+    }
+}
+
+impl ToSql for String {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok(self.as_str().into())
+    }
+}
+
+impl ToSql for [u8] {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok(self.into())
+    }
+}
+
+impl ToSql for Vec<u8> {
+    fn to_sql(&self) -> anyhow::Result<SerialValue> {
+        Ok(self.as_slice().into())
+    }
+}
+
+impl<T: FromSql> FromSql for Option<T> {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+        match value {
+            SerialValue::Null => Ok(None),
+            value => T::column_result(value).map(Some),
+        }
+    }
+}
+
+impl FromSql for SerialValue {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl FromSql for bool {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+        i64::column_result(value).map(|num| num != 0)
+    }
+}
+
+macro_rules! integer_from_sql {
+    ($($ty:ty),*) => {
+        $(impl FromSql for $ty {
+            fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+                let Some(num) = value.as_integer() else {
+                    bail!("invalid column type {}, expected integer", type_name(value));
+                };
+                match <$ty>::try_from(num) {
+                    Ok(num) => Ok(num),
+                    Err(_) => bail!("integer {num} is out of range for {}", stringify!($ty)),
+                }
+            }
+        })*
+    };
+}
+
+integer_from_sql!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromSql for f64 {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+        match value.as_real() {
+            Some(num) => Ok(num),
+            None => bail!("invalid column type {}, expected real", type_name(value)),
+        }
+    }
+}
+
+impl FromSql for f32 {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+        f64::column_result(value).map(|num| num as f32)
+    }
+}
+
+impl FromSql for String {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+        match value {
+            SerialValue::String(txt) => Ok(txt.clone()),
+            value => bail!("invalid column type {}, expected text", type_name(value)),
+        }
+    }
+}
+
+impl FromSql for Vec<u8> {
+    fn column_result(value: &SerialValue) -> anyhow::Result<Self> {
+        match value {
+            SerialValue::Blob(bytes) => Ok(bytes.clone()),
+            value => bail!("invalid column type {}, expected blob", type_name(value)),
+        }
+    }
+}
@@ -0,0 +1,640 @@
+use std::{cmp::Ordering, collections::HashMap, ops::ControlFlow};
+
+use anyhow::bail;
+use sqlparser::ast::{
+    Expr, Function, FunctionArg, FunctionArgExpr, NamedWindowDefinition, Query, Visit, Visitor,
+    WindowFrame, WindowFrameBound, WindowFrameUnits, WindowSpec, WindowType,
+};
+
+use crate::{
+    aggregate::{Accumulator, AggregateCall},
+    collation::Collation,
+    column::SerialValue,
+    expr::{collation, eval, ColumnName, Context, Scope},
+    order::{strip_collate, SortTerm},
+};
+
+/// A row on its way to the projection, together with the results of the aggregate and window
+/// calls that the projection reads, keyed by the text of the call.
+pub type WindowRow = (Vec<SerialValue>, HashMap<String, SerialValue>);
+
+#[derive(Debug, Clone)]
+pub enum WindowFunc {
+    RowNumber,
+    Rank,
+    DenseRank,
+    PercentRank,
+    CumeDist,
+    Ntile,
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+    NthValue,
+    /// An aggregate function computed over the frame of each row.
+    Aggregate(Box<AggregateCall>),
+}
+
+/// A function call with an OVER clause.
+#[derive(Debug, Clone)]
+pub struct WindowCall {
+    /// The text of the call, which the evaluator uses to find its result.
+    pub key: String,
+    pub func: WindowFunc,
+    pub args: Vec<Expr>,
+    pub spec: WindowSpec,
+}
+
+impl WindowCall {
+    pub fn from_function(
+        function: &Function,
+        named_windows: &[NamedWindowDefinition],
+    ) -> anyhow::Result<Option<Self>> {
+        let spec = match &function.over {
+            None => return Ok(None),
+            Some(WindowType::WindowSpec(spec)) => spec.clone(),
+            Some(WindowType::NamedWindow(name)) => {
+                match named_windows
+                    .iter()
+                    .find(|window| window.0.value.eq_ignore_ascii_case(&name.value))
+                {
+                    Some(window) => window.1.clone(),
+                    None => bail!("no such window: {name}"),
+                }
+            }
+        };
+
+        let name = function.name.to_string();
+        let (func, nargs) = match name.to_lowercase().as_str() {
+            "row_number" => (WindowFunc::RowNumber, 0..=0),
+            "rank" => (WindowFunc::Rank, 0..=0),
+            "dense_rank" => (WindowFunc::DenseRank, 0..=0),
+            "percent_rank" => (WindowFunc::PercentRank, 0..=0),
+            "cume_dist" => (WindowFunc::CumeDist, 0..=0),
+            "ntile" => (WindowFunc::Ntile, 1..=1),
+            "lag" => (WindowFunc::Lag, 1..=3),
+            "lead" => (WindowFunc::Lead, 1..=3),
+            "first_value" => (WindowFunc::FirstValue, 1..=1),
+            "last_value" => (WindowFunc::LastValue, 1..=1),
+            "nth_value" => (WindowFunc::NthValue, 2..=2),
+            _ => {
+                let aggregate = Function {
+                    over: None,
+                    ..function.clone()
+                };
+                match AggregateCall::from_function(&aggregate)? {
+                    Some(call) if call.distinct => {
+                        bail!("DISTINCT is not supported for window functions")
+                    }
+                    Some(call) => {
+                        return Ok(Some(Self {
+                            key: function.to_string(),
+                            args: call.args.clone(),
+                            func: WindowFunc::Aggregate(Box::new(call)),
+                            spec,
+                        }))
+                    }
+                    None => bail!("{name}() may not be used as a window function"),
+                }
+            }
+        };
+
+        let args = function
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr.clone()),
+                _ => bail!("wrong arguments to function {name}()"),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if !nargs.contains(&args.len()) {
+            bail!("wrong number of arguments to function {name}()");
+        }
+        if function.filter.is_some() {
+            bail!("FILTER clause may only be used with aggregate window functions");
+        }
+
+        Ok(Some(Self {
+            key: function.to_string(),
+            func,
+            args,
+            spec,
+        }))
+    }
+}
+
+/// Collects the window function calls in `exprs`, skipping those that belong to subqueries.
+pub fn collect_windows<'a>(
+    exprs: impl IntoIterator<Item = &'a Expr>,
+    named_windows: &[NamedWindowDefinition],
+) -> anyhow::Result<Vec<WindowCall>> {
+    struct WindowVisitor<'w> {
+        calls: Vec<WindowCall>,
+        named_windows: &'w [NamedWindowDefinition],
+        query_depth: usize,
+    }
+
+    impl Visitor for WindowVisitor<'_> {
+        type Break = anyhow::Error;
+
+        fn pre_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+            self.query_depth += 1;
+            ControlFlow::Continue(())
+        }
+
+        fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+            self.query_depth -= 1;
+            ControlFlow::Continue(())
+        }
+
+        fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+            if let (Expr::Function(function), 0) = (expr, self.query_depth) {
+                match WindowCall::from_function(function, self.named_windows) {
+                    Ok(Some(call)) => {
+                        if !self.calls.iter().any(|c| c.key == call.key) {
+                            self.calls.push(call);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => return ControlFlow::Break(err),
+                }
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut visitor = WindowVisitor {
+        calls: Vec::new(),
+        named_windows,
+        query_depth: 0,
+    };
+    for expr in exprs {
+        if let ControlFlow::Break(err) = expr.visit(&mut visitor) {
+            return Err(err);
+        }
+    }
+
+    Ok(visitor.calls)
+}
+
+/// Computes the window calls for every row and stores their results with the row. Calls that
+/// share a window are computed together. Like SQLite, the windows are processed from the last
+/// one to the first, each sorting the rows by its partition and order, so that the rows end up
+/// in the order of the first window.
+pub fn compute_windows(
+    calls: &[WindowCall],
+    columns: &[ColumnName],
+    rows: &mut Vec<WindowRow>,
+    context: Context,
+) -> anyhow::Result<()> {
+    let mut specs: Vec<&WindowSpec> = Vec::new();
+    for call in calls {
+        if !specs.contains(&&call.spec) {
+            specs.push(&call.spec);
+        }
+    }
+
+    for spec in specs.into_iter().rev() {
+        let calls: Vec<&WindowCall> = calls.iter().filter(|call| call.spec == *spec).collect();
+        compute_window(spec, &calls, columns, rows, context)?;
+    }
+
+    Ok(())
+}
+
+fn compute_window(
+    spec: &WindowSpec,
+    calls: &[&WindowCall],
+    columns: &[ColumnName],
+    rows: &mut Vec<WindowRow>,
+    context: Context,
+) -> anyhow::Result<()> {
+    let scope = Scope::new(columns, &[]);
+    let mut terms = Vec::new();
+    let mut key_exprs = Vec::new();
+    for expr in &spec.partition_by {
+        let collation = collation(expr, &scope)?.unwrap_or_default();
+        terms.push(SortTerm::ascending(key_exprs.len(), collation));
+        key_exprs.push(expr);
+    }
+    for order in &spec.order_by {
+        let (expr, explicit) = strip_collate(&order.expr)?;
+        let collation = explicit.or(collation(expr, &scope)?).unwrap_or_default();
+        terms.push(SortTerm::new(key_exprs.len(), order, collation));
+        key_exprs.push(expr);
+    }
+    let (partition_terms, order_terms) = terms.split_at(spec.partition_by.len());
+
+    let mut keyed = std::mem::take(rows)
+        .into_iter()
+        .map(|row| {
+            let key = evaluate(key_exprs.iter().copied(), &row, columns, context)?;
+            Ok((key, row))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    keyed.sort_by(|(a, _), (b, _)| compare(&terms, a, b));
+    let (keys, sorted): (Vec<_>, Vec<_>) = keyed.into_iter().unzip();
+    *rows = sorted;
+
+    let frame = Frame::new(spec, context)?;
+    let mut start = 0;
+    while start < rows.len() {
+        let end = (start..rows.len())
+            .find(|&i| compare(partition_terms, &keys[start], &keys[i]).is_ne())
+            .unwrap_or(rows.len());
+        let partition = Partition::new(&keys[start..end], order_terms);
+
+        for call in calls {
+            let args = rows[start..end]
+                .iter()
+                .map(|row| match &call.func {
+                    WindowFunc::Aggregate(aggregate) => {
+                        aggregate.eval_args(&row_scope(row, columns, context))
+                    }
+                    _ => evaluate(&call.args, row, columns, context),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let (filter, collation) = match &call.func {
+                WindowFunc::Aggregate(aggregate) => {
+                    (aggregate.filter.as_ref(), aggregate.collation(&scope)?)
+                }
+                _ => (None, Collation::Binary),
+            };
+            let included = match filter {
+                Some(filter) => rows[start..end]
+                    .iter()
+                    .map(|row| {
+                        let value = evaluate([filter], row, columns, context)?;
+                        Ok(value[0].as_bool() == Some(true))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+                None => vec![true; end - start],
+            };
+
+            let results = partition.compute(call, collation, &frame, &args, &included)?;
+            for (row, result) in rows[start..end].iter_mut().zip(results) {
+                row.1.insert(call.key.clone(), result);
+            }
+        }
+
+        start = end;
+    }
+
+    Ok(())
+}
+
+fn evaluate<'e>(
+    exprs: impl IntoIterator<Item = &'e Expr>,
+    row: &WindowRow,
+    columns: &[ColumnName],
+    context: Context,
+) -> anyhow::Result<Vec<SerialValue>> {
+    let scope = row_scope(row, columns, context);
+    exprs.into_iter().map(|expr| eval(expr, &scope)).collect()
+}
+
+/// The scope of a row, which sees the results of the window calls computed before.
+fn row_scope<'a>(row: &'a WindowRow, columns: &'a [ColumnName], context: Context<'a>) -> Scope<'a> {
+    Scope {
+        columns,
+        row: &row.0,
+        aggregates: Some(&row.1),
+        context,
+    }
+}
+
+fn compare(terms: &[SortTerm], a: &[SerialValue], b: &[SerialValue]) -> Ordering {
+    terms
+        .iter()
+        .map(|term| term.compare(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Bound {
+    UnboundedPreceding,
+    Preceding(f64),
+    CurrentRow,
+    Following(f64),
+    UnboundedFollowing,
+}
+
+/// The frame of a window. Without a frame specification it reaches from the start of the
+/// partition to the last peer of the current row.
+struct Frame {
+    units: WindowFrameUnits,
+    start: Bound,
+    end: Bound,
+}
+
+impl Frame {
+    fn new(spec: &WindowSpec, context: Context) -> anyhow::Result<Self> {
+        let Some(WindowFrame {
+            units,
+            start_bound,
+            end_bound,
+        }) = &spec.window_frame
+        else {
+            return Ok(Self {
+                units: WindowFrameUnits::Range,
+                start: Bound::UnboundedPreceding,
+                end: Bound::CurrentRow,
+            });
+        };
+
+        let start = bound(start_bound, *units, "starting", context)?;
+        let end = match end_bound {
+            Some(end_bound) => bound(end_bound, *units, "ending", context)?,
+            None => Bound::CurrentRow,
+        };
+        if matches!(start, Bound::UnboundedFollowing) || matches!(end, Bound::UnboundedPreceding) {
+            bail!("unsupported frame specification");
+        }
+
+        let offset = |bound| matches!(bound, Bound::Preceding(_) | Bound::Following(_));
+        if *units == WindowFrameUnits::Range
+            && (offset(start) || offset(end))
+            && spec.order_by.len() != 1
+        {
+            bail!("RANGE with offset PRECEDING/FOLLOWING requires one ORDER BY expression");
+        }
+
+        Ok(Self {
+            units: *units,
+            start,
+            end,
+        })
+    }
+}
+
+fn bound(
+    bound: &WindowFrameBound,
+    units: WindowFrameUnits,
+    which: &str,
+    context: Context,
+) -> anyhow::Result<Bound> {
+    let offset = |expr: &Expr| -> anyhow::Result<f64> {
+        let value = eval(expr, &Scope::within(context, &[], &[]))?.numeric_affinity();
+        match (units, value.as_integer(), value.as_real()) {
+            (_, Some(num), _) if num >= 0 => Ok(num as f64),
+            (WindowFrameUnits::Range, None, Some(num)) if num >= 0.0 => Ok(num),
+            (WindowFrameUnits::Range, _, _) => {
+                bail!("frame {which} offset must be a non-negative number")
+            }
+            _ => bail!("frame {which} offset must be a non-negative integer"),
+        }
+    };
+
+    Ok(match bound {
+        WindowFrameBound::CurrentRow => Bound::CurrentRow,
+        WindowFrameBound::Preceding(None) => Bound::UnboundedPreceding,
+        WindowFrameBound::Preceding(Some(expr)) => Bound::Preceding(offset(expr)?),
+        WindowFrameBound::Following(None) => Bound::UnboundedFollowing,
+        WindowFrameBound::Following(Some(expr)) => Bound::Following(offset(expr)?),
+    })
+}
+
+/// The sorted rows of one partition, split into groups of peers: rows that are equal under
+/// the ORDER BY of the window.
+struct Partition<'k> {
+    keys: &'k [Vec<SerialValue>],
+    order_terms: &'k [SortTerm],
+    /// The peer group of each row.
+    group_of: Vec<usize>,
+    /// The rows where each peer group starts, followed by the length of the partition.
+    group_starts: Vec<usize>,
+}
+
+impl<'k> Partition<'k> {
+    fn new(keys: &'k [Vec<SerialValue>], order_terms: &'k [SortTerm]) -> Self {
+        let mut group_of = Vec::with_capacity(keys.len());
+        let mut group_starts = Vec::new();
+        for i in 0..keys.len() {
+            if i == 0 || compare(order_terms, &keys[i - 1], &keys[i]).is_ne() {
+                group_starts.push(i);
+            }
+            group_of.push(group_starts.len() - 1);
+        }
+        group_starts.push(keys.len());
+
+        Self {
+            keys,
+            order_terms,
+            group_of,
+            group_starts,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn ngroups(&self) -> usize {
+        self.group_starts.len() - 1
+    }
+
+    /// The rows in the frame of row `i`, as a range that is empty when the frame is.
+    fn frame(&self, frame: &Frame, i: usize) -> (usize, usize) {
+        let n = self.len();
+        let g = self.group_of[i];
+        let (group_start, group_end) = (self.group_starts[g], self.group_starts[g + 1]);
+
+        let start = match (frame.units, frame.start) {
+            (_, Bound::UnboundedPreceding) => 0,
+            (_, Bound::UnboundedFollowing) => n,
+            (WindowFrameUnits::Rows, Bound::CurrentRow) => i,
+            (WindowFrameUnits::Rows, Bound::Preceding(k)) => i.saturating_sub(k as usize),
+            (WindowFrameUnits::Rows, Bound::Following(k)) => (i + k as usize).min(n),
+            (_, Bound::CurrentRow) => group_start,
+            (WindowFrameUnits::Groups, Bound::Preceding(k)) => {
+                self.group_starts[g.saturating_sub(k as usize)]
+            }
+            (WindowFrameUnits::Groups, Bound::Following(k)) => {
+                self.group_starts[(g + k as usize).min(self.ngroups())]
+            }
+            (WindowFrameUnits::Range, bound) => match self.range_bound(i, bound) {
+                Some(value) => self.position(|ordering| ordering.is_lt(), &value),
+                None => group_start,
+            },
+        };
+
+        let end = match (frame.units, frame.end) {
+            (_, Bound::UnboundedFollowing) => n,
+            (_, Bound::UnboundedPreceding) => 0,
+            (WindowFrameUnits::Rows, Bound::CurrentRow) => i + 1,
+            (WindowFrameUnits::Rows, Bound::Preceding(k)) => (i + 1).saturating_sub(k as usize),
+            (WindowFrameUnits::Rows, Bound::Following(k)) => (i + 1 + k as usize).min(n),
+            (_, Bound::CurrentRow) => group_end,
+            (WindowFrameUnits::Groups, Bound::Preceding(k)) => match g.checked_sub(k as usize) {
+                Some(g) => self.group_starts[g + 1],
+                None => 0,
+            },
+            (WindowFrameUnits::Groups, Bound::Following(k)) => {
+                self.group_starts[(g + k as usize + 1).min(self.ngroups())]
+            }
+            (WindowFrameUnits::Range, bound) => match self.range_bound(i, bound) {
+                Some(value) => self.position(|ordering| ordering.is_le(), &value),
+                None => group_end,
+            },
+        };
+
+        (start, end.max(start))
+    }
+
+    /// The ORDER BY value an offset RANGE bound of row `i` stands for. Rows whose value is
+    /// NULL have only their peers in such a frame.
+    fn range_bound(&self, i: usize, bound: Bound) -> Option<SerialValue> {
+        let term = &self.order_terms[0];
+        let value = &self.keys[i][term.column];
+        if value.is_null() {
+            return None;
+        }
+
+        let offset = match bound {
+            Bound::Preceding(k) => -k,
+            Bound::Following(k) => k,
+            _ => 0.0,
+        };
+        let offset = if term.desc { -offset } else { offset };
+        Some(match value.as_integer() {
+            Some(num) if offset.fract() == 0.0 => SerialValue::I64(num + offset as i64),
+            _ => SerialValue::Float64(value.to_real() + offset),
+        })
+    }
+
+    /// The first row whose ORDER BY value no longer satisfies `before` when compared with
+    /// `value` in the order of the window.
+    fn position(&self, before: impl Fn(Ordering) -> bool, value: &SerialValue) -> usize {
+        let term = &self.order_terms[0];
+        self.keys
+            .partition_point(|key| before(term.compare_values(&key[term.column], value)))
+    }
+
+    fn compute(
+        &self,
+        call: &WindowCall,
+        collation: Collation,
+        frame: &Frame,
+        args: &[Vec<SerialValue>],
+        included: &[bool],
+    ) -> anyhow::Result<Vec<SerialValue>> {
+        let n = self.len();
+        let mut results = Vec::with_capacity(n);
+
+        match &call.func {
+            WindowFunc::Aggregate(aggregate) => {
+                // Frames that keep their start and only grow reuse the running accumulator.
+                let mut running: Option<(usize, usize, Accumulator)> = None;
+                for i in 0..n {
+                    let (start, end) = self.frame(frame, i);
+                    let (from, mut accumulator) = match running.take() {
+                        Some((running_start, running_end, accumulator))
+                            if running_start == start && running_end <= end =>
+                        {
+                            (running_end, accumulator)
+                        }
+                        _ => (start, Accumulator::new(aggregate, collation)),
+                    };
+                    for j in from..end {
+                        if included[j] {
+                            accumulator.step(&args[j])?;
+                        }
+                    }
+                    results.push(accumulator.finish()?);
+                    running = Some((start, end, accumulator));
+                }
+            }
+            WindowFunc::RowNumber => {
+                results.extend((1..=n as i64).map(SerialValue::I64));
+            }
+            WindowFunc::Rank => {
+                for i in 0..n {
+                    let rank = self.group_starts[self.group_of[i]] + 1;
+                    results.push(SerialValue::I64(rank as i64));
+                }
+            }
+            WindowFunc::DenseRank => {
+                for i in 0..n {
+                    results.push(SerialValue::I64(self.group_of[i] as i64 + 1));
+                }
+            }
+            WindowFunc::PercentRank => {
+                for i in 0..n {
+                    let rank = self.group_starts[self.group_of[i]];
+                    let percent = if n > 1 {
+                        rank as f64 / (n - 1) as f64
+                    } else {
+                        0.0
+                    };
+                    results.push(SerialValue::Float64(percent));
+                }
+            }
+            WindowFunc::CumeDist => {
+                for i in 0..n {
+                    let peers_end = self.group_starts[self.group_of[i] + 1];
+                    results.push(SerialValue::Float64(peers_end as f64 / n as f64));
+                }
+            }
+            WindowFunc::Ntile => {
+                for (i, args) in args.iter().enumerate() {
+                    let buckets = match args[0].numeric_affinity().as_integer() {
+                        Some(buckets) if buckets > 0 => buckets as usize,
+                        _ => bail!("argument of ntile must be a positive integer"),
+                    };
+                    let size = n / buckets;
+                    let larger = n % buckets;
+                    let bucket = if size == 0 {
+                        i
+                    } else if i < larger * (size + 1) {
+                        i / (size + 1)
+                    } else {
+                        larger + (i - larger * (size + 1)) / size
+                    };
+                    results.push(SerialValue::I64(bucket as i64 + 1));
+                }
+            }
+            WindowFunc::Lag | WindowFunc::Lead => {
+                for (i, row_args) in args.iter().enumerate() {
+                    let offset = match row_args.get(1) {
+                        Some(offset) => offset.to_integer(),
+                        None => 1,
+                    };
+                    let offset = if matches!(call.func, WindowFunc::Lag) {
+                        -offset
+                    } else {
+                        offset
+                    };
+                    let value = (i as i64)
+                        .checked_add(offset)
+                        .filter(|j| (0..n as i64).contains(j))
+                        .map(|j| args[j as usize][0].clone());
+                    results.push(
+                        value.unwrap_or_else(|| {
+                            row_args.get(2).cloned().unwrap_or(SerialValue::Null)
+                        }),
+                    );
+                }
+            }
+            WindowFunc::FirstValue | WindowFunc::LastValue | WindowFunc::NthValue => {
+                for i in 0..n {
+                    let (start, end) = self.frame(frame, i);
+                    let position = match call.func {
+                        WindowFunc::FirstValue => Some(start),
+                        WindowFunc::LastValue => end.checked_sub(1),
+                        _ => match args[i][1].numeric_affinity().as_integer() {
+                            Some(nth) if nth > 0 => Some(start + nth as usize - 1),
+                            _ => bail!("second argument to nth_value must be a positive integer"),
+                        },
+                    };
+                    results.push(match position {
+                        Some(j) if j >= start && j < end => args[j][0].clone(),
+                        _ => SerialValue::Null,
+                    });
+                }
+            }
+        }
+
+        Ok(results)
+    }
+}
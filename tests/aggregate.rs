@@ -0,0 +1,184 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT count(...), sum(...), ... FROM `table`
+*
+* **/
+#[test]
+fn test_cli_aggregates_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT count(*), count(eye_color), count(DISTINCT eye_color), sum(appearance_count), min(appearance_count), max(appearance_count) FROM superheroes",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["6895|3267|17|154532|1|3093"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_aggregates_empty_input_sample_db() {
+    let output = build_query_command(
+        "sample.db",
+        "SELECT count(*), sum(id), total(id), avg(id), group_concat(name) FROM apples WHERE id > 10",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["0||0.0||"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_group_concat_sample_db() {
+    let output = build_query_command("sample.db", "SELECT group_concat(name, '; ') FROM apples");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["Granny Smith; Fuji; Honeycrisp; Golden Delicious"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT `field_name`, count(*) FROM `table` GROUP BY ... HAVING ...
+*
+* **/
+#[test]
+fn test_cli_group_by_having_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT eye_color, count(*) FROM superheroes GROUP BY eye_color HAVING count(*) > 100",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+    let expects = vec![
+        "|3628",
+        "Black Eyes|412",
+        "Blue Eyes|1101",
+        "Brown Eyes|879",
+        "Green Eyes|291",
+        "Red Eyes|208",
+        "White Eyes|116",
+    ];
+
+    assert_eq!(expects, stdout.lines().collect::<Vec<_>>());
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_max_bare_column_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT max(appearance_count), name FROM superheroes",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["3093|Batman (Bruce Wayne)"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ..., count(*), min(...), max(...) FROM `table` GROUP BY nocase_column
+*
+* **/
+#[test]
+fn test_cli_group_by_nocase_column_library_db() {
+    // The index on the NOCASE column feeds the rows in order, so the groups are streamed.
+    let output = build_query_command(
+        "library.db",
+        "SELECT lower(label), count(*), min(name), max(name) FROM tags GROUP BY label",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["abc|3|ABC|abc", "abd|3|ABD|abd", "xyz|2|Xyz|xyz"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT lower(label), count(*) FROM (SELECT label FROM tags ORDER BY id) GROUP BY label",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["abc|3", "abd|3", "xyz|2"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT min(nocase_column), max(...), count(DISTINCT ...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_min_max_distinct_nocase_column_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT min(label), max(label), min(name), max(name), count(DISTINCT label), count(DISTINCT name), (SELECT count(*) FROM (SELECT DISTINCT label FROM tags)) FROM tags",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["abc|XYZ|ABC|xyz|3|7|3"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT `column` AS `alias`, count(*) AS `alias` FROM `table` GROUP BY `alias` HAVING `alias` ...
+*
+* **/
+#[test]
+fn test_cli_group_by_having_alias_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT genre g, count(*) c FROM books GROUP BY g HAVING c > 1",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["novel|8"]);
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT year / 100 AS century, count(*) FROM books GROUP BY century ORDER BY century",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["18|9", "19|3"]);
+    assert!(output.status.success());
+
+    // A column of the FROM clause takes precedence over an alias of the same name.
+    let output = build_query_command(
+        "library.db",
+        "SELECT count(*) AS author_id FROM books GROUP BY author_id HAVING author_id > 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["2", "2", "2"]);
+    assert!(output.status.success());
+}
@@ -0,0 +1,134 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT CAST(... AS type)
+*
+* **/
+#[test]
+fn test_cli_cast_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT CAST('12abc' AS INTEGER), CAST(-1.9 AS INTEGER), CAST('1e3' AS NUMERIC), CAST('1.5x' AS REAL), CAST(x'616263' AS TEXT), typeof(CAST(12 AS BLOB)), typeof(CAST('1' AS VARCHAR(10))), typeof(CAST('1.5' AS DECIMAL(10,2)))",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["12|-1|1000|1.5|abc|blob|text|real"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... + ..., ... / ..., ... % ...
+*
+* **/
+#[test]
+fn test_cli_arithmetic_conversions_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT 7 / 2, 7.0 / 2, 7 % 3, 7.5 % 2, '3' + 4, '3.5x' * 2, 'abc' + 1, 1 / 0, NULL + 1",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["3|3.5|1|1.0|7|7.0|1||"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_integer_overflow_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT 9223372036854775807 + 1, -9223372036854775808, -9223372036854775808 / -1, 9223372036854775807 * 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["9.22337203685478e+18|-9223372036854775808|9.22337203685478e+18|1.84467440737096e+19"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT column * ..., column || ... FROM `table` WHERE column - ... < ...
+*
+* **/
+#[test]
+fn test_cli_arithmetic_on_columns_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id, id * 10 + 1, title || ' (' || id || ')', 2 * 3 || 4 FROM books WHERE id - 1 < 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["1|11|Pride and Prejudice (1)|68", "2|21|Emma (2)|68"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE integer_column = 'text' OR ... IN ('text', ...)
+*
+* **/
+#[test]
+fn test_cli_comparison_affinity_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT title FROM books WHERE year = '1869' OR id = '2' ORDER BY id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["Emma", "War and Peace", "The Idiot"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT (SELECT count(*) FROM books WHERE author_id = '2'), count(*) FROM books WHERE author_id IN ('2', '3')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["3|5"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT real_column, typeof(real_column) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_real_column_stored_as_integer_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT amount, typeof(amount) FROM ledger WHERE id BETWEEN -5 AND 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["3.0|real", "4.0|real", "5.0|real", "6.0|real"]
+    );
+    assert!(output.status.success());
+}
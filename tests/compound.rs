@@ -0,0 +1,229 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT ... UNION SELECT ...
+*
+* **/
+#[test]
+fn test_cli_union_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT country FROM authors UNION SELECT genre FROM books",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["", "England", "Russia", "USA", "essay", "memoir", "novel", "novella", "poetry"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_union_keeps_later_duplicate_library_db() {
+    let output = build_query_command("library.db", "SELECT 1 UNION SELECT 1.0");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1.0"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_union_collation_library_db() {
+    let output = build_query_command("library.db", "SELECT 'B' UNION SELECT 'b' COLLATE NOCASE");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["b"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_union_takes_left_column_collation_library_db() {
+    // A column without COLLATE is BINARY, so the NOCASE column on the right does not apply.
+    let output = build_query_command(
+        "library.db",
+        "SELECT name FROM tags UNION SELECT label FROM tags",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["ABC", "ABD", "Abc", "Abd", "XYZ", "Xyz", "abc", "abd", "xyz"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT label FROM tags UNION SELECT name FROM tags",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["ABC", "abd", "Xyz"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_union_subquery_column_is_binary_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT x FROM (SELECT 'b' AS x) UNION SELECT 'B' COLLATE NOCASE",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["B", "b"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_union_column_count_mismatch_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id, name FROM authors UNION SELECT id FROM books",
+    );
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains(
+        "SELECTs to the left and right of UNION do not have the same number of result columns"
+    ));
+    assert!(!output.status.success());
+}
+
+/*
+*
+* SELECT ... UNION ALL SELECT ...
+*
+* **/
+#[test]
+fn test_cli_union_all_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT name FROM authors WHERE country = 'Russia' UNION ALL SELECT name FROM authors WHERE id < 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Leo Tolstoy",
+            "Fyodor Dostoevsky",
+            "Jane Austen",
+            "Leo Tolstoy"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... INTERSECT SELECT ...
+* SELECT ... EXCEPT SELECT ...
+*
+* **/
+#[test]
+fn test_cli_intersect_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT author_id FROM books INTERSECT SELECT id FROM authors",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["1", "2", "3", "4", "5"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_except_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM authors EXCEPT SELECT author_id FROM books",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["6"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_compound_operators_are_left_associative_library_db() {
+    let output = build_query_command("library.db", "SELECT 1 UNION SELECT 2 INTERSECT SELECT 2");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["2"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... UNION SELECT ... ORDER BY ... LIMIT ... OFFSET ...
+*
+* **/
+#[test]
+fn test_cli_compound_order_by_limit_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT name AS n FROM authors UNION SELECT title FROM books ORDER BY n DESC LIMIT 3 OFFSET 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "The Idiot",
+            "The Death of Ivan Ilyich",
+            "Pride and Prejudice"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_compound_order_by_unknown_column_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM authors UNION SELECT id FROM books ORDER BY title",
+    );
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("1st ORDER BY term does not match any column in the result set"));
+    assert!(!output.status.success());
+}
+
+/*
+*
+* SELECT ... ORDER BY ... LIMIT ...
+*
+* **/
+#[test]
+fn test_cli_select_order_by_limit_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT title, year FROM books WHERE genre = 'novel' ORDER BY year DESC, 1 LIMIT 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Mrs Dalloway|1925",
+            "Adventures of Huckleberry Finn|1884",
+            "Anna Karenina|1878"
+        ]
+    );
+    assert!(output.status.success());
+}
@@ -0,0 +1,141 @@
+use rust_sqlite::{named_params, params, Connection};
+
+/*
+*
+* SELECT ... FROM `table` WHERE `column` = ? (read through the library API)
+*
+* **/
+#[test]
+fn test_query_rows_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare("SELECT id, name, country FROM authors WHERE country = ? ORDER BY id")
+        .expect("prepare");
+
+    let rows = statement.query(["Russia"]).expect("query");
+    assert_eq!(rows.column_names(), vec!["id", "name", "country"]);
+    assert_eq!(rows.column_index("NAME").expect("column index"), 1);
+
+    let authors = rows
+        .map(|row| {
+            let row = row?;
+            Ok((row.get::<i64>(0)?, row.get::<String>("name")?))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("read rows");
+    assert_eq!(
+        authors,
+        vec![
+            (2, "Leo Tolstoy".to_string()),
+            (3, "Fyodor Dostoevsky".to_string())
+        ]
+    );
+
+    let names = statement
+        .query_map(["England"], |row| row.get::<String>(1))
+        .expect("query")
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("read rows");
+    assert_eq!(names, vec!["Jane Austen", "Virginia Woolf"]);
+
+    assert!(statement.query(params![]).is_err());
+}
+
+/*
+*
+* SELECT ... WHERE `column` = :name AND ... (named parameters of different types)
+*
+* **/
+#[test]
+fn test_query_row_named_params_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+
+    let count: i64 = conn
+        .query_row(
+            "SELECT count(*) FROM books WHERE author_id = :author AND year > :year",
+            named_params! { ":author": 2, ":year": 1870.5 },
+            |row| row.get(0),
+        )
+        .expect("query row");
+    assert_eq!(count, 2);
+
+    let (title, year): (String, Option<u32>) = conn
+        .query_row(
+            "SELECT title, year FROM books WHERE id = ?1 OR title = ?2",
+            params![1, "none"],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("query row");
+    assert_eq!((title.as_str(), year), ("Pride and Prejudice", Some(1813)));
+
+    let missing: Option<String> = conn
+        .query_row("SELECT NULL", (), |row| row.get(0))
+        .expect("query row");
+    assert_eq!(missing, None);
+
+    let wrong_type = conn.query_row("SELECT title FROM books", (), |row| row.get::<i64>(0));
+    assert!(wrong_type.is_err());
+
+    let no_rows = conn.query_row("SELECT 1 FROM books WHERE id = -1", (), |row| {
+        row.get::<i64>(0)
+    });
+    assert!(no_rows.is_err());
+}
+
+/*
+*
+* SELECT ... FROM `table` (rows read one at a time, failing at the row that fails)
+*
+* **/
+#[test]
+fn test_rows_are_lazy_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare("SELECT id, title, json(substr('1{', id, 1)) FROM books")
+        .expect("prepare");
+
+    let mut rows = statement.query(()).expect("query");
+    let row = rows.next().expect("a row").expect("read row");
+    assert_eq!(
+        row.get::<String>("title").expect("title"),
+        "Pride and Prejudice"
+    );
+    let err = rows.next().expect("a row").err().expect("malformed JSON");
+    assert_eq!(err.to_string(), "malformed JSON");
+}
+
+/*
+*
+* SELECT `column`, `column` AS alias, `expr` FROM (SELECT ...) (declared types of the columns)
+*
+* **/
+#[test]
+fn test_column_decltypes_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare("SELECT id, b.title AS name, year + 1, 'x' FROM (SELECT * FROM books) AS b")
+        .expect("prepare");
+
+    let rows = statement.query(()).expect("query");
+    let columns: Vec<_> = rows
+        .columns()
+        .iter()
+        .map(|column| {
+            (
+                column.name().to_string(),
+                column.decl_type().map(String::from),
+            )
+        })
+        .collect();
+    assert_eq!(
+        columns,
+        vec![
+            ("id".to_string(), Some("integer".to_string())),
+            ("name".to_string(), Some("text".to_string())),
+            ("year + 1".to_string(), None),
+            ("'x'".to_string(), None),
+        ]
+    );
+    assert_eq!(rows.column_decltype("NAME").expect("column"), Some("text"));
+    assert!(rows.column_decltype(4).is_err());
+}
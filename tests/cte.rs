@@ -0,0 +1,188 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* WITH `name` AS (SELECT ...) SELECT ... FROM `name`
+*
+* **/
+#[test]
+fn test_cli_with_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "WITH russians AS (SELECT id, name FROM authors WHERE country = 'Russia'), titles AS (SELECT b.title AS title, r.name AS name FROM books b JOIN russians r ON r.id = b.author_id) SELECT name, count(*) FROM titles GROUP BY name",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["Fyodor Dostoevsky|2", "Leo Tolstoy|3"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_with_column_count_mismatch_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "WITH t(a, b) AS (SELECT name FROM authors) SELECT a FROM t",
+    );
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("table t has 1 values for 2 columns"));
+    assert!(!output.status.success());
+}
+
+/*
+*
+* WITH RECURSIVE `name` AS (SELECT ... UNION [ALL] SELECT ... FROM `name` ...) SELECT ...
+*
+* **/
+#[test]
+fn test_cli_with_recursive_management_chain_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "WITH RECURSIVE chain(id, name) AS (SELECT id, name FROM staff WHERE name = 'Ken' UNION ALL SELECT s.id, s.name FROM staff s JOIN chain c ON s.id = (SELECT manager_id FROM staff WHERE id = c.id)) SELECT name FROM chain",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["Ken", "Edsger", "Grace", "Ada"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_with_recursive_reports_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "WITH RECURSIVE reports(id) AS (SELECT id FROM staff WHERE name = 'Grace' UNION ALL SELECT s.id FROM staff s JOIN reports r ON s.manager_id = r.id) SELECT count(*) FROM reports",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["5"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_with_recursive_category_tree_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "WITH RECURSIVE sub(id) AS (SELECT id FROM categories WHERE name = 'Fiction' UNION SELECT c.id FROM categories c, sub WHERE c.parent_id = sub.id) SELECT c.name FROM categories c WHERE c.id IN (SELECT id FROM sub)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Fiction",
+            "Novels",
+            "Short stories",
+            "Russian novels",
+            "English novels"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_with_recursive_union_stops_on_repeated_rows_library_db() {
+    // UNION drops rows that were already produced, so this cycle ends after its first row.
+    let output = build_query_command(
+        "library.db",
+        "WITH RECURSIVE cycle(x) AS (SELECT 'a' UNION SELECT x FROM cycle) SELECT count(*) FROM cycle",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* WITH RECURSIVE `name` AS (SELECT ... UNION ALL SELECT ... FROM `name` ORDER BY ... LIMIT ... OFFSET ...) SELECT ...
+*
+* **/
+#[test]
+fn test_cli_with_recursive_limit_stops_unbounded_recursion_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "WITH RECURSIVE cnt(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM cnt LIMIT 5) SELECT x FROM cnt",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["1", "2", "3", "4", "5"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "WITH RECURSIVE cnt(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM cnt LIMIT 3 OFFSET 2) SELECT x FROM cnt",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["3", "4", "5"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_with_recursive_order_by_depth_first_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "WITH RECURSIVE chain(id, name, depth) AS (SELECT id, name, 0 FROM staff WHERE id = 1 UNION ALL SELECT s.id, s.name, chain.depth + 1 FROM staff s JOIN chain ON s.manager_id = chain.id ORDER BY 3 DESC, 2) SELECT name, depth FROM chain",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Ada|0",
+            "Alan|1",
+            "Donald|2",
+            "Grace|1",
+            "Barbara|2",
+            "Edsger|2",
+            "Dennis|3",
+            "Ken|3"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_with_materialized_once_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN WITH c AS (SELECT id FROM books WHERE year > 1800) SELECT count(*) FROM c AS a, c AS b WHERE a.id = b.id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout
+            .lines()
+            .filter(|line| line.contains("MATERIALIZE"))
+            .count(),
+        1
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "WITH c AS (SELECT id FROM books WHERE year > 1800) SELECT count(*) FROM c AS a, c AS b WHERE a.id = b.id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["12"]);
+    assert!(output.status.success());
+}
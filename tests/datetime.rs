@@ -0,0 +1,130 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT date(...), strftime(...), unixepoch(...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_date_functions_on_column_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT member, day, date(day, '+14 days'), strftime('%w|%j|%W', day), unixepoch(day) FROM loans WHERE id IN (29, 30, 59, 60)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "member 128|2020-01-30|2020-02-13|4|030|04|1580342400",
+            "member 159|2020-01-31|2020-02-14|5|031|04|1580428800",
+            "member 30|2020-02-29|2020-03-14|6|060|08|1582934400",
+            "member 61|2020-03-01|2020-03-15|0|061|08|1583020800"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT strftime(...), count(*) FROM `table` GROUP BY strftime(...)
+*
+* **/
+#[test]
+fn test_cli_group_by_month_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT strftime('%Y-%m', day), count(*) FROM loans WHERE day < '2020-05-01' GROUP BY strftime('%Y-%m', day)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["2020-01|93", "2020-02|87", "2020-03|93", "2020-04|90"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT date(..., modifiers...)
+*
+* **/
+#[test]
+fn test_cli_date_modifiers_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT date('2024-01-31', '+1 month'), date('2024-01-31', '+1 month', 'floor'), datetime('2024-01-15 10:00', '+90 minutes'), datetime('2024-01-15 10:00', '-01:30'), date('2024-01-17', 'weekday 0'), datetime('2024-05-17 13:14', 'start of year'), date('2023-02-31')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["2024-03-02|2024-02-29|2024-01-15 11:30:00|2024-01-15 08:30:00|2024-01-21|2024-01-01 00:00:00|2023-03-03"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_unixepoch_julianday_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT datetime(1705314030, 'unixepoch'), datetime(1705314030.75, 'unixepoch', 'subsec'), unixepoch('2024-01-15 10:20:30+05:30'), julianday('2000-01-01 12:00'), datetime(2460000.5, 'auto'), date('2024-01-01', 'bogus')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "2024-01-15 10:20:30|2024-01-15 10:20:30.750|1705294230|2451545.0|2023-02-25 00:00:00|"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT strftime(...), timediff(...)
+*
+* **/
+#[test]
+fn test_cli_strftime_timediff_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT strftime('%Y-%m-%d %H:%M:%f', '2024-01-15 10:20:30.456'), strftime('%j|%w|%u|%W|%U|%V|%G|%s', '2021-01-01'), strftime('%e|%l|%p|%R|%%', '2024-03-05 17:08:09'), timediff('2024-03-15 12:00:00', '2023-01-10 08:30:15.25'), timediff('2024-01-31', '2024-03-01')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["2024-01-15 10:20:30.456|001|5|5|00|00|53|2020|1609459200| 5| 5|PM|17:08|%|+0001-02-05 03:29:44.750|-0000-01-01 00:00:00.000"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_localtime_utc_library_db() {
+    let output = Command::new("./target/debug/rust-sqlite")
+        .env("TZ", "America/New_York")
+        .arg(".query")
+        .arg("library.db")
+        .arg("SELECT datetime('2024-07-15 10:00', 'localtime'), datetime('2024-01-15 10:00', 'localtime'), datetime('2024-07-15 06:00', 'utc')")
+        .output()
+        .expect("failed to execute .query process");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["2024-07-15 06:00:00|2024-01-15 05:00:00|2024-07-15 10:00:00"]
+    );
+    assert!(output.status.success());
+}
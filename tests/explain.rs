@@ -0,0 +1,303 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+fn build_eqp_command(mode: &str, db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".eqp")
+        .arg(mode)
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .eqp process")
+}
+
+/*
+*
+* EXPLAIN QUERY PLAN SELECT ... FROM `table` WHERE `rowid` ... / `indexed_column` ...
+*
+* **/
+#[test]
+fn test_cli_explain_search_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT * FROM readings WHERE id BETWEEN 5 AND 9",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH readings USING INTEGER PRIMARY KEY (rowid>? AND rowid<?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "explain query plan SELECT value, id FROM readings WHERE sensor = 'sensor-2' AND value > 495",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH readings USING COVERING INDEX idx_readings_sensor_value (sensor=? AND value>?)"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* EXPLAIN QUERY PLAN SELECT ... FROM `table` ORDER BY ...
+*
+* **/
+#[test]
+fn test_cli_explain_scan_order_by_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT title FROM books ORDER BY title",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SCAN books",
+            "`--USE TEMP B-TREE FOR ORDER BY"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* EXPLAIN QUERY PLAN SELECT ... FROM `table` WHERE ... GROUP BY `indexed_column`
+*
+* **/
+#[test]
+fn test_cli_explain_group_by_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT genre, count(*) FROM books WHERE id = 3 GROUP BY genre",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH books USING INTEGER PRIMARY KEY (rowid=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT genre, count(*) FROM books WHERE author_id = 3 GROUP BY genre",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SEARCH books USING INDEX idx_books_author_id (author_id=?)",
+            "`--USE TEMP B-TREE FOR GROUP BY"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT genre, count(*) FROM books WHERE author_id > 3 GROUP BY genre",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SCAN books USING INDEX idx_books_genre_year"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT genre, count(*) FROM books WHERE author_id = 3 GROUP BY genre",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["novel|2"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* EXPLAIN QUERY PLAN SELECT ... FROM `table` JOIN ... LEFT JOIN ... with a correlated subquery
+*
+* **/
+#[test]
+fn test_cli_explain_join_subquery_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT b.title, (SELECT name FROM authors a WHERE a.id = b.author_id) \
+         FROM books b LEFT JOIN loans l ON l.book_id = b.id \
+         WHERE b.id IN (SELECT book_id FROM loans)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SEARCH b USING INTEGER PRIMARY KEY (rowid=?)",
+            "|--SEARCH l USING INDEX idx_loans_book_id (book_id=?) LEFT-JOIN",
+            "|--CORRELATED SCALAR SUBQUERY 1",
+            "|  `--SEARCH a USING INTEGER PRIMARY KEY (rowid=?)",
+            "`--LIST SUBQUERY 2",
+            "   `--SCAN loans USING COVERING INDEX idx_loans_book_id"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* EXPLAIN QUERY PLAN SELECT ... WHERE NOT EXISTS (SELECT ... WHERE `indexed_column` = `outer_column`)
+*
+* **/
+#[test]
+fn test_cli_explain_correlated_search_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT name FROM authors a \
+         WHERE NOT EXISTS (SELECT 1 FROM books b WHERE b.author_id = a.id)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SCAN a",
+            "`--CORRELATED SCALAR SUBQUERY 1",
+            "   `--SEARCH b USING COVERING INDEX idx_books_author_id (author_id=?)"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* EXPLAIN QUERY PLAN WITH RECURSIVE ... SELECT ... UNION ...
+*
+* **/
+#[test]
+fn test_cli_explain_cte_compound_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN WITH RECURSIVE r(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM r WHERE n < 3) \
+         SELECT n FROM r UNION SELECT max(id) FROM readings",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--COMPOUND QUERY",
+            "   |--LEFT-MOST SUBQUERY",
+            "   |  |--MATERIALIZE r",
+            "   |  |  |--SETUP",
+            "   |  |  |  `--SCAN CONSTANT ROW",
+            "   |  |  `--RECURSIVE STEP",
+            "   |  |     `--SCAN r",
+            "   |  `--SCAN r",
+            "   `--UNION USING TEMP B-TREE",
+            "      `--SEARCH readings"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` a LEFT JOIN `table` b ON b.`unindexed` = a.`rowid`
+*
+* **/
+#[test]
+fn test_cli_explain_automatic_index_library_db() {
+    let statement =
+        "SELECT s.name, count(r.id) FROM staff s LEFT JOIN staff r ON r.manager_id = s.id \
+        GROUP BY s.id ORDER BY s.id LIMIT 5";
+
+    let output = build_query_command("library.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SCAN s",
+            "|--SEARCH r USING AUTOMATIC COVERING INDEX (manager_id=?) LEFT-JOIN",
+            "|--USE TEMP B-TREE FOR GROUP BY",
+            "`--USE TEMP B-TREE FOR ORDER BY"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("library.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["Ada|2", "Grace|2", "Alan|1", "Edsger|2", "Barbara|0"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* .eqp on|off
+*
+* **/
+#[test]
+fn test_cli_eqp_library_db() {
+    let statement = "SELECT genre, count(*) FROM books WHERE genre > 'novel' GROUP BY genre";
+
+    let output = build_eqp_command("on", "library.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH books USING COVERING INDEX idx_books_genre_year (genre>?)",
+            "novella|1",
+            "poetry|1"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_eqp_command("off", "library.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["novella|1", "poetry|1"]
+    );
+    assert!(output.status.success());
+}
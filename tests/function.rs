@@ -0,0 +1,151 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT upper(...), length(...), substr(...), ifnull(...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_string_functions_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT upper(name), length(name), substr(name, 1, 3), ifnull(country, 'unknown') FROM authors",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "JANE AUSTEN|11|Jan|England",
+            "LEO TOLSTOY|11|Leo|Russia",
+            "FYODOR DOSTOEVSKY|17|Fyo|Russia",
+            "MARK TWAIN|10|Mar|USA",
+            "VIRGINIA WOOLF|14|Vir|England",
+            "FRANZ KAFKA|11|Fra|unknown"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_misc_functions_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT quote(1.5), quote('it''s'), quote(NULL), quote(x'0a'), hex('abc'), replace('hello', 'l', 'L'), instr('hello', 'lo'), trim('  hi  '), coalesce(NULL, NULL, 3), nullif(1, 1), char(72, 105), unicode('A')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["1.5|'it''s'|NULL|X'0A'|616263|heLLo|4|hi|3||Hi|65"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT round(...), typeof(...)
+*
+* **/
+#[test]
+fn test_cli_round_typeof_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT round(2.675, 2), round(2.5), round(1.005, 2), round(0.285, 2), typeof(1), typeof(1.5), typeof('a'), typeof(NULL), typeof(x'00')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["2.67|3.0|1.0|0.28|integer|real|text|null|blob"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT printf(...)
+*
+* **/
+#[test]
+fn test_cli_printf_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT printf('%d|%5d|%-5d|%05d|%+d|%x|%,d', 42, 42, 42, 42, 42, 255, 1234567), printf('%.2f|%e|%g|%s|%Q', 3.14159, 31415.9, 0.0001, 'abc', 'it''s')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["42|   42|42   |00042|+42|ff|1,234,567|3.14|3.141590e+04|0.0001|abc|'it''s'"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT printf(...) and replace(...) results past the length limit
+*
+* **/
+#[test]
+fn test_cli_printf_width_limits_library_db() {
+    let output = build_query_command("library.db", "SELECT printf('%2147483647d', 1)");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("string or blob too big"));
+    assert!(!output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT printf('%*d|%.*d', -2147483648, 1, -3, 5)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1|005"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_replace_length_limit_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT replace(replace(replace(hex(zeroblob(1000)), '0', hex(zeroblob(1000))), '0', hex(zeroblob(1000))), '0', 'ab')",
+    );
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("string or blob too big"));
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cli_function_errors_library_db() {
+    let output = build_query_command("library.db", "SELECT upper()");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("wrong number of arguments to function upper()"));
+    assert!(!output.status.success());
+
+    let output = build_query_command("library.db", "SELECT foo(1)");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("no such function: foo"));
+    assert!(!output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT count(*) FROM authors WHERE count(*) > 0",
+    );
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("misuse of aggregate: count()"));
+    assert!(!output.status.success());
+}
@@ -0,0 +1,477 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `first_column` = ... AND `second_column` BETWEEN ... AND ...
+*
+* **/
+#[test]
+fn test_cli_index_equality_and_range_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id, value FROM readings WHERE sensor = 'sensor-3' AND value BETWEEN 100 AND 105 ORDER BY value",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "773|101",
+            "4294969042|102",
+            "281474976713375|103",
+            "192|104",
+            "4294968461|105"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = ... with 48 and 64-bit rowids
+*
+* **/
+#[test]
+fn test_cli_index_wide_rowids_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT COUNT(*), MIN(id), MAX(id) FROM readings WHERE sensor = 'sensor-0' AND id > 4294967296",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["286|4294968297|281474976713652"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `first_column` = ... AND `second_column` >= ... (numeric keys)
+*
+* **/
+#[test]
+fn test_cli_index_numeric_range_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT title FROM books WHERE genre = 'novel' AND year >= 1869 ORDER BY year",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "War and Peace",
+            "The Idiot",
+            "Anna Karenina",
+            "Adventures of Huckleberry Finn",
+            "Mrs Dalloway"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` > ... AND `indexed_column` < ...
+*
+* **/
+#[test]
+fn test_cli_index_range_scan_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT COUNT(*), SUM(value) FROM readings WHERE sensor > 'sensor-0' AND sensor < 'sensor-2'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["429|106627"]);
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT id, genre FROM books WHERE genre BETWEEN 'fantasy' AND 'novel'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "8|memoir", "1|novel", "2|novel", "5|novel", "3|novel", "6|novel", "4|novel",
+            "7|novel", "10|novel"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` LIKE 'prefix%'
+*
+* **/
+#[test]
+fn test_cli_index_like_prefix_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT COUNT(*), MIN(sensor), MAX(sensor) FROM readings WHERE sensor LIKE 'SENSOR-1%'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["429|sensor-1|sensor-1"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT `indexed_column`, COUNT(*) FROM `table` GROUP BY `indexed_column` (covering index)
+*
+* **/
+#[test]
+fn test_cli_covering_index_group_by_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT sensor, COUNT(*) FROM readings GROUP BY sensor",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "sensor-0|428",
+            "sensor-1|429",
+            "sensor-2|429",
+            "sensor-3|429",
+            "sensor-4|429",
+            "sensor-5|428",
+            "sensor-6|428"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT `indexed_column`, rowid FROM `table` WHERE ... (covering index)
+*
+* **/
+#[test]
+fn test_cli_covering_index_search_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT value, id FROM readings WHERE sensor = 'sensor-2' AND value > 495",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["496|4294968404", "497|281474976712737", "499|527"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT * FROM `table` WHERE ... (a wildcard covered by an index)
+*
+* **/
+#[test]
+fn test_cli_covering_index_wildcard_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT r.* FROM readings r WHERE sensor = 'sensor-2'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH r USING COVERING INDEX idx_readings_sensor_value (sensor=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT * FROM readings WHERE sensor = 'sensor-2' AND value > 495",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "4294968404|sensor-2|496",
+            "281474976712737|sensor-2|497",
+            "527|sensor-2|499"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT max(rowid) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_max_rowid_library_db() {
+    let output = build_query_command("library.db", "SELECT max(id) FROM readings");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["281474976713656"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT min(`indexed_column`), `column` FROM `table`
+*
+* **/
+#[test]
+fn test_cli_min_max_index_library_db() {
+    let output = build_query_command("library.db", "SELECT max(sensor), id FROM readings");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["sensor-6|27"]);
+    assert!(output.status.success());
+
+    // books.author_id holds a NULL, which sorts first in the index.
+    let output = build_query_command("library.db", "SELECT min(author_id) FROM books");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = ... OR rowid < ... OR (`other_indexed_column` = ... AND ...)
+*
+* **/
+#[test]
+fn test_cli_multi_index_or_library_db() {
+    let statement = "SELECT id, title FROM books \
+        WHERE author_id = 3 OR id < 3 OR (genre = 'poetry' AND year > 1900)";
+
+    let output = build_query_command("library.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "1|Pride and Prejudice",
+            "2|Emma",
+            "5|Crime and Punishment",
+            "6|The Idiot"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("library.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--MULTI-INDEX OR",
+            "   |--INDEX 1",
+            "   |  `--SEARCH books USING INDEX idx_books_author_id (author_id=?)",
+            "   |--INDEX 2",
+            "   |  `--SEARCH books USING INTEGER PRIMARY KEY (rowid<?)",
+            "   `--INDEX 3",
+            "      `--SEARCH books USING INDEX idx_books_genre_year (genre=? AND year>?)"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* EXPLAIN QUERY PLAN SELECT min(`indexed_real_column`)[, `column`] FROM `table`
+*
+* **/
+#[test]
+fn test_cli_min_max_covering_index_library_db() {
+    // ledger.amount is REAL, and the index stores its whole values as integers.
+    let output = build_query_command(
+        "library.db",
+        "SELECT min(amount), memo FROM ledger UNION ALL SELECT max(amount), NULL FROM ledger",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1.0|min", "8.0|"]);
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT max(amount) FROM ledger",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH ledger USING COVERING INDEX idx_ledger_amount"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT min(author_id), title FROM books",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH books USING INDEX idx_books_author_id"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `nocase_indexed_column` = ... / > ...
+*
+* **/
+#[test]
+fn test_cli_index_nocase_seek_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT id FROM tags WHERE label = 'abc'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH tags USING COVERING INDEX idx_tags_label (label=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM tags WHERE label = 'abc' ORDER BY id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1", "2", "5"]);
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM tags WHERE label > 'ABD' ORDER BY id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["6", "7"]);
+    assert!(output.status.success());
+
+    // A comparison under another collation cannot search the index.
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT id FROM tags WHERE label = 'abc' COLLATE BINARY",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SCAN tags USING COVERING INDEX idx_tags_label"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` with a partial index (CREATE INDEX ... WHERE a > 5)
+*
+* **/
+#[test]
+fn test_cli_partial_index_library_db() {
+    // Without terms that imply its WHERE clause, the index misses rows.
+    let output = build_query_command("library.db", "SELECT count(*) FROM counters");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["100"]);
+    assert!(output.status.success());
+
+    let output = build_query_command("library.db", "SELECT count(*) FROM counters WHERE a = 2");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["10"]);
+    assert!(output.status.success());
+
+    let output = build_query_command("library.db", "SELECT min(a) FROM counters");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["0"]);
+    assert!(output.status.success());
+
+    let output = build_query_command("library.db", "SELECT a, count(*) FROM counters GROUP BY a");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["0|10", "1|10", "2|10", "3|10", "4|10", "5|10", "6|10", "7|10", "8|10", "9|10"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT count(*) FROM counters WHERE a = 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["QUERY PLAN", "`--SCAN counters"]
+    );
+    assert!(output.status.success());
+
+    // a = 7 and a >= 8 imply a > 5.
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT count(*) FROM counters WHERE a = 7",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH counters USING COVERING INDEX idx_counters_a (a=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT a, count(*) FROM counters WHERE a >= 8 GROUP BY a",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["8|10", "9|10"]);
+    assert!(output.status.success());
+}
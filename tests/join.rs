@@ -0,0 +1,339 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT ... FROM `table` a JOIN `table` b ON ...
+*
+* **/
+#[test]
+fn test_cli_join_on_indexed_column_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT b.title, count(*) FROM books b JOIN loans l ON l.book_id = b.id GROUP BY b.title HAVING count(*) < 231",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["The Death of Ivan Ilyich|230", "The Idiot|230"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_join_on_rowid_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT a.id, b.name FROM superheroes a JOIN superheroes b ON b.id = a.id WHERE a.eye_color = 'Pink Eyes'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "297|Stealth (New Earth)",
+            "790|Tobias Whale (New Earth)",
+            "1085|Felicity (New Earth)",
+            "2729|Thrust (New Earth)",
+            "3289|Angora Lapin (New Earth)",
+            "3913|Matris Ater Clementia (New Earth)"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_comma_join_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT authors.name, books.title FROM authors, books WHERE books.author_id = authors.id AND authors.country = 'Russia'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Leo Tolstoy|War and Peace",
+            "Leo Tolstoy|Anna Karenina",
+            "Leo Tolstoy|The Death of Ivan Ilyich",
+            "Fyodor Dostoevsky|Crime and Punishment",
+            "Fyodor Dostoevsky|The Idiot"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_cross_join_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT count(*) FROM authors CROSS JOIN books",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["72"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` a LEFT JOIN `table` b ON ...
+*
+* **/
+#[test]
+fn test_cli_left_join_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT a.name, b.title FROM authors a LEFT JOIN books b ON b.author_id = a.id WHERE a.country = 'England' OR a.country IS NULL",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Jane Austen|Pride and Prejudice",
+            "Jane Austen|Emma",
+            "Virginia Woolf|A Room of One's Own",
+            "Virginia Woolf|Mrs Dalloway",
+            "Franz Kafka|"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` JOIN `table` USING (...)
+*
+* **/
+#[test]
+fn test_cli_join_using_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT title, genre, description FROM books JOIN genres USING (genre) WHERE year > 1880",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Adventures of Huckleberry Finn|novel|long fiction",
+            "Life on the Mississippi|memoir|personal history",
+            "A Room of One's Own|essay|nonfiction prose",
+            "Mrs Dalloway|novel|long fiction",
+            "The Death of Ivan Ilyich|novella|short novel"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_natural_join_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT count(*), count(DISTINCT genre) FROM books NATURAL JOIN genres",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["11|4"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_join_ambiguous_column_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM authors JOIN books ON books.author_id = authors.id",
+    );
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("ambiguous column name: id"));
+    assert!(!output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` a JOIN `table` b ON a.`unindexed` = b.`unindexed`
+*
+* **/
+#[test]
+fn test_cli_hash_join_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT count(*) FROM superheroes a JOIN superheroes b ON a.name = b.name",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["6895"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` a JOIN (SELECT ...) x ON x.`column` = a.`column` (the left rows hashed)
+*
+* **/
+#[test]
+fn test_cli_hash_join_smaller_left_library_db() {
+    // The six authors are hashed rather than the books, and the rows keep the authors' order.
+    let output = build_query_command(
+        "library.db",
+        "SELECT a.name, x.title FROM authors a JOIN (SELECT author_id, title FROM books) x ON x.author_id = a.id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Jane Austen|Pride and Prejudice",
+            "Jane Austen|Emma",
+            "Leo Tolstoy|War and Peace",
+            "Leo Tolstoy|Anna Karenina",
+            "Leo Tolstoy|The Death of Ivan Ilyich",
+            "Fyodor Dostoevsky|Crime and Punishment",
+            "Fyodor Dostoevsky|The Idiot",
+            "Mark Twain|Adventures of Huckleberry Finn",
+            "Mark Twain|Life on the Mississippi",
+            "Virginia Woolf|A Room of One's Own",
+            "Virginia Woolf|Mrs Dalloway"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_left_hash_join_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT count(*), count(b.id) FROM superheroes a LEFT JOIN superheroes b ON a.first_appearance = b.first_appearance AND b.id > a.id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["54302|53460"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` a JOIN (SELECT ...) x ON a.`column` = x.`column` COLLATE NOCASE
+*
+* **/
+#[test]
+fn test_cli_hash_join_collation_and_affinity_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT count(*) FROM tags a JOIN (SELECT upper(name) AS n FROM tags) x ON a.name = x.n COLLATE NOCASE",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["22"]);
+    assert!(output.status.success());
+
+    // The INTEGER column converts the text keys of the subquery.
+    let output = build_query_command(
+        "library.db",
+        "SELECT count(*) FROM books b JOIN (SELECT '' || id AS t FROM authors) x ON b.author_id = x.t",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["11"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` x JOIN `table` y ON x.`nocase_indexed` = y.`nocase_indexed`
+*
+* **/
+#[test]
+fn test_cli_index_probe_collation_library_db() {
+    // The NOCASE index is searched under NOCASE, and a term comparing under another collation
+    // does not use it.
+    let output = build_query_command(
+        "library.db",
+        "SELECT (SELECT count(*) FROM tags x JOIN tags y ON x.label = y.label), (SELECT count(*) FROM tags x JOIN tags y ON y.label = x.name COLLATE BINARY), count(*) FROM (SELECT DISTINCT genre FROM books) g JOIN books b ON b.genre = upper(g.genre) COLLATE NOCASE",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["22|7|12"]);
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT count(*) FROM tags x JOIN tags y ON x.label = y.label",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert!(stdout.contains("SEARCH y USING INDEX idx_tags_label (label=?)"));
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` x JOIN `table` y ON y.`unindexed` = x.`unindexed` COLLATE NOCASE
+*
+* **/
+#[test]
+fn test_cli_automatic_index_collation_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT (SELECT count(*) FROM tags x JOIN tags y ON y.name = x.name COLLATE NOCASE), count(*) FROM tags x JOIN tags y ON y.name = x.name COLLATE NOCASE AND y.name = x.name",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["22|10"]);
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT count(*) FROM tags x JOIN tags y ON y.name = x.name COLLATE NOCASE",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert!(stdout.contains("SEARCH y USING AUTOMATIC COVERING INDEX (name=?)"));
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` JOIN `table` ON `partially_indexed_column` = ...
+*
+* **/
+#[test]
+fn test_cli_partial_index_probe_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT (SELECT count(*) FROM counters x JOIN counters y ON y.a = x.b), count(*) FROM counters x JOIN counters y ON y.a = x.b WHERE y.a > 5",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1000|140"]);
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT count(*) FROM counters x JOIN counters y ON y.a = x.b",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert!(!stdout.contains("idx_counters_a"));
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT count(*) FROM counters x JOIN counters y ON y.a = x.b WHERE y.a > 5",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert!(stdout.contains("SEARCH y USING INDEX idx_counters_a (a=?)"));
+    assert!(output.status.success());
+}
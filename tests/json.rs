@@ -0,0 +1,183 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT json(...), json_valid(...)
+*
+* **/
+#[test]
+fn test_cli_json_minify_and_valid_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT json(' { \"a\" : [1, 2.5, null] } '), json('{a:0x1F, b:.5, c:[1,],}'), json_valid('{\"a\":1}'), json_valid('{a:1}'), json_valid('{a:1}', 2)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["{\"a\":[1,2.5,null]}|{\"a\":31,\"b\":0.5,\"c\":[1]}|1|0|1"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT json_extract(...), ... -> ..., ... ->> ...
+*
+* **/
+#[test]
+fn test_cli_json_extract_and_arrows_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT json_extract('{\"a\":{\"b\":[1,2,3]}}', '$.a.b[#-1]'), '{\"a\":{\"b\":[1,2]}}' -> 'a', '{\"a\":\"x\"}' -> 'a', '{\"a\":\"x\"}' ->> 'a', '[1,2,3]' ->> 1 = 2, json_type('[1,2.0]', '$[1]')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["3|{\"b\":[1,2]}|\"x\"|x|1|real"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT json_array(...), json_object(...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_json_array_and_object_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT json_array(1, 'x', NULL, json('[2]'), '[3]'), json_object('id', id, 'title', title), json_array_length(json_array(id, title)) FROM books WHERE id < 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "[1,\"x\",null,[2],\"[3]\"]|{\"id\":1,\"title\":\"Pride and Prejudice\"}|2",
+            "[1,\"x\",null,[2],\"[3]\"]|{\"id\":2,\"title\":\"Emma\"}|2"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT json_set(...), json_insert(...), json_replace(...), json_remove(...)
+*
+* **/
+#[test]
+fn test_cli_json_edit_functions_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT json_set('{\"a\":1}', '$.a', 2, '$.b', json_array(3)), json_insert('{\"a\":1}', '$.a', 2, '$.c[#]', 4), json_replace('{\"a\":1}', '$.a', 2, '$.b', 3), json_remove('[1,2,3]', '$[1]')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["{\"a\":2,\"b\":[3]}|{\"a\":1,\"c\":[4]}|{\"a\":2}|[1,3]"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM json_tree(...)
+*
+* **/
+#[test]
+fn test_cli_json_tree_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT key, value, type, id, parent, fullkey, path FROM json_tree('{\"a\":1,\"b\":[true,{\"c\":null}]}')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "|{\"a\":1,\"b\":[true,{\"c\":null}]}|object|0||$|$",
+            "a|1|integer|2|0|$.a|$",
+            "b|[true,{\"c\":null}]|array|6|0|$.b|$",
+            "0|1|true|9|6|$.b[0]|$.b",
+            "1|{\"c\":null}|object|10|6|$.b[1]|$.b",
+            "c||null|11|10|$.b[1].c|$.b[1]"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table`, json_each(`table`.column)
+*
+* **/
+#[test]
+fn test_cli_json_each_joined_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT a.name, e.key, e.value FROM authors AS a, json_each(json_array(a.country, a.id)) AS e WHERE a.id < 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Jane Austen|0|England",
+            "Jane Austen|1|1",
+            "Leo Tolstoy|0|Russia",
+            "Leo Tolstoy|1|2"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT json_group_array(...), json_group_object(...) FROM `table` GROUP BY ...
+*
+* **/
+#[test]
+fn test_cli_json_group_functions_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT genre, json_group_array(id), json_group_object(title, id) FROM books WHERE id < 4 GROUP BY genre",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["novel|[1,2,3]|{\"Pride and Prejudice\":1,\"Emma\":2,\"War and Peace\":3}"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_json_errors_library_db() {
+    let output = build_query_command("library.db", "SELECT json_extract('[1]', 'x')");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+    assert!(stderr.contains("bad JSON path: 'x'"));
+    assert!(!output.status.success());
+
+    let output = build_query_command("library.db", "SELECT json('{\"a\":')");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+    assert!(stderr.contains("malformed JSON"));
+    assert!(!output.status.success());
+
+    let output = build_query_command("library.db", "SELECT key FROM json_each('[1]', 1, 2)");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+    assert!(stderr.contains("too many arguments on json_each() - max 2"));
+    assert!(!output.status.success());
+}
@@ -0,0 +1,103 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT * FROM `table`
+*
+* **/
+#[test]
+fn test_cli_select_star_library_db() {
+    let output = build_query_command("library.db", "SELECT * FROM authors WHERE id < 3");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["1|Jane Austen|England", "2|Leo Tolstoy|Russia"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT * FROM `table` JOIN `table` USING (...)
+*
+* **/
+#[test]
+fn test_cli_select_star_using_join_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT * FROM books JOIN authors USING (id) WHERE id < 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "1|Pride and Prejudice|1|1813|novel|Jane Austen|England",
+            "2|Emma|1|1815|novel|Leo Tolstoy|Russia"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT t.*, ... FROM `table` AS t JOIN ...
+*
+* **/
+#[test]
+fn test_cli_select_qualified_star_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT b.*, a.name FROM books AS b JOIN authors AS a ON a.id = b.author_id WHERE b.id IN (1, 6)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "1|Pride and Prejudice|1|1813|novel|Jane Austen",
+            "6|The Idiot|3|1869|novel|Fyodor Dostoevsky"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT * FROM (SELECT column, expr, expr FROM `table`)
+*
+* **/
+#[test]
+fn test_cli_select_star_from_subquery_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT * FROM (SELECT id, id + 1, id + 1 FROM authors) WHERE id = 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["2|3|3"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_select_star_errors_library_db() {
+    let output = build_query_command("library.db", "SELECT *");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+    assert!(stderr.contains("no tables specified"));
+    assert!(!output.status.success());
+
+    let output = build_query_command("library.db", "SELECT foo.* FROM authors");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+    assert!(stderr.contains("no such table: foo"));
+    assert!(!output.status.success());
+}
@@ -0,0 +1,220 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE rowid BETWEEN ... AND ...
+*
+* **/
+#[test]
+fn test_cli_rowid_between_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT id, name FROM superheroes WHERE rowid BETWEEN 4998 AND 5002",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "4998|Robert Castell (New Earth)",
+            "4999|Slipknot II (New Earth)",
+            "5000|Spin Doctor I (New Earth)",
+            "5001|Thaddeus Sivana, Jr. (New Earth)",
+            "5002|Thormon Tox (New Earth)"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT COUNT(*), MIN(id), MAX(id) FROM `table` WHERE rowid > ... AND id <= ...
+*
+* **/
+#[test]
+fn test_cli_rowid_range_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT COUNT(*), MIN(id), MAX(id) FROM superheroes WHERE rowid > 6800 AND id <= 6890",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["90|6801|6890"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE id = ... / id IN (...)
+*
+* **/
+#[test]
+fn test_cli_rowid_equality_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT id, name FROM superheroes WHERE 3 = id OR 0",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["3|Green Lantern (Hal Jordan)"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT id FROM superheroes WHERE id IN (6895, 7000, 2.0, 2.5, NULL, 5) AND oid > 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["5", "6895"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE rowid > ... with keys at the ends of the rowid range
+*
+* **/
+#[test]
+fn test_cli_rowid_range_bounds_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT (SELECT COUNT(*) FROM superheroes WHERE id > 9223372036854775807), (SELECT COUNT(*) FROM superheroes WHERE id < 2.5), (SELECT COUNT(*) FROM superheroes WHERE id > 6893.5), (SELECT COUNT(*) FROM superheroes WHERE id < 'abc'), (SELECT COUNT(*) FROM superheroes WHERE id BETWEEN NULL AND 5)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["0|2|2|6895|0"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE rowid ... with negative and 64-bit rowids, stored as 9-byte varints
+*
+* **/
+#[test]
+fn test_cli_negative_rowids_library_db() {
+    let output = build_query_command("library.db", "SELECT id, memo FROM ledger");
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "-9223372036854775808|min",
+            "-140737488355329|i64 negative",
+            "-5|minus five",
+            "-1|minus one",
+            "0|zero",
+            "3|three",
+            "281474976710656|i64 positive",
+            "9223372036854775807|max"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM ledger WHERE id BETWEEN -200000000000000 AND 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["-140737488355329", "-5", "-1", "0", "3"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT min(id), max(id), (SELECT group_concat(memo) FROM ledger WHERE id = -5 OR id = 3) FROM ledger",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["-9223372036854775808|9223372036854775807|minus five,three"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT a.memo FROM ledger a JOIN ledger b ON b.id = a.id - 0 WHERE a.id < 0",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["min", "i64 negative", "minus five", "minus one"]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE rowid = (SELECT ...) / rowid IN (SELECT ...)
+*
+* **/
+#[test]
+fn test_cli_rowid_subquery_keys_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "EXPLAIN QUERY PLAN SELECT id FROM books WHERE id > (SELECT 6)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SEARCH books USING INTEGER PRIMARY KEY (rowid>?)",
+            "`--SCALAR SUBQUERY 1",
+            "   `--SCAN CONSTANT ROW"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT id, title FROM books WHERE id = (SELECT max(id) FROM books)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["12|The Death of Ivan Ilyich"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM books WHERE id IN (SELECT author_id FROM books WHERE genre = 'novel')",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["1", "2", "3", "4", "5"]
+    );
+    assert!(output.status.success());
+
+    // A subquery that refers to the table gives no key, and the table is scanned.
+    let output = build_query_command(
+        "library.db",
+        "SELECT id FROM books WHERE id = (SELECT count(*) FROM authors WHERE id <= author_id)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1"]);
+    assert!(output.status.success());
+}
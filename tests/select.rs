@@ -28,7 +28,7 @@ fn test_cli_select_count_superheroes_db() {
     let output = build_select_count_command("superheroes.db", "SELECT COUNT(*) FROM superheroes");
     let stdout = String::from_utf8(output.stdout).expect("parse to String");
 
-    assert!(stdout.contains("108"));
+    assert!(stdout.contains("6895"));
     assert!(output.status.success());
 }
 
@@ -169,3 +169,65 @@ fn test_cli_select_multiple_field_with_where_companies_db() {
     assert_eq!(expects, outputs);
     assert!(output.status.success());
 }
+
+/*
+*
+* SELECT `field_name` FROM `table` WHERE ... (duplicates are kept)
+*
+* **/
+#[test]
+fn test_cli_select_keeps_duplicates_superheroes_db() {
+    let output = build_select_field_command(
+        "superheroes.db",
+        "SELECT eye_color FROM superheroes WHERE eye_color = 'Pink Eyes'",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["Pink Eyes"; 6]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT DISTINCT `field_name` FROM `table`
+*
+* **/
+#[test]
+fn test_cli_select_distinct_superheroes_db() {
+    let output = build_select_field_command(
+        "superheroes.db",
+        "SELECT DISTINCT eye_color FROM superheroes WHERE appearance_count > 1000",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+    let expects = vec!["Blue Eyes", "Brown Eyes"];
+
+    assert_eq!(expects, stdout.lines().collect::<Vec<_>>());
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT count(*), sum(nocase_column = 'text' COLLATE BINARY), ... FROM `table`
+*
+* **/
+#[test]
+fn test_cli_comparison_collation_precedence_library_db() {
+    // COLLATE on either side wins, then the column on the left, then the one on the right.
+    let output = build_select_field_command(
+        "library.db",
+        "SELECT count(*), sum(label = 'abc' COLLATE BINARY), sum(label COLLATE BINARY = 'abc'), sum(name = label), sum(label = name), sum(name = 'ABC' COLLATE NOCASE) FROM tags",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["8|1|1|5|8|3"]);
+    assert!(output.status.success());
+
+    let output = build_select_field_command(
+        "library.db",
+        "SELECT count(*) FROM tags WHERE label = 'abc' COLLATE BINARY",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1"]);
+    assert!(output.status.success());
+}
@@ -0,0 +1,166 @@
+#![cfg(feature = "serde")]
+
+use rust_sqlite::{
+    de::{from_row, from_rows},
+    Connection,
+};
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Book {
+    id: i64,
+    title: String,
+    year: Option<u16>,
+    genre: Genre,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Genre {
+    Essay,
+    Memoir,
+    Novel,
+    Novella,
+    Poetry,
+}
+
+/*
+*
+* SELECT ... FROM `table` (rows deserialized into structs by column name)
+*
+* **/
+#[test]
+fn test_deserialize_structs_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare(
+            "SELECT genre, title, id, year, author_id FROM books WHERE author_id = ? ORDER BY id",
+        )
+        .expect("prepare");
+
+    let books = from_rows::<Book>(statement.query([1]).expect("query"))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("deserialize");
+    assert_eq!(
+        books,
+        vec![
+            Book {
+                id: 1,
+                title: "Pride and Prejudice".to_string(),
+                year: Some(1813),
+                genre: Genre::Novel,
+            },
+            Book {
+                id: 2,
+                title: "Emma".to_string(),
+                year: Some(1815),
+                genre: Genre::Novel,
+            },
+        ]
+    );
+
+    let row = statement
+        .query([2])
+        .expect("query")
+        .next()
+        .expect("a row")
+        .expect("read row");
+    let (genre, title): (&str, &str) = from_row(&row).expect("deserialize");
+    assert_eq!((genre, title), ("novel", "War and Peace"));
+}
+
+/*
+*
+* SELECT ... (values that do not convert to the field types)
+*
+* **/
+#[test]
+fn test_deserialize_errors_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Counts {
+        count: u8,
+        label: String,
+    }
+
+    let row = conn
+        .prepare("SELECT 300 AS count, 'x' AS label")
+        .and_then(|mut statement| statement.query(())?.next().expect("a row"))
+        .expect("query");
+    let err = from_row::<Counts>(&row).expect_err("300 does not fit in u8");
+    assert_eq!(
+        err.to_string(),
+        "column \"count\": invalid value: integer `300`, expected u8"
+    );
+
+    let row = conn
+        .prepare("SELECT 1 AS count, 2.5 AS label")
+        .and_then(|mut statement| statement.query(())?.next().expect("a row"))
+        .expect("query");
+    let err = from_row::<Counts>(&row).expect_err("a real is not a string");
+    assert_eq!(
+        err.to_string(),
+        "column \"label\": invalid type: floating point `2.5`, expected a string"
+    );
+
+    let row = conn
+        .prepare("SELECT 1 AS count")
+        .and_then(|mut statement| statement.query(())?.next().expect("a row"))
+        .expect("query");
+    let err = from_row::<Counts>(&row).expect_err("label is missing");
+    assert_eq!(err.to_string(), "missing field `label`");
+
+    let row = conn
+        .prepare("SELECT 1 AS count, NULL AS label")
+        .and_then(|mut statement| statement.query(())?.next().expect("a row"))
+        .expect("query");
+    let err = from_row::<Counts>(&row).expect_err("NULL is not a string");
+    assert_eq!(
+        err.to_string(),
+        "column \"label\": invalid type: NULL, expected a string"
+    );
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Genres {
+        genre: Genre,
+    }
+
+    let row = conn
+        .prepare("SELECT 3 AS genre")
+        .and_then(|mut statement| statement.query(())?.next().expect("a row"))
+        .expect("query");
+    let err = from_row::<Genres>(&row).expect_err("an integer is not a variant");
+    assert_eq!(
+        err.to_string(),
+        "column \"genre\": invalid type: integer `3`, expected a string"
+    );
+}
+
+/*
+*
+* SELECT `column` AS `COLUMN` ... (columns matched to fields in any case)
+*
+* **/
+#[test]
+fn test_deserialize_field_case_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare("SELECT ID, Title, year AS YEAR, GENRE FROM books WHERE id = ?")
+        .expect("prepare");
+
+    let book: Book = statement
+        .query_row([2], |row| from_row(row))
+        .expect("deserialize");
+    assert_eq!(
+        book,
+        Book {
+            id: 2,
+            title: "Emma".to_string(),
+            year: Some(1815),
+            genre: Genre::Novel,
+        }
+    );
+}
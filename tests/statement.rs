@@ -0,0 +1,196 @@
+use rust_sqlite::{column::SerialValue, Connection};
+
+/*
+*
+* SELECT ?, ?NNN, :name, @name, $name (numbered the way SQLite numbers them)
+*
+* **/
+#[test]
+fn test_parameter_numbers() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let statement = conn
+        .prepare("SELECT ?, ?5, :a, ?, @b, :a, $c")
+        .expect("prepare");
+
+    assert_eq!(statement.parameter_count(), 9);
+    assert_eq!(statement.parameter_index(":a"), Some(6));
+    assert_eq!(statement.parameter_index("@b"), Some(8));
+    assert_eq!(statement.parameter_index("$c"), Some(9));
+    assert_eq!(statement.parameter_index(":b"), None);
+    assert_eq!(statement.parameter_name(6), Some(":a"));
+    assert_eq!(statement.parameter_name(1), None);
+    assert_eq!(statement.parameter_name(0), None);
+
+    assert!(conn.prepare("SELECT ?0").is_err());
+    assert!(conn.prepare("SELECT ?32767").is_err());
+}
+
+/*
+*
+* SELECT ?1, typeof(?2), ... (values of every type bound by number)
+*
+* **/
+#[test]
+fn test_bind_values() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare("SELECT ?1, typeof(?2), ?3 || 'x', ?4, ?5 IS NULL ORDER BY 1")
+        .expect("prepare");
+
+    statement.bind(1, 42).expect("bind");
+    statement.bind(2, 1.5).expect("bind");
+    statement.bind(3, "it's").expect("bind");
+    statement.bind(4, vec![0xca, 0xfe]).expect("bind");
+    statement.bind(5, None::<i64>).expect("bind");
+    assert!(statement.bind(6, 1).is_err());
+    assert!(statement.bind(0, 1).is_err());
+
+    let result = statement.execute().expect("execute");
+    assert_eq!(
+        result.columns,
+        vec!["?1", "typeof(?2)", "?3 || 'x'", "?4", "?5 IS NULL"]
+    );
+    assert_eq!(
+        result.rows,
+        vec![vec![
+            SerialValue::I64(42),
+            SerialValue::String("real".to_string()),
+            SerialValue::String("it'sx".to_string()),
+            SerialValue::Blob(vec![0xca, 0xfe]),
+            SerialValue::One,
+        ]]
+    );
+
+    statement.clear_bindings();
+    let result = statement.execute().expect("execute");
+    assert_eq!(result.rows[0][0], SerialValue::Null);
+    assert_eq!(result.rows[0][1], SerialValue::String("null".to_string()));
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = :name (run with one value, then another)
+*
+* **/
+#[test]
+fn test_execute_many_times() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare("SELECT title FROM books WHERE author_id = :author ORDER BY id")
+        .expect("prepare");
+
+    let mut titles = Vec::new();
+    for author in 1..=2 {
+        statement.bind_name(":author", author).expect("bind");
+        let result = statement.execute().expect("execute");
+        titles.extend(result.rows.into_iter().map(|row| row[0].clone()));
+    }
+    assert_eq!(
+        titles,
+        [
+            "Pride and Prejudice",
+            "Emma",
+            "War and Peace",
+            "Anna Karenina",
+            "The Death of Ivan Ilyich"
+        ]
+        .map(|title| SerialValue::String(title.to_string()))
+    );
+    assert!(statement.bind_name(":other", 1).is_err());
+
+    let mut statement = conn
+        .prepare("EXPLAIN QUERY PLAN SELECT title FROM books WHERE author_id = ?")
+        .expect("prepare");
+    statement.bind(1, 2).expect("bind");
+    let result = statement.execute().expect("execute");
+    assert_eq!(
+        result.rows.last().and_then(|row| row.last()),
+        Some(&SerialValue::String(
+            "SEARCH books USING INDEX idx_books_author_id (author_id=?)".to_string()
+        ))
+    );
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = ? (planned before any value is bound)
+*
+* **/
+#[test]
+fn test_plan_with_null_first_library_db() {
+    let conn = Connection::open("library.db").expect("open library.db");
+    let mut statement = conn
+        .prepare("SELECT title FROM books WHERE author_id = ?")
+        .expect("prepare");
+    let search = vec![
+        "QUERY PLAN",
+        "`--SEARCH books USING INDEX idx_books_author_id (author_id=?)",
+    ];
+
+    // The parameter is NULL until it is bound, which matches no row.
+    let plan = statement.query_plan().expect("query plan").to_string();
+    assert_eq!(plan.lines().collect::<Vec<_>>(), search);
+    assert!(statement.execute().expect("execute").rows.is_empty());
+
+    statement.bind(1, 1).expect("bind");
+    let result = statement.execute().expect("execute");
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![SerialValue::String("Pride and Prejudice".to_string())],
+            vec![SerialValue::String("Emma".to_string())],
+        ]
+    );
+    let plan = statement.query_plan().expect("query plan").to_string();
+    assert_eq!(plan.lines().collect::<Vec<_>>(), search);
+
+    // Each branch of an OR keeps its own way of searching.
+    let mut statement = conn
+        .prepare("SELECT title FROM books WHERE id = ? OR author_id = ?")
+        .expect("prepare");
+    let multi_index = vec![
+        "QUERY PLAN",
+        "`--MULTI-INDEX OR",
+        "   |--INDEX 1",
+        "   |  `--SEARCH books USING INTEGER PRIMARY KEY (rowid=?)",
+        "   `--INDEX 2",
+        "      `--SEARCH books USING INDEX idx_books_author_id (author_id=?)",
+    ];
+    let plan = statement.query_plan().expect("query plan").to_string();
+    assert_eq!(plan.lines().collect::<Vec<_>>(), multi_index);
+
+    statement.bind(1, 3).expect("bind");
+    statement.bind(2, 1).expect("bind");
+    let result = statement.execute().expect("execute");
+    assert_eq!(result.rows.len(), 3);
+    let plan = statement.query_plan().expect("query plan").to_string();
+    assert_eq!(plan.lines().collect::<Vec<_>>(), multi_index);
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = ? (the same plan whatever is bound first)
+*
+* **/
+#[test]
+fn test_plan_independent_of_values_shop_db() {
+    let conn = Connection::open("shop.db").expect("open shop.db");
+
+    let mut plans = Vec::new();
+    for first in ["cancelled", "shipped"] {
+        let mut statement = conn
+            .prepare("SELECT count(*), sum(total) FROM orders WHERE status = ?")
+            .expect("prepare");
+        let rows = [first, "cancelled", "shipped", "pending"].map(|status| {
+            statement.bind(1, status).expect("bind");
+            statement.execute().expect("execute").rows.remove(0)
+        });
+        assert_eq!(
+            rows[1..],
+            [[25, 7101], [4725, 1226452], [250, 63947]]
+                .map(|row| row.map(SerialValue::I64).to_vec())
+        );
+        plans.push(statement.query_plan().expect("query plan").to_string());
+    }
+    assert_eq!(plans[0], plans[1]);
+}
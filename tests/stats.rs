@@ -0,0 +1,299 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = ... AND `other_indexed_column` = ...
+*
+* **/
+#[test]
+fn test_cli_stat1_index_choice_shop_db() {
+    let statement = "SELECT count(*) FROM orders WHERE status = 'shipped' AND customer_id = 5";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH orders USING INDEX idx_orders_customer (customer_id=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["5"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = `rare value` AND `other_indexed_column` BETWEEN ...
+*
+* **/
+#[test]
+fn test_cli_stat4_sample_shop_db() {
+    let statement =
+        "SELECT count(*) FROM orders WHERE status = 'cancelled' AND customer_id BETWEEN 1 AND 500";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH orders USING INDEX idx_orders_status (status=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["9"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT count(*) FROM `table` WHERE `indexed_column` > ... (a covering index seek)
+*
+* **/
+#[test]
+fn test_cli_covering_seek_cost_shop_db() {
+    let statement = "SELECT count(*) FROM orders WHERE status > 'p'";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SEARCH orders USING COVERING INDEX idx_orders_status (status>?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["4975"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT * FROM `table`, `table`, `table` WHERE ... (joined in the cheapest order)
+*
+* **/
+#[test]
+fn test_cli_join_order_shop_db() {
+    let statement = "SELECT * FROM orders o, customers c, regions r \
+        WHERE o.customer_id = c.id AND c.region_id = r.id AND r.name = 'east' \
+        ORDER BY o.id LIMIT 3";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SCAN r",
+            "|--SEARCH c USING INDEX idx_customers_region (region_id=?)",
+            "|--SEARCH o USING INDEX idx_orders_customer (customer_id=?)",
+            "`--USE TEMP B-TREE FOR ORDER BY"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "1|8|shipped|47|8|customer-8|3|3|east",
+            "6|43|shipped|232|43|customer-43|3|3|east",
+            "11|78|shipped|417|78|customer-78|3|3|east"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command(
+        "shop.db",
+        "SELECT o.id, o.total, c.name FROM orders o JOIN customers c ON o.customer_id = c.id \
+        JOIN regions r ON r.id = c.region_id AND r.name = 'east' \
+        ORDER BY o.total DESC, o.id LIMIT 3",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "81|507|customer-568",
+            "581|507|customer-68",
+            "1081|507|customer-568"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` JOIN `table` ON ... WHERE ... (the driving table follows the samples)
+*
+* **/
+#[test]
+fn test_cli_join_order_stat4_sample_shop_db() {
+    // sqlite_stat1 puts 1667 orders under each status, but the samples count 4725 shipped
+    // orders against 200 customers in the region, so the customers are read first.
+    let statement = "SELECT count(*), sum(o.total) FROM customers c JOIN orders o \
+        ON o.customer_id = c.id WHERE c.region_id = 2 AND o.status = 'shipped'";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SCAN c",
+            "`--SEARCH o USING INDEX idx_orders_customer (customer_id=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["944|244769"]);
+    assert!(output.status.success());
+
+    // Only 25 orders are cancelled, so they drive the join.
+    let statement = "SELECT count(*), sum(o.total) FROM customers c JOIN orders o \
+        ON o.customer_id = c.id WHERE c.region_id = 2 AND o.status = 'cancelled'";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SEARCH o USING INDEX idx_orders_status (status=?)",
+            "`--SEARCH c USING INTEGER PRIMARY KEY (rowid=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["4|1414"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM `table` WHERE `indexed_column` = ... OR `other_indexed_column` = ...
+*
+* **/
+#[test]
+fn test_cli_multi_index_or_cost_shop_db() {
+    let output = build_query_command(
+        "shop.db",
+        "EXPLAIN QUERY PLAN SELECT count(*) FROM orders WHERE status = 'cancelled' OR customer_id = 5",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--MULTI-INDEX OR",
+            "   |--INDEX 1",
+            "   |  `--SEARCH orders USING INDEX idx_orders_status (status=?)",
+            "   `--INDEX 2",
+            "      `--SEARCH orders USING INDEX idx_orders_customer (customer_id=?)"
+        ]
+    );
+    assert!(output.status.success());
+
+    // Most orders are shipped, so reading them through an index costs more than a scan.
+    let statement =
+        "SELECT count(*), sum(total) FROM orders WHERE status = 'shipped' OR customer_id = 5";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["QUERY PLAN", "`--SCAN orders"]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["4725|1226452"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT `column`, ... FROM `table` WHERE `other_indexed_column` = ... GROUP BY `indexed_column`
+*
+* **/
+#[test]
+fn test_cli_group_order_cost_shop_db() {
+    let statement = "SELECT status, sum(total) FROM orders WHERE customer_id = 7 GROUP BY status";
+
+    let output = build_query_command("shop.db", &format!("EXPLAIN QUERY PLAN {statement}"));
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "|--SEARCH orders USING INDEX idx_orders_customer (customer_id=?)",
+            "`--USE TEMP B-TREE FOR GROUP BY"
+        ]
+    );
+    assert!(output.status.success());
+
+    let output = build_query_command("shop.db", statement);
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["shipped|1280"]);
+    assert!(output.status.success());
+
+    // Without a term to search by, the index that leads with the GROUP BY column saves the sort.
+    let output = build_query_command(
+        "shop.db",
+        "EXPLAIN QUERY PLAN SELECT status, count(*) FROM orders GROUP BY status",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "QUERY PLAN",
+            "`--SCAN orders USING COVERING INDEX idx_orders_status"
+        ]
+    );
+    assert!(output.status.success());
+}
@@ -0,0 +1,177 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT `field_name`, (SELECT ...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_correlated_scalar_subquery_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT name, (SELECT count(*) FROM books WHERE author_id = authors.id) FROM authors",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Jane Austen|2",
+            "Leo Tolstoy|3",
+            "Fyodor Dostoevsky|2",
+            "Mark Twain|2",
+            "Virginia Woolf|2",
+            "Franz Kafka|0"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT count(*) FROM `table` s WHERE ... AND EXISTS (SELECT ... WHERE t.`rowid` = s.`rowid`)
+*
+* **/
+#[test]
+fn test_cli_correlated_rowid_search_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT count(*) FROM superheroes s \
+         WHERE s.id < 3000 AND EXISTS (SELECT 1 FROM superheroes t WHERE t.id = s.id)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["2999"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_uncorrelated_scalar_subquery_superheroes_db() {
+    let output = build_query_command(
+        "superheroes.db",
+        "SELECT count(*) FROM superheroes WHERE appearance_count > (SELECT avg(appearance_count) FROM superheroes)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["1140"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_uncorrelated_subqueries_in_aggregates_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT a.id, (SELECT max((SELECT 'x')) || min((SELECT 'y')) || group_concat((SELECT 'p'), (SELECT 'q')) FROM books b WHERE b.author_id = a.id) FROM authors a",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "1|xypqp",
+            "2|xypqpqp",
+            "3|xypqp",
+            "4|xypqp",
+            "5|xypqp",
+            "6|"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_scalar_subquery_with_two_columns_library_db() {
+    let output = build_query_command("library.db", "SELECT (SELECT name, id FROM authors)");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("sub-select returns 2 columns - expected 1"));
+    assert!(!output.status.success());
+}
+
+/*
+*
+* SELECT `field_name` FROM `table` WHERE `field_name` [NOT] IN (SELECT ...)
+*
+* **/
+#[test]
+fn test_cli_in_subquery_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT name FROM authors WHERE id IN (SELECT author_id FROM books WHERE year > 1880)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["Leo Tolstoy", "Mark Twain", "Virginia Woolf"]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_not_in_subquery_with_null_library_db() {
+    // One of the books has no author, so NOT IN is never true.
+    let output = build_query_command(
+        "library.db",
+        "SELECT name FROM authors WHERE id NOT IN (SELECT author_id FROM books WHERE year > 1880)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert!(stdout.is_empty());
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT `field_name` FROM `table` WHERE [NOT] EXISTS (SELECT ...)
+*
+* **/
+#[test]
+fn test_cli_not_exists_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT name FROM authors a WHERE NOT EXISTS (SELECT 1 FROM books b WHERE b.author_id = a.id)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["Franz Kafka"]);
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... FROM (SELECT ...) AS `alias`
+*
+* **/
+#[test]
+fn test_cli_derived_table_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT s.name, s.n FROM (SELECT a.name AS name, count(*) AS n FROM authors a JOIN books b ON b.author_id = a.id GROUP BY a.name) AS s WHERE s.n > 2",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["Leo Tolstoy|3"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_join_derived_table_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT count(*) FROM books b JOIN (SELECT id, name FROM authors WHERE country = 'Russia') s ON s.id = b.author_id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(stdout.lines().collect::<Vec<_>>(), vec!["5"]);
+    assert!(output.status.success());
+}
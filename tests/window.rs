@@ -0,0 +1,189 @@
+use std::process::{Command, Output};
+
+fn build_query_command(db_name: &str, statement: &str) -> Output {
+    Command::new("./target/debug/rust-sqlite")
+        .arg(".query")
+        .arg(db_name)
+        .arg(statement)
+        .output()
+        .expect("failed to execute .query process")
+}
+
+/*
+*
+* SELECT row_number() OVER (ORDER BY ...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_row_number_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT name, row_number() OVER (ORDER BY name DESC) FROM authors",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Virginia Woolf|1",
+            "Mark Twain|2",
+            "Leo Tolstoy|3",
+            "Jane Austen|4",
+            "Fyodor Dostoevsky|5",
+            "Franz Kafka|6"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_ranking_functions_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT name, rank() OVER (ORDER BY country), dense_rank() OVER (ORDER BY country), percent_rank() OVER (ORDER BY country), cume_dist() OVER (ORDER BY country) FROM authors ORDER BY name",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Franz Kafka|1|1|0.0|0.166666666666667",
+            "Fyodor Dostoevsky|4|3|0.6|0.833333333333333",
+            "Jane Austen|2|2|0.2|0.5",
+            "Leo Tolstoy|4|3|0.6|0.833333333333333",
+            "Mark Twain|6|4|1.0|1.0",
+            "Virginia Woolf|2|2|0.2|0.5"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT lag(...) OVER (...), lead(...) OVER (...), ntile(...) OVER (...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_lag_lead_ntile_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id, lag(id) OVER (ORDER BY id), lead(id, 2, 0) OVER (ORDER BY id), ntile(5) OVER (ORDER BY id) FROM books ORDER BY id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "1||3|1",
+            "2|1|4|1",
+            "3|2|5|1",
+            "4|3|6|2",
+            "5|4|7|2",
+            "6|5|8|2",
+            "7|6|9|3",
+            "8|7|10|3",
+            "9|8|11|4",
+            "10|9|12|4",
+            "11|10|0|5",
+            "12|11|0|5"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT sum(...) OVER (PARTITION BY ... ORDER BY ...) FROM `table` GROUP BY ...
+*
+* **/
+#[test]
+fn test_cli_running_total_of_groups_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT genre, count(*), sum(count(*)) OVER (ORDER BY genre) FROM books GROUP BY genre",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "essay|1|1",
+            "memoir|1|2",
+            "novel|8|10",
+            "novella|1|11",
+            "poetry|1|12"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT ... OVER `name` FROM `table` WINDOW `name` AS (... ROWS BETWEEN ... AND ...)
+*
+* **/
+#[test]
+fn test_cli_named_window_rows_frame_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT title, sum(year) OVER w, avg(year) OVER w FROM books WHERE genre = 'novel' WINDOW w AS (ORDER BY year, title ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "Pride and Prejudice|1813|1813.0",
+            "Emma|3628|1814.0",
+            "Crime and Punishment|3681|1840.5",
+            "The Idiot|3735|1867.5",
+            "War and Peace|3738|1869.0",
+            "Anna Karenina|3747|1873.5",
+            "Adventures of Huckleberry Finn|3762|1881.0",
+            "Mrs Dalloway|3809|1904.5"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+/*
+*
+* SELECT first_value(...) OVER (... RANGE ...), last_value(...) OVER (... GROUPS ...) FROM `table`
+*
+* **/
+#[test]
+fn test_cli_range_and_groups_frames_library_db() {
+    let output = build_query_command(
+        "library.db",
+        "SELECT id, year, first_value(id) OVER (ORDER BY year RANGE BETWEEN 10 PRECEDING AND 10 FOLLOWING), last_value(id) OVER (ORDER BY year GROUPS BETWEEN CURRENT ROW AND 1 FOLLOWING), nth_value(id, 2) OVER (ORDER BY year) FROM books ORDER BY id",
+    );
+    let stdout = String::from_utf8(output.stdout).expect("parse to String");
+
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "1|1813|1|2|",
+            "2|1815|1|5|2",
+            "3|1869|5|4|2",
+            "4|1878|3|8|2",
+            "5|1866|5|6|2",
+            "6|1869|5|4|2",
+            "7|1884|4|12|2",
+            "8|1883|4|7|2",
+            "9|1929|10|9|2",
+            "10|1925|10|9|2",
+            "11|1900|11|10|2",
+            "12|1886|4|11|2"
+        ]
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cli_no_such_window_library_db() {
+    let output = build_query_command("library.db", "SELECT row_number() OVER w FROM books");
+    let stderr = String::from_utf8(output.stderr).expect("parse to String");
+
+    assert!(stderr.contains("no such window: w"));
+    assert!(!output.status.success());
+}